@@ -114,6 +114,7 @@ impl Example for App {
                                 value: self.opacity,
                             }
                         ],
+                        colors: vec![],
                     },
                 );
                 txn.generate_frame();
@@ -62,6 +62,7 @@ impl Example for App {
                 vec![],
                 None,
                 ScrollSensitivity::ScriptAndInputEvents,
+                OverscrollBehavior::Auto,
             );
             builder.push_clip_id(clip_id);
 
@@ -93,6 +94,7 @@ impl Example for App {
                 vec![],
                 None,
                 ScrollSensitivity::ScriptAndInputEvents,
+                OverscrollBehavior::Auto,
             );
             builder.push_clip_id(nested_clip_id);
 
@@ -0,0 +1,93 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#define VECS_PER_SPECIFIC_BRUSH 2
+
+#include shared,prim_shared,brush
+
+#define PI                  3.141592653589793
+#define TWO_PI              6.283185307179586
+
+flat varying int vGradientAddress;
+flat varying float vGradientRepeat;
+
+flat varying vec2 vCenter;
+flat varying float vAngle;
+flat varying float vStartOffset;
+flat varying float vOffsetScale;
+
+varying vec2 vPos;
+
+#ifdef WR_FEATURE_ALPHA_PASS
+varying vec2 vLocalPos;
+#endif
+
+#ifdef WR_VERTEX_SHADER
+
+struct ConicGradient {
+    vec4 center_start_end_offset;
+    vec4 angle_extend_mode;
+};
+
+ConicGradient fetch_conic_gradient(int address) {
+    vec4 data[2] = fetch_from_resource_cache_2(address);
+    return ConicGradient(data[0], data[1]);
+}
+
+void brush_vs(
+    VertexInfo vi,
+    int prim_address,
+    RectWithSize local_rect,
+    ivec3 user_data,
+    PictureTask pic_task
+) {
+    ConicGradient gradient = fetch_conic_gradient(prim_address);
+
+    vPos = vi.local_pos - local_rect.p0;
+
+    vCenter = gradient.center_start_end_offset.xy;
+
+    // The gradient angle is measured clockwise from "12 o'clock",
+    // whereas atan() measures from the positive x axis. Since the
+    // y axis points down, atan() already sweeps clockwise, so we
+    // only need to rotate the starting point by a quarter turn.
+    vAngle = PI * 0.5 - gradient.angle_extend_mode.x;
+
+    // Map the normalized stops range back onto a full turn.
+    float start_offset = gradient.center_start_end_offset.z;
+    float end_offset = gradient.center_start_end_offset.w;
+    vStartOffset = start_offset;
+    vOffsetScale = 1.0 / max(end_offset - start_offset, 0.000001);
+
+    vGradientAddress = user_data.x;
+
+    // Whether to repeat the gradient instead of clamping.
+    vGradientRepeat = float(int(gradient.angle_extend_mode.y) != EXTEND_MODE_CLAMP);
+
+#ifdef WR_FEATURE_ALPHA_PASS
+    vLocalPos = vi.local_pos;
+#endif
+}
+#endif
+
+#ifdef WR_FRAGMENT_SHADER
+vec4 brush_fs() {
+    vec2 dir = vPos - vCenter;
+
+    // Angle of this fragment around the center, as a fraction of a full
+    // turn in the range [0, 1), starting from the gradient angle.
+    float turn = fract((atan(dir.y, dir.x) + vAngle) / TWO_PI);
+    float offset = (turn - vStartOffset) * vOffsetScale;
+
+    vec4 color = sample_gradient(vGradientAddress,
+                                 offset,
+                                 vGradientRepeat);
+
+#ifdef WR_FEATURE_ALPHA_PASS
+    color *= init_transform_fs(vLocalPos);
+#endif
+
+    return color;
+}
+#endif
@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-#define VECS_PER_SPECIFIC_BRUSH 0
+#define VECS_PER_SPECIFIC_BRUSH 1
 
 #include shared,prim_shared,brush
 
@@ -34,6 +34,16 @@ void brush_vs(
     vec2 uv0 = res.uv_rect.p0;
     vec2 uv1 = res.uv_rect.p1;
 
+    // Select the part of the image to draw, if any. The sub-rect is in
+    // texels relative to the image, so step along the direction of the
+    // UV rect in case it is inverted.
+    vec4 sub_rect = fetch_from_resource_cache_1(prim_address);
+    if (sub_rect.x >= 0.0) {
+        vec2 dir = sign(uv1 - uv0);
+        uv1 = uv0 + dir * sub_rect.zw;
+        uv0 = uv0 + dir * sub_rect.xy;
+    }
+
     vUv.z = res.layer;
 
     vec2 f = (vi.local_pos - local_rect.p0) / local_rect.size;
@@ -0,0 +1,93 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#include shared,prim_shared,clip_shared
+
+// Note: has to match the FillRule enum in the API.
+#define FILL_RULE_NONZERO   0
+#define FILL_RULE_EVENODD   1
+
+varying vec3 vPos;
+flat varying float vClipMode;
+flat varying int vFillRule;
+flat varying int vPointCount;
+flat varying ivec2 vPointsAddress;
+
+#ifdef WR_VERTEX_SHADER
+struct ClipPolygon {
+    RectWithSize bounds;
+    vec4 count_fill_rule_mode;
+};
+
+ClipPolygon fetch_clip_polygon(ivec2 address) {
+    vec4 data[2] = fetch_from_resource_cache_2_direct(address);
+    return ClipPolygon(RectWithSize(data[0].xy, data[0].zw), data[1]);
+}
+
+void main(void) {
+    ClipMaskInstance cmi = fetch_clip_item();
+    ClipArea area = fetch_clip_area(cmi.render_task_address);
+    ClipScrollNode scroll_node = fetch_clip_scroll_node(cmi.scroll_node_id);
+    ClipPolygon polygon = fetch_clip_polygon(cmi.clip_data_address);
+
+    ClipVertexInfo vi = write_clip_tile_vertex(polygon.bounds, scroll_node, area);
+    vPos = vi.local_pos;
+
+    vPointCount = int(polygon.count_fill_rule_mode.x);
+    vFillRule = int(polygon.count_fill_rule_mode.y);
+    vClipMode = polygon.count_fill_rule_mode.z;
+
+    // The points follow the two header blocks, two points per block.
+    vPointsAddress = cmi.clip_data_address + ivec2(2, 0);
+}
+#endif
+
+#ifdef WR_FRAGMENT_SHADER
+vec2 fetch_point(int index) {
+    vec4 pair = texelFetch(sResourceCache, vPointsAddress + ivec2(index / 2, 0), 0);
+    return (index % 2) == 0 ? pair.xy : pair.zw;
+}
+
+void main(void) {
+    vec2 local_pos = vPos.xy / vPos.z;
+    float aa_range = compute_aa_range(local_pos);
+
+    // Walk the edges once, accumulating the winding number of the
+    // polygon around this fragment and the distance to the closest edge.
+    int winding = 0;
+    float min_distance = 1.0e10;
+    vec2 a = fetch_point(max(vPointCount - 1, 0));
+    for (int i = 0; i < vPointCount; i++) {
+        vec2 b = fetch_point(i);
+        vec2 edge = b - a;
+        vec2 to_pos = local_pos - a;
+
+        float t = clamp(dot(to_pos, edge) / max(dot(edge, edge), EPSILON), 0.0, 1.0);
+        min_distance = min(min_distance, length(to_pos - edge * t));
+
+        float side = edge.x * to_pos.y - to_pos.x * edge.y;
+        if (a.y <= local_pos.y) {
+            if (b.y > local_pos.y && side > 0.0) {
+                winding += 1;
+            }
+        } else if (b.y <= local_pos.y && side < 0.0) {
+            winding -= 1;
+        }
+
+        a = b;
+    }
+
+    bool inside = vFillRule == FILL_RULE_EVENODD ? (abs(winding) % 2) != 0 : winding != 0;
+
+    // The polygon edges are antialiased on their own, so the coverage of
+    // the bounding rect isn't needed here.
+    float signed_distance = inside ? -min_distance : min_distance;
+    float clip_alpha = distance_aa(aa_range, signed_distance);
+
+    // Select alpha or inverse alpha depending on clip in/out.
+    float final_alpha = mix(clip_alpha, 1.0 - clip_alpha, vClipMode);
+
+    oFragColor = vec4(final_alpha, 0.0, 0.0, 1.0);
+}
+#endif
@@ -0,0 +1,99 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#include shared,prim_shared
+
+// Note: has to match the FillRule enum in the API.
+#define FILL_RULE_NONZERO   0
+#define FILL_RULE_EVENODD   1
+
+// The number of samples taken along each axis of a pixel.
+#define SAMPLES_PER_AXIS    4
+
+varying vec2 vLocalPos;
+flat varying vec2 vPixelSize;
+flat varying int vEdgeCount;
+flat varying int vFillRule;
+flat varying ivec2 vEdgesAddress;
+
+#ifdef WR_VERTEX_SHADER
+
+in int aPathRenderTaskAddress;
+in ivec2 aPathEdgesAddress;
+
+struct PathTask {
+    RenderTaskCommonData common_data;
+    vec2 local_size;
+};
+
+PathTask fetch_path_task(int address) {
+    RenderTaskData task_data = fetch_render_task_data(address);
+
+    PathTask task = PathTask(
+        task_data.common_data,
+        task_data.data1.xy
+    );
+
+    return task;
+}
+
+void main(void) {
+    PathTask path_task = fetch_path_task(aPathRenderTaskAddress);
+    RectWithSize target_rect = path_task.common_data.task_rect;
+
+    vec2 pos = target_rect.p0 + target_rect.size * aPosition.xy;
+
+    // The edges are relative to the origin of the local rect,
+    // which is stretched over the whole task rect.
+    vLocalPos = path_task.local_size * aPosition.xy;
+    vPixelSize = path_task.local_size / target_rect.size;
+
+    vec4 header = fetch_from_resource_cache_1_direct(aPathEdgesAddress);
+    vEdgeCount = int(header.x);
+    vFillRule = int(header.y);
+    vEdgesAddress = aPathEdgesAddress + ivec2(1, 0);
+
+    gl_Position = uTransform * vec4(pos, 0.0, 1.0);
+}
+#endif
+
+#ifdef WR_FRAGMENT_SHADER
+void main(void) {
+    vec2 sample_size = vPixelSize / float(SAMPLES_PER_AXIS);
+    vec2 first_sample = vLocalPos - 0.5 * vPixelSize + 0.5 * sample_size;
+    vec4 sample_x = first_sample.x + vec4(0.0, 1.0, 2.0, 3.0) * sample_size.x;
+
+    // Count the samples inside the path, with the winding number
+    // of each sample on a row found from the edges to its left.
+    // Supersampling (rather than estimating the distance to the
+    // edges) keeps shared edges of adjacent polygons seamless.
+    float coverage = 0.0;
+    for (int row = 0; row < SAMPLES_PER_AXIS; row++) {
+        float y = first_sample.y + float(row) * sample_size.y;
+        ivec4 winding = ivec4(0);
+
+        for (int i = 0; i < vEdgeCount; i++) {
+            vec4 edge = texelFetch(sResourceCache, vEdgesAddress + ivec2(i, 0), 0);
+
+            // Half-open on y, so that a sample level with a vertex
+            // is only crossed by one of the edges that meet there.
+            if ((edge.y <= y) != (edge.w <= y)) {
+                float x = mix(edge.x, edge.z, (y - edge.y) / (edge.w - edge.y));
+                int direction = edge.w > edge.y ? 1 : -1;
+                winding += ivec4(lessThan(vec4(x), sample_x)) * direction;
+            }
+        }
+
+        bvec4 inside;
+        if (vFillRule == FILL_RULE_EVENODD) {
+            inside = notEqual(abs(winding) % 2, ivec4(0));
+        } else {
+            inside = notEqual(winding, ivec4(0));
+        }
+        coverage += dot(vec4(inside), vec4(1.0));
+    }
+
+    oFragColor = vec4(coverage / float(SAMPLES_PER_AXIS * SAMPLES_PER_AXIS));
+}
+#endif
@@ -0,0 +1,199 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#include shared,prim_shared
+
+// Note: has to match the instances built in tiling.rs
+#define FILTER_COMPONENT_TRANSFER   0
+#define FILTER_FLOOD                1
+#define FILTER_OFFSET               2
+#define FILTER_COMPOSITE            3
+
+#define COMPOSITE_OVER              0
+#define COMPOSITE_IN                1
+#define COMPOSITE_OUT               2
+#define COMPOSITE_ATOP              3
+#define COMPOSITE_XOR               4
+#define COMPOSITE_LIGHTER           5
+#define COMPOSITE_ARITHMETIC        6
+
+varying vec3 vInput1Uv;
+varying vec3 vInput2Uv;
+flat varying vec4 vInput1UvRect;
+flat varying vec4 vInput2UvRect;
+flat varying int vFilterKind;
+flat varying int vFilterInputCount;
+flat varying int vFilterOperator;
+flat varying vec4 vFilterData;
+flat varying ivec2 vLutAddress;
+
+#ifdef WR_VERTEX_SHADER
+
+in int aFilterRenderTaskAddress;
+in int aFilterInput1TaskAddress;
+in int aFilterInput2TaskAddress;
+in int aFilterKind;
+in int aFilterInputCount;
+in int aFilterGenericInt;
+in ivec2 aFilterExtraDataAddress;
+
+struct SvgFilterTask {
+    RenderTaskCommonData common_data;
+    vec2 screen_origin;
+    vec4 data;
+};
+
+SvgFilterTask fetch_svg_filter_task(int address) {
+    RenderTaskData task_data = fetch_render_task_data(address);
+
+    SvgFilterTask task = SvgFilterTask(
+        task_data.common_data,
+        task_data.data1.xy,
+        task_data.data2
+    );
+
+    return task;
+}
+
+// Find where the given screen position is in an input task,
+// which is a picture task rasterized in screen space.
+vec3 input_uv(PictureTask input_task, vec2 screen_pos, vec2 texture_size) {
+    vec2 uv = input_task.common_data.task_rect.p0 + screen_pos - input_task.content_origin;
+    return vec3(uv / texture_size, input_task.common_data.texture_layer_index);
+}
+
+vec4 input_uv_rect(PictureTask input_task, vec2 texture_size) {
+    RectWithSize rect = input_task.common_data.task_rect;
+    return vec4(rect.p0, rect.p0 + rect.size) / texture_size.xyxy;
+}
+
+void main(void) {
+    SvgFilterTask filter_task = fetch_svg_filter_task(aFilterRenderTaskAddress);
+    RectWithSize target_rect = filter_task.common_data.task_rect;
+
+    vec2 pos = target_rect.p0 + target_rect.size * aPosition.xy;
+    vec2 screen_pos = filter_task.screen_origin + target_rect.size * aPosition.xy;
+
+    vFilterKind = aFilterKind;
+    vFilterInputCount = aFilterInputCount;
+    vFilterOperator = aFilterGenericInt;
+    vFilterData = filter_task.data;
+    vLutAddress = aFilterExtraDataAddress;
+
+    // The offset filter reads its input from before the move.
+    vec2 input_pos = screen_pos;
+    if (aFilterKind == FILTER_OFFSET) {
+        input_pos -= filter_task.data.xy;
+    }
+
+    PictureTask input_1 = fetch_picture_task(aFilterInput1TaskAddress);
+    vec2 input_1_texture_size = vec2(textureSize(sCacheRGBA8, 0).xy);
+    vInput1Uv = input_uv(input_1, input_pos, input_1_texture_size);
+    vInput1UvRect = input_uv_rect(input_1, input_1_texture_size);
+
+    if (aFilterInputCount > 1) {
+        PictureTask input_2 = fetch_picture_task(aFilterInput2TaskAddress);
+        vec2 input_2_texture_size = vec2(textureSize(sColor0, 0).xy);
+        vInput2Uv = input_uv(input_2, screen_pos, input_2_texture_size);
+        vInput2UvRect = input_uv_rect(input_2, input_2_texture_size);
+    } else {
+        vInput2Uv = vec3(0.0);
+        vInput2UvRect = vec4(0.0);
+    }
+
+    gl_Position = uTransform * vec4(pos, 0.0, 1.0);
+}
+#endif
+
+#ifdef WR_FRAGMENT_SHADER
+
+bool is_in_rect(vec2 uv, vec4 uv_rect) {
+    return all(greaterThanEqual(uv, uv_rect.xy)) &&
+           all(lessThan(uv, uv_rect.zw));
+}
+
+// Inputs are transparent outside of their task rects.
+vec4 sample_input_1() {
+    if (!is_in_rect(vInput1Uv.xy, vInput1UvRect)) {
+        return vec4(0.0);
+    }
+    return texture(sCacheRGBA8, vInput1Uv);
+}
+
+vec4 sample_input_2() {
+    if (vFilterInputCount < 2 || !is_in_rect(vInput2Uv.xy, vInput2UvRect)) {
+        return vec4(0.0);
+    }
+    return texture(sColor0, vInput2Uv);
+}
+
+vec4 component_transfer(vec4 color) {
+    // The transfer functions work on unpremultiplied values.
+    if (color.a != 0.0) {
+        color.rgb /= color.a;
+    }
+
+    // Each entry of the lookup table holds the mapped
+    // r, g, b and a values for one 8-bit input value.
+    ivec4 k = ivec4(floor(clamp(color, 0.0, 1.0) * 255.0 + 0.5));
+    color = vec4(
+        texelFetch(sResourceCache, vLutAddress + ivec2(k.r, 0), 0).r,
+        texelFetch(sResourceCache, vLutAddress + ivec2(k.g, 0), 0).g,
+        texelFetch(sResourceCache, vLutAddress + ivec2(k.b, 0), 0).b,
+        texelFetch(sResourceCache, vLutAddress + ivec2(k.a, 0), 0).a
+    );
+
+    return vec4(color.rgb * color.a, color.a);
+}
+
+// Composite the result of the filter chain (Cs) with the unfiltered
+// source (Cb). Both are premultiplied.
+vec4 composite(vec4 Cs, vec4 Cb) {
+    switch (vFilterOperator) {
+        case COMPOSITE_OVER:
+            return Cs + Cb * (1.0 - Cs.a);
+        case COMPOSITE_IN:
+            return Cs * Cb.a;
+        case COMPOSITE_OUT:
+            return Cs * (1.0 - Cb.a);
+        case COMPOSITE_ATOP:
+            return Cs * Cb.a + Cb * (1.0 - Cs.a);
+        case COMPOSITE_XOR:
+            return Cs * (1.0 - Cb.a) + Cb * (1.0 - Cs.a);
+        case COMPOSITE_LIGHTER:
+            return min(Cs + Cb, vec4(1.0));
+        case COMPOSITE_ARITHMETIC: {
+            vec4 k = vFilterData;
+            vec4 result = clamp(k.x * Cs * Cb + k.y * Cs + k.z * Cb + k.w, 0.0, 1.0);
+            return vec4(min(result.rgb, vec3(result.a)), result.a);
+        }
+        default:
+            return Cs;
+    }
+}
+
+void main(void) {
+    vec4 color;
+
+    switch (vFilterKind) {
+        case FILTER_COMPONENT_TRANSFER:
+            color = component_transfer(sample_input_1());
+            break;
+        case FILTER_FLOOD:
+            color = vFilterData;
+            break;
+        case FILTER_OFFSET:
+            color = sample_input_1();
+            break;
+        case FILTER_COMPOSITE:
+            color = composite(sample_input_1(), sample_input_2());
+            break;
+        default:
+            color = sample_input_1();
+            break;
+    }
+
+    oFragColor = color;
+}
+#endif
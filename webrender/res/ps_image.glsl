@@ -53,6 +53,14 @@ void main(void) {
     vec2 uv0 = res.uv_rect.p0;
     vec2 uv1 = res.uv_rect.p1;
 
+    // Select the part of the image to draw, if any. Step along the
+    // direction of the UV rect in case it is inverted.
+    if (image.sub_rect.x >= 0.0) {
+        vec2 dir = sign(uv1 - uv0);
+        uv1 = uv0 + dir * image.sub_rect.zw;
+        uv0 = uv0 + dir * image.sub_rect.xy;
+    }
+
     // vUv will contain how many times this image has wrapped around the image size.
     vec2 st0 = uv0 / texture_size_normalization_factor;
     vec2 st1 = uv1 / texture_size_normalization_factor;
@@ -0,0 +1,398 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use api::{Animation, AnimationDirection, AnimationEasing, AnimationIterations, ColorF};
+use api::{DynamicProperties, Keyframe, LayoutTransform, PropertyAnimation, PropertyBindingId};
+use api::{PropertyScrollLink, ScrollAxis, ScrollLink};
+use clip_scroll_tree::ClipScrollTree;
+use internal_types::FastHashMap;
+
+/// How close the x coordinate of a sampled cubic bezier has to get to the input.
+const BEZIER_EPSILON: f32 = 1.0e-5;
+
+/// The number of Newton-Raphson steps tried before falling back to bisection.
+const BEZIER_NEWTON_ITERATIONS: usize = 8;
+
+/// The properties that can be interpolated between keyframes.
+trait Interpolate: Copy {
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, other: &f32, t: f32) -> f32 {
+        *self + (*other - *self) * t
+    }
+}
+
+impl Interpolate for ColorF {
+    fn interpolate(&self, other: &ColorF, t: f32) -> ColorF {
+        ColorF::new(
+            self.r.interpolate(&other.r, t),
+            self.g.interpolate(&other.g, t),
+            self.b.interpolate(&other.b, t),
+            self.a.interpolate(&other.a, t),
+        )
+    }
+}
+
+impl Interpolate for LayoutTransform {
+    fn interpolate(&self, other: &LayoutTransform, t: f32) -> LayoutTransform {
+        let a = self.to_row_major_array();
+        let b = other.to_row_major_array();
+        let mut m = [0.0; 16];
+        for (value, (from, to)) in m.iter_mut().zip(a.iter().zip(b.iter())) {
+            *value = from.interpolate(to, t);
+        }
+
+        LayoutTransform::row_major(
+            m[0], m[1], m[2], m[3],
+            m[4], m[5], m[6], m[7],
+            m[8], m[9], m[10], m[11],
+            m[12], m[13], m[14], m[15],
+        )
+    }
+}
+
+fn cubic_bezier(p1: f32, p2: f32, t: f32) -> f32 {
+    // The curve starts at 0 and ends at 1.
+    let u = 1.0 - t;
+    3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t
+}
+
+fn cubic_bezier_derivative(p1: f32, p2: f32, t: f32) -> f32 {
+    let u = 1.0 - t;
+    3.0 * u * u * p1 + 6.0 * u * t * (p2 - p1) + 3.0 * t * t * (1.0 - p2)
+}
+
+/// Find the y coordinate of the curve at the given x coordinate.
+fn solve_cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, x: f32) -> f32 {
+    // Newton-Raphson converges quickly, unless the slope gets too flat.
+    let mut t = x;
+    for _ in 0 .. BEZIER_NEWTON_ITERATIONS {
+        let error = cubic_bezier(x1, x2, t) - x;
+        if error.abs() < BEZIER_EPSILON {
+            return cubic_bezier(y1, y2, t);
+        }
+        let slope = cubic_bezier_derivative(x1, x2, t);
+        if slope.abs() < BEZIER_EPSILON {
+            break;
+        }
+        t -= error / slope;
+    }
+
+    // x grows monotonically with t while the control points are in [0, 1].
+    let (mut lo, mut hi) = (0.0, 1.0);
+    t = x;
+    while hi - lo > BEZIER_EPSILON {
+        if cubic_bezier(x1, x2, t) < x {
+            lo = t;
+        } else {
+            hi = t;
+        }
+        t = 0.5 * (lo + hi);
+    }
+
+    cubic_bezier(y1, y2, t)
+}
+
+pub fn ease(easing: AnimationEasing, t: f32) -> f32 {
+    match easing {
+        AnimationEasing::Linear => t,
+        AnimationEasing::Ease => solve_cubic_bezier(0.25, 0.1, 0.25, 1.0, t),
+        AnimationEasing::EaseIn => solve_cubic_bezier(0.42, 0.0, 1.0, 1.0, t),
+        AnimationEasing::EaseOut => solve_cubic_bezier(0.0, 0.0, 0.58, 1.0, t),
+        AnimationEasing::EaseInOut => solve_cubic_bezier(0.42, 0.0, 0.58, 1.0, t),
+        AnimationEasing::CubicBezier(x1, y1, x2, y2) => solve_cubic_bezier(x1, y1, x2, y2, t),
+        AnimationEasing::Steps(0) => t,
+        AnimationEasing::Steps(steps) => {
+            let steps = steps as f32;
+            (t * steps).floor() / steps
+        }
+    }
+}
+
+/// The position within the keyframes of an animation at some time.
+struct Progress {
+    progress: f32,
+    finished: bool,
+}
+
+fn progress<T>(animation: &Animation<T>, elapsed_ms: f32) -> Progress {
+    let count = match animation.iterations {
+        AnimationIterations::Count(count) => Some(count),
+        AnimationIterations::Infinite => None,
+    };
+
+    let (iteration, progress, finished) = match count {
+        Some(0) => (0, 0.0, true),
+        Some(count) if animation.duration <= 0.0 ||
+            elapsed_ms >= animation.duration * count as f32 => {
+            (count - 1, 1.0, true)
+        }
+        // An infinite animation without a duration stays at its start.
+        None if animation.duration <= 0.0 => (0, 0.0, false),
+        _ => {
+            let iterations = elapsed_ms / animation.duration;
+            (iterations as u32, iterations.fract(), false)
+        }
+    };
+
+    let reverse = match animation.direction {
+        AnimationDirection::Normal => false,
+        AnimationDirection::Reverse => true,
+        AnimationDirection::Alternate => iteration % 2 == 1,
+        AnimationDirection::AlternateReverse => iteration % 2 == 0,
+    };
+
+    Progress {
+        progress: if reverse { 1.0 - progress } else { progress },
+        finished,
+    }
+}
+
+fn sample<T: Interpolate>(keyframes: &[Keyframe<T>], progress: f32) -> Option<T> {
+    let first = keyframes.first()?;
+    if progress <= first.offset {
+        return Some(first.value);
+    }
+
+    let index = keyframes
+        .iter()
+        .rposition(|keyframe| keyframe.offset <= progress)
+        .unwrap_or(0);
+    let from = &keyframes[index];
+    let to = match keyframes.get(index + 1) {
+        Some(to) => to,
+        None => return Some(from.value),
+    };
+
+    let width = to.offset - from.offset;
+    let t = if width > 0.0 {
+        (progress - from.offset) / width
+    } else {
+        1.0
+    };
+
+    Some(from.value.interpolate(&to.value, ease(from.easing, t)))
+}
+
+struct RunningAnimation {
+    animation: PropertyAnimation,
+    start_time_ns: u64,
+}
+
+/// The keyframe animations that the render backend runs for a document.
+pub struct AnimationSet {
+    animations: FastHashMap<PropertyBindingId, RunningAnimation>,
+}
+
+impl AnimationSet {
+    pub fn new() -> Self {
+        AnimationSet {
+            animations: FastHashMap::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.animations.is_empty()
+    }
+
+    pub fn start(&mut self, animation: PropertyAnimation, time_ns: u64) {
+        self.animations.insert(animation.id(), RunningAnimation {
+            animation,
+            start_time_ns: time_ns,
+        });
+    }
+
+    pub fn stop(&mut self, id: PropertyBindingId) {
+        self.animations.remove(&id);
+    }
+
+    /// Sample every animation at the given time. Finished animations are
+    /// sampled at their end one last time, then removed and returned.
+    pub fn sample(&mut self, time_ns: u64) -> (DynamicProperties, Vec<PropertyBindingId>) {
+        let mut properties = DynamicProperties {
+            transforms: Vec::new(),
+            floats: Vec::new(),
+            colors: Vec::new(),
+        };
+        let mut finished = Vec::new();
+
+        for (id, running) in &self.animations {
+            let elapsed_ms = time_ns.saturating_sub(running.start_time_ns) as f32 / 1_000_000.0;
+            let is_finished = match running.animation {
+                PropertyAnimation::Transform(ref animation) => {
+                    let p = progress(animation, elapsed_ms);
+                    if let Some(value) = sample(&animation.keyframes, p.progress) {
+                        properties.transforms.push(animation.key.with(value));
+                    }
+                    p.finished
+                }
+                PropertyAnimation::Float(ref animation) => {
+                    let p = progress(animation, elapsed_ms);
+                    if let Some(value) = sample(&animation.keyframes, p.progress) {
+                        properties.floats.push(animation.key.with(value));
+                    }
+                    p.finished
+                }
+                PropertyAnimation::Color(ref animation) => {
+                    let p = progress(animation, elapsed_ms);
+                    if let Some(value) = sample(&animation.keyframes, p.progress) {
+                        properties.colors.push(animation.key.with(value));
+                    }
+                    p.finished
+                }
+            };
+
+            if is_finished {
+                finished.push(*id);
+            }
+        }
+
+        for id in &finished {
+            self.animations.remove(id);
+        }
+
+        (properties, finished)
+    }
+}
+
+fn scroll_progress<T>(link: &ScrollLink<T>, clip_scroll_tree: &ClipScrollTree) -> Option<f32> {
+    let offset = clip_scroll_tree.external_scroll_offset(link.scroll_id)?;
+
+    // Scroll offsets move the content, so they are the negated scroll origin.
+    let origin = match link.axis {
+        ScrollAxis::Horizontal => -offset.x,
+        ScrollAxis::Vertical => -offset.y,
+    };
+
+    let range = link.end - link.start;
+    let progress = if range != 0.0 {
+        (origin - link.start) / range
+    } else if origin < link.start {
+        0.0
+    } else {
+        1.0
+    };
+
+    Some(progress.max(0.0).min(1.0))
+}
+
+/// The property bindings that the render backend drives from scroll frames.
+pub struct ScrollLinkSet {
+    links: FastHashMap<PropertyBindingId, PropertyScrollLink>,
+}
+
+impl ScrollLinkSet {
+    pub fn new() -> Self {
+        ScrollLinkSet {
+            links: FastHashMap::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    pub fn add(&mut self, link: PropertyScrollLink) {
+        self.links.insert(link.id(), link);
+    }
+
+    pub fn remove(&mut self, id: PropertyBindingId) {
+        self.links.remove(&id);
+    }
+
+    /// Evaluate every link against the current scroll offsets. Links to
+    /// scroll frames that aren't in the tree are skipped.
+    pub fn sample(&self, clip_scroll_tree: &ClipScrollTree) -> DynamicProperties {
+        let mut properties = DynamicProperties {
+            transforms: Vec::new(),
+            floats: Vec::new(),
+            colors: Vec::new(),
+        };
+
+        for link in self.links.values() {
+            match *link {
+                PropertyScrollLink::Transform(ref link) => {
+                    let value = scroll_progress(link, clip_scroll_tree)
+                        .and_then(|progress| sample(&link.keyframes, progress));
+                    if let Some(value) = value {
+                        properties.transforms.push(link.key.with(value));
+                    }
+                }
+                PropertyScrollLink::Float(ref link) => {
+                    let value = scroll_progress(link, clip_scroll_tree)
+                        .and_then(|progress| sample(&link.keyframes, progress));
+                    if let Some(value) = value {
+                        properties.floats.push(link.key.with(value));
+                    }
+                }
+                PropertyScrollLink::Color(ref link) => {
+                    let value = scroll_progress(link, clip_scroll_tree)
+                        .and_then(|progress| sample(&link.keyframes, progress));
+                    if let Some(value) = value {
+                        properties.colors.push(link.key.with(value));
+                    }
+                }
+            }
+        }
+
+        properties
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use api::{Animation, AnimationDirection, AnimationEasing, AnimationIterations, Keyframe};
+    use api::PropertyBindingKey;
+    use super::{ease, progress, sample};
+
+    fn animation(iterations: AnimationIterations, direction: AnimationDirection) -> Animation<f32> {
+        Animation {
+            key: PropertyBindingKey::new(0),
+            keyframes: vec![
+                Keyframe { offset: 0.0, value: 0.0, easing: AnimationEasing::Linear },
+                Keyframe { offset: 0.5, value: 10.0, easing: AnimationEasing::Steps(2) },
+                Keyframe { offset: 1.0, value: 20.0, easing: AnimationEasing::Linear },
+            ],
+            duration: 100.0,
+            iterations,
+            direction,
+        }
+    }
+
+    #[test]
+    fn easing_endpoints() {
+        let easings = [
+            AnimationEasing::Ease,
+            AnimationEasing::EaseIn,
+            AnimationEasing::EaseOut,
+            AnimationEasing::EaseInOut,
+            AnimationEasing::CubicBezier(0.1, 0.7, 1.0, 0.1),
+        ];
+        for &easing in &easings {
+            assert!(ease(easing, 0.0).abs() < 1.0e-3);
+            assert!((ease(easing, 1.0) - 1.0).abs() < 1.0e-3);
+        }
+        assert!((ease(AnimationEasing::EaseInOut, 0.5) - 0.5).abs() < 1.0e-3);
+        assert_eq!(ease(AnimationEasing::Steps(4), 0.6), 0.5);
+    }
+
+    #[test]
+    fn keyframe_sampling() {
+        let animation = animation(AnimationIterations::Count(2), AnimationDirection::Alternate);
+
+        let at = |elapsed_ms| {
+            let p = progress(&animation, elapsed_ms);
+            (sample(&animation.keyframes, p.progress).unwrap(), p.finished)
+        };
+
+        assert_eq!(at(25.0), (5.0, false));
+        assert_eq!(at(60.0), (10.0, false));
+        assert_eq!(at(80.0), (15.0, false));
+        // The second iteration runs backwards.
+        assert_eq!(at(125.0), (15.0, false));
+        assert_eq!(at(500.0), (0.0, true));
+    }
+}
@@ -76,6 +76,7 @@ pub enum BrushBatchKind {
     },
     YuvImage(ImageBufferKind, YuvFormat, YuvColorSpace),
     RadialGradient,
+    ConicGradient,
     LinearGradient,
 }
 
@@ -480,6 +481,21 @@ impl AlphaBatchBuilder {
             }
         };
 
+        // The input of backdrop filters draws the saved backdrop, where
+        // it was drawn on screen.
+        if let PictureKind::Image {
+            backdrop_prim_index: Some(..),
+            secondary_render_task_id: Some(backdrop_id),
+            ..
+        } = pic.kind {
+            self.add_backdrop_to_batch(
+                backdrop_id,
+                task_address,
+                content_origin,
+                render_tasks,
+            );
+        }
+
         // Even though most of the time a splitter isn't used or needed,
         // they are cheap to construct so we will always pass one down.
         let mut splitter = BspSplitter::new();
@@ -546,6 +562,60 @@ impl AlphaBatchBuilder {
         }
     }
 
+    // Add a copy of a saved backdrop to the batch list, at
+    // the same screen position.
+    fn add_backdrop_to_batch(
+        &mut self,
+        backdrop_id: RenderTaskId,
+        task_address: RenderTaskAddress,
+        content_origin: ContentOrigin,
+        render_tasks: &RenderTaskTree,
+    ) {
+        let backdrop_task = &render_tasks[backdrop_id];
+        let saved_index = backdrop_task.saved_index.expect("no saved index!?");
+        debug_assert_ne!(saved_index, SavedTargetIndex::PENDING);
+
+        let backdrop_rect = match backdrop_task.kind {
+            RenderTaskKind::Picture(ref task) => match task.content_origin {
+                ContentOrigin::Screen(origin) => {
+                    DeviceIntRect::new(origin, backdrop_task.get_dynamic_size())
+                }
+                ContentOrigin::Local(_) => panic!("bug: local-space rasterized backdrop?"),
+            },
+            _ => panic!("bug: backdrop is not a picture?"),
+        };
+        let task_relative_bounding_rect = match content_origin {
+            ContentOrigin::Screen(point) => backdrop_rect.translate(&-point.to_vector()),
+            ContentOrigin::Local(_) => panic!("bug: backdrop in a local-space rasterized picture?"),
+        };
+
+        let textures = BatchTextures {
+            colors: [
+                SourceTexture::RenderTaskCache(saved_index),
+                SourceTexture::Invalid,
+                SourceTexture::Invalid,
+            ],
+        };
+        let key = BatchKey::new(
+            BatchKind::HardwareComposite,
+            BlendMode::PremultipliedAlpha,
+            textures,
+        );
+        let batch = self.batch_list.get_suitable_batch(key, &task_relative_bounding_rect);
+        let instance = CompositePrimitiveInstance::new(
+            task_address,
+            render_tasks.get_task_address(backdrop_id),
+            RenderTaskAddress(0),
+            backdrop_rect.origin.x,
+            backdrop_rect.origin.y,
+            0,
+            backdrop_rect.size.width,
+            backdrop_rect.size.height,
+        );
+
+        batch.push(PrimitiveInstance::from(instance));
+    }
+
     // Helper to add an entire primitive run to a batch list.
     // TODO(gw): Restructure this so the param list isn't quite
     //           so daunting!
@@ -1004,7 +1074,11 @@ impl AlphaBatchBuilder {
                                 match composite_mode.expect("bug: only composites here") {
                                     PictureCompositeMode::Filter(filter) => {
                                         match filter {
-                                            FilterOp::Blur(..) => {
+                                            FilterOp::Blur(..) |
+                                            FilterOp::ComponentTransfer |
+                                            FilterOp::Flood(..) |
+                                            FilterOp::Offset(..) |
+                                            FilterOp::Composite(..) => {
                                                 let src_task_address = render_tasks.get_task_address(source_id);
                                                 let key = BatchKey::new(
                                                     BatchKind::HardwareComposite,
@@ -1110,6 +1184,13 @@ impl AlphaBatchBuilder {
                                                     FilterOp::ColorMatrix(..) => {
                                                         (10, extra_gpu_data_handle.as_int(gpu_cache))
                                                     }
+                                                    // Drawn by cs_svg_filter and composited above.
+                                                    FilterOp::ComponentTransfer |
+                                                    FilterOp::Flood(..) |
+                                                    FilterOp::Offset(..) |
+                                                    FilterOp::Composite(..) => {
+                                                        unreachable!("bug: {:?} is not drawn by brush_blend", filter)
+                                                    }
                                                 };
 
                                                 let instance = BrushInstance {
@@ -1172,11 +1253,27 @@ impl AlphaBatchBuilder {
                                         batch.push(PrimitiveInstance::from(instance));
                                     }
                                     PictureCompositeMode::Blit => {
+                                        // A saved picture (e.g. a backdrop) may be drawn in an
+                                        // earlier pass than the previous one, so read it from
+                                        // where it is saved.
+                                        let textures = match render_tasks[source_id].saved_index {
+                                            Some(saved_index) => {
+                                                debug_assert_ne!(saved_index, SavedTargetIndex::PENDING);
+                                                BatchTextures {
+                                                    colors: [
+                                                        SourceTexture::RenderTaskCache(saved_index),
+                                                        SourceTexture::Invalid,
+                                                        SourceTexture::Invalid,
+                                                    ],
+                                                }
+                                            }
+                                            None => BatchTextures::render_target_cache(),
+                                        };
                                         let src_task_address = render_tasks.get_task_address(source_id);
                                         let key = BatchKey::new(
                                             BatchKind::HardwareComposite,
                                             BlendMode::PremultipliedAlpha,
-                                            BatchTextures::render_target_cache(),
+                                            textures,
                                         );
                                         let batch = self.batch_list.get_suitable_batch(key, &task_relative_bounding_rect);
                                         let item_bounding_rect = prim_metadata.screen_rect.expect("bug!!").clipped;
@@ -1346,6 +1443,25 @@ impl BrushPrimitive {
             BrushKind::Picture => {
                 panic!("bug: get_batch_key is handled at higher level for pictures");
             }
+            BrushKind::Path { ref mask, .. } => {
+                let cache_item = &mask.cache_item;
+
+                if cache_item.texture_id == SourceTexture::Invalid {
+                    None
+                } else {
+                    // The cached coverage mask is drawn with the brush
+                    // color, like a box shadow picture.
+                    Some((
+                        BrushBatchKind::Picture(BrushImageSourceKind::Alpha),
+                        BatchTextures::color(cache_item.texture_id),
+                        [
+                            cache_item.uv_rect_handle.as_int(gpu_cache),
+                            BrushImageKind::Simple as i32,
+                            0,
+                        ],
+                    ))
+                }
+            }
             BrushKind::Solid { .. } => {
                 Some((
                     BrushBatchKind::Solid,
@@ -1372,6 +1488,18 @@ impl BrushPrimitive {
                     ],
                 ))
             }
+            BrushKind::ConicGradient { gradient_index, .. } => {
+                let stops_handle = &cached_gradients[gradient_index.0].handle;
+                Some((
+                    BrushBatchKind::ConicGradient,
+                    BatchTextures::no_texture(),
+                    [
+                        stops_handle.as_int(gpu_cache),
+                        0,
+                        0,
+                    ],
+                ))
+            }
             BrushKind::LinearGradient { gradient_index, .. } => {
                 let stops_handle = &cached_gradients[gradient_index.0].handle;
                 Some((
@@ -1482,7 +1610,9 @@ impl AlphaBatchHelpers for PrimitiveStore {
                     BrushKind::Line { .. } |
                     BrushKind::YuvImage { .. } |
                     BrushKind::RadialGradient { .. } |
+                    BrushKind::ConicGradient { .. } |
                     BrushKind::LinearGradient { .. } |
+                    BrushKind::Path { .. } |
                     BrushKind::Picture => {
                         BlendMode::PremultipliedAlpha
                     }
@@ -1590,6 +1720,8 @@ pub struct ClipBatcher {
     pub images: FastHashMap<SourceTexture, Vec<ClipMaskInstance>>,
     pub border_clears: Vec<ClipMaskInstance>,
     pub borders: Vec<ClipMaskInstance>,
+    /// Polygon draws rasterize the polygon clips.
+    pub polygons: Vec<ClipMaskInstance>,
 }
 
 impl ClipBatcher {
@@ -1599,6 +1731,7 @@ impl ClipBatcher {
             images: FastHashMap::default(),
             border_clears: Vec::new(),
             borders: Vec::new(),
+            polygons: Vec::new(),
         }
     }
 
@@ -1665,6 +1798,12 @@ impl ClipBatcher {
                             ..instance
                         });
                     }
+                    ClipSource::Polygon(..) => {
+                        self.polygons.push(ClipMaskInstance {
+                            clip_data_address: gpu_address,
+                            ..instance
+                        });
+                    }
                     ClipSource::BorderCorner(ref source) => {
                         self.border_clears.push(ClipMaskInstance {
                             clip_data_address: gpu_address,
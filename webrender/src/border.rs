@@ -3,9 +3,11 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use api::{BorderRadius, BorderSide, BorderStyle, BorderWidths, ColorF, LayerPoint};
-use api::{LayerPrimitiveInfo, LayerRect, LayerSize, NormalBorder, RepeatMode, TexelRect};
+use api::{LayerPrimitiveInfo, LayerRect, LayerSize, NormalBorder, PropertyBinding, RepeatMode};
+use api::TexelRect;
 use clip::ClipSource;
 use ellipse::Ellipse;
+use euclid::{SideOffsets2D, vec2};
 use frame_builder::FrameBuilder;
 use gpu_cache::GpuDataRequest;
 use prim_store::{BorderPrimitiveCpu, BrushClipMaskKind, BrushSegment, BrushSegmentDescriptor};
@@ -463,7 +465,7 @@ impl FrameBuilder {
                 self.add_solid_rectangle(
                     clip_and_scroll,
                     &info,
-                    border.top.color,
+                    PropertyBinding::Value(border.top.color),
                     Some(descriptor),
                 );
             }
@@ -479,7 +481,7 @@ impl FrameBuilder {
                 self.add_solid_rectangle(
                     clip_and_scroll,
                     &info,
-                    border.left.color,
+                    PropertyBinding::Value(border.left.color),
                     Some(descriptor),
                 );
             }
@@ -495,7 +497,7 @@ impl FrameBuilder {
                 self.add_solid_rectangle(
                     clip_and_scroll,
                     &info,
-                    border.right.color,
+                    PropertyBinding::Value(border.right.color),
                     Some(descriptor),
                 );
             }
@@ -513,7 +515,7 @@ impl FrameBuilder {
                 self.add_solid_rectangle(
                     clip_and_scroll,
                     &info,
-                    border.bottom.color,
+                    PropertyBinding::Value(border.bottom.color),
                     Some(descriptor),
                 );
             }
@@ -903,45 +905,323 @@ pub struct ImageBorderSegment {
 }
 
 impl ImageBorderSegment {
+    /// Lays out the tiles of one segment of a border image. `tile_size` is
+    /// the size a single copy of `sub_rect` takes up in the border, once
+    /// scaled as per the CSS border-image drawing rules. Returns None if
+    /// the segment ends up drawing nothing.
     pub fn new(
         rect: LayerRect,
         sub_rect: TexelRect,
+        tile_size: LayerSize,
         repeat_horizontal: RepeatMode,
         repeat_vertical: RepeatMode,
-    ) -> ImageBorderSegment {
-        let tile_spacing = LayerSize::zero();
-
+    ) -> Option<ImageBorderSegment> {
         debug_assert!(sub_rect.uv1.x >= sub_rect.uv0.x);
         debug_assert!(sub_rect.uv1.y >= sub_rect.uv0.y);
 
-        let image_size = LayerSize::new(
-            sub_rect.uv1.x - sub_rect.uv0.x,
-            sub_rect.uv1.y - sub_rect.uv0.y,
+        let (x0, x1, stretch_size_x, tile_spacing_x) = layout_border_tiles(
+            rect.origin.x,
+            rect.size.width,
+            tile_size.width,
+            repeat_horizontal,
+        )?;
+
+        let (y0, y1, stretch_size_y, tile_spacing_y) = layout_border_tiles(
+            rect.origin.y,
+            rect.size.height,
+            tile_size.height,
+            repeat_vertical,
+        )?;
+
+        Some(ImageBorderSegment {
+            geom_rect: LayerRect::from_floats(x0, y0, x1, y1),
+            sub_rect,
+            stretch_size: LayerSize::new(stretch_size_x, stretch_size_y),
+            tile_spacing: LayerSize::new(tile_spacing_x, tile_spacing_y),
+        })
+    }
+
+    /// Maps a point in the space of the whole border image into the local
+    /// space of each tile of this segment.
+    pub fn map_to_tile(&self, point: LayerPoint) -> LayerPoint {
+        let scale = self.tile_scale();
+        LayerPoint::new(
+            (point.x - self.sub_rect.uv0.x) * scale.width,
+            (point.y - self.sub_rect.uv0.y) * scale.height,
+        )
+    }
+
+    /// Maps a linear gradient over the whole border image into the local
+    /// space of each tile. Tiles may be scaled by a different amount in
+    /// each direction, which changes the direction the gradient runs in.
+    pub fn map_linear_gradient_to_tile(
+        &self,
+        start_point: LayerPoint,
+        end_point: LayerPoint,
+    ) -> (LayerPoint, LayerPoint) {
+        let scale = self.tile_scale();
+        let dir = end_point - start_point;
+        let dir_length_sq = dir.square_length();
+
+        let start_point = self.map_to_tile(start_point);
+        if dir_length_sq <= 0.0 {
+            return (start_point, start_point);
+        }
+
+        // The gradient offset of a point p is dot(p - start, dir) / |dir|^2.
+        // Under the scale this becomes dot(p' - start', v) with v as below,
+        // which is the offset function of a gradient with direction
+        // v / |v|^2.
+        let v = vec2(
+            dir.x / (scale.width * dir_length_sq),
+            dir.y / (scale.height * dir_length_sq),
         );
+        let end_point = start_point + v / v.square_length();
 
-        let stretch_size_x = match repeat_horizontal {
-            RepeatMode::Stretch => rect.size.width,
-            RepeatMode::Repeat => image_size.width,
-            RepeatMode::Round | RepeatMode::Space => {
-                error!("Round/Space not supported yet!");
-                rect.size.width
-            }
-        };
+        (start_point, end_point)
+    }
+
+    /// Maps a radial gradient over the whole border image into the local
+    /// space of each tile, returning the new centers, radii and ratio_xy.
+    pub fn map_radial_gradient_to_tile(
+        &self,
+        start_center: LayerPoint,
+        start_radius: f32,
+        end_center: LayerPoint,
+        end_radius: f32,
+        ratio_xy: f32,
+    ) -> (LayerPoint, f32, LayerPoint, f32, f32) {
+        let scale = self.tile_scale();
+        (
+            self.map_to_tile(start_center),
+            start_radius * scale.width,
+            self.map_to_tile(end_center),
+            end_radius * scale.width,
+            ratio_xy * scale.width / scale.height,
+        )
+    }
+
+    fn tile_scale(&self) -> LayerSize {
+        LayerSize::new(
+            self.stretch_size.width / (self.sub_rect.uv1.x - self.sub_rect.uv0.x),
+            self.stretch_size.height / (self.sub_rect.uv1.y - self.sub_rect.uv0.y),
+        )
+    }
+}
 
-        let stretch_size_y = match repeat_vertical {
-            RepeatMode::Stretch => rect.size.height,
-            RepeatMode::Repeat => image_size.height,
-            RepeatMode::Round | RepeatMode::Space => {
-                error!("Round/Space not supported yet!");
-                rect.size.height
+/// Positions the tiles of a border image segment along one axis, returning
+/// the start and end of the area covered by tiles, the size of each tile
+/// and the spacing between them.
+fn layout_border_tiles(
+    start: f32,
+    length: f32,
+    tile_size: f32,
+    repeat: RepeatMode,
+) -> Option<(f32, f32, f32, f32)> {
+    if length <= 0.0 || (repeat != RepeatMode::Stretch && tile_size <= 0.0) {
+        return None;
+    }
+
+    match repeat {
+        RepeatMode::Stretch => Some((start, start + length, length, 0.0)),
+        RepeatMode::Repeat => Some((start, start + length, tile_size, 0.0)),
+        RepeatMode::Round => {
+            // Scale the tiles so that a whole number of them fit.
+            let count = (length / tile_size).round().max(1.0);
+            Some((start, start + length, length / count, 0.0))
+        }
+        RepeatMode::Space => {
+            // Fit in as many whole tiles as we can, and distribute the
+            // leftover space evenly around them. If not even one tile
+            // fits, nothing is drawn.
+            let count = (length / tile_size).floor();
+            if count < 1.0 {
+                return None;
             }
-        };
+            let spacing = (length - count * tile_size) / (count + 1.0);
+            Some((start + spacing, start + length - spacing, tile_size, spacing))
+        }
+    }
+}
 
-        ImageBorderSegment {
-            geom_rect: rect,
-            sub_rect,
-            stretch_size: LayerSize::new(stretch_size_x, stretch_size_y),
-            tile_spacing,
+/// Slices a border image into the four corners, four edges and (if `fill`
+/// is set) the middle, and works out how each piece is drawn into the
+/// border image area `rect`: corners are scaled to the border widths, edges
+/// are scaled to the border width across them and tiled along them, and
+/// the middle is scaled to match the top and left edges and tiled in both
+/// directions.
+pub fn create_image_border_segments(
+    rect: LayerRect,
+    widths: &BorderWidths,
+    image_size: LayerSize,
+    slice: SideOffsets2D<f32>,
+    fill: bool,
+    repeat_horizontal: RepeatMode,
+    repeat_vertical: RepeatMode,
+) -> Vec<ImageBorderSegment> {
+    // Calculate the local texel coords of the slices.
+    let px0 = 0.0;
+    let px1 = slice.left;
+    let px2 = image_size.width - slice.right;
+    let px3 = image_size.width;
+
+    let py0 = 0.0;
+    let py1 = slice.top;
+    let py2 = image_size.height - slice.bottom;
+    let py3 = image_size.height;
+
+    let tl_outer = LayerPoint::new(rect.origin.x, rect.origin.y);
+    let tl_inner = tl_outer + vec2(widths.left, widths.top);
+
+    let tr_outer = LayerPoint::new(rect.origin.x + rect.size.width, rect.origin.y);
+    let tr_inner = tr_outer + vec2(-widths.right, widths.top);
+
+    let bl_outer = LayerPoint::new(rect.origin.x, rect.origin.y + rect.size.height);
+    let bl_inner = bl_outer + vec2(widths.left, -widths.bottom);
+
+    let br_outer = LayerPoint::new(
+        rect.origin.x + rect.size.width,
+        rect.origin.y + rect.size.height,
+    );
+    let br_inner = br_outer - vec2(widths.right, widths.bottom);
+
+    // The factors the edge slices are scaled by so that they are as
+    // thick as the border.
+    let scale_top = widths.top / slice.top;
+    let scale_bottom = widths.bottom / slice.bottom;
+    let scale_left = widths.left / slice.left;
+    let scale_right = widths.right / slice.right;
+
+    // The middle is scaled horizontally like the top edge and vertically
+    // like the left edge, falling back to the opposite edge if that factor
+    // is zero or infinite, and to no scaling at all if both are.
+    fn pick_scale(first: f32, second: f32) -> f32 {
+        if first.is_normal() {
+            first
+        } else if second.is_normal() {
+            second
+        } else {
+            1.0
         }
     }
+    let scale_middle_x = pick_scale(scale_top, scale_bottom);
+    let scale_middle_y = pick_scale(scale_left, scale_right);
+
+    fn add_segment(
+        segments: &mut Vec<ImageBorderSegment>,
+        rect: LayerRect,
+        uv_rect: TexelRect,
+        tile_size: LayerSize,
+        repeat_horizontal: RepeatMode,
+        repeat_vertical: RepeatMode) {
+        if uv_rect.uv1.x > uv_rect.uv0.x &&
+           uv_rect.uv1.y > uv_rect.uv0.y {
+            segments.extend(ImageBorderSegment::new(
+                rect,
+                uv_rect,
+                tile_size,
+                repeat_horizontal,
+                repeat_vertical,
+            ));
+        }
+    }
+
+    // Build the list of image segments
+    let mut segments = vec![];
+
+    // Corners are stretched to fill the rect, so their tile size is unused.
+
+    // Top left
+    let corner = LayerRect::from_floats(tl_outer.x, tl_outer.y, tl_inner.x, tl_inner.y);
+    add_segment(
+        &mut segments,
+        corner,
+        TexelRect::new(px0, py0, px1, py1),
+        corner.size,
+        RepeatMode::Stretch,
+        RepeatMode::Stretch
+    );
+    // Top right
+    let corner = LayerRect::from_floats(tr_inner.x, tr_outer.y, tr_outer.x, tr_inner.y);
+    add_segment(
+        &mut segments,
+        corner,
+        TexelRect::new(px2, py0, px3, py1),
+        corner.size,
+        RepeatMode::Stretch,
+        RepeatMode::Stretch
+    );
+    // Bottom right
+    let corner = LayerRect::from_floats(br_inner.x, br_inner.y, br_outer.x, br_outer.y);
+    add_segment(
+        &mut segments,
+        corner,
+        TexelRect::new(px2, py2, px3, py3),
+        corner.size,
+        RepeatMode::Stretch,
+        RepeatMode::Stretch
+    );
+    // Bottom left
+    let corner = LayerRect::from_floats(bl_outer.x, bl_inner.y, bl_inner.x, bl_outer.y);
+    add_segment(
+        &mut segments,
+        corner,
+        TexelRect::new(px0, py2, px1, py3),
+        corner.size,
+        RepeatMode::Stretch,
+        RepeatMode::Stretch
+    );
+
+    // Center
+    if fill {
+        add_segment(
+            &mut segments,
+            LayerRect::from_floats(tl_inner.x, tl_inner.y, tr_inner.x, bl_inner.y),
+            TexelRect::new(px1, py1, px2, py2),
+            LayerSize::new((px2 - px1) * scale_middle_x, (py2 - py1) * scale_middle_y),
+            repeat_horizontal,
+            repeat_vertical
+        );
+    }
+
+    // Add edge segments.
+
+    // Top
+    add_segment(
+        &mut segments,
+        LayerRect::from_floats(tl_inner.x, tl_outer.y, tr_inner.x, tl_inner.y),
+        TexelRect::new(px1, py0, px2, py1),
+        LayerSize::new((px2 - px1) * scale_top, widths.top),
+        repeat_horizontal,
+        RepeatMode::Stretch,
+    );
+    // Bottom
+    add_segment(
+        &mut segments,
+        LayerRect::from_floats(bl_inner.x, bl_inner.y, br_inner.x, bl_outer.y),
+        TexelRect::new(px1, py2, px2, py3),
+        LayerSize::new((px2 - px1) * scale_bottom, widths.bottom),
+        repeat_horizontal,
+        RepeatMode::Stretch,
+    );
+    // Left
+    add_segment(
+        &mut segments,
+        LayerRect::from_floats(tl_outer.x, tl_inner.y, tl_inner.x, bl_inner.y),
+        TexelRect::new(px0, py1, px1, py2),
+        LayerSize::new(widths.left, (py2 - py1) * scale_left),
+        RepeatMode::Stretch,
+        repeat_vertical,
+    );
+    // Right
+    add_segment(
+        &mut segments,
+        LayerRect::from_floats(tr_inner.x, tr_inner.y, br_outer.x, br_inner.y),
+        TexelRect::new(px2, py1, px3, py2),
+        LayerSize::new(widths.right, (py2 - py1) * scale_right),
+        RepeatMode::Stretch,
+        repeat_vertical,
+    );
+
+    segments
 }
@@ -4,7 +4,7 @@
 
 use api::{BorderRadius, BoxShadowClipMode, ClipMode, ColorF, ComplexClipRegion, LayerPoint};
 use api::{LayerPrimitiveInfo, LayerRect, LayerSize, LayerVector2D, LayoutSize, LocalClip};
-use api::PipelineId;
+use api::{PipelineId, PropertyBinding};
 use app_units::Au;
 use clip::ClipSource;
 use frame_builder::FrameBuilder;
@@ -137,9 +137,8 @@ impl FrameBuilder {
                 &fast_info,
                 clips,
                 PrimitiveContainer::Brush(
-                    BrushPrimitive::new(BrushKind::Solid {
-                            color: *color,
-                        },
+                    BrushPrimitive::new(
+                        BrushKind::new_solid(PropertyBinding::Value(*color)),
                         None,
                     )
                 ),
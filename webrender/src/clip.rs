@@ -2,15 +2,17 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{BorderRadius, ClipMode, ComplexClipRegion, DeviceIntRect, DevicePixelScale, ImageMask};
-use api::{ImageRendering, LayerRect, LayoutPoint, LayoutVector2D, LocalClip};
+use api::{BorderRadius, ClipMode, ClipPolygon, ComplexClipRegion, DeviceIntRect, DevicePixelScale};
+use api::{FillRule, ImageMask, ImageRendering, LayerPoint, LayerRect, LayoutPoint, LayoutVector2D};
+use api::LocalClip;
 use border::{BorderCornerClipSource, ensure_no_corner_overlap};
 use clip_scroll_tree::{ClipChainIndex, CoordinateSystemId};
 use ellipse::Ellipse;
 use freelist::{FreeList, FreeListHandle, WeakFreeListHandle};
 use gpu_cache::{GpuCache, GpuCacheHandle, ToGpuBlocks};
 use gpu_types::ClipScrollNodeIndex;
-use prim_store::{ClipData, ImageMaskData};
+use prim_store::{ClipData, ClipPolygonData, ImageMaskData};
+use renderer::MAX_VERTEX_TEXTURE_WIDTH;
 use resource_cache::{ImageRequest, ResourceCache};
 use util::{LayerToWorldFastTransform, MaxRect, calculate_screen_bounding_rect};
 use util::extract_inner_rect_safe;
@@ -25,6 +27,7 @@ pub struct ClipRegion {
     pub main: LayerRect,
     pub image_mask: Option<ImageMask>,
     pub complex_clips: Vec<ComplexClipRegion>,
+    pub polygon: Option<(ClipPolygon, Vec<LayoutPoint>)>,
 }
 
 impl ClipRegion {
@@ -32,6 +35,7 @@ impl ClipRegion {
         rect: LayerRect,
         mut complex_clips: Vec<ComplexClipRegion>,
         mut image_mask: Option<ImageMask>,
+        mut polygon: Option<(ClipPolygon, Vec<LayoutPoint>)>,
         reference_frame_relative_offset: &LayoutVector2D,
     ) -> ClipRegion {
         let rect = rect.translate(reference_frame_relative_offset);
@@ -44,10 +48,17 @@ impl ClipRegion {
             complex_clip.rect = complex_clip.rect.translate(reference_frame_relative_offset);
         }
 
+        if let Some((_, ref mut points)) = polygon {
+            for point in points.iter_mut() {
+                *point += *reference_frame_relative_offset;
+            }
+        }
+
         ClipRegion {
             main: rect,
             image_mask,
             complex_clips,
+            polygon,
         }
     }
 
@@ -63,6 +74,7 @@ impl ClipRegion {
             *local_clip.clip_rect(),
             complex_clips,
             None,
+            None,
             reference_frame_relative_offset
         )
     }
@@ -78,6 +90,7 @@ pub enum ClipSource {
     /// adjacent border edges. Expand to handle dotted style
     /// and different styles per edge.
     BorderCorner(BorderCornerClipSource),
+    Polygon(ClipPolygonSource),
 }
 
 impl From<ClipRegion> for ClipSources {
@@ -98,6 +111,14 @@ impl From<ClipRegion> for ClipSources {
             ));
         }
 
+        if let Some((polygon, points)) = region.polygon {
+            clips.push(ClipSource::Polygon(ClipPolygonSource::new(
+                points,
+                polygon.fill_rule,
+                polygon.mode,
+            )));
+        }
+
         ClipSources::new(clips)
     }
 }
@@ -117,6 +138,38 @@ impl ClipSource {
     }
 }
 
+/// The largest number of points a polygon clip can have, so that its
+/// GPU data (two header blocks, then two points per block) fits in a
+/// single row of the GPU cache.
+pub const MAX_POLYGON_CLIP_POINTS: usize = 2 * (MAX_VERTEX_TEXTURE_WIDTH - 2);
+
+#[derive(Debug)]
+pub struct ClipPolygonSource {
+    pub points: Vec<LayerPoint>,
+    pub bounds: LayerRect,
+    pub fill_rule: FillRule,
+    pub mode: ClipMode,
+}
+
+impl ClipPolygonSource {
+    pub fn new(mut points: Vec<LayerPoint>, fill_rule: FillRule, mode: ClipMode) -> Self {
+        if points.len() > MAX_POLYGON_CLIP_POINTS {
+            warn!("Polygon clip has {} points, only using the first {}",
+                  points.len(), MAX_POLYGON_CLIP_POINTS);
+            points.truncate(MAX_POLYGON_CLIP_POINTS);
+        }
+
+        let bounds = LayerRect::from_points(&points);
+
+        ClipPolygonSource {
+            points,
+            bounds,
+            fill_rule,
+            mode,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ClipSources {
     pub clips: Vec<(ClipSource, GpuCacheHandle)>,
@@ -186,6 +239,18 @@ impl ClipSources {
                     local_inner = local_inner
                         .and_then(|r| inner_rect.and_then(|ref inner| r.intersection(inner)));
                 }
+                ClipSource::Polygon(ref polygon) => {
+                    // As with rounded rectangles, a polygon clip-out leaves
+                    // the mask bounds unknown.
+                    if polygon.mode == ClipMode::ClipOut {
+                        can_calculate_inner_rect = false;
+                        break;
+                    }
+
+                    can_calculate_outer_rect = true;
+                    local_outer = local_outer.and_then(|r| r.intersection(&polygon.bounds));
+                    local_inner = None;
+                }
                 ClipSource::BorderCorner { .. } => {
                     can_calculate_inner_rect = false;
                     break;
@@ -229,6 +294,15 @@ impl ClipSources {
                     ClipSource::BorderCorner(ref mut source) => {
                         source.write(request);
                     }
+                    ClipSource::Polygon(ref polygon) => {
+                        let data = ClipPolygonData {
+                            bounds: polygon.bounds,
+                            points: &polygon.points,
+                            fill_rule: polygon.fill_rule,
+                            mode: polygon.mode,
+                        };
+                        data.write(&mut request);
+                    }
                 }
             }
 
@@ -353,6 +427,32 @@ pub fn rounded_rectangle_contains_point(point: &LayoutPoint,
     true
 }
 
+/// Whether a point is inside a polygon, using the winding number of the
+/// polygon around the point and the given fill rule.
+pub fn polygon_contains_point(point: &LayoutPoint,
+                              points: &[LayoutPoint],
+                              fill_rule: FillRule)
+                              -> bool {
+    let mut winding = 0;
+
+    for (i, a) in points.iter().enumerate() {
+        let b = &points[(i + 1) % points.len()];
+        let side = (b.x - a.x) * (point.y - a.y) - (point.x - a.x) * (b.y - a.y);
+        if a.y <= point.y {
+            if b.y > point.y && side > 0.0 {
+                winding += 1;
+            }
+        } else if b.y <= point.y && side < 0.0 {
+            winding -= 1;
+        }
+    }
+
+    match fill_rule {
+        FillRule::Nonzero => winding != 0,
+        FillRule::Evenodd => winding % 2 != 0,
+    }
+}
+
 pub type ClipChainNodeRef = Option<Arc<ClipChainNode>>;
 
 #[derive(Debug, Clone)]
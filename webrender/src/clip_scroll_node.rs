@@ -2,10 +2,13 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{DevicePixelScale, ExternalScrollId, LayerPixel, LayerPoint, LayerRect, LayerSize};
-use api::{LayerVector2D, LayoutTransform, LayoutVector2D, PipelineId, PropertyBinding};
-use api::{ScrollClamping, ScrollEventPhase, ScrollLocation, ScrollSensitivity, StickyOffsetBounds};
-use api::WorldPoint;
+use animation::ease;
+use api::{AnimationEasing, DevicePixelScale, ExternalScrollId, LayerPixel, LayerPoint, LayerRect};
+use api::{LayerSize, LayerVector2D, LayoutRect, LayoutTransform, LayoutVector2D};
+use api::{OverscrollBehavior, PipelineId, PropertyBinding, ScrollClamping, ScrollEventPhase};
+use api::{ScrollLocation, ScrollSensitivity};
+use api::{ScrollSnapAlignment, ScrollSnapArea, ScrollSnapStrictness, ScrollSnapType};
+use api::{StickyOffsetBounds, WorldPoint};
 use clip::{ClipChain, ClipSourcesHandle, ClipStore, ClipWorkItem};
 use clip_scroll_tree::{ClipChainIndex, ClipScrollNodeIndex, CoordinateSystemId};
 use clip_scroll_tree::TransformUpdateState;
@@ -16,6 +19,8 @@ use gpu_types::{ClipScrollNodeIndex as GPUClipScrollNodeIndex, ClipScrollNodeDat
 use resource_cache::ResourceCache;
 use scene::SceneProperties;
 use spring::{DAMPING, STIFFNESS, Spring};
+use std::{f32, mem};
+use std::cmp::Ordering;
 use util::{LayerToWorldFastTransform, LayerFastTransform, LayoutFastTransform};
 use util::{TransformedRectKind};
 
@@ -25,6 +30,32 @@ const CAN_OVERSCROLL: bool = true;
 #[cfg(not(target_os = "macos"))]
 const CAN_OVERSCROLL: bool = false;
 
+/// The fraction of its velocity that a fling loses every millisecond, by default.
+pub const DEFAULT_FLING_FRICTION: f32 = 0.002;
+
+/// The speed under which a fling stops, in pixels per millisecond.
+const FLING_MIN_VELOCITY: f32 = 0.02;
+
+/// How long a scroll gesture can pause before it ends for it to still start a fling.
+const FLING_MAX_PAUSE_MS: f32 = 100.0;
+
+/// How much of the velocity of a scroll gesture comes from its latest event.
+const VELOCITY_SMOOTHING: f32 = 0.5;
+
+/// The distance of a line scroll, unless the scroll frame is too small for it.
+const LINE_SCROLL_DISTANCE: f32 = 40.0;
+
+/// How much of the size of a scroll frame a page scroll covers, which leaves some
+/// of the previous page in view.
+const PAGE_SCROLL_PROPORTION: f32 = 0.875;
+
+/// How long the smooth scroll of a line or page scroll takes, in milliseconds.
+const KEYBOARD_SCROLL_DURATION: f32 = 150.0;
+
+/// How far from where scrolling ends a snap destination can be for a scroll frame
+/// with proximity snapping to snap to it.
+const SNAP_PROXIMITY_THRESHOLD: f32 = 200.0;
+
 #[derive(Debug)]
 pub struct StickyFrameInfo {
     pub margins: SideOffsets2D<Option<f32>>,
@@ -166,14 +197,20 @@ impl ClipScrollNode {
         frame_rect: &LayerRect,
         content_size: &LayerSize,
         scroll_sensitivity: ScrollSensitivity,
+        overscroll_behavior: OverscrollBehavior,
+        snap_info: ScrollSnapInfo,
+        fling_friction: f32,
     ) -> Self {
         let node_type = NodeType::ScrollFrame(ScrollFrameInfo::new(
             scroll_sensitivity,
+            overscroll_behavior,
             LayerSize::new(
                 (content_size.width - frame_rect.size.width).max(0.0),
                 (content_size.height - frame_rect.size.height).max(0.0)
             ),
             external_id,
+            snap_info,
+            fling_friction,
         ));
 
         Self::new(pipeline_id, Some(parent_index), frame_rect, node_type)
@@ -220,9 +257,18 @@ impl ClipScrollNode {
             NodeType::ScrollFrame(ref mut scrolling) => {
                 let scroll_sensitivity = scrolling.scroll_sensitivity;
                 let scrollable_size = scrolling.scrollable_size;
-                *scrolling = *old_scrolling_state;
+                let fling_friction = scrolling.fling_friction;
+                let overscroll_behavior = scrolling.overscroll_behavior;
+                let snap_info = mem::replace(&mut scrolling.snap_info, ScrollSnapInfo::none());
+                *scrolling = old_scrolling_state.clone();
                 scrolling.scroll_sensitivity = scroll_sensitivity;
                 scrolling.scrollable_size = scrollable_size;
+                scrolling.fling_friction = fling_friction;
+                scrolling.overscroll_behavior = overscroll_behavior;
+                scrolling.snap_info = snap_info;
+
+                let (stiffness, damping) = spring_coefficients(overscroll_behavior);
+                scrolling.spring.set_coefficients(stiffness, damping);
             }
             _ if old_scrolling_state.offset != LayerVector2D::zero() => {
                 warn!("Tried to scroll a non-scroll node.")
@@ -232,10 +278,6 @@ impl ClipScrollNode {
     }
 
     pub fn set_scroll_origin(&mut self, origin: &LayerPoint, clamp: ScrollClamping) -> bool {
-        let scrollable_size = self.scrollable_size();
-        let scrollable_width = scrollable_size.width;
-        let scrollable_height = scrollable_size.height;
-
         let scrolling = match self.node_type {
             NodeType::ScrollFrame(ref mut scrolling) => scrolling,
             _ => {
@@ -244,19 +286,12 @@ impl ClipScrollNode {
             }
         };
 
-        let new_offset = match clamp {
-            ScrollClamping::ToContentBounds => {
-                if scrollable_height <= 0. && scrollable_width <= 0. {
-                    return false;
-                }
+        scrolling.interrupt_smooth_scroll();
+        scrolling.flinging = false;
 
-                let origin = LayerPoint::new(origin.x.max(0.0), origin.y.max(0.0));
-                LayerVector2D::new(
-                    (-origin.x).max(-scrollable_width).min(0.0).round(),
-                    (-origin.y).max(-scrollable_height).min(0.0).round(),
-                )
-            }
-            ScrollClamping::NoClamping => LayerPoint::zero() - *origin,
+        let new_offset = match scrolling.offset_for_origin(origin, clamp) {
+            Some(offset) => offset,
+            None => return false,
         };
 
         if new_offset == scrolling.offset {
@@ -269,6 +304,42 @@ impl ClipScrollNode {
         true
     }
 
+    /// Start animating the scroll offset towards the given origin. Returns false
+    /// if the node can't be scrolled there.
+    pub fn start_smooth_scroll(
+        &mut self,
+        origin: &LayerPoint,
+        clamp: ScrollClamping,
+        duration: f32,
+        easing: AnimationEasing,
+        time_ns: u64,
+    ) -> bool {
+        let scrolling = match self.node_type {
+            NodeType::ScrollFrame(ref mut scrolling) => scrolling,
+            _ => {
+                warn!("Tried to scroll a non-scroll node.");
+                return false;
+            }
+        };
+
+        let end_offset = match scrolling.offset_for_origin(origin, clamp) {
+            Some(offset) => offset,
+            None => return false,
+        };
+
+        scrolling.start_smooth_scroll(end_offset, duration, easing, time_ns);
+        true
+    }
+
+    /// Advance the smooth scroll of this node, if it has one. Returns whether the
+    /// smooth scroll was interrupted, once it has ended.
+    pub fn tick_smooth_scroll(&mut self, time_ns: u64) -> Option<bool> {
+        match self.node_type {
+            NodeType::ScrollFrame(ref mut scrolling) => scrolling.tick_smooth_scroll(time_ns),
+            _ => None,
+        }
+    }
+
     pub fn mark_uninvertible(&mut self) {
         self.invertible = false;
         self.world_content_transform = LayerToWorldFastTransform::identity();
@@ -637,18 +708,46 @@ impl ClipScrollNode {
 
     pub fn scrollable_size(&self) -> LayerSize {
         match self.node_type {
-           NodeType:: ScrollFrame(state) => state.scrollable_size,
+           NodeType:: ScrollFrame(ref state) => state.scrollable_size,
             _ => LayerSize::zero(),
         }
     }
 
 
-    pub fn scroll(&mut self, scroll_location: ScrollLocation, phase: ScrollEventPhase) -> bool {
+    pub fn scroll(
+        &mut self,
+        scroll_location: ScrollLocation,
+        phase: ScrollEventPhase,
+        time_ns: u64,
+    ) -> bool {
+        let viewport_size = self.local_viewport_rect.size;
         let scrolling = match self.node_type {
             NodeType::ScrollFrame(ref mut scrolling) => scrolling,
             _ => return false,
         };
 
+        let page = LayerVector2D::new(
+            viewport_size.width * PAGE_SCROLL_PROPORTION,
+            viewport_size.height * PAGE_SCROLL_PROPORTION,
+        );
+        let distance = match scroll_location {
+            ScrollLocation::Lines(lines) => Some(LayerVector2D::new(
+                lines.x * LINE_SCROLL_DISTANCE.min(page.x),
+                lines.y * LINE_SCROLL_DISTANCE.min(page.y),
+            )),
+            ScrollLocation::Pages(pages) => {
+                Some(LayerVector2D::new(pages.x * page.x, pages.y * page.y))
+            }
+            _ => None,
+        };
+        if let Some(distance) = distance {
+            return scrolling.smooth_scroll_by(distance, time_ns);
+        }
+
+        // Scrolling by the user takes over from a smooth scroll or a fling.
+        scrolling.interrupt_smooth_scroll();
+        scrolling.flinging = false;
+
         if scrolling.started_bouncing_back && phase == ScrollEventPhase::Move(false) {
             return false;
         }
@@ -674,10 +773,14 @@ impl ClipScrollNode {
                 scrolling.offset.y = end_pos;
                 return true;
             }
+            ScrollLocation::Lines(..) | ScrollLocation::Pages(..) => unreachable!(),
         };
 
+        scrolling.track_velocity(delta, phase, time_ns);
+
+        let can_overscroll = scrolling.can_overscroll();
         let overscroll_amount = scrolling.overscroll_amount();
-        let overscrolling = CAN_OVERSCROLL && (overscroll_amount != LayerVector2D::zero());
+        let overscrolling = can_overscroll && (overscroll_amount != LayerVector2D::zero());
         if overscrolling {
             if overscroll_amount.x != 0.0 {
                 delta.x /= overscroll_amount.x.abs()
@@ -694,20 +797,25 @@ impl ClipScrollNode {
 
         if scrollable_width > 0. {
             scrolling.offset.x = scrolling.offset.x + delta.x;
-            if is_unscrollable || !CAN_OVERSCROLL {
+            if is_unscrollable || !can_overscroll {
                 scrolling.offset.x = scrolling.offset.x.min(0.0).max(-scrollable_width).round();
             }
         }
 
         if scrollable_height > 0. {
             scrolling.offset.y = scrolling.offset.y + delta.y;
-            if is_unscrollable || !CAN_OVERSCROLL {
+            if is_unscrollable || !can_overscroll {
                 scrolling.offset.y = scrolling.offset.y.min(0.0).max(-scrollable_height).round();
             }
         }
 
+        if can_overscroll {
+            scrolling.limit_overscroll();
+        }
+
         if phase == ScrollEventPhase::Start || phase == ScrollEventPhase::Move(true) {
-            scrolling.started_bouncing_back = false
+            scrolling.started_bouncing_back = false;
+            scrolling.bouncing_back = false;
         } else if overscrolling &&
             ((delta.x < 1.0 && delta.y < 1.0) || phase == ScrollEventPhase::End)
         {
@@ -715,13 +823,29 @@ impl ClipScrollNode {
             scrolling.bouncing_back = true
         }
 
-        if CAN_OVERSCROLL {
+        if can_overscroll {
             scrolling.stretch_overscroll_spring(overscroll_amount);
         }
 
+        if phase == ScrollEventPhase::End && !scrolling.started_bouncing_back {
+            scrolling.start_fling(time_ns);
+            // A fling snaps once it comes to rest instead.
+            if !scrolling.flinging {
+                scrolling.snap();
+            }
+        }
+
         scrolling.offset != original_layer_scroll_offset || scrolling.started_bouncing_back
     }
 
+    /// Advance the fling of this node, if it has one. Returns true if the node moved.
+    pub fn tick_fling(&mut self, time_ns: u64) -> bool {
+        match self.node_type {
+            NodeType::ScrollFrame(ref mut scrolling) => scrolling.tick_fling(time_ns),
+            _ => false,
+        }
+    }
+
     pub fn tick_scrolling_bounce_animation(&mut self) {
         if let NodeType::ScrollFrame(ref mut scrolling) = self.node_type {
             scrolling.tick_scrolling_bounce_animation();
@@ -767,13 +891,136 @@ impl ClipScrollNode {
 
     pub fn matches_external_id(&self, external_id: ExternalScrollId) -> bool {
         match self.node_type {
-            NodeType::ScrollFrame(info) if info.external_id == Some(external_id) => true,
+            NodeType::ScrollFrame(ref info) if info.external_id == Some(external_id) => true,
             _ => false,
         }
     }
 }
 
+/// An animated scroll towards a destination offset, started through the API.
 #[derive(Copy, Clone, Debug)]
+pub struct SmoothScroll {
+    start_offset: LayerVector2D,
+    end_offset: LayerVector2D,
+    start_time_ns: u64,
+    /// The duration of the scroll, in milliseconds.
+    duration: f32,
+    easing: AnimationEasing,
+    /// Whether a scroll by the user or a jump through the API took over. Interrupted
+    /// smooth scrolls are kept until the next tick, to report that they ended.
+    interrupted: bool,
+}
+
+/// The scroll origins that a scroll frame can snap to, resolved from the snap
+/// areas of its display item.
+#[derive(Clone, Debug)]
+pub struct ScrollSnapInfo {
+    pub snap_type: ScrollSnapType,
+    pub origins_x: Vec<f32>,
+    pub origins_y: Vec<f32>,
+}
+
+impl ScrollSnapInfo {
+    pub fn none() -> Self {
+        ScrollSnapInfo {
+            snap_type: ScrollSnapType::none(),
+            origins_x: Vec::new(),
+            origins_y: Vec::new(),
+        }
+    }
+
+    pub fn new(
+        snap_type: ScrollSnapType,
+        snap_areas: &[ScrollSnapArea],
+        content_rect: &LayoutRect,
+        frame_size: &LayerSize,
+    ) -> Self {
+        let scrollable_width = (content_rect.size.width - frame_size.width).max(0.0);
+        let scrollable_height = (content_rect.size.height - frame_size.height).max(0.0);
+
+        let mut info = ScrollSnapInfo {
+            snap_type,
+            origins_x: Vec::new(),
+            origins_y: Vec::new(),
+        };
+
+        for area in snap_areas {
+            if let Some(origin) = snap_origin(
+                area.align_x,
+                area.rect.min_x() - content_rect.min_x(),
+                area.rect.size.width,
+                frame_size.width,
+            ) {
+                info.origins_x.push(origin.max(0.0).min(scrollable_width));
+            }
+            if let Some(origin) = snap_origin(
+                area.align_y,
+                area.rect.min_y() - content_rect.min_y(),
+                area.rect.size.height,
+                frame_size.height,
+            ) {
+                info.origins_y.push(origin.max(0.0).min(scrollable_height));
+            }
+        }
+
+        info
+    }
+
+    /// The scroll origin to snap to from the given one, if any. Each axis snaps
+    /// on its own, so an axis without a snap destination keeps its origin.
+    fn destination(&self, origin: &LayerPoint) -> Option<LayerPoint> {
+        let x = snap_destination(self.snap_type.x, &self.origins_x, origin.x);
+        let y = snap_destination(self.snap_type.y, &self.origins_y, origin.y);
+        if x.is_none() && y.is_none() {
+            return None;
+        }
+
+        Some(LayerPoint::new(x.unwrap_or(origin.x), y.unwrap_or(origin.y)))
+    }
+}
+
+/// The stiffness and damping of the spring that a scroll frame bounces back with.
+fn spring_coefficients(overscroll_behavior: OverscrollBehavior) -> (f32, f32) {
+    match overscroll_behavior {
+        OverscrollBehavior::Elastic(elasticity) => (elasticity.stiffness, elasticity.damping),
+        _ => (STIFFNESS, DAMPING),
+    }
+}
+
+/// The scroll origin along an axis that aligns a snap area, which starts at `start`
+/// within the content, with the scroll frame.
+fn snap_origin(
+    alignment: ScrollSnapAlignment,
+    start: f32,
+    length: f32,
+    frame_length: f32,
+) -> Option<f32> {
+    match alignment {
+        ScrollSnapAlignment::None => None,
+        ScrollSnapAlignment::Start => Some(start),
+        ScrollSnapAlignment::Center => Some(start + (length - frame_length) / 2.0),
+        ScrollSnapAlignment::End => Some(start + length - frame_length),
+    }
+}
+
+fn snap_destination(strictness: ScrollSnapStrictness, origins: &[f32], origin: f32) -> Option<f32> {
+    if strictness == ScrollSnapStrictness::None {
+        return None;
+    }
+
+    let nearest = origins.iter().cloned().min_by(|a, b| {
+        (a - origin).abs().partial_cmp(&(b - origin).abs()).unwrap_or(Ordering::Equal)
+    })?;
+
+    match strictness {
+        ScrollSnapStrictness::Proximity if (nearest - origin).abs() > SNAP_PROXIMITY_THRESHOLD => {
+            None
+        }
+        _ => Some(nearest),
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct ScrollFrameInfo {
     pub offset: LayerVector2D,
     pub spring: Spring,
@@ -781,6 +1028,7 @@ pub struct ScrollFrameInfo {
     pub bouncing_back: bool,
     pub should_handoff_scroll: bool,
     pub scroll_sensitivity: ScrollSensitivity,
+    pub overscroll_behavior: OverscrollBehavior,
 
     /// Amount that this ScrollFrame can scroll in both directions.
     pub scrollable_size: LayerSize,
@@ -790,25 +1038,309 @@ pub struct ScrollFrameInfo {
     /// which may change between frames.
     pub external_id: Option<ExternalScrollId>,
 
+    pub smooth_scroll: Option<SmoothScroll>,
+
+    /// The velocity of the current scroll gesture, or of the fling that followed
+    /// it, in pixels per millisecond.
+    pub velocity: LayerVector2D,
+
+    /// The time of the last scroll event of the current gesture, or of the last
+    /// tick of the fling that followed it.
+    pub last_scroll_time_ns: u64,
+
+    /// Whether the frame is decelerating after a scroll gesture ended.
+    pub flinging: bool,
+
+    /// The fraction of its velocity that a fling loses every millisecond.
+    pub fling_friction: f32,
+
+    pub snap_info: ScrollSnapInfo,
 }
 
 /// Manages scrolling offset, overscroll state, etc.
 impl ScrollFrameInfo {
     pub fn new(
         scroll_sensitivity: ScrollSensitivity,
+        overscroll_behavior: OverscrollBehavior,
         scrollable_size: LayerSize,
         external_id: Option<ExternalScrollId>,
+        snap_info: ScrollSnapInfo,
+        fling_friction: f32,
     ) -> ScrollFrameInfo {
+        let (stiffness, damping) = spring_coefficients(overscroll_behavior);
         ScrollFrameInfo {
             offset: LayerVector2D::zero(),
-            spring: Spring::at(LayerPoint::zero(), STIFFNESS, DAMPING),
+            spring: Spring::at(LayerPoint::zero(), stiffness, damping),
             started_bouncing_back: false,
             bouncing_back: false,
             should_handoff_scroll: false,
             scroll_sensitivity,
+            overscroll_behavior,
             scrollable_size,
             external_id,
+            smooth_scroll: None,
+            velocity: LayerVector2D::zero(),
+            last_scroll_time_ns: 0,
+            flinging: false,
+            fling_friction,
+            snap_info,
+        }
+    }
+
+    pub fn can_overscroll(&self) -> bool {
+        match self.overscroll_behavior {
+            OverscrollBehavior::Auto => CAN_OVERSCROLL,
+            OverscrollBehavior::None | OverscrollBehavior::Clamp => false,
+            OverscrollBehavior::Elastic(_) => true,
+        }
+    }
+
+    /// Whether a scroll gesture that starts while this frame is overscrolled can
+    /// continue on the root scroll frame.
+    pub fn can_handoff_scroll(&self) -> bool {
+        self.overscroll_behavior != OverscrollBehavior::None
+    }
+
+    /// Keep the frame from being stretched further past its bounds than it allows.
+    fn limit_overscroll(&mut self) {
+        let max_stretch = match self.overscroll_behavior {
+            OverscrollBehavior::Elastic(elasticity) => elasticity.max_stretch,
+            _ => f32::INFINITY,
+        };
+
+        self.offset.x = self.offset.x
+            .min(max_stretch)
+            .max(-self.scrollable_size.width - max_stretch);
+        self.offset.y = self.offset.y
+            .min(max_stretch)
+            .max(-self.scrollable_size.height - max_stretch);
+    }
+
+    /// The offset that scrolls this frame to the given origin, or None if the
+    /// frame can't be scrolled.
+    fn offset_for_origin(
+        &self,
+        origin: &LayerPoint,
+        clamp: ScrollClamping,
+    ) -> Option<LayerVector2D> {
+        let scrollable_width = self.scrollable_size.width;
+        let scrollable_height = self.scrollable_size.height;
+
+        match clamp {
+            ScrollClamping::ToContentBounds => {
+                if scrollable_height <= 0. && scrollable_width <= 0. {
+                    return None;
+                }
+
+                let origin = LayerPoint::new(origin.x.max(0.0), origin.y.max(0.0));
+                Some(LayerVector2D::new(
+                    (-origin.x).max(-scrollable_width).min(0.0).round(),
+                    (-origin.y).max(-scrollable_height).min(0.0).round(),
+                ))
+            }
+            ScrollClamping::NoClamping => Some(LayerPoint::zero() - *origin),
+        }
+    }
+
+    fn start_smooth_scroll(
+        &mut self,
+        end_offset: LayerVector2D,
+        duration: f32,
+        easing: AnimationEasing,
+        time_ns: u64,
+    ) {
+        self.flinging = false;
+        self.smooth_scroll = Some(SmoothScroll {
+            start_offset: self.offset,
+            end_offset,
+            start_time_ns: time_ns,
+            duration,
+            easing,
+            interrupted: false,
+        });
+        self.bouncing_back = false;
+        self.started_bouncing_back = false;
+    }
+
+    /// Smoothly scroll the origin by the given distance, clamped to the content
+    /// bounds. The distance is added to the destination of the current smooth
+    /// scroll, if there is one, so that repeated key presses add up.
+    fn smooth_scroll_by(&mut self, distance: LayerVector2D, time_ns: u64) -> bool {
+        let start_offset = match self.smooth_scroll {
+            Some(ref smooth_scroll) if !smooth_scroll.interrupted => smooth_scroll.end_offset,
+            _ => self.offset,
+        };
+
+        let origin = LayerPoint::zero() - start_offset + distance;
+        let end_offset = match self.offset_for_origin(&origin, ScrollClamping::ToContentBounds) {
+            Some(offset) => offset,
+            None => return false,
+        };
+        if end_offset == self.offset {
+            return false;
+        }
+
+        self.start_smooth_scroll(
+            end_offset,
+            KEYBOARD_SCROLL_DURATION,
+            AnimationEasing::EaseOut,
+            time_ns,
+        );
+        true
+    }
+
+    pub fn is_smooth_scrolling(&self) -> bool {
+        self.smooth_scroll.map_or(false, |smooth_scroll| !smooth_scroll.interrupted)
+    }
+
+    fn interrupt_smooth_scroll(&mut self) {
+        if let Some(ref mut smooth_scroll) = self.smooth_scroll {
+            smooth_scroll.interrupted = true;
+        }
+    }
+
+    fn tick_smooth_scroll(&mut self, time_ns: u64) -> Option<bool> {
+        let smooth_scroll = self.smooth_scroll?;
+        if smooth_scroll.interrupted {
+            self.smooth_scroll = None;
+            return Some(true);
+        }
+
+        let elapsed_ms = time_ns.saturating_sub(smooth_scroll.start_time_ns) as f32 / 1_000_000.0;
+        let progress = if smooth_scroll.duration > 0.0 {
+            (elapsed_ms / smooth_scroll.duration).min(1.0)
+        } else {
+            1.0
+        };
+
+        let t = ease(smooth_scroll.easing, progress);
+        let distance = smooth_scroll.end_offset - smooth_scroll.start_offset;
+        self.offset = smooth_scroll.start_offset + distance * t;
+
+        if progress < 1.0 {
+            return None;
+        }
+
+        // Land exactly on the destination, whatever the easing.
+        self.offset = smooth_scroll.end_offset;
+        self.smooth_scroll = None;
+        Some(false)
+    }
+
+    fn track_velocity(&mut self, delta: LayerVector2D, phase: ScrollEventPhase, time_ns: u64) {
+        match phase {
+            ScrollEventPhase::Start => {
+                self.velocity = LayerVector2D::zero();
+                self.last_scroll_time_ns = time_ns;
+            }
+            // Only track the fingers of the user, not flings simulated by the client.
+            ScrollEventPhase::Move(true) => {
+                let elapsed_ms =
+                    time_ns.saturating_sub(self.last_scroll_time_ns) as f32 / 1_000_000.0;
+                if elapsed_ms > 0.0 {
+                    let velocity = delta / elapsed_ms;
+                    self.velocity = self.velocity * (1.0 - VELOCITY_SMOOTHING) +
+                        velocity * VELOCITY_SMOOTHING;
+                }
+                self.last_scroll_time_ns = time_ns;
+            }
+            ScrollEventPhase::Move(false) |
+            ScrollEventPhase::End => {}
+        }
+    }
+
+    fn start_fling(&mut self, time_ns: u64) {
+        let paused_ms = time_ns.saturating_sub(self.last_scroll_time_ns) as f32 / 1_000_000.0;
+        if paused_ms > FLING_MAX_PAUSE_MS {
+            return;
+        }
+
+        // Don't fling along an axis that can't be scrolled.
+        if self.scrollable_size.width <= 0.0 {
+            self.velocity.x = 0.0;
+        }
+        if self.scrollable_size.height <= 0.0 {
+            self.velocity.y = 0.0;
+        }
+
+        if self.velocity.length() < FLING_MIN_VELOCITY {
+            return;
+        }
+
+        self.flinging = true;
+        self.last_scroll_time_ns = time_ns;
+    }
+
+    fn tick_fling(&mut self, time_ns: u64) -> bool {
+        if !self.flinging {
+            return false;
+        }
+
+        let elapsed_ms = time_ns.saturating_sub(self.last_scroll_time_ns) as f32 / 1_000_000.0;
+        self.last_scroll_time_ns = time_ns;
+
+        // The velocity decays exponentially, so the distance covered during this
+        // tick is the integral of `velocity * decay^t` over the elapsed time.
+        let decay = 1.0 - self.fling_friction.max(0.0).min(1.0);
+        let factor = decay.powf(elapsed_ms);
+        let distance = if decay <= 0.0 {
+            LayerVector2D::zero()
+        } else if decay >= 1.0 {
+            self.velocity * elapsed_ms
+        } else {
+            self.velocity * ((factor - 1.0) / decay.ln())
+        };
+
+        self.offset += distance;
+        self.velocity = self.velocity * factor;
+
+        let can_overscroll = self.can_overscroll();
+        if can_overscroll {
+            self.limit_overscroll();
+        }
+
+        let overscroll_amount = self.overscroll_amount();
+        if overscroll_amount != LayerVector2D::zero() {
+            // Hand off to the overscroll spring at the content bounds.
+            self.flinging = false;
+            self.velocity = LayerVector2D::zero();
+            if can_overscroll {
+                self.stretch_overscroll_spring(overscroll_amount);
+                self.started_bouncing_back = true;
+                self.bouncing_back = true;
+            } else {
+                self.offset += overscroll_amount;
+            }
+        } else if self.velocity.length() < FLING_MIN_VELOCITY {
+            self.flinging = false;
+        }
+
+        if !self.flinging && !self.bouncing_back {
+            self.snap();
+        }
+
+        distance != LayerVector2D::zero()
+    }
+
+    /// Start moving to the snap destination closest to the current scroll origin,
+    /// if there is one. The spring that bounces back from overscrolling is reused
+    /// for this, so the move is driven by `TickScrollingBounce` too.
+    fn snap(&mut self) {
+        let origin = LayerPoint::zero() - self.offset;
+        let destination = match self.snap_info.destination(&origin) {
+            Some(destination) => destination,
+            None => return,
+        };
+
+        let current = self.offset.to_point();
+        let target = LayerPoint::new(-destination.x, -destination.y);
+        if current == target {
+            return;
         }
+
+        self.spring.coords(current, current, target);
+        self.started_bouncing_back = true;
+        self.bouncing_back = true;
     }
 
     pub fn sensitive_to_input_events(&self) -> bool {
@@ -825,6 +1357,10 @@ impl ScrollFrameInfo {
     }
 
     pub fn tick_scrolling_bounce_animation(&mut self) {
+        if !self.bouncing_back {
+            return;
+        }
+
         let finished = self.spring.animate();
         self.offset = self.spring.current().to_vector();
         if finished {
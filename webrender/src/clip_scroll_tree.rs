@@ -2,7 +2,8 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{DeviceIntRect, DevicePixelScale, ExternalScrollId, LayerPoint, LayerRect, LayerVector2D};
+use api::{AnimationEasing, DeviceIntRect, DevicePixelScale, ExternalScrollId, LayerPoint};
+use api::{LayerRect, LayerVector2D};
 use api::{PipelineId, ScrollClamping, ScrollEventPhase, ScrollLocation, ScrollNodeState};
 use api::WorldPoint;
 use clip::{ClipChain, ClipSourcesHandle, ClipStore};
@@ -164,7 +165,7 @@ impl ClipScrollTree {
         }
 
         match node.node_type {
-            NodeType::ScrollFrame(state) if state.sensitive_to_input_events() => {}
+            NodeType::ScrollFrame(ref state) if state.sensitive_to_input_events() => {}
             _ => return None,
         }
 
@@ -183,15 +184,28 @@ impl ClipScrollTree {
     pub fn get_scroll_node_state(&self) -> Vec<ScrollNodeState> {
         let mut result = vec![];
         for node in &self.nodes {
-            if let NodeType::ScrollFrame(info) = node.node_type {
+            if let NodeType::ScrollFrame(ref info) = node.node_type {
                 if let Some(id) = info.external_id {
-                    result.push(ScrollNodeState { id, scroll_offset: info.offset })
+                    result.push(ScrollNodeState {
+                        id,
+                        scroll_offset: info.offset,
+                        is_smooth_scrolling: info.is_smooth_scrolling(),
+                        is_bouncing_back: info.bouncing_back,
+                    })
                 }
             }
         }
         result
     }
 
+    /// The scroll offset of the scroll frame with the given external id, if it's in the tree.
+    pub fn external_scroll_offset(&self, id: ExternalScrollId) -> Option<LayerVector2D> {
+        self.nodes
+            .iter()
+            .find(|node| node.matches_external_id(id))
+            .map(|node| node.scroll_offset())
+    }
+
     pub fn drain(&mut self) -> ScrollStates {
         self.current_new_node_item = 1;
 
@@ -201,11 +215,10 @@ impl ClipScrollTree {
                 continue;
             }
 
-            match old_node.node_type {
-                NodeType::ScrollFrame(info) if info.external_id.is_some() => {
-                    scroll_states.insert(info.external_id.unwrap(), info);
+            if let NodeType::ScrollFrame(info) = old_node.node_type {
+                if let Some(id) = info.external_id {
+                    scroll_states.insert(id, info);
                 }
-                _ => {}
             }
         }
 
@@ -231,11 +244,34 @@ impl ClipScrollTree {
         false
     }
 
+    /// Start a smooth scroll of the scroll frame with the given id. Scroll frames that
+    /// aren't in the tree yet have nothing to scroll from, so they jump to the origin
+    /// once they are added instead.
+    pub fn smooth_scroll_node(
+        &mut self,
+        origin: LayerPoint,
+        id: ExternalScrollId,
+        clamp: ScrollClamping,
+        duration: f32,
+        easing: AnimationEasing,
+        time_ns: u64,
+    ) -> bool {
+        for node in &mut self.nodes {
+            if node.matches_external_id(id) {
+                return node.start_smooth_scroll(&origin, clamp, duration, easing, time_ns);
+            }
+        }
+
+        self.pending_scroll_offsets.insert(id, (origin, clamp));
+        false
+    }
+
     pub fn scroll(
         &mut self,
         scroll_location: ScrollLocation,
         cursor: WorldPoint,
         phase: ScrollEventPhase,
+        time_ns: u64,
     ) -> bool {
         if self.nodes.is_empty() {
             return false;
@@ -279,7 +315,8 @@ impl ClipScrollTree {
                         // if this is a new gesture, we do not switch node,
                         // however we do save the state of non_root_overscroll,
                         // for use in the subsequent Move phase.
-                        scrolling.should_handoff_scroll = non_root_overscroll;
+                        scrolling.should_handoff_scroll =
+                            non_root_overscroll && scrolling.can_handoff_scroll();
                     }
                     ScrollEventPhase::Move(_) => {
                         // Switch node if movement originated in a new gesture,
@@ -300,7 +337,7 @@ impl ClipScrollTree {
             node_index
         };
 
-        self.nodes[node_index.0].scroll(scroll_location, phase)
+        self.nodes[node_index.0].scroll(scroll_location, phase, time_ns)
     }
 
     pub fn update_tree(
@@ -444,6 +481,44 @@ impl ClipScrollTree {
         }
     }
 
+    pub fn has_smooth_scrolls(&self) -> bool {
+        self.nodes.iter().any(|node| match node.node_type {
+            NodeType::ScrollFrame(ref scrolling) => scrolling.smooth_scroll.is_some(),
+            _ => false,
+        })
+    }
+
+    /// Advance all smooth scrolls to the given time. Returns the scroll frames whose
+    /// smooth scrolls have ended, with whether they were interrupted.
+    pub fn tick_smooth_scrolls(&mut self, time_ns: u64) -> Vec<(ExternalScrollId, bool)> {
+        let mut ended = Vec::new();
+        for node in &mut self.nodes {
+            if let Some(interrupted) = node.tick_smooth_scroll(time_ns) {
+                if let NodeType::ScrollFrame(ScrollFrameInfo { external_id: Some(id), .. }) =
+                    node.node_type {
+                    ended.push((id, interrupted));
+                }
+            }
+        }
+        ended
+    }
+
+    pub fn has_flings(&self) -> bool {
+        self.nodes.iter().any(|node| match node.node_type {
+            NodeType::ScrollFrame(ref scrolling) => scrolling.flinging,
+            _ => false,
+        })
+    }
+
+    /// Advance all flings to the given time. Returns true if any scroll frame moved.
+    pub fn tick_flings(&mut self, time_ns: u64) -> bool {
+        let mut moved = false;
+        for node in &mut self.nodes {
+            moved |= node.tick_fling(time_ns);
+        }
+        moved
+    }
+
     pub fn finalize_and_apply_pending_scroll_offsets(&mut self, old_states: ScrollStates) {
         for node in &mut self.nodes {
             let external_id = match node.node_type {
@@ -565,7 +640,7 @@ impl ClipScrollTree {
                 pt.new_level(format!("ReferenceFrame {:?}", info.resolved_transform));
                 pt.add_item(format!("index: {:?}", index));
             }
-            NodeType::ScrollFrame(scrolling_info) => {
+            NodeType::ScrollFrame(ref scrolling_info) => {
                 pt.new_level(format!("ScrollFrame"));
                 pt.add_item(format!("index: {:?}", index));
                 pt.add_item(format!("scrollable_size: {:?}", scrolling_info.scrollable_size));
@@ -4,13 +4,16 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use api::{BuiltDisplayListIter, ClipAndScrollInfo, ClipId, ColorF, ComplexClipRegion};
-use api::{DeviceUintSize, DisplayItemRef, Epoch, ExternalScrollId, FilterOp, IframeDisplayItem};
-use api::{ImageDisplayItem, ItemRange, LayerPoint, LayerPrimitiveInfo, LayerRect, LayerSize};
-use api::{LayerVector2D, LayoutSize, PipelineId, ScrollFrameDisplayItem, ScrollPolicy};
-use api::{ScrollSensitivity, SpecificDisplayItem, StackingContext, StickyFrameDisplayItem};
-use api::{TileOffset, TransformStyle};
+use api::{DeviceUintSize, DisplayItemRef, Epoch, ExternalScrollId, FilterData, FilterOp};
+use api::FragmentId;
+use api::{IframeDisplayItem, ImageDisplayItem, ItemRange, LayerPoint, LayerPrimitiveInfo};
+use api::{LayerRect, LayerSize, LayerVector2D, LayoutSize, PathOp, PipelineId, RepeatMode};
+use api::{PropertyBinding, ScrollFrameDisplayItem, ScrollPolicy, ScrollSensitivity};
+use api::{OverscrollBehavior, ScrollSnapArea};
+use api::{SpecificDisplayItem, StackingContext, StickyFrameDisplayItem, TexelRect, TileOffset};
+use api::TransformStyle;
 use clip::ClipRegion;
-use clip_scroll_node::StickyFrameInfo;
+use clip_scroll_node::{ScrollSnapInfo, StickyFrameInfo};
 use clip_scroll_tree::{ClipChainIndex, ClipScrollNodeIndex, ClipScrollTree};
 use euclid::rect;
 use frame_builder::{FrameBuilder, FrameBuilderConfig, ScrollbarInfo};
@@ -19,6 +22,7 @@ use prim_store::ScrollNodeAndClipChain;
 use resource_cache::{FontInstanceMap, TiledImageMap};
 use scene::{Scene, ScenePipeline, StackingContextHelpers};
 use scene_builder::{SceneRequest, BuiltScene};
+use std::mem;
 use tiling::{CompositeOps};
 use render_backend::DocumentView;
 
@@ -132,6 +136,12 @@ pub struct FlattenContext<'a> {
     replacements: Vec<(ClipId, ClipId)>,
     output_pipelines: &'a FastHashSet<PipelineId>,
     id_to_index_mapper: ClipIdToIndexMapper,
+    /// The items of each fragment defined so far, which are flattened again for
+    /// every instance of the fragment.
+    fragments: FastHashMap<(PipelineId, FragmentId), BuiltDisplayListIter<'a>>,
+    /// The clip and scroll node of the fragment instance being flattened, which
+    /// replaces those of the items of the fragment.
+    fragment_clip_and_scroll: Option<ClipAndScrollInfo>,
 }
 
 impl<'a> FlattenContext<'a> {
@@ -175,6 +185,8 @@ impl<'a> FlattenContext<'a> {
                 replacements: Vec::new(),
                 output_pipelines,
                 id_to_index_mapper: ClipIdToIndexMapper::default(),
+                fragments: FastHashMap::default(),
+                fragment_clip_and_scroll: None,
             };
 
             roller.id_to_index_mapper.initialize_for_pipeline(root_pipeline);
@@ -234,6 +246,20 @@ impl<'a> FlattenContext<'a> {
             .collect()
     }
 
+    fn get_polygon_points(
+        &self,
+        pipeline_id: PipelineId,
+        points: ItemRange<LayerPoint>,
+    ) -> Vec<LayerPoint> {
+        self.scene
+            .pipelines
+            .get(&pipeline_id)
+            .expect("No display list?")
+            .display_list
+            .get(points)
+            .collect()
+    }
+
     fn get_clip_chain_items(
         &self,
         pipeline_id: PipelineId,
@@ -268,6 +294,7 @@ impl<'a> FlattenContext<'a> {
             true,
             true,
             scroll_frame_info,
+            &LayerRect::new(LayerPoint::zero(), *frame_size),
             self.output_pipelines,
         );
 
@@ -281,7 +308,7 @@ impl<'a> FlattenContext<'a> {
                     self.builder.add_solid_rectangle(
                         reference_frame_info,
                         &info,
-                        bg_color,
+                        PropertyBinding::Value(bg_color),
                         None,
                     );
                 }
@@ -317,8 +344,10 @@ impl<'a> FlattenContext<'a> {
                     None => break,
                 };
 
-                if SpecificDisplayItem::PopStackingContext == *item.item() {
-                    return;
+                match *item.item() {
+                    SpecificDisplayItem::PopStackingContext |
+                    SpecificDisplayItem::PopFragmentDefinition => return,
+                    _ => {}
                 }
 
                 self.flatten_item(
@@ -376,6 +405,7 @@ impl<'a> FlattenContext<'a> {
             *item.local_clip().clip_rect(),
             complex_clips,
             info.image_mask,
+            None,
             &reference_frame_relative_offset,
         );
         // Just use clip rectangle as the frame rect for this scroll frame.
@@ -387,6 +417,12 @@ impl<'a> FlattenContext<'a> {
             .translate(&reference_frame_relative_offset);
         let content_rect = item.rect().translate(&reference_frame_relative_offset);
 
+        // Snap areas are only compared with the content rect, so they don't need
+        // to be translated.
+        let snap_areas: Vec<ScrollSnapArea> = item.display_list().get(item.snap_areas()).collect();
+        let snap_info =
+            ScrollSnapInfo::new(info.snap_type, &snap_areas, &item.rect(), &frame_rect.size);
+
         debug_assert!(info.clip_id != info.scroll_frame_id);
 
         self.builder.add_clip_node(
@@ -405,6 +441,8 @@ impl<'a> FlattenContext<'a> {
             &frame_rect,
             &content_rect.size,
             info.scroll_sensitivity,
+            info.overscroll_behavior,
+            snap_info,
             self.clip_scroll_tree,
             &mut self.id_to_index_mapper,
         );
@@ -420,6 +458,8 @@ impl<'a> FlattenContext<'a> {
         bounds: &LayerRect,
         stacking_context: &StackingContext,
         filters: ItemRange<FilterOp>,
+        filter_data: ItemRange<FilterData>,
+        backdrop_filters: ItemRange<FilterOp>,
         is_backface_visible: bool,
     ) {
         // Avoid doing unnecessary work for empty stacking contexts.
@@ -438,7 +478,9 @@ impl<'a> FlattenContext<'a> {
                 .display_list;
             CompositeOps::new(
                 stacking_context.filter_ops_for_compositing(display_list, filters),
+                stacking_context.filter_data_for_compositing(display_list, filter_data),
                 stacking_context.mix_blend_mode_for_compositing(),
+                stacking_context.filter_ops_for_compositing(display_list, backdrop_filters),
             )
         };
 
@@ -488,6 +530,7 @@ impl<'a> FlattenContext<'a> {
             is_backface_visible,
             false,
             stacking_context_clip_and_scroll,
+            &LayerRect::new(reference_frame_relative_offset.to_point(), bounds.size),
             self.output_pipelines,
         );
 
@@ -561,6 +604,8 @@ impl<'a> FlattenContext<'a> {
             &iframe_rect,
             &pipeline.content_size,
             ScrollSensitivity::ScriptAndInputEvents,
+            OverscrollBehavior::Auto,
+            ScrollSnapInfo::none(),
             self.clip_scroll_tree,
             &mut self.id_to_index_mapper,
         );
@@ -570,13 +615,41 @@ impl<'a> FlattenContext<'a> {
         self.builder.pop_reference_frame();
     }
 
+    fn flatten_fragment(
+        &mut self,
+        id: FragmentId,
+        clip_and_scroll_ids: ClipAndScrollInfo,
+        pipeline_id: PipelineId,
+        reference_frame_relative_offset: LayerVector2D,
+    ) {
+        if self.fragment_clip_and_scroll.is_some() {
+            warn!("Fragments can't be drawn inside other fragments");
+            return;
+        }
+
+        let mut traversal = match self.fragments.get(&(pipeline_id, id)) {
+            Some(definition) => definition.as_ref().sub_iter(),
+            None => {
+                warn!("Unknown fragment");
+                debug!("id={:?}", id);
+                return;
+            }
+        };
+
+        let outer_clip_and_scroll =
+            mem::replace(&mut self.fragment_clip_and_scroll, Some(clip_and_scroll_ids));
+        self.flatten_items(&mut traversal, pipeline_id, reference_frame_relative_offset);
+        self.fragment_clip_and_scroll = outer_clip_and_scroll;
+    }
+
     fn flatten_item<'b>(
         &'b mut self,
         item: DisplayItemRef<'a, 'b>,
         pipeline_id: PipelineId,
         reference_frame_relative_offset: LayerVector2D,
     ) -> Option<BuiltDisplayListIter<'a>> {
-        let mut clip_and_scroll_ids = item.clip_and_scroll();
+        let mut clip_and_scroll_ids =
+            self.fragment_clip_and_scroll.unwrap_or_else(|| item.clip_and_scroll());
         let unreplaced_scroll_id = clip_and_scroll_ids.scroll_node_id;
         clip_and_scroll_ids.scroll_node_id =
             self.apply_scroll_frame_id_replacement(clip_and_scroll_ids.scroll_node_id);
@@ -585,6 +658,7 @@ impl<'a> FlattenContext<'a> {
         let prim_info = item.get_layer_primitive_info(&reference_frame_relative_offset);
         match *item.item() {
             SpecificDisplayItem::Image(ref info) => {
+                let info = &resolve_image_repeat(info, &prim_info.rect);
                 match self.tiled_image_map.get(&info.image_key).cloned() {
                     Some(tiling) => {
                         // The image resource is tiled. We have to generate an image primitive
@@ -603,7 +677,7 @@ impl<'a> FlattenContext<'a> {
                             &prim_info,
                             info.stretch_size,
                             info.tile_spacing,
-                            None,
+                            info.source_rect,
                             info.image_key,
                             info.image_rendering,
                             info.alpha_type,
@@ -658,6 +732,9 @@ impl<'a> FlattenContext<'a> {
                     &prim_info,
                 );
             }
+            SpecificDisplayItem::HitTest => {
+                self.builder.add_primitive_to_hit_testing_list(&prim_info, clip_and_scroll);
+            }
             SpecificDisplayItem::Line(ref info) => {
                 self.builder.add_line(
                     clip_and_scroll,
@@ -668,6 +745,18 @@ impl<'a> FlattenContext<'a> {
                     info.style,
                 );
             }
+            SpecificDisplayItem::Path(ref info) => {
+                let ops: Vec<PathOp> = item.display_list().get(item.path_ops()).collect();
+                self.builder.add_path(
+                    clip_and_scroll,
+                    reference_frame_relative_offset,
+                    &prim_info,
+                    &ops,
+                    info.fill_rule,
+                    info.fill_color,
+                    info.stroke,
+                );
+            }
             SpecificDisplayItem::Gradient(ref info) => {
                 self.builder.add_gradient(
                     clip_and_scroll,
@@ -696,6 +785,20 @@ impl<'a> FlattenContext<'a> {
                     info.tile_spacing,
                 );
             }
+            SpecificDisplayItem::ConicGradient(ref info) => {
+                self.builder.add_conic_gradient(
+                    clip_and_scroll,
+                    &prim_info,
+                    info.gradient.center,
+                    info.gradient.angle,
+                    info.gradient.start_offset,
+                    info.gradient.end_offset,
+                    item.gradient_stops(),
+                    info.gradient.extend_mode,
+                    info.tile_size,
+                    info.tile_spacing,
+                );
+            }
             SpecificDisplayItem::BoxShadow(ref box_shadow_info) => {
                 let bounds = box_shadow_info
                     .box_bounds
@@ -734,6 +837,8 @@ impl<'a> FlattenContext<'a> {
                     &item.rect(),
                     &info.stacking_context,
                     item.filters(),
+                    item.filter_data(),
+                    item.backdrop_filters(),
                     prim_info.is_backface_visible,
                 );
                 return Some(subtraversal);
@@ -748,10 +853,14 @@ impl<'a> FlattenContext<'a> {
             }
             SpecificDisplayItem::Clip(ref info) => {
                 let complex_clips = self.get_complex_clips(pipeline_id, item.complex_clip().0);
+                let polygon = info.polygon.map(|polygon| {
+                    (polygon, self.get_polygon_points(pipeline_id, item.polygon_points()))
+                });
                 let clip_region = ClipRegion::create_for_clip_node(
                     *item.local_clip().clip_rect(),
                     complex_clips,
                     info.image_mask,
+                    polygon,
                     &reference_frame_relative_offset,
                 );
                 self.builder.add_clip_node(
@@ -796,6 +905,29 @@ impl<'a> FlattenContext<'a> {
             // Do nothing; these are dummy items for the display list parser
             SpecificDisplayItem::SetGradientStops => {}
 
+            // Item groups only matter when a delta is applied to the display list.
+            SpecificDisplayItem::PushItemGroup(..) |
+            SpecificDisplayItem::PopItemGroup => {}
+
+            SpecificDisplayItem::PushFragmentDefinition(id) => {
+                // The items of the definition are only flattened where the fragment is drawn.
+                self.fragments.insert((pipeline_id, id), item.sub_iter());
+                let mut subtraversal = item.sub_iter();
+                subtraversal.skip_current_fragment_definition();
+                return Some(subtraversal);
+            }
+            SpecificDisplayItem::PopFragmentDefinition => {
+                unreachable!("Should have returned in parent method.")
+            }
+            SpecificDisplayItem::Fragment(ref info) => {
+                self.flatten_fragment(
+                    info.id,
+                    item.clip_and_scroll(),
+                    pipeline_id,
+                    reference_frame_relative_offset + item.rect().origin.to_vector(),
+                );
+            }
+
             SpecificDisplayItem::PopStackingContext => {
                 unreachable!("Should have returned in parent method.")
             }
@@ -831,7 +963,10 @@ impl<'a> FlattenContext<'a> {
         image_size: DeviceUintSize,
         tile_size: u32,
     ) {
-        let no_vertical_tiling = image_size.height <= tile_size;
+        let source_rect = image_source_rect(info, image_size);
+        let (first_row, last_row) =
+            tile_range(source_rect.uv0.y, source_rect.uv1.y, tile_size, image_size.height);
+        let no_vertical_tiling = last_row - first_row <= 1;
         let no_vertical_spacing = info.tile_spacing.height == 0.0;
         let item_rect = prim_info.rect;
         if no_vertical_tiling && no_vertical_spacing {
@@ -877,7 +1012,10 @@ impl<'a> FlattenContext<'a> {
         image_size: DeviceUintSize,
         tile_size: u32,
     ) {
-        let no_horizontal_tiling = image_size.width <= tile_size;
+        let source_rect = image_source_rect(info, image_size);
+        let (first_column, last_column) =
+            tile_range(source_rect.uv0.x, source_rect.uv1.x, tile_size, image_size.width);
+        let no_horizontal_tiling = last_column - first_column <= 1;
         let no_horizontal_spacing = info.tile_spacing.width == 0.0;
         if no_horizontal_tiling && no_horizontal_spacing {
             self.decompose_tiled_image(
@@ -943,11 +1081,14 @@ impl<'a> FlattenContext<'a> {
         //
         // In the ascii diagram above, a large image is plit into tiles of almost regular size.
         // The tiles on the right and bottom edges (hatched in the diagram) are smaller than
-        // the regular tiles and are handled separately in the code see leftover_width/height.
+        // the regular tiles, so their texel rects are clamped to the image size.
         // each generated image primitive corresponds to a tile in the texture cache, with the
         // assumption that the smaller tiles with leftover sizes are sized to fit their own
         // irregular size in the texture cache.
         //
+        // If the item has a source rect, only the tiles it overlaps are drawn, and the tiles
+        // on its edges only draw the part of the tile that is inside the source rect.
+        //
         // For the case where we don't tile along an axis, we can still perform the repetition in
         // the shader (for this particular axis), and it is worth special-casing for this to avoid
         // generating many primitives.
@@ -958,92 +1099,75 @@ impl<'a> FlattenContext<'a> {
         let needs_repeat_x = info.stretch_size.width < item_rect.size.width;
         let needs_repeat_y = info.stretch_size.height < item_rect.size.height;
 
-        let tiled_in_x = image_size.width > tile_size;
-        let tiled_in_y = image_size.height > tile_size;
+        let source_rect = image_source_rect(info, image_size);
+        let source_width = source_rect.uv1.x - source_rect.uv0.x;
+        let source_height = source_rect.uv1.y - source_rect.uv0.y;
+        if source_width <= 0.0 || source_height <= 0.0 {
+            return;
+        }
+
+        let (first_x, last_x) =
+            tile_range(source_rect.uv0.x, source_rect.uv1.x, tile_size, image_size.width);
+        let (first_y, last_y) =
+            tile_range(source_rect.uv0.y, source_rect.uv1.y, tile_size, image_size.height);
+
+        let tiled_in_x = last_x - first_x > 1;
+        let tiled_in_y = last_y - first_y > 1;
 
         // If we don't actually tile in this dimension, repeating can be done in the shader.
         let shader_repeat_x = needs_repeat_x && !tiled_in_x;
         let shader_repeat_y = needs_repeat_y && !tiled_in_y;
 
-        let tile_size_f32 = tile_size as f32;
-
-        // Note: this rounds down so it excludes the partially filled tiles on the right and
-        // bottom edges (we handle them separately below).
-        let num_tiles_x = (image_size.width / tile_size) as u16;
-        let num_tiles_y = (image_size.height / tile_size) as u16;
-
-        // Ratio between (image space) tile size and image size.
-        let img_dw = tile_size_f32 / (image_size.width as f32);
-        let img_dh = tile_size_f32 / (image_size.height as f32);
-
-        // Strected size of the tile in layout space.
-        let stretched_tile_size = LayerSize::new(
-            img_dw * info.stretch_size.width,
-            img_dh * info.stretch_size.height,
-        );
-
-        // The size in pixels of the tiles on the right and bottom edges, smaller
-        // than the regular tile size if the image is not a multiple of the tile size.
-        // Zero means the image size is a multiple of the tile size.
-        let leftover =
-            DeviceUintSize::new(image_size.width % tile_size, image_size.height % tile_size);
-
-        for ty in 0 .. num_tiles_y {
-            for tx in 0 .. num_tiles_x {
-                self.add_tile_primitive(
-                    clip_and_scroll,
-                    prim_info,
-                    info,
-                    TileOffset::new(tx, ty),
-                    stretched_tile_size,
-                    1.0,
-                    1.0,
-                    shader_repeat_x,
-                    shader_repeat_y,
+        // Ratio between the stretched size in layout space and the source rect in texels.
+        let scale_x = info.stretch_size.width / source_width;
+        let scale_y = info.stretch_size.height / source_height;
+
+        for ty in first_y .. last_y {
+            for tx in first_x .. last_x {
+                let tile_rect = TexelRect::new(
+                    (tx * tile_size) as f32,
+                    (ty * tile_size) as f32,
+                    ((tx + 1) * tile_size).min(image_size.width) as f32,
+                    ((ty + 1) * tile_size).min(image_size.height) as f32,
                 );
-            }
-            if leftover.width != 0 {
-                // Tiles on the right edge that are smaller than the tile size.
-                self.add_tile_primitive(
-                    clip_and_scroll,
-                    prim_info,
-                    info,
-                    TileOffset::new(num_tiles_x, ty),
-                    stretched_tile_size,
-                    (leftover.width as f32) / tile_size_f32,
-                    1.0,
-                    shader_repeat_x,
-                    shader_repeat_y,
-                );
-            }
-        }
 
-        if leftover.height != 0 {
-            for tx in 0 .. num_tiles_x {
-                // Tiles on the bottom edge that are smaller than the tile size.
-                self.add_tile_primitive(
-                    clip_and_scroll,
-                    prim_info,
-                    info,
-                    TileOffset::new(tx, num_tiles_y),
-                    stretched_tile_size,
-                    1.0,
-                    (leftover.height as f32) / tile_size_f32,
-                    shader_repeat_x,
-                    shader_repeat_y,
+                // The part of the tile that is inside the source rect.
+                let visible_rect = TexelRect::new(
+                    tile_rect.uv0.x.max(source_rect.uv0.x),
+                    tile_rect.uv0.y.max(source_rect.uv0.y),
+                    tile_rect.uv1.x.min(source_rect.uv1.x),
+                    tile_rect.uv1.y.min(source_rect.uv1.y),
                 );
-            }
+                if visible_rect.uv1.x <= visible_rect.uv0.x ||
+                   visible_rect.uv1.y <= visible_rect.uv0.y {
+                    continue;
+                }
+
+                let sub_rect = if visible_rect == tile_rect {
+                    None
+                } else {
+                    Some(TexelRect::new(
+                        visible_rect.uv0.x - tile_rect.uv0.x,
+                        visible_rect.uv0.y - tile_rect.uv0.y,
+                        visible_rect.uv1.x - tile_rect.uv0.x,
+                        visible_rect.uv1.y - tile_rect.uv0.y,
+                    ))
+                };
 
-            if leftover.width != 0 {
-                // Finally, the bottom-right tile with a "leftover" size.
                 self.add_tile_primitive(
                     clip_and_scroll,
                     prim_info,
                     info,
-                    TileOffset::new(num_tiles_x, num_tiles_y),
-                    stretched_tile_size,
-                    (leftover.width as f32) / tile_size_f32,
-                    (leftover.height as f32) / tile_size_f32,
+                    TileOffset::new(tx as u16, ty as u16),
+                    LayerVector2D::new(
+                        (visible_rect.uv0.x - source_rect.uv0.x) * scale_x,
+                        (visible_rect.uv0.y - source_rect.uv0.y) * scale_y,
+                    ),
+                    LayerSize::new(
+                        (visible_rect.uv1.x - visible_rect.uv0.x) * scale_x,
+                        (visible_rect.uv1.y - visible_rect.uv0.y) * scale_y,
+                    ),
+                    sub_rect,
                     shader_repeat_x,
                     shader_repeat_y,
                 );
@@ -1057,9 +1181,9 @@ impl<'a> FlattenContext<'a> {
         prim_info: &LayerPrimitiveInfo,
         info: &ImageDisplayItem,
         tile_offset: TileOffset,
-        stretched_tile_size: LayerSize,
-        tile_ratio_width: f32,
-        tile_ratio_height: f32,
+        stretched_offset: LayerVector2D,
+        stretched_size: LayerSize,
+        sub_rect: Option<TexelRect>,
         shader_repeat_x: bool,
         shader_repeat_y: bool,
     ) {
@@ -1069,30 +1193,19 @@ impl<'a> FlattenContext<'a> {
         // has to be emulated by generating more primitives).
         // If the image is not tiled along this axis, we can perform the repetition in the
         // shader. in this case we use the item's size in the primitive (on that particular
-        // axis).
+        // axis). There is a single tile along that axis, so its offset is zero.
         // See the shader_repeat_x/y code below.
 
-        let stretched_size = LayerSize::new(
-            stretched_tile_size.width * tile_ratio_width,
-            stretched_tile_size.height * tile_ratio_height,
-        );
-
         let mut prim_rect = LayerRect::new(
-            prim_info.rect.origin +
-                LayerVector2D::new(
-                    tile_offset.x as f32 * stretched_tile_size.width,
-                    tile_offset.y as f32 * stretched_tile_size.height,
-                ),
+            prim_info.rect.origin + stretched_offset,
             stretched_size,
         );
 
         if shader_repeat_x {
-            assert_eq!(tile_offset.x, 0);
             prim_rect.size.width = prim_info.rect.size.width;
         }
 
         if shader_repeat_y {
-            assert_eq!(tile_offset.y, 0);
             prim_rect.size.height = prim_info.rect.size.height;
         }
 
@@ -1105,7 +1218,7 @@ impl<'a> FlattenContext<'a> {
                 &prim_info,
                 stretched_size,
                 info.tile_spacing,
-                None,
+                sub_rect,
                 info.image_key,
                 info.image_rendering,
                 info.alpha_type,
@@ -1115,6 +1228,85 @@ impl<'a> FlattenContext<'a> {
     }
 }
 
+/// Resolves the repeat modes of an image item into a plain repeating image,
+/// adjusting its stretch size and tile spacing to fit the item's rect.
+fn resolve_image_repeat(info: &ImageDisplayItem, rect: &LayerRect) -> ImageDisplayItem {
+    let (stretch_width, spacing_width) = resolve_repeat(
+        rect.size.width,
+        info.stretch_size.width,
+        info.tile_spacing.width,
+        info.repeat_horizontal,
+    );
+    let (stretch_height, spacing_height) = resolve_repeat(
+        rect.size.height,
+        info.stretch_size.height,
+        info.tile_spacing.height,
+        info.repeat_vertical,
+    );
+
+    ImageDisplayItem {
+        stretch_size: LayerSize::new(stretch_width, stretch_height),
+        tile_spacing: LayerSize::new(spacing_width, spacing_height),
+        repeat_horizontal: RepeatMode::Repeat,
+        repeat_vertical: RepeatMode::Repeat,
+        ..*info
+    }
+}
+
+/// Computes the tile size and spacing along one axis for a repeat mode.
+fn resolve_repeat(
+    length: f32,
+    tile_size: f32,
+    tile_spacing: f32,
+    repeat: RepeatMode,
+) -> (f32, f32) {
+    if tile_size <= 0.0 {
+        return (tile_size, tile_spacing);
+    }
+
+    match repeat {
+        RepeatMode::Repeat => (tile_size, tile_spacing),
+        RepeatMode::Stretch => (length, 0.0),
+        RepeatMode::Round => {
+            // Scale the tiles so that a whole number of them fit.
+            let stride = tile_size + tile_spacing;
+            let count = ((length + tile_spacing) / stride).round().max(1.0);
+            ((length + tile_spacing) / count - tile_spacing, tile_spacing)
+        }
+        RepeatMode::Space => {
+            // Fit as many whole tiles as we can, with the first and last
+            // ones touching the edges, and spread the leftover space evenly
+            // between them. If there is only room for one tile, it is drawn
+            // once at the start.
+            let count = (length / tile_size).floor();
+            if count < 2.0 {
+                (tile_size, (length - tile_size).max(0.0))
+            } else {
+                (tile_size, (length - count * tile_size) / (count - 1.0))
+            }
+        }
+    }
+}
+
+/// Returns the part of the image to draw for an image item, in texels.
+fn image_source_rect(info: &ImageDisplayItem, image_size: DeviceUintSize) -> TexelRect {
+    info.source_rect.unwrap_or(TexelRect::new(
+        0.0,
+        0.0,
+        image_size.width as f32,
+        image_size.height as f32,
+    ))
+}
+
+/// Returns the range of tiles that overlap the texels between `start` and `end`
+/// along one axis of a tiled image.
+fn tile_range(start: f32, end: f32, tile_size: u32, image_size: u32) -> (u32, u32) {
+    let num_tiles = (image_size + tile_size - 1) / tile_size;
+    let first = ((start.max(0.0) / tile_size as f32).floor() as u32).min(num_tiles);
+    let last = ((end.max(0.0) / tile_size as f32).ceil() as u32).min(num_tiles);
+    (first, last.max(first))
+}
+
 pub fn build_scene(config: &FrameBuilderConfig, request: SceneRequest) -> BuiltScene {
     let mut pipeline_epoch_map = FastHashMap::default();
     let mut clip_scroll_tree = ClipScrollTree::new();
@@ -5,22 +5,24 @@
 use api::{AlphaType, BorderDetails, BorderDisplayItem, BuiltDisplayList, ClipId, ColorF};
 use api::{DeviceIntPoint, DeviceIntRect, DeviceIntSize, DevicePixelScale, DeviceUintPoint};
 use api::{DeviceUintRect, DeviceUintSize, DocumentLayer, Epoch, ExtendMode, ExternalScrollId};
-use api::{FontRenderMode, GlyphInstance, GlyphOptions, GradientStop, ImageKey, ImageRendering};
-use api::{ItemRange, LayerPoint, LayerPrimitiveInfo, LayerRect, LayerSize, LayerVector2D};
-use api::{LayoutTransform, LayoutVector2D, LineOrientation, LineStyle, LocalClip, PipelineId};
-use api::{PremultipliedColorF, PropertyBinding, RepeatMode, ScrollSensitivity, Shadow, TexelRect};
-use api::{TileOffset, TransformStyle, WorldPoint, YuvColorSpace, YuvData};
+use api::{FillRule, FilterOp, FontRenderMode, GlyphInstance, GlyphOptions, GradientStop};
+use api::{ImageKey, ImageRendering, ItemRange, LayerPoint, LayerPrimitiveInfo, LayerRect, LayerSize};
+use api::{LayerVector2D, LayoutTransform, LayoutVector2D, LineOrientation, LineStyle, LocalClip};
+use api::{OverscrollBehavior, PathOp, PathStroke, PipelineId, PremultipliedColorF};
+use api::{PropertyBinding, ScrollSensitivity};
+use api::{Shadow, TexelRect, TileOffset, TransformStyle, WorldPoint, YuvColorSpace, YuvData};
 use app_units::Au;
-use border::ImageBorderSegment;
+use border::create_image_border_segments;
 use clip::{ClipChain, ClipRegion, ClipSource, ClipSources, ClipStore};
-use clip_scroll_node::{ClipScrollNode, NodeType};
+use clip_scroll_node::{ClipScrollNode, DEFAULT_FLING_FRICTION, NodeType, ScrollSnapInfo};
 use clip_scroll_tree::{ClipChainIndex, ClipScrollNodeIndex, ClipScrollTree};
-use euclid::{SideOffsets2D, vec2};
+use euclid::SideOffsets2D;
 use frame::ClipIdToIndexMapper;
 use glyph_rasterizer::FontInstance;
 use gpu_cache::GpuCache;
 use gpu_types::{ClipChainRectIndex, ClipScrollNodeData, PictureType};
 use hit_test::{HitTester, HitTestingItem, HitTestingRun};
+use path::PathMask;
 use internal_types::{FastHashMap, FastHashSet};
 use picture::{ContentOrigin, PictureCompositeMode, PictureKind, PicturePrimitive, PictureSurface};
 use prim_store::{BrushKind, BrushPrimitive, BrushSegmentDescriptor, CachedGradient, CachedGradientIndex};
@@ -77,6 +79,7 @@ pub struct FrameBuilderConfig {
     pub debug: bool,
     pub dual_source_blending_is_supported: bool,
     pub dual_source_blending_is_enabled: bool,
+    pub fling_friction: f32,
 }
 
 /// A builder structure for `tiling::Frame`
@@ -142,12 +145,20 @@ pub struct PictureContext<'a> {
 
 pub struct PictureState {
     pub tasks: Vec<RenderTaskId>,
+    /// The saved render task holding the unfiltered content of the
+    /// enclosing stacking context, if a composite filter needs it.
+    pub source_graphic: Option<RenderTaskId>,
+    /// The saved render task of the last backdrop picture drawn
+    /// before the current one, if any, for backdrop filters to read.
+    pub backdrop: Option<(PrimitiveIndex, RenderTaskId)>,
 }
 
 impl PictureState {
     pub fn new() -> PictureState {
         PictureState {
             tasks: Vec::new(),
+            source_graphic: None,
+            backdrop: None,
         }
     }
 }
@@ -194,6 +205,7 @@ impl FrameBuilder {
                 debug: false,
                 dual_source_blending_is_enabled: true,
                 dual_source_blending_is_supported: false,
+                fling_friction: DEFAULT_FLING_FRICTION,
             },
         }
     }
@@ -322,6 +334,7 @@ impl FrameBuilder {
         is_backface_visible: bool,
         is_pipeline_root: bool,
         clip_and_scroll: ScrollNodeAndClipChain,
+        bounds: &LayerRect,
         output_pipelines: &FastHashSet<PipelineId>,
     ) {
         // Construct the necessary set of Picture primitives
@@ -410,6 +423,7 @@ impl FrameBuilder {
         // elements in this context.
         let participating_in_3d_context =
             composite_ops.count() == 0 &&
+            composite_ops.backdrop_filters.is_empty() &&
             (parent_transform_style == TransformStyle::Preserve3D ||
              transform_style == TransformStyle::Preserve3D);
 
@@ -474,15 +488,43 @@ impl FrameBuilder {
             *self.picture_stack.last().unwrap()
         };
 
+        // Draw the backdrop filters before the stacking context, into the
+        // same surface. Such a stacking context is never in a 3d context,
+        // so its parent is the current picture.
+        if !composite_ops.backdrop_filters.is_empty() {
+            self.add_backdrop_filters(
+                pipeline_id,
+                &composite_ops.backdrop_filters,
+                is_backface_visible,
+                clip_and_scroll,
+                bounds,
+            );
+        }
+
+        // Each component transfer filter uses the next set of filter data.
+        let mut filter_data = composite_ops.filter_data.iter();
+        let filters: Vec<_> = composite_ops.filters
+            .iter()
+            .map(|filter| {
+                match *filter {
+                    FilterOp::ComponentTransfer => (*filter, filter_data.next().cloned()),
+                    _ => (*filter, None),
+                }
+            })
+            .collect();
+
         // For each filter, create a new image with that composite mode.
-        for filter in composite_ops.filters.iter().rev() {
-            let src_prim = PicturePrimitive::new_image(
-                Some(PictureCompositeMode::Filter(*filter)),
+        for (filter, data) in filters.into_iter().rev() {
+            let mut src_prim = PicturePrimitive::new_image(
+                Some(PictureCompositeMode::Filter(filter)),
                 false,
                 pipeline_id,
                 current_reference_frame_index,
                 None,
             );
+            if let PictureKind::Image { ref mut filter_data, .. } = src_prim.kind {
+                *filter_data = data;
+            }
             let src_clip_sources = self.clip_store.insert(ClipSources::new(Vec::new()));
 
             let src_prim_index = self.prim_store.add_primitive(
@@ -559,14 +601,29 @@ impl FrameBuilder {
             composite_mode = Some(PictureCompositeMode::Blit);
         }
 
+        // If a composite filter needs the unfiltered content, draw
+        // it to an intermediate surface that can be kept around.
+        let is_source_graphic = composite_ops.filters.iter().any(|filter| {
+            match *filter {
+                FilterOp::Composite(..) => true,
+                _ => false,
+            }
+        });
+        if is_source_graphic && composite_mode.is_none() {
+            composite_mode = Some(PictureCompositeMode::Blit);
+        }
+
         // Add picture for this actual stacking context contents to render into.
-        let sc_prim = PicturePrimitive::new_image(
+        let mut sc_prim = PicturePrimitive::new_image(
             composite_mode,
             participating_in_3d_context,
             pipeline_id,
             current_reference_frame_index,
             frame_output_pipeline_id,
         );
+        if let PictureKind::Image { is_source_graphic: ref mut flag, .. } = sc_prim.kind {
+            *flag = is_source_graphic;
+        }
 
         let sc_clip_sources = self.clip_store.insert(ClipSources::new(Vec::new()));
         let sc_prim_index = self.prim_store.add_primitive(
@@ -607,6 +664,169 @@ impl FrameBuilder {
         self.sc_stack.push(sc);
     }
 
+    // Add the pictures that draw the backdrop filters of a stacking
+    // context with the given bounds to the current picture. What is
+    // already drawn into the surface of the current picture is moved
+    // into a backdrop picture that is saved, so that the filters can
+    // read it after it is drawn.
+    fn add_backdrop_filters(
+        &mut self,
+        pipeline_id: PipelineId,
+        filters: &[FilterOp],
+        is_backface_visible: bool,
+        clip_and_scroll: ScrollNodeAndClipChain,
+        bounds: &LayerRect,
+    ) {
+        let max_clip = LayerRect::max_rect();
+
+        // Take the runs from the current picture down to the picture that
+        // owns the surface, keeping only the runs of the pictures in
+        // between that lead up to the current picture.
+        let mut moved_runs = Vec::new();
+        let mut child_prim_index = None;
+        for pic_prim_index in self.picture_stack.iter().rev() {
+            let cpu_prim_index = self.prim_store.cpu_metadata[pic_prim_index.0].cpu_prim_index;
+            let pic = &mut self.prim_store.cpu_pictures[cpu_prim_index.0];
+
+            // Primitives are drawn with the display list of their picture.
+            if pic.pipeline_id != pipeline_id {
+                break;
+            }
+
+            let mut runs = mem::replace(&mut pic.runs, Vec::new());
+            if let Some(child_prim_index) = child_prim_index {
+                // The child can only be split out if it is the last
+                // primitive of the picture (e.g. not with content
+                // hoisted into a 3d context after it).
+                let is_last = match runs.last() {
+                    Some(run) => run.base_prim_index.0 + run.count == child_prim_index.0 + 1,
+                    None => false,
+                };
+                if !is_last {
+                    pic.runs = runs;
+                    break;
+                }
+
+                let mut run = runs.pop().unwrap();
+                run.count -= 1;
+                pic.runs.push(PrimitiveRun {
+                    base_prim_index: child_prim_index,
+                    count: 1,
+                    clip_and_scroll: run.clip_and_scroll,
+                });
+                if run.count > 0 {
+                    runs.push(run);
+                }
+            }
+            moved_runs.push(runs);
+            child_prim_index = Some(*pic_prim_index);
+
+            let has_surface = match pic.kind {
+                PictureKind::Image { composite_mode, .. } => composite_mode.is_some(),
+                PictureKind::TextShadow { .. } |
+                PictureKind::BoxShadow { .. } => true,
+            };
+            if has_surface {
+                break;
+            }
+        }
+
+        // The backdrop can hold content from any node, so it is drawn
+        // in the space of the root reference frame, without a clip.
+        let root_reference_frame_index = self.reference_frame_stack[0].1;
+        let mut backdrop_pic = PicturePrimitive::new_image(
+            Some(PictureCompositeMode::Blit),
+            false,
+            pipeline_id,
+            root_reference_frame_index,
+            None,
+        );
+        backdrop_pic.runs = moved_runs.into_iter().rev().flat_map(|runs| runs).collect();
+        if let PictureKind::Image { ref mut is_backdrop, .. } = backdrop_pic.kind {
+            *is_backdrop = true;
+        }
+        let backdrop_clip_sources = self.clip_store.insert(ClipSources::new(Vec::new()));
+        let backdrop_prim_index = self.prim_store.add_primitive(
+            &LayerRect::zero(),
+            &max_clip,
+            true,
+            backdrop_clip_sources,
+            None,
+            PrimitiveContainer::Picture(backdrop_pic),
+        );
+
+        // The input of the filters draws the saved backdrop where the
+        // stacking context is. If a composite filter needs it unfiltered,
+        // it is saved too.
+        let mut input_pic = PicturePrimitive::new_image(
+            Some(PictureCompositeMode::Blit),
+            false,
+            pipeline_id,
+            self.current_reference_frame_index(),
+            None,
+        );
+        if let PictureKind::Image {
+            backdrop_prim_index: ref mut input_backdrop_prim_index,
+            ref mut is_source_graphic,
+            ..
+        } = input_pic.kind {
+            *input_backdrop_prim_index = Some(backdrop_prim_index);
+            *is_source_graphic = filters.iter().any(|filter| {
+                match *filter {
+                    FilterOp::Composite(..) => true,
+                    _ => false,
+                }
+            });
+        }
+        let input_clip_sources = self.clip_store.insert(ClipSources::new(Vec::new()));
+        let mut input_prim_index = self.prim_store.add_primitive(
+            bounds,
+            bounds,
+            is_backface_visible,
+            input_clip_sources,
+            None,
+            PrimitiveContainer::Picture(input_pic),
+        );
+
+        // For each filter, create a new image with that composite mode,
+        // around the previous one, clipped to the stacking context.
+        for filter in filters {
+            let mut filter_pic = PicturePrimitive::new_image(
+                Some(PictureCompositeMode::Filter(*filter)),
+                false,
+                pipeline_id,
+                self.current_reference_frame_index(),
+                None,
+            );
+            filter_pic.add_primitive(
+                input_prim_index,
+                clip_and_scroll,
+            );
+            let filter_clip_sources = self.clip_store.insert(ClipSources::new(Vec::new()));
+
+            input_prim_index = self.prim_store.add_primitive(
+                &LayerRect::zero(),
+                bounds,
+                is_backface_visible,
+                filter_clip_sources,
+                None,
+                PrimitiveContainer::Picture(filter_pic),
+            );
+        }
+
+        let parent_pic_prim_index = *self.picture_stack.last().unwrap();
+        let pic_prim_index = self.prim_store.cpu_metadata[parent_pic_prim_index.0].cpu_prim_index;
+        let pic = &mut self.prim_store.cpu_pictures[pic_prim_index.0];
+        pic.add_primitive(
+            backdrop_prim_index,
+            ScrollNodeAndClipChain::new(root_reference_frame_index, ClipChainIndex(0)),
+        );
+        pic.add_primitive(
+            input_prim_index,
+            clip_and_scroll,
+        );
+    }
+
     pub fn pop_stacking_context(&mut self) {
         let sc = self.sc_stack.pop().unwrap();
 
@@ -723,6 +943,8 @@ impl FrameBuilder {
             &viewport_rect,
             content_size,
             ScrollSensitivity::ScriptAndInputEvents,
+            OverscrollBehavior::Auto,
+            ScrollSnapInfo::none(),
             clip_scroll_tree,
             id_to_index_mapper,
         );
@@ -763,6 +985,8 @@ impl FrameBuilder {
         frame_rect: &LayerRect,
         content_size: &LayerSize,
         scroll_sensitivity: ScrollSensitivity,
+        overscroll_behavior: OverscrollBehavior,
+        snap_info: ScrollSnapInfo,
         clip_scroll_tree: &mut ClipScrollTree,
         id_to_index_mapper: &mut ClipIdToIndexMapper,
     ) -> ClipScrollNodeIndex {
@@ -774,6 +998,9 @@ impl FrameBuilder {
             frame_rect,
             content_size,
             scroll_sensitivity,
+            overscroll_behavior,
+            snap_info,
+            self.config.fling_friction,
         );
 
         clip_scroll_tree.add_node(node, node_index);
@@ -834,20 +1061,23 @@ impl FrameBuilder {
         &mut self,
         clip_and_scroll: ScrollNodeAndClipChain,
         info: &LayerPrimitiveInfo,
-        color: ColorF,
+        color: PropertyBinding<ColorF>,
         segments: Option<BrushSegmentDescriptor>,
     ) {
-        if color.a == 0.0 {
+        let is_transparent = match color {
+            PropertyBinding::Value(color) => color.a == 0.0,
+            PropertyBinding::Binding(..) => false,
+        };
+        if is_transparent {
             // Don't add transparent rectangles to the draw list, but do consider them for hit
-            // testing. This allows specifying invisible hit testing areas.
+            // testing. This allows specifying invisible hit testing areas. Bound colors may
+            // become visible later, so they are always added.
             self.add_primitive_to_hit_testing_list(info, clip_and_scroll);
             return;
         }
 
         let prim = BrushPrimitive::new(
-            BrushKind::Solid {
-                color,
-            },
+            BrushKind::new_solid(color),
             segments,
         );
 
@@ -889,9 +1119,7 @@ impl FrameBuilder {
         }
 
         let prim = BrushPrimitive::new(
-            BrushKind::Solid {
-                color,
-            },
+            BrushKind::new_solid(PropertyBinding::Value(color)),
             None,
         );
 
@@ -996,6 +1224,49 @@ impl FrameBuilder {
         }
     }
 
+    pub fn add_path(
+        &mut self,
+        clip_and_scroll: ScrollNodeAndClipChain,
+        path_offset: LayoutVector2D,
+        info: &LayerPrimitiveInfo,
+        ops: &[PathOp],
+        fill_rule: FillRule,
+        fill_color: ColorF,
+        stroke: Option<PathStroke>,
+    ) {
+        // The path is hit tested as its bounds, even when it isn't drawn.
+        self.add_primitive_to_hit_testing_list(info, clip_and_scroll);
+
+        // The ops are in the space of the display item,
+        // before the offset applied to the primitive rect.
+        let origin = info.rect.origin - path_offset;
+
+        let mut brushes = Vec::new();
+        if fill_color.a > 0.0 {
+            brushes.push(BrushKind::Path {
+                color: fill_color,
+                mask: PathMask::new(ops, origin, info.rect.size, fill_rule, None),
+            });
+        }
+        if let Some(stroke) = stroke {
+            if stroke.color.a > 0.0 && stroke.width > 0.0 {
+                brushes.push(BrushKind::Path {
+                    color: stroke.color,
+                    mask: PathMask::new(ops, origin, info.rect.size, fill_rule, Some(stroke)),
+                });
+            }
+        }
+
+        for kind in brushes {
+            let prim_index = self.create_primitive(
+                info,
+                Vec::new(),
+                PrimitiveContainer::Brush(BrushPrimitive::new(kind, None)),
+            );
+            self.add_primitive_to_draw_list(prim_index, clip_and_scroll);
+        }
+    }
+
     pub fn add_border(
         &mut self,
         clip_and_scroll: ScrollNodeAndClipChain,
@@ -1005,186 +1276,32 @@ impl FrameBuilder {
         gradient_stops_count: usize,
     ) {
         let rect = info.rect;
-        let create_segments = |outset: SideOffsets2D<f32>| {
-            // Calculate the modified rect as specific by border-image-outset
-            let origin = LayerPoint::new(rect.origin.x - outset.left, rect.origin.y - outset.top);
-            let size = LayerSize::new(
-                rect.size.width + outset.left + outset.right,
-                rect.size.height + outset.top + outset.bottom,
-            );
-            let rect = LayerRect::new(origin, size);
-
-            let tl_outer = LayerPoint::new(rect.origin.x, rect.origin.y);
-            let tl_inner = tl_outer + vec2(border_item.widths.left, border_item.widths.top);
-
-            let tr_outer = LayerPoint::new(rect.origin.x + rect.size.width, rect.origin.y);
-            let tr_inner = tr_outer + vec2(-border_item.widths.right, border_item.widths.top);
-
-            let bl_outer = LayerPoint::new(rect.origin.x, rect.origin.y + rect.size.height);
-            let bl_inner = bl_outer + vec2(border_item.widths.left, -border_item.widths.bottom);
 
-            let br_outer = LayerPoint::new(
-                rect.origin.x + rect.size.width,
-                rect.origin.y + rect.size.height,
-            );
-            let br_inner = br_outer - vec2(border_item.widths.right, border_item.widths.bottom);
-
-            // Build the list of gradient segments
-            vec![
-                // Top left
-                LayerRect::from_floats(tl_outer.x, tl_outer.y, tl_inner.x, tl_inner.y),
-                // Top right
-                LayerRect::from_floats(tr_inner.x, tr_outer.y, tr_outer.x, tr_inner.y),
-                // Bottom right
-                LayerRect::from_floats(br_inner.x, br_inner.y, br_outer.x, br_outer.y),
-                // Bottom left
-                LayerRect::from_floats(bl_outer.x, bl_inner.y, bl_inner.x, bl_outer.y),
-                // Top
-                LayerRect::from_floats(tl_inner.x, tl_outer.y, tr_inner.x, tl_inner.y),
-                // Bottom
-                LayerRect::from_floats(bl_inner.x, bl_inner.y, br_inner.x, bl_outer.y),
-                // Left
-                LayerRect::from_floats(tl_outer.x, tl_inner.y, tl_inner.x, bl_inner.y),
-                // Right
-                LayerRect::from_floats(tr_inner.x, tr_inner.y, br_outer.x, br_inner.y),
-            ]
+        // Calculate the border image area, as modified by border-image-outset.
+        let outset_rect = |outset: SideOffsets2D<f32>| {
+            LayerRect::new(
+                LayerPoint::new(rect.origin.x - outset.left, rect.origin.y - outset.top),
+                LayerSize::new(
+                    rect.size.width + outset.left + outset.right,
+                    rect.size.height + outset.top + outset.bottom,
+                ),
+            )
         };
 
         match border_item.details {
             BorderDetails::Image(ref border) => {
-                // Calculate the modified rect as specific by border-image-outset
-                let origin = LayerPoint::new(
-                    rect.origin.x - border.outset.left,
-                    rect.origin.y - border.outset.top,
-                );
-                let size = LayerSize::new(
-                    rect.size.width + border.outset.left + border.outset.right,
-                    rect.size.height + border.outset.top + border.outset.bottom,
-                );
-                let rect = LayerRect::new(origin, size);
-
-                // Calculate the local texel coords of the slices.
-                let px0 = 0.0;
-                let px1 = border.patch.slice.left as f32;
-                let px2 = border.patch.width as f32 - border.patch.slice.right as f32;
-                let px3 = border.patch.width as f32;
-
-                let py0 = 0.0;
-                let py1 = border.patch.slice.top as f32;
-                let py2 = border.patch.height as f32 - border.patch.slice.bottom as f32;
-                let py3 = border.patch.height as f32;
-
-                let tl_outer = LayerPoint::new(rect.origin.x, rect.origin.y);
-                let tl_inner = tl_outer + vec2(border_item.widths.left, border_item.widths.top);
-
-                let tr_outer = LayerPoint::new(rect.origin.x + rect.size.width, rect.origin.y);
-                let tr_inner = tr_outer + vec2(-border_item.widths.right, border_item.widths.top);
-
-                let bl_outer = LayerPoint::new(rect.origin.x, rect.origin.y + rect.size.height);
-                let bl_inner = bl_outer + vec2(border_item.widths.left, -border_item.widths.bottom);
-
-                let br_outer = LayerPoint::new(
-                    rect.origin.x + rect.size.width,
-                    rect.origin.y + rect.size.height,
-                );
-                let br_inner = br_outer - vec2(border_item.widths.right, border_item.widths.bottom);
-
-                fn add_segment(
-                    segments: &mut Vec<ImageBorderSegment>,
-                    rect: LayerRect,
-                    uv_rect: TexelRect,
-                    repeat_horizontal: RepeatMode,
-                    repeat_vertical: RepeatMode) {
-                    if uv_rect.uv1.x > uv_rect.uv0.x &&
-                       uv_rect.uv1.y > uv_rect.uv0.y {
-                        segments.push(ImageBorderSegment::new(
-                            rect,
-                            uv_rect,
-                            repeat_horizontal,
-                            repeat_vertical,
-                        ));
-                    }
-                }
-
-                // Build the list of image segments
-                let mut segments = vec![];
-
-                // Top left
-                add_segment(
-                    &mut segments,
-                    LayerRect::from_floats(tl_outer.x, tl_outer.y, tl_inner.x, tl_inner.y),
-                    TexelRect::new(px0, py0, px1, py1),
-                    RepeatMode::Stretch,
-                    RepeatMode::Stretch
-                );
-                // Top right
-                add_segment(
-                    &mut segments,
-                    LayerRect::from_floats(tr_inner.x, tr_outer.y, tr_outer.x, tr_inner.y),
-                    TexelRect::new(px2, py0, px3, py1),
-                    RepeatMode::Stretch,
-                    RepeatMode::Stretch
-                );
-                // Bottom right
-                add_segment(
-                    &mut segments,
-                    LayerRect::from_floats(br_inner.x, br_inner.y, br_outer.x, br_outer.y),
-                    TexelRect::new(px2, py2, px3, py3),
-                    RepeatMode::Stretch,
-                    RepeatMode::Stretch
-                );
-                // Bottom left
-                add_segment(
-                    &mut segments,
-                    LayerRect::from_floats(bl_outer.x, bl_inner.y, bl_inner.x, bl_outer.y),
-                    TexelRect::new(px0, py2, px1, py3),
-                    RepeatMode::Stretch,
-                    RepeatMode::Stretch
-                );
-
-                // Center
-                if border.fill {
-                    add_segment(
-                        &mut segments,
-                        LayerRect::from_floats(tl_inner.x, tl_inner.y, tr_inner.x, bl_inner.y),
-                        TexelRect::new(px1, py1, px2, py2),
-                        border.repeat_horizontal,
-                        border.repeat_vertical
-                    );
-                }
-
-                // Add edge segments.
-
-                // Top
-                add_segment(
-                    &mut segments,
-                    LayerRect::from_floats(tl_inner.x, tl_outer.y, tr_inner.x, tl_inner.y),
-                    TexelRect::new(px1, py0, px2, py1),
+                let segments = create_image_border_segments(
+                    outset_rect(border.outset),
+                    &border_item.widths,
+                    LayerSize::new(border.patch.width as f32, border.patch.height as f32),
+                    SideOffsets2D::new(
+                        border.patch.slice.top as f32,
+                        border.patch.slice.right as f32,
+                        border.patch.slice.bottom as f32,
+                        border.patch.slice.left as f32,
+                    ),
+                    border.fill,
                     border.repeat_horizontal,
-                    RepeatMode::Stretch,
-                );
-                // Bottom
-                add_segment(
-                    &mut segments,
-                    LayerRect::from_floats(bl_inner.x, bl_inner.y, br_inner.x, bl_outer.y),
-                    TexelRect::new(px1, py2, px2, py3),
-                    border.repeat_horizontal,
-                    RepeatMode::Stretch,
-                );
-                // Left
-                add_segment(
-                    &mut segments,
-                    LayerRect::from_floats(tl_outer.x, tl_inner.y, tl_inner.x, bl_inner.y),
-                    TexelRect::new(px0, py1, px1, py2),
-                    RepeatMode::Stretch,
-                    border.repeat_vertical,
-                );
-                // Right
-                add_segment(
-                    &mut segments,
-                    LayerRect::from_floats(tr_inner.x, tr_inner.y, br_outer.x, br_inner.y),
-                    TexelRect::new(px2, py1, px3, py2),
-                    RepeatMode::Stretch,
                     border.repeat_vertical,
                 );
 
@@ -1207,41 +1324,79 @@ impl FrameBuilder {
             BorderDetails::Normal(ref border) => {
                 self.add_normal_border(info, border, &border_item.widths, clip_and_scroll);
             }
-            BorderDetails::Gradient(ref border) => for segment in create_segments(border.outset) {
-                let segment_rel = segment.origin - rect.origin;
-                let mut info = info.clone();
-                info.rect = segment;
-
-                self.add_gradient(
-                    clip_and_scroll,
-                    &info,
-                    border.gradient.start_point - segment_rel,
-                    border.gradient.end_point - segment_rel,
-                    gradient_stops,
-                    gradient_stops_count,
-                    border.gradient.extend_mode,
-                    segment.size,
-                    LayerSize::zero(),
+            BorderDetails::Gradient(ref border) => {
+                // The gradient is sized to the border image area, and its
+                // points are relative to the border rect.
+                let image_rect = outset_rect(border.outset);
+                let image_offset = rect.origin - image_rect.origin;
+                let segments = create_image_border_segments(
+                    image_rect,
+                    &border_item.widths,
+                    image_rect.size,
+                    border.slice,
+                    false,
+                    border.repeat_horizontal,
+                    border.repeat_vertical,
                 );
-            },
+
+                for segment in segments {
+                    let (start_point, end_point) = segment.map_linear_gradient_to_tile(
+                        border.gradient.start_point + image_offset,
+                        border.gradient.end_point + image_offset,
+                    );
+                    let mut info = info.clone();
+                    info.rect = segment.geom_rect;
+
+                    self.add_gradient(
+                        clip_and_scroll,
+                        &info,
+                        start_point,
+                        end_point,
+                        gradient_stops,
+                        gradient_stops_count,
+                        border.gradient.extend_mode,
+                        segment.stretch_size,
+                        segment.tile_spacing,
+                    );
+                }
+            }
             BorderDetails::RadialGradient(ref border) => {
-                for segment in create_segments(border.outset) {
-                    let segment_rel = segment.origin - rect.origin;
+                let image_rect = outset_rect(border.outset);
+                let image_offset = rect.origin - image_rect.origin;
+                let segments = create_image_border_segments(
+                    image_rect,
+                    &border_item.widths,
+                    image_rect.size,
+                    border.slice,
+                    false,
+                    border.repeat_horizontal,
+                    border.repeat_vertical,
+                );
+
+                for segment in segments {
+                    let (start_center, start_radius, end_center, end_radius, ratio_xy) =
+                        segment.map_radial_gradient_to_tile(
+                            border.gradient.start_center + image_offset,
+                            border.gradient.start_radius,
+                            border.gradient.end_center + image_offset,
+                            border.gradient.end_radius,
+                            border.gradient.ratio_xy,
+                        );
                     let mut info = info.clone();
-                    info.rect = segment;
+                    info.rect = segment.geom_rect;
 
                     self.add_radial_gradient(
                         clip_and_scroll,
                         &info,
-                        border.gradient.start_center - segment_rel,
-                        border.gradient.start_radius,
-                        border.gradient.end_center - segment_rel,
-                        border.gradient.end_radius,
-                        border.gradient.ratio_xy,
+                        start_center,
+                        start_radius,
+                        end_center,
+                        end_radius,
+                        ratio_xy,
                         gradient_stops,
                         border.gradient.extend_mode,
-                        segment.size,
-                        LayerSize::zero(),
+                        segment.stretch_size,
+                        segment.tile_spacing,
                     );
                 }
             }
@@ -1432,6 +1587,90 @@ impl FrameBuilder {
         }
     }
 
+    fn add_conic_gradient_impl(
+        &mut self,
+        clip_and_scroll: ScrollNodeAndClipChain,
+        info: &LayerPrimitiveInfo,
+        center: LayerPoint,
+        angle: f32,
+        start_offset: f32,
+        end_offset: f32,
+        stops: ItemRange<GradientStop>,
+        extend_mode: ExtendMode,
+        gradient_index: CachedGradientIndex,
+    ) {
+        let prim = BrushPrimitive::new(
+            BrushKind::ConicGradient {
+                stops_range: stops,
+                extend_mode,
+                center,
+                angle,
+                start_offset,
+                end_offset,
+                gradient_index,
+            },
+            None,
+        );
+
+        self.add_primitive(
+            clip_and_scroll,
+            info,
+            Vec::new(),
+            PrimitiveContainer::Brush(prim),
+        );
+    }
+
+    pub fn add_conic_gradient(
+        &mut self,
+        clip_and_scroll: ScrollNodeAndClipChain,
+        info: &LayerPrimitiveInfo,
+        center: LayerPoint,
+        angle: f32,
+        start_offset: f32,
+        end_offset: f32,
+        stops: ItemRange<GradientStop>,
+        extend_mode: ExtendMode,
+        tile_size: LayerSize,
+        tile_spacing: LayerSize,
+    ) {
+        let gradient_index = CachedGradientIndex(self.cached_gradients.len());
+        self.cached_gradients.push(CachedGradient::new());
+
+        let prim_infos = info.decompose(
+            tile_size,
+            tile_spacing,
+            64 * 64,
+        );
+
+        if prim_infos.is_empty() {
+            self.add_conic_gradient_impl(
+                clip_and_scroll,
+                info,
+                center,
+                angle,
+                start_offset,
+                end_offset,
+                stops,
+                extend_mode,
+                gradient_index,
+            );
+        } else {
+            for prim_info in prim_infos {
+                self.add_conic_gradient_impl(
+                    clip_and_scroll,
+                    &prim_info,
+                    center,
+                    angle,
+                    start_offset,
+                    end_offset,
+                    stops,
+                    extend_mode,
+                    gradient_index,
+                );
+            }
+        }
+    }
+
     pub fn add_text(
         &mut self,
         clip_and_scroll: ScrollNodeAndClipChain,
@@ -1615,16 +1854,17 @@ impl FrameBuilder {
         };
 
         // See if conditions are met to run through the new
-        // image brush shader, which supports segments.
+        // image brush shader, which supports segments and
+        // sub-rects.
         if tile_spacing == LayerSize::zero() &&
            stretch_size == info.rect.size &&
-           sub_rect.is_none() &&
            tile_offset.is_none() {
             let prim = BrushPrimitive::new(
                 BrushKind::Image {
                     request,
                     current_epoch: Epoch::invalid(),
                     alpha_type,
+                    sub_rect,
                 },
                 None,
             );
@@ -2,13 +2,16 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{BorderRadius, ClipMode, HitTestFlags, HitTestItem, HitTestResult, ItemTag, LayerPoint};
-use api::{LayerPrimitiveInfo, LayerRect, LocalClip, PipelineId, WorldPoint};
-use clip::{ClipSource, ClipStore, Contains, rounded_rectangle_contains_point};
+use api::{BorderRadius, ClipMode, DocumentId, FillRule, HitTestArea, HitTestFlags, HitTestItem};
+use api::{HitTestResult, ItemTag, LayerPoint, LayerPrimitiveInfo, LayerRect, LayerSize, LocalClip};
+use api::{PipelineId, WorldPoint, WorldRect, WorldSize};
+use clip::{ClipSource, ClipStore, Contains, polygon_contains_point};
+use clip::rounded_rectangle_contains_point;
 use clip_scroll_node::{ClipScrollNode, NodeType};
 use clip_scroll_tree::{ClipChainIndex, ClipScrollNodeIndex, ClipScrollTree};
 use internal_types::FastHashMap;
 use prim_store::ScrollNodeAndClipChain;
+use std::sync::{Arc, RwLock};
 use util::LayerToWorldFastTransform;
 
 /// A copy of important clip scroll node data to use during hit testing. This a copy of
@@ -75,6 +78,7 @@ pub struct HitTestingRun(pub Vec<HitTestingItem>, pub ScrollNodeAndClipChain);
 enum HitTestRegion {
     Rectangle(LayerRect),
     RoundedRectangle(LayerRect, BorderRadius, ClipMode),
+    Polygon(Vec<LayerPoint>, FillRule, ClipMode),
 }
 
 impl HitTestRegion {
@@ -85,6 +89,24 @@ impl HitTestRegion {
                 rounded_rectangle_contains_point(point, &rect, &radii),
             &HitTestRegion::RoundedRectangle(rect, radii, ClipMode::ClipOut) =>
                 !rounded_rectangle_contains_point(point, &rect, &radii),
+            &HitTestRegion::Polygon(ref points, fill_rule, ClipMode::Clip) =>
+                polygon_contains_point(point, points, fill_rule),
+            &HitTestRegion::Polygon(ref points, fill_rule, ClipMode::ClipOut) =>
+                !polygon_contains_point(point, points, fill_rule),
+        }
+    }
+
+    /// The part of the rect that may be inside this region. Curved and polygonal regions
+    /// are approximated by their bounds, and regions that clip out don't clip the rect.
+    pub fn clip_rect(&self, rect: &LayerRect) -> Option<LayerRect> {
+        match self {
+            &HitTestRegion::Rectangle(ref rectangle) |
+            &HitTestRegion::RoundedRectangle(ref rectangle, _, ClipMode::Clip) =>
+                rect.intersection(rectangle),
+            &HitTestRegion::Polygon(ref points, _, ClipMode::Clip) =>
+                rect.intersection(&LayerRect::from_points(points)),
+            &HitTestRegion::RoundedRectangle(_, _, ClipMode::ClipOut) |
+            &HitTestRegion::Polygon(_, _, ClipMode::ClipOut) => Some(*rect),
         }
     }
 }
@@ -218,9 +240,16 @@ impl HitTester {
         true
     }
 
-    pub fn hit_test(&self, mut test: HitTest) -> HitTestResult {
-        let point = test.get_absolute_point(self);
+    pub fn hit_test(&self, test: HitTest) -> HitTestResult {
+        match test.get_absolute_area(self) {
+            HitTestArea::Point(point) => self.hit_test_point(point, test),
+            HitTestArea::Rect(rect) => self.hit_test_rect(rect, test),
+            HitTestArea::Nearest(point, max_distance) =>
+                self.hit_test_nearest(point, max_distance, test),
+        }
+    }
 
+    fn hit_test_point(&self, point: WorldPoint, mut test: HitTest) -> HitTestResult {
         let mut result = HitTestResult::default();
         for &HitTestingRun(ref items, ref clip_and_scroll) in self.runs.iter().rev() {
             let scroll_node_id = clip_and_scroll.scroll_node_id;
@@ -264,11 +293,13 @@ impl HitTester {
                     None => continue,
                 };
 
+                let point_relative_to_item = point_in_layer - item.rect.origin.to_vector();
                 result.items.push(HitTestItem {
                     pipeline: pipeline_id,
                     tag: item.tag,
                     point_in_viewport,
-                    point_relative_to_item: point_in_layer - item.rect.origin.to_vector(),
+                    point_relative_to_item,
+                    intersection: LayerRect::new(point_relative_to_item, LayerSize::zero()),
                 });
                 if !test.flags.contains(HitTestFlags::FIND_ALL) {
                     return result;
@@ -280,6 +311,197 @@ impl HitTester {
         result
     }
 
+    /// The part of the world rect that the clips of the node may let through.
+    fn clip_world_rect_for_node(
+        &self,
+        rect: &WorldRect,
+        node_index: ClipScrollNodeIndex,
+    ) -> Option<WorldRect> {
+        let node = &self.nodes[node_index.0];
+        if node.regions.is_empty() {
+            return Some(*rect);
+        }
+
+        let transform = &node.world_viewport_transform;
+        let mut rect_in_node = transform.unapply(rect)?;
+
+        for region in &node.regions {
+            rect_in_node = region.clip_rect(&rect_in_node)?;
+        }
+        transform.transform_rect(&rect_in_node).intersection(rect)
+    }
+
+    /// The part of the world rect that the clips of the clip chain may let through.
+    fn clip_world_rect_for_clip_chain(
+        &self,
+        rect: &WorldRect,
+        clip_chain_index: ClipChainIndex,
+        cache: &mut FastHashMap<ClipChainIndex, Option<WorldRect>>,
+    ) -> Option<WorldRect> {
+        if let Some(result) = cache.get(&clip_chain_index) {
+            return *result;
+        }
+
+        let descriptor = &self.clip_chains[clip_chain_index.0];
+        let mut result = match descriptor.parent {
+            None => Some(*rect),
+            Some(parent) => self.clip_world_rect_for_clip_chain(rect, parent, cache),
+        };
+        for clip_node_index in &descriptor.clips {
+            result = match result {
+                Some(rect) => self.clip_world_rect_for_node(&rect, *clip_node_index),
+                None => break,
+            };
+        }
+
+        cache.insert(clip_chain_index, result);
+        result
+    }
+
+    /// Call `callback` with every item that intersects the world rect, from front to back,
+    /// along with the part of the item inside the rect, in the space of the item, and the
+    /// scroll node of the item. Stops when the callback returns false.
+    fn for_each_item_in_rect<F>(&self, rect: &WorldRect, test: &HitTest, mut callback: F)
+    where
+        F: FnMut(PipelineId, &HitTestingItem, LayerRect, &HitTestClipScrollNode) -> bool,
+    {
+        let mut clip_chain_cache = FastHashMap::default();
+        for &HitTestingRun(ref items, ref clip_and_scroll) in self.runs.iter().rev() {
+            let scroll_node = &self.nodes[clip_and_scroll.scroll_node_id.0];
+            let pipeline_id = scroll_node.pipeline_id;
+            match (test.pipeline_id, pipeline_id) {
+                (Some(id), node_id) if node_id != id => continue,
+                _ => {},
+            }
+
+            // As with points, the root reference frame is checked as well, because items
+            // that are clipped by clip chains won't test against that part of the hierarchy.
+            let root_node_index = self.pipeline_root_nodes[&pipeline_id];
+            let clipped_rect = self
+                .clip_world_rect_for_clip_chain(
+                    rect,
+                    clip_and_scroll.clip_chain_index,
+                    &mut clip_chain_cache,
+                )
+                .and_then(|rect| self.clip_world_rect_for_node(&rect, root_node_index));
+            let rect_in_layer = match clipped_rect
+                .and_then(|rect| scroll_node.world_content_transform.unapply(&rect)) {
+                Some(rect) => rect,
+                None => continue,
+            };
+
+            for item in items.iter().rev() {
+                let intersection = item.rect
+                    .intersection(&rect_in_layer)
+                    .and_then(|rect| rect.intersection(item.clip.clip_rect()));
+                if let Some(intersection) = intersection {
+                    if !callback(pipeline_id, item, intersection, scroll_node) {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The point in the viewport of the root reference frame of the pipeline.
+    fn point_in_viewport(&self, point: &WorldPoint, pipeline_id: PipelineId) -> Option<LayerPoint> {
+        let root_node = self.get_pipeline_root(pipeline_id);
+        let inverse = root_node.world_viewport_transform.inverse()?;
+        Some(inverse.transform_point2d(point) - root_node.node_origin.to_vector())
+    }
+
+    fn make_area_item(
+        &self,
+        pipeline_id: PipelineId,
+        item: &HitTestingItem,
+        intersection: &LayerRect,
+        point_in_layer: &LayerPoint,
+        scroll_node: &HitTestClipScrollNode,
+    ) -> Option<HitTestItem> {
+        let point = scroll_node.world_content_transform.transform_point2d(point_in_layer);
+        let item_origin = item.rect.origin.to_vector();
+        Some(HitTestItem {
+            pipeline: pipeline_id,
+            tag: item.tag,
+            point_in_viewport: self.point_in_viewport(&point, pipeline_id)?,
+            point_relative_to_item: *point_in_layer - item_origin,
+            intersection: intersection.translate(&-item_origin),
+        })
+    }
+
+    fn hit_test_rect(&self, rect: WorldRect, test: HitTest) -> HitTestResult {
+        let mut result = HitTestResult::default();
+        self.for_each_item_in_rect(&rect, &test, |pipeline_id, item, intersection, scroll_node| {
+            let center = LayerPoint::new(
+                intersection.origin.x + intersection.size.width * 0.5,
+                intersection.origin.y + intersection.size.height * 0.5,
+            );
+            if let Some(hit) =
+                self.make_area_item(pipeline_id, item, &intersection, &center, scroll_node) {
+                result.items.push(hit);
+                if !test.flags.contains(HitTestFlags::FIND_ALL) {
+                    return false;
+                }
+            }
+            true
+        });
+
+        result.items.dedup();
+        result
+    }
+
+    fn hit_test_nearest(
+        &self,
+        point: WorldPoint,
+        max_distance: f32,
+        test: HitTest,
+    ) -> HitTestResult {
+        let rect = WorldRect::new(
+            point - WorldPoint::new(max_distance, max_distance).to_vector(),
+            WorldSize::new(max_distance * 2.0, max_distance * 2.0),
+        );
+
+        let mut nearest: Option<(f32, HitTestItem)> = None;
+        self.for_each_item_in_rect(&rect, &test, |pipeline_id, item, intersection, scroll_node| {
+            let point_in_layer = match scroll_node.world_content_transform.inverse() {
+                Some(inverse) => inverse.transform_point2d(&point),
+                None => return true,
+            };
+
+            // The point of the visible part of the item that is nearest to the hit test point.
+            let nearest_in_layer = LayerPoint::new(
+                point_in_layer.x.max(intersection.min_x()).min(intersection.max_x()),
+                point_in_layer.y.max(intersection.min_y()).min(intersection.max_y()),
+            );
+            let nearest_point = scroll_node.world_content_transform
+                .transform_point2d(&nearest_in_layer);
+            let distance = (nearest_point - point).length();
+
+            // Items are visited from front to back, so the frontmost item wins a tie.
+            let is_nearer = nearest.as_ref().map_or(true, |&(nearest_distance, _)| {
+                distance < nearest_distance
+            });
+            if distance <= max_distance && is_nearer {
+                if let Some(hit) = self.make_area_item(
+                    pipeline_id,
+                    item,
+                    &intersection,
+                    &nearest_in_layer,
+                    scroll_node,
+                ) {
+                    nearest = Some((distance, hit));
+                }
+            }
+
+            // Nothing can be nearer than an item under the point.
+            distance > 0.0
+        });
+
+        HitTestResult {
+            items: nearest.into_iter().map(|(_, item)| item).collect(),
+        }
+    }
+
     pub fn get_pipeline_root(&self, pipeline_id: PipelineId) -> &HitTestClipScrollNode {
         &self.nodes[self.pipeline_root_nodes[&pipeline_id].0]
     }
@@ -300,6 +522,8 @@ fn get_regions_for_clip_scroll_node(
             ClipSource::RoundedRectangle(ref rect, ref radii, ref mode) =>
                 HitTestRegion::RoundedRectangle(*rect, *radii, *mode),
             ClipSource::Image(ref mask) => HitTestRegion::Rectangle(mask.rect),
+            ClipSource::Polygon(ref polygon) =>
+                HitTestRegion::Polygon(polygon.points.clone(), polygon.fill_rule, polygon.mode),
             ClipSource::BorderCorner(_) =>
                 unreachable!("Didn't expect to hit test against BorderCorner"),
         }
@@ -308,7 +532,7 @@ fn get_regions_for_clip_scroll_node(
 
 pub struct HitTest {
     pipeline_id: Option<PipelineId>,
-    point: WorldPoint,
+    area: HitTestArea,
     flags: HitTestFlags,
     node_cache: FastHashMap<ClipScrollNodeIndex, Option<LayerPoint>>,
     clip_chain_cache: Vec<Option<bool>>,
@@ -317,12 +541,12 @@ pub struct HitTest {
 impl HitTest {
     pub fn new(
         pipeline_id: Option<PipelineId>,
-        point: WorldPoint,
+        area: HitTestArea,
         flags: HitTestFlags,
     ) -> HitTest {
         HitTest {
             pipeline_id,
-            point,
+            area,
             flags,
             node_cache: FastHashMap::default(),
             clip_chain_cache: Vec::new(),
@@ -344,14 +568,81 @@ impl HitTest {
         self.clip_chain_cache[index.0] = Some(value);
     }
 
-    pub fn get_absolute_point(&self, hit_tester: &HitTester) -> WorldPoint {
+    pub fn get_absolute_area(&self, hit_tester: &HitTester) -> HitTestArea {
+        match self.area {
+            HitTestArea::Point(point) =>
+                HitTestArea::Point(self.get_absolute_point(hit_tester, point)),
+            HitTestArea::Rect(rect) => {
+                let rect = match self.pipeline_root_transform(hit_tester) {
+                    Some(transform) => transform.transform_rect(&LayerRect::from_untyped(
+                        &rect.to_untyped())
+                    ),
+                    None => rect,
+                };
+                HitTestArea::Rect(rect)
+            }
+            HitTestArea::Nearest(point, max_distance) =>
+                HitTestArea::Nearest(self.get_absolute_point(hit_tester, point), max_distance),
+        }
+    }
+
+    fn pipeline_root_transform<'a>(
+        &self,
+        hit_tester: &'a HitTester,
+    ) -> Option<&'a LayerToWorldFastTransform> {
         if !self.flags.contains(HitTestFlags::POINT_RELATIVE_TO_PIPELINE_VIEWPORT) {
-            return self.point;
+            return None;
         }
 
-        let point =  &LayerPoint::new(self.point.x, self.point.y);
-        self.pipeline_id.map(|id|
-            hit_tester.get_pipeline_root(id).world_viewport_transform.transform_point2d(&point)
-        ).unwrap_or_else(|| WorldPoint::new(self.point.x, self.point.y))
+        self.pipeline_id.map(|id| &hit_tester.get_pipeline_root(id).world_viewport_transform)
+    }
+
+    fn get_absolute_point(&self, hit_tester: &HitTester, point: WorldPoint) -> WorldPoint {
+        let point = &LayerPoint::new(point.x, point.y);
+        self.pipeline_root_transform(hit_tester)
+            .map(|transform| transform.transform_point2d(&point))
+            .unwrap_or_else(|| WorldPoint::new(point.x, point.y))
+    }
+}
+
+/// The hit testers of the last frames rendered for each document. The render backend
+/// publishes a new hit tester whenever it renders a frame, and clients can hit test
+/// against it from any thread, without waiting for the render backend to handle a
+/// message. The results are consistent with the last rendered frame, including its
+/// scroll offsets.
+#[derive(Clone, Default)]
+pub struct SharedHitTesters {
+    hit_testers: Arc<RwLock<FastHashMap<DocumentId, Arc<HitTester>>>>,
+}
+
+impl SharedHitTesters {
+    pub fn new() -> Self {
+        SharedHitTesters::default()
+    }
+
+    pub fn publish(&self, document_id: DocumentId, hit_tester: Arc<HitTester>) {
+        self.hit_testers.write().unwrap().insert(document_id, hit_tester);
+    }
+
+    pub fn remove(&self, document_id: DocumentId) {
+        self.hit_testers.write().unwrap().remove(&document_id);
+    }
+
+    /// Does a hit test like `RenderApi::hit_test_area`. There are no results for a
+    /// document that hasn't rendered a frame yet.
+    pub fn hit_test(
+        &self,
+        document_id: DocumentId,
+        pipeline_id: Option<PipelineId>,
+        area: HitTestArea,
+        flags: HitTestFlags,
+    ) -> HitTestResult {
+        // The lock is only held while cloning the `Arc`, so that publishing the
+        // hit tester of a new frame doesn't wait for hit tests to finish.
+        let hit_tester = self.hit_testers.read().unwrap().get(&document_id).cloned();
+        match hit_tester {
+            Some(hit_tester) => hit_tester.hit_test(HitTest::new(pipeline_id, area, flags)),
+            None => HitTestResult::default(),
+        }
     }
 }
@@ -52,6 +52,7 @@ extern crate thread_profiler;
 #[macro_use]
 extern crate serde;
 
+mod animation;
 mod batch;
 mod border;
 mod box_shadow;
@@ -79,6 +80,7 @@ mod gpu_cache;
 mod gpu_types;
 mod hit_test;
 mod internal_types;
+mod path;
 mod picture;
 mod prim_store;
 mod print_tree;
@@ -167,6 +169,7 @@ pub extern crate webrender_api;
 
 #[doc(hidden)]
 pub use device::{build_shader_strings, ProgramCache, ReadPixelsFormat, UploadMethod, VertexUsageHint};
+pub use hit_test::SharedHitTesters;
 pub use renderer::{CpuProfile, DebugFlags, GpuProfile, OutputImageHandler, RendererKind};
 pub use renderer::{ExternalImage, ExternalImageHandler, ExternalImageSource};
 pub use renderer::{GraphicsApi, GraphicsApiInfo, Renderer, RendererOptions};
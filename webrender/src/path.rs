@@ -0,0 +1,671 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use api::{DeviceIntSize, FillRule, LayerPoint, LayerSize, LayerVector2D};
+use api::{LineCap, LineJoin, PathOp, PathStroke};
+use app_units::Au;
+use gpu_cache::{GpuCache, GpuCacheHandle};
+use renderer::MAX_VERTEX_TEXTURE_WIDTH;
+use resource_cache::CacheItem;
+use std::f32;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+// Paths are flattened into a list of line segments (edges) on the CPU.
+// The coverage of those edges is then rasterized on the GPU by the
+// cs_path shader into an A8 mask in the render task cache, which is
+// drawn with the brush picture shader. Strokes are expanded into a
+// set of polygons that all wind the same way, so that they can be
+// filled with the nonzero rule.
+
+// The edges are stored in a single row of the GPU cache,
+// after one header block.
+pub const MAX_PATH_EDGES: usize = MAX_VERTEX_TEXTURE_WIDTH - 1;
+
+// The largest width or height of a path mask, in device pixels.
+const MAX_PATH_MASK_SIZE: f32 = 2048.0;
+
+// The largest distance between a curve and its flattened
+// line segments, in device pixels.
+const PATH_TOLERANCE: f32 = 0.25;
+
+// Limits on the number of line segments used for a single
+// curve or circle, however large it is.
+const MAX_CURVE_SEGMENTS: usize = 100;
+const MIN_CIRCLE_SEGMENTS: usize = 8;
+
+// The number of times a path with too many edges is flattened
+// again with a larger tolerance, before checking whether it can
+// fit at all.
+const MAX_FLATTEN_ATTEMPTS: usize = 4;
+
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct PathPointKey {
+    pub x: Au,
+    pub y: Au,
+}
+
+impl<'a> From<&'a LayerPoint> for PathPointKey {
+    fn from(point: &'a LayerPoint) -> PathPointKey {
+        PathPointKey {
+            x: Au::from_f32_px(point.x),
+            y: Au::from_f32_px(point.y),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum PathOpKey {
+    MoveTo(PathPointKey),
+    LineTo(PathPointKey),
+    QuadTo(PathPointKey, PathPointKey),
+    CubicTo(PathPointKey, PathPointKey, PathPointKey),
+    Close,
+}
+
+impl<'a> From<&'a PathOp> for PathOpKey {
+    fn from(op: &'a PathOp) -> PathOpKey {
+        match *op {
+            PathOp::MoveTo(ref p) => PathOpKey::MoveTo(p.into()),
+            PathOp::LineTo(ref p) => PathOpKey::LineTo(p.into()),
+            PathOp::QuadTo(ref c, ref p) => PathOpKey::QuadTo(c.into(), p.into()),
+            PathOp::CubicTo(ref c1, ref c2, ref p) => {
+                PathOpKey::CubicTo(c1.into(), c2.into(), p.into())
+            }
+            PathOp::Close => PathOpKey::Close,
+        }
+    }
+}
+
+// How the edges of a path are made from its ops.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum PathModeKey {
+    Fill(FillRule),
+    Stroke {
+        width: Au,
+        miter_limit: Au,
+        join: LineJoin,
+        cap: LineCap,
+    },
+}
+
+// The geometry of a path, with its coordinates quantized to app
+// units (1/60 px) like those of a box shadow. Paths that differ by
+// less than that can share a key, and so share the mask of
+// whichever of them was rasterized first. The ops are shared with
+// the primitive, so that making the key every frame is cheap.
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct PathCacheKey {
+    pub ops: Arc<Vec<PathOpKey>>,
+    pub mode: PathModeKey,
+    pub width: Au,
+    pub height: Au,
+    pub scale: Au,
+}
+
+// A list of connected points, made from one sub-path.
+#[derive(Debug)]
+struct Contour {
+    points: Vec<LayerPoint>,
+    closed: bool,
+}
+
+#[derive(Debug)]
+pub struct PathMask {
+    // The path, relative to the origin of the primitive rect.
+    ops: Vec<PathOp>,
+    fill_rule: FillRule,
+    // If present, the mask covers the stroke of the path
+    // rather than its interior.
+    stroke: Option<PathStroke>,
+    size: LayerSize,
+    // The key of the mask. Its scale is the raster scale the edges
+    // were last flattened at, which is zero until they are first
+    // flattened, since `mask_size` never returns a zero scale.
+    key: PathCacheKey,
+    // None if the path has too many edges to be drawn.
+    edges: Option<Vec<[LayerPoint; 2]>>,
+    pub edges_handle: GpuCacheHandle,
+    pub cache_item: CacheItem,
+}
+
+impl PathMask {
+    // The ops are in the same space as `origin`, which is
+    // the origin of the primitive rect.
+    pub fn new(
+        ops: &[PathOp],
+        origin: LayerPoint,
+        size: LayerSize,
+        fill_rule: FillRule,
+        stroke: Option<PathStroke>,
+    ) -> Self {
+        // Make the path relative to the primitive, so that moving
+        // the primitive around doesn't invalidate the cached mask.
+        let offset = origin.to_vector();
+        let ops: Vec<PathOp> = ops
+            .iter()
+            .map(|op| translate_op(op, -offset))
+            .collect();
+
+        let ops_key = ops.iter().map(PathOpKey::from).collect();
+        let mode = match stroke {
+            Some(ref stroke) => PathModeKey::Stroke {
+                width: Au::from_f32_px(stroke.width),
+                miter_limit: Au::from_f32_px(stroke.miter_limit),
+                join: stroke.join,
+                cap: stroke.cap,
+            },
+            None => PathModeKey::Fill(fill_rule),
+        };
+        let key = PathCacheKey {
+            ops: Arc::new(ops_key),
+            mode,
+            width: Au::from_f32_px(size.width),
+            height: Au::from_f32_px(size.height),
+            scale: Au(0),
+        };
+
+        PathMask {
+            ops,
+            fill_rule,
+            stroke,
+            size,
+            key,
+            edges: None,
+            edges_handle: GpuCacheHandle::new(),
+            cache_item: CacheItem::invalid(),
+        }
+    }
+
+    // Returns the size of the mask, and the scale it is rasterized
+    // at, for a primitive drawn at the given device scale.
+    pub fn mask_size(&self, device_scale: f32) -> (DeviceIntSize, f32) {
+        let max_extent = self.size.width.max(self.size.height);
+        let mut scale = device_scale;
+        if max_extent * scale > MAX_PATH_MASK_SIZE {
+            scale = MAX_PATH_MASK_SIZE / max_extent;
+        }
+        // Quantize the scale, so that small changes don't
+        // cause the mask to be rasterized again. It is kept above
+        // zero, which would make the flattening tolerance infinite,
+        // and the mask size is clamped again for paths that are
+        // too large even at the smallest scale.
+        let scale = Au::from_f32_px(scale).max(Au(1)).to_f32_px();
+
+        let size = DeviceIntSize::new(
+            (self.size.width * scale).ceil().max(1.0).min(MAX_PATH_MASK_SIZE) as i32,
+            (self.size.height * scale).ceil().max(1.0).min(MAX_PATH_MASK_SIZE) as i32,
+        );
+
+        (size, scale)
+    }
+
+    // Flatten the path for the given raster scale, if it changed
+    // since the last frame, and return the key of the mask, or None
+    // if the path has too many edges to be drawn.
+    pub fn update(
+        &mut self,
+        scale: f32,
+        gpu_cache: &mut GpuCache,
+    ) -> Option<&PathCacheKey> {
+        let scale_au = Au::from_f32_px(scale);
+
+        if self.key.scale != scale_au {
+            self.key.scale = scale_au;
+            self.edges = self.build_edges(PATH_TOLERANCE / scale);
+            gpu_cache.invalidate(&self.edges_handle);
+        }
+
+        match self.edges {
+            Some(..) => Some(&self.key),
+            None => None,
+        }
+    }
+
+    pub fn request_edges(&mut self, gpu_cache: &mut GpuCache) {
+        let edges = match self.edges {
+            Some(ref edges) => edges,
+            None => return,
+        };
+        if let Some(mut request) = gpu_cache.request(&mut self.edges_handle) {
+            // Strokes are always filled with the nonzero rule.
+            let fill_rule = match self.stroke {
+                Some(..) => FillRule::Nonzero,
+                None => self.fill_rule,
+            };
+
+            // Note: has to match cs_path.glsl
+            request.push([
+                edges.len() as f32,
+                fill_rule as u32 as f32,
+                0.0,
+                0.0,
+            ]);
+            for edge in edges {
+                request.push([edge[0].x, edge[0].y, edge[1].x, edge[1].y]);
+            }
+        }
+    }
+
+    // Flatten the path, raising the tolerance until its edges fit
+    // in the GPU cache. Dropping edges would leave contours open,
+    // so a path that doesn't fit even when every curve is a single
+    // line segment isn't drawn at all.
+    fn build_edges(&self, tolerance: f32) -> Option<Vec<[LayerPoint; 2]>> {
+        let mut tolerance = tolerance;
+
+        for _ in 0 .. MAX_FLATTEN_ATTEMPTS {
+            let edges = self.build_edges_with_tolerance(tolerance);
+            if edges.len() <= MAX_PATH_EDGES {
+                return Some(edges);
+            }
+            tolerance *= 4.0;
+        }
+
+        let coarsest = self.build_edges_with_tolerance(f32::INFINITY);
+        if coarsest.len() > MAX_PATH_EDGES {
+            warn!("Path has too many edges ({}) to be drawn", coarsest.len());
+            return None;
+        }
+
+        // This ends at the latest when the tolerance overflows to
+        // infinity, which gives the edges that are known to fit.
+        loop {
+            let edges = self.build_edges_with_tolerance(tolerance);
+            if edges.len() <= MAX_PATH_EDGES {
+                return Some(edges);
+            }
+            tolerance *= 4.0;
+        }
+    }
+
+    fn build_edges_with_tolerance(&self, tolerance: f32) -> Vec<[LayerPoint; 2]> {
+        let contours = flatten(&self.ops, tolerance);
+        let mut edges = Vec::new();
+
+        match self.stroke {
+            Some(ref stroke) => {
+                for polygon in stroke_contours(&contours, stroke, tolerance) {
+                    add_polygon_edges(&polygon, &mut edges);
+                }
+            }
+            None => {
+                // Sub-paths are implicitly closed when filled.
+                for contour in &contours {
+                    add_polygon_edges(&contour.points, &mut edges);
+                }
+            }
+        }
+
+        edges
+    }
+}
+
+fn translate_op(op: &PathOp, offset: LayerVector2D) -> PathOp {
+    match *op {
+        PathOp::MoveTo(p) => PathOp::MoveTo(p + offset),
+        PathOp::LineTo(p) => PathOp::LineTo(p + offset),
+        PathOp::QuadTo(c, p) => PathOp::QuadTo(c + offset, p + offset),
+        PathOp::CubicTo(c1, c2, p) => PathOp::CubicTo(c1 + offset, c2 + offset, p + offset),
+        PathOp::Close => PathOp::Close,
+    }
+}
+
+// The number of line segments needed to keep a curve within the
+// tolerance, from Wang's formula. `dd` is the length of the largest
+// second difference of the control points, and `degree` the degree
+// of the curve.
+fn curve_segment_count(dd: f32, degree: f32, tolerance: f32) -> usize {
+    let n = (degree * (degree - 1.0) * dd / (8.0 * tolerance)).sqrt().ceil();
+    if n.is_finite() {
+        (n as usize).max(1).min(MAX_CURVE_SEGMENTS)
+    } else {
+        1
+    }
+}
+
+fn flatten(ops: &[PathOp], tolerance: f32) -> Vec<Contour> {
+    let mut contours = Vec::new();
+    let mut points: Vec<LayerPoint> = Vec::new();
+    let mut current = LayerPoint::zero();
+
+    for op in ops {
+        // Drawing without a preceding move starts
+        // a new sub-path at the current point.
+        let starts_sub_path = match *op {
+            PathOp::MoveTo(..) | PathOp::Close => false,
+            _ => points.is_empty(),
+        };
+        if starts_sub_path {
+            points.push(current);
+        }
+
+        match *op {
+            PathOp::MoveTo(p) => {
+                if !points.is_empty() {
+                    contours.push(Contour { points, closed: false });
+                }
+                points = vec![p];
+                current = p;
+            }
+            PathOp::LineTo(p) => {
+                points.push(p);
+                current = p;
+            }
+            PathOp::QuadTo(c, p) => {
+                let dd = (current - c * 2.0 + p.to_vector()).length();
+                let count = curve_segment_count(dd, 2.0, tolerance);
+                for i in 1 .. count + 1 {
+                    let t = i as f32 / count as f32;
+                    let mt = 1.0 - t;
+                    points.push(
+                        (current.to_vector() * (mt * mt) +
+                         c.to_vector() * (2.0 * mt * t) +
+                         p.to_vector() * (t * t)).to_point()
+                    );
+                }
+                current = p;
+            }
+            PathOp::CubicTo(c1, c2, p) => {
+                let dd0 = (current - c1 * 2.0 + c2.to_vector()).length();
+                let dd1 = (c1 - c2 * 2.0 + p.to_vector()).length();
+                let count = curve_segment_count(dd0.max(dd1), 3.0, tolerance);
+                for i in 1 .. count + 1 {
+                    let t = i as f32 / count as f32;
+                    let mt = 1.0 - t;
+                    points.push(
+                        (current.to_vector() * (mt * mt * mt) +
+                         c1.to_vector() * (3.0 * mt * mt * t) +
+                         c2.to_vector() * (3.0 * mt * t * t) +
+                         p.to_vector() * (t * t * t)).to_point()
+                    );
+                }
+                current = p;
+            }
+            PathOp::Close => {
+                if !points.is_empty() {
+                    // The next sub-path starts where this one started.
+                    current = points[0];
+                    contours.push(Contour { points, closed: true });
+                    points = Vec::new();
+                }
+            }
+        }
+    }
+
+    if !points.is_empty() {
+        contours.push(Contour { points, closed: false });
+    }
+
+    contours
+}
+
+// Add the edges of a closed polygon. Horizontal edges
+// never cross a scanline, so they are left out.
+fn add_polygon_edges(points: &[LayerPoint], edges: &mut Vec<[LayerPoint; 2]>) {
+    if points.len() < 2 {
+        return;
+    }
+
+    let mut prev = points[points.len() - 1];
+    for &point in points {
+        if point.y != prev.y {
+            edges.push([prev, point]);
+        }
+        prev = point;
+    }
+}
+
+fn normal(dir: LayerVector2D) -> LayerVector2D {
+    LayerVector2D::new(-dir.y, dir.x)
+}
+
+fn cross(a: LayerVector2D, b: LayerVector2D) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn circle(center: LayerPoint, radius: f32, tolerance: f32) -> Vec<LayerPoint> {
+    let count = if tolerance < radius {
+        let n = (PI / (1.0 - tolerance / radius).acos()).ceil() as usize;
+        n.max(MIN_CIRCLE_SEGMENTS).min(MAX_CURVE_SEGMENTS)
+    } else {
+        MIN_CIRCLE_SEGMENTS
+    };
+
+    (0 .. count)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f32 / count as f32;
+            center + LayerVector2D::new(angle.cos(), angle.sin()) * radius
+        })
+        .collect()
+}
+
+// Make the polygon wind in the positive direction, so that the
+// union of the polygons can be filled with the nonzero rule.
+fn orient(mut polygon: Vec<LayerPoint>) -> Vec<LayerPoint> {
+    let mut area = 0.0;
+    let mut prev = polygon[polygon.len() - 1];
+    for point in &polygon {
+        area += prev.x * point.y - point.x * prev.y;
+        prev = *point;
+    }
+    if area < 0.0 {
+        polygon.reverse();
+    }
+    polygon
+}
+
+fn stroke_contours(
+    contours: &[Contour],
+    stroke: &PathStroke,
+    tolerance: f32,
+) -> Vec<Vec<LayerPoint>> {
+    let hw = 0.5 * stroke.width;
+    let mut polygons = Vec::new();
+
+    for contour in contours {
+        let mut points = contour.points.clone();
+        points.dedup();
+        if contour.closed && points.len() > 1 && points[0] == points[points.len() - 1] {
+            points.pop();
+        }
+
+        // A sub-path without any length only draws its caps.
+        if points.len() == 1 {
+            let p = points[0];
+            match stroke.cap {
+                LineCap::Butt => {}
+                LineCap::Round => polygons.push(circle(p, hw, tolerance)),
+                LineCap::Square => polygons.push(vec![
+                    p + LayerVector2D::new(-hw, -hw),
+                    p + LayerVector2D::new(hw, -hw),
+                    p + LayerVector2D::new(hw, hw),
+                    p + LayerVector2D::new(-hw, hw),
+                ]),
+            }
+            continue;
+        }
+
+        let closed = contour.closed && points.len() > 2;
+        let segment_count = if closed { points.len() } else { points.len() - 1 };
+        let dirs: Vec<LayerVector2D> = (0 .. segment_count)
+            .map(|i| {
+                let a = points[i];
+                let b = points[(i + 1) % points.len()];
+                (b - a) / (b - a).length()
+            })
+            .collect();
+
+        for (i, dir) in dirs.iter().enumerate() {
+            let a = points[i];
+            let b = points[(i + 1) % points.len()];
+            let n = normal(*dir) * hw;
+            polygons.push(vec![a + n, b + n, b - n, a - n]);
+        }
+
+        // Joins between consecutive segments.
+        let first_join = if closed { 0 } else { 1 };
+        for i in first_join .. segment_count {
+            let p = points[i];
+            let d0 = dirs[(i + segment_count - 1) % segment_count];
+            let d1 = dirs[i];
+            let turn = cross(d0, d1);
+            let dot = d0.dot(d1);
+            if turn.abs() < 1.0e-6 && dot > 0.0 {
+                continue;
+            }
+
+            // The side of the stroke on the outside of the turn.
+            let side = if turn > 0.0 { -1.0 } else { 1.0 };
+            let n0 = normal(d0) * (side * hw);
+            let n1 = normal(d1) * (side * hw);
+
+            match stroke.join {
+                LineJoin::Round => {
+                    polygons.push(circle(p, hw, tolerance));
+                }
+                LineJoin::Miter if dot > -1.0 &&
+                    (2.0 / (1.0 + dot)).sqrt() <= stroke.miter_limit => {
+                    let tip = p + (n0 + n1) / (1.0 + dot);
+                    polygons.push(vec![p, p + n0, tip, p + n1]);
+                }
+                LineJoin::Miter | LineJoin::Bevel => {
+                    polygons.push(vec![p, p + n0, p + n1]);
+                }
+            }
+        }
+
+        // Caps at the ends of open sub-paths.
+        if !closed {
+            let ends = [
+                (points[0], -dirs[0]),
+                (points[points.len() - 1], dirs[segment_count - 1]),
+            ];
+            for &(p, dir) in &ends {
+                match stroke.cap {
+                    LineCap::Butt => {}
+                    LineCap::Round => polygons.push(circle(p, hw, tolerance)),
+                    LineCap::Square => {
+                        let n = normal(dir) * hw;
+                        let d = dir * hw;
+                        polygons.push(vec![p + n, p - n, p - n + d, p + n + d]);
+                    }
+                }
+            }
+        }
+    }
+
+    polygons.into_iter().map(orient).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use api::{ColorF, FillRule, LayerPoint, LayerSize, LineCap, LineJoin, PathOp, PathStroke};
+    use super::{MAX_PATH_EDGES, MAX_PATH_MASK_SIZE, PATH_TOLERANCE, PathMask};
+    use super::{flatten, stroke_contours};
+
+    fn winding(polygons: &[Vec<LayerPoint>], p: LayerPoint) -> i32 {
+        let mut winding = 0;
+        for polygon in polygons {
+            let mut prev = polygon[polygon.len() - 1];
+            for &point in polygon {
+                if (prev.y <= p.y) != (point.y <= p.y) {
+                    let t = (p.y - prev.y) / (point.y - prev.y);
+                    let x = prev.x + t * (point.x - prev.x);
+                    if x < p.x {
+                        winding += if point.y > prev.y { 1 } else { -1 };
+                    }
+                }
+                prev = point;
+            }
+        }
+        winding
+    }
+
+    #[test]
+    fn flatten_curves() {
+        let ops = [
+            PathOp::MoveTo(LayerPoint::new(0.0, 0.0)),
+            PathOp::QuadTo(LayerPoint::new(50.0, 100.0), LayerPoint::new(100.0, 0.0)),
+            PathOp::Close,
+            PathOp::LineTo(LayerPoint::new(0.0, 10.0)),
+        ];
+        let contours = flatten(&ops, 0.25);
+        assert_eq!(contours.len(), 2);
+        assert!(contours[0].closed);
+        assert!(contours[0].points.len() > 3);
+        assert_eq!(*contours[0].points.last().unwrap(), LayerPoint::new(100.0, 0.0));
+        // The line after the close starts from the start of the closed sub-path.
+        assert_eq!(contours[1].points, vec![LayerPoint::new(0.0, 0.0), LayerPoint::new(0.0, 10.0)]);
+    }
+
+    #[test]
+    fn stroke_square_caps() {
+        let ops = [
+            PathOp::MoveTo(LayerPoint::new(10.0, 10.0)),
+            PathOp::LineTo(LayerPoint::new(50.0, 10.0)),
+            PathOp::LineTo(LayerPoint::new(50.0, 50.0)),
+        ];
+        let stroke = PathStroke {
+            width: 4.0,
+            color: ColorF::new(0.0, 0.0, 0.0, 1.0),
+            join: LineJoin::Miter,
+            cap: LineCap::Square,
+            miter_limit: 4.0,
+        };
+        let polygons = stroke_contours(&flatten(&ops, 0.25), &stroke, 0.25);
+
+        // All the polygons wind the same way, so the overlaps
+        // at the join don't cancel out.
+        for point in &[(9.0, 10.0), (30.0, 11.5), (51.5, 8.5), (50.0, 51.0)] {
+            assert_ne!(winding(&polygons, LayerPoint::new(point.0, point.1)), 0);
+        }
+        for point in &[(7.0, 10.0), (30.0, 13.0), (53.0, 8.0), (50.0, 53.0), (45.0, 15.0)] {
+            assert_eq!(winding(&polygons, LayerPoint::new(point.0, point.1)), 0);
+        }
+    }
+
+    #[test]
+    fn build_edges_without_truncating() {
+        // Steep curves, which need a larger tolerance to fit.
+        let mut ops = vec![PathOp::MoveTo(LayerPoint::new(0.0, 0.0))];
+        for i in 0 .. 50 {
+            let x = i as f32 * 100.0;
+            ops.push(PathOp::QuadTo(
+                LayerPoint::new(x + 50.0, 10000.0),
+                LayerPoint::new(x + 100.0, 0.0),
+            ));
+        }
+        let size = LayerSize::new(5000.0, 10000.0);
+        let mask = PathMask::new(&ops, LayerPoint::zero(), size, FillRule::Nonzero, None);
+        let edges = mask.build_edges(PATH_TOLERANCE).unwrap();
+        assert!(edges.len() > 50 && edges.len() <= MAX_PATH_EDGES);
+
+        // Straight lines can't be flattened any coarser.
+        let ops: Vec<PathOp> = (0 .. 2000)
+            .map(|i| PathOp::LineTo(LayerPoint::new(i as f32, (i % 2) as f32 * 10.0)))
+            .collect();
+        let size = LayerSize::new(2000.0, 10.0);
+        let mask = PathMask::new(&ops, LayerPoint::zero(), size, FillRule::Nonzero, None);
+        assert!(mask.build_edges(PATH_TOLERANCE).is_none());
+    }
+
+    #[test]
+    fn mask_size_of_huge_path() {
+        let ops = [PathOp::LineTo(LayerPoint::new(1.0e7, 1.0e7))];
+        let size = LayerSize::new(1.0e7, 1.0e7);
+        let mask = PathMask::new(&ops, LayerPoint::zero(), size, FillRule::Nonzero, None);
+        let (size, scale) = mask.mask_size(1.0);
+        assert!(scale > 0.0);
+        assert_eq!(size.width, MAX_PATH_MASK_SIZE as i32);
+    }
+}
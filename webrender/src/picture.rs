@@ -2,9 +2,9 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{BoxShadowClipMode, ColorF, DeviceIntPoint, DeviceIntRect, FilterOp, LayerPoint};
-use api::{LayerRect, LayerToWorldScale, LayerVector2D, MixBlendMode, PipelineId};
-use api::{PremultipliedColorF, Shadow};
+use api::{BoxShadowClipMode, ColorF, ComponentTransferFuncType, DeviceIntPoint, DeviceIntRect};
+use api::{FilterData, FilterOp, LayerPoint, LayerRect, LayerToWorldScale, LayerVector2D};
+use api::{MixBlendMode, PipelineId, PremultipliedColorF, Shadow};
 use box_shadow::{BLUR_SAMPLE_SCALE, BoxShadowCacheKey};
 use clip_scroll_tree::ClipScrollNodeIndex;
 use frame_builder::{FrameContext, FrameState, PictureState};
@@ -13,7 +13,7 @@ use gpu_types::{BrushImageKind, PictureType};
 use prim_store::{BrushKind, BrushPrimitive, PrimitiveIndex, PrimitiveRun, PrimitiveRunLocalRect};
 use prim_store::ScrollNodeAndClipChain;
 use render_task::{ClearMode, RenderTask, RenderTaskCacheKey};
-use render_task::{RenderTaskCacheKeyKind, RenderTaskId, RenderTaskLocation};
+use render_task::{RenderTaskCacheKeyKind, RenderTaskId, RenderTaskLocation, SvgFilterInfo};
 use resource_cache::CacheItem;
 use scene::{FilterOpHelpers, SceneProperties};
 use tiling::RenderTargetKind;
@@ -74,6 +74,8 @@ pub enum PictureKind {
         // For drop-shadow filter, this will store the original
         // picture task which would be rendered on screen after
         // blur pass.
+        // For the input of backdrop filters, this will store the
+        // saved backdrop that is drawn into it.
         secondary_render_task_id: Option<RenderTaskId>,
         /// How this picture should be composited.
         /// If None, don't composite - just draw directly on parent surface.
@@ -93,6 +95,20 @@ pub enum PictureKind {
         // in the GPU cache, depending on the type of
         // picture.
         extra_gpu_data_handle: GpuCacheHandle,
+        // The transfer functions of a component transfer filter.
+        filter_data: Option<FilterData>,
+        // If true, this picture holds the unfiltered content of
+        // a stacking context that has a composite filter, and
+        // is saved for the composite to read.
+        is_source_graphic: bool,
+        // If true, this picture holds what was drawn behind a
+        // stacking context that has backdrop filters, and is
+        // saved for the filters to read.
+        is_backdrop: bool,
+        // If set, this picture draws the saved contents of the
+        // given backdrop picture, as the input of the backdrop
+        // filters of a stacking context.
+        backdrop_prim_index: Option<PrimitiveIndex>,
     },
 }
 
@@ -223,6 +239,10 @@ impl PicturePrimitive {
                 reference_frame_index,
                 real_local_rect: LayerRect::zero(),
                 extra_gpu_data_handle: GpuCacheHandle::new(),
+                filter_data: None,
+                is_source_graphic: false,
+                is_backdrop: false,
+                backdrop_prim_index: None,
             },
             pipeline_id,
             cull_children: true,
@@ -260,9 +280,20 @@ impl PicturePrimitive {
         let local_content_rect = prim_run_rect.local_rect_in_actual_parent_space;
 
         match self.kind {
-            PictureKind::Image { composite_mode, ref mut real_local_rect, .. } => {
+            PictureKind::Image {
+                composite_mode,
+                ref mut real_local_rect,
+                backdrop_prim_index,
+                ..
+            } => {
                 *real_local_rect = prim_run_rect.local_rect_in_original_parent_space;
 
+                // The input of backdrop filters covers the stacking
+                // context, which is known when the scene is built.
+                if backdrop_prim_index.is_some() {
+                    return prim_local_rect;
+                }
+
                 match composite_mode {
                     Some(PictureCompositeMode::Filter(FilterOp::Blur(blur_radius))) => {
                         let inflate_size = blur_radius * BLUR_SAMPLE_SCALE;
@@ -273,6 +304,11 @@ impl PicturePrimitive {
                         local_content_rect.inflate(inflate_size, inflate_size)
                                           .translate(&offset)
                     }
+                    Some(PictureCompositeMode::Filter(FilterOp::Offset(offset))) => {
+                        // Keep the original content rect too, so that a
+                        // composite filter can still draw the source there.
+                        local_content_rect.union(&local_content_rect.translate(&offset))
+                    }
                     _ => {
                         local_content_rect
                     }
@@ -329,7 +365,7 @@ impl PicturePrimitive {
         prim_index: PrimitiveIndex,
         prim_screen_rect: &DeviceIntRect,
         prim_local_rect: &LayerRect,
-        pic_state_for_children: PictureState,
+        mut pic_state_for_children: PictureState,
         pic_state: &mut PictureState,
         frame_context: &FrameContext,
         frame_state: &mut FrameState,
@@ -341,9 +377,35 @@ impl PicturePrimitive {
                 ref mut secondary_render_task_id,
                 ref mut extra_gpu_data_handle,
                 composite_mode,
+                ref filter_data,
+                is_source_graphic,
+                is_backdrop,
+                backdrop_prim_index,
                 ..
             } => {
                 let content_origin = ContentOrigin::Screen(prim_screen_rect.origin);
+
+                // Draw the saved backdrop that the backdrop filters apply to.
+                if let Some(backdrop_prim_index) = backdrop_prim_index {
+                    *secondary_render_task_id = match pic_state.backdrop {
+                        Some((prim_index, task_id)) if prim_index == backdrop_prim_index => {
+                            Some(task_id)
+                        }
+                        _ => None,
+                    };
+                    pic_state_for_children.tasks.extend(*secondary_render_task_id);
+                }
+
+                // Pass the unfiltered content of the stacking context up
+                // through its filter chain, for any composite filter.
+                match composite_mode {
+                    Some(PictureCompositeMode::Filter(..)) |
+                    Some(PictureCompositeMode::MixBlend(..)) => {
+                        pic_state.source_graphic = pic_state_for_children.source_graphic;
+                    }
+                    Some(PictureCompositeMode::Blit) | None => {}
+                }
+
                 match composite_mode {
                     Some(PictureCompositeMode::Filter(FilterOp::Blur(blur_radius))) => {
                         let picture_task = RenderTask::new_picture(
@@ -405,6 +467,72 @@ impl PicturePrimitive {
                         pic_state.tasks.push(render_task_id);
                         self.surface = Some(PictureSurface::RenderTask(render_task_id));
                     }
+                    Some(PictureCompositeMode::Filter(filter @ FilterOp::ComponentTransfer)) |
+                    Some(PictureCompositeMode::Filter(filter @ FilterOp::Flood(..))) |
+                    Some(PictureCompositeMode::Filter(filter @ FilterOp::Offset(..))) |
+                    Some(PictureCompositeMode::Filter(filter @ FilterOp::Composite(..)))
+                        if !filter.is_noop() => {
+                        // The offset filter draws its input from where
+                        // the content is before being moved.
+                        let (input_rect, info) = match filter {
+                            FilterOp::ComponentTransfer => {
+                                (*prim_screen_rect, SvgFilterInfo::ComponentTransfer)
+                            }
+                            FilterOp::Flood(color) => {
+                                (*prim_screen_rect, SvgFilterInfo::Flood(color.premultiplied()))
+                            }
+                            FilterOp::Offset(offset) => {
+                                let device_offset = (offset * content_scale).round();
+                                (
+                                    prim_screen_rect.translate(&-device_offset.to_i32()),
+                                    SvgFilterInfo::Offset(device_offset),
+                                )
+                            }
+                            FilterOp::Composite(operator) => {
+                                (*prim_screen_rect, SvgFilterInfo::Composite(operator))
+                            }
+                            _ => unreachable!(),
+                        };
+
+                        let picture_task = RenderTask::new_picture(
+                            RenderTaskLocation::Dynamic(None, input_rect.size),
+                            prim_index,
+                            RenderTargetKind::Color,
+                            ContentOrigin::Screen(input_rect.origin),
+                            PremultipliedColorF::TRANSPARENT,
+                            ClearMode::Transparent,
+                            pic_state_for_children.tasks,
+                            PictureType::Image,
+                        );
+                        let picture_task_id = frame_state.render_tasks.add(picture_task);
+
+                        let extra_gpu_cache_handle = match *filter_data {
+                            Some(ref filter_data) => {
+                                if let Some(mut request) = frame_state.gpu_cache.request(extra_gpu_data_handle) {
+                                    write_component_transfer_lut(filter_data, &mut request);
+                                }
+                                Some(*extra_gpu_data_handle)
+                            }
+                            None => None,
+                        };
+
+                        let source_graphic = match filter {
+                            FilterOp::Composite(..) => pic_state_for_children.source_graphic,
+                            _ => None,
+                        };
+
+                        let filter_task = RenderTask::new_svg_filter(
+                            info,
+                            picture_task_id,
+                            source_graphic,
+                            *prim_screen_rect,
+                            extra_gpu_cache_handle,
+                        );
+
+                        let render_task_id = frame_state.render_tasks.add(filter_task);
+                        pic_state.tasks.push(render_task_id);
+                        self.surface = Some(PictureSurface::RenderTask(render_task_id));
+                    }
                     Some(PictureCompositeMode::MixBlend(..)) => {
                         let picture_task = RenderTask::new_picture(
                             RenderTaskLocation::Dynamic(None, prim_screen_rect.size),
@@ -462,7 +590,7 @@ impl PicturePrimitive {
                         }
                     }
                     Some(PictureCompositeMode::Blit) => {
-                        let picture_task = RenderTask::new_picture(
+                        let mut picture_task = RenderTask::new_picture(
                             RenderTaskLocation::Dynamic(None, prim_screen_rect.size),
                             prim_index,
                             RenderTargetKind::Color,
@@ -472,8 +600,17 @@ impl PicturePrimitive {
                             pic_state_for_children.tasks,
                             PictureType::Image,
                         );
+                        if is_source_graphic || is_backdrop {
+                            picture_task.mark_for_saving();
+                        }
 
                         let render_task_id = frame_state.render_tasks.add(picture_task);
+                        if is_source_graphic {
+                            pic_state.source_graphic = Some(render_task_id);
+                        }
+                        if is_backdrop {
+                            pic_state.backdrop = Some((prim_index, render_task_id));
+                        }
                         pic_state.tasks.push(render_task_id);
                         self.surface = Some(PictureSurface::RenderTask(render_task_id));
                     }
@@ -628,7 +765,11 @@ impl PicturePrimitive {
                             // Go through different paths
                             FilterOp::Blur(..) |
                             FilterOp::DropShadow(..) |
-                            FilterOp::ColorMatrix(_) => 0.0,
+                            FilterOp::ColorMatrix(_) |
+                            FilterOp::ComponentTransfer |
+                            FilterOp::Flood(..) |
+                            FilterOp::Offset(..) |
+                            FilterOp::Composite(..) => 0.0,
                         };
 
                         request.push([amount, 1.0 - amount, 0.0, 0.0]);
@@ -652,3 +793,63 @@ impl PicturePrimitive {
         }
     }
 }
+
+// The number of entries in the lookup table of a component
+// transfer filter, one per 8-bit value of a channel.
+const COMPONENT_TRANSFER_LUT_ENTRIES: usize = 256;
+
+// Evaluate a transfer function for a channel value in [0, 1], as
+// described for feComponentTransfer in the SVG filter spec.
+fn apply_transfer_func(func_type: ComponentTransferFuncType, values: &[f32], c: f32) -> f32 {
+    let result = match func_type {
+        ComponentTransferFuncType::Identity => c,
+        ComponentTransferFuncType::Table => {
+            if values.is_empty() {
+                return c;
+            }
+            let n = values.len() - 1;
+            if n == 0 {
+                values[0]
+            } else {
+                let k = ((c * n as f32).floor() as usize).min(n - 1);
+                let t = c * n as f32 - k as f32;
+                values[k] + t * (values[k + 1] - values[k])
+            }
+        }
+        ComponentTransferFuncType::Discrete => {
+            if values.is_empty() {
+                return c;
+            }
+            let n = values.len();
+            let k = ((c * n as f32).floor() as usize).min(n - 1);
+            values[k]
+        }
+        ComponentTransferFuncType::Linear => {
+            let slope = values.get(0).cloned().unwrap_or(1.0);
+            let intercept = values.get(1).cloned().unwrap_or(0.0);
+            slope * c + intercept
+        }
+        ComponentTransferFuncType::Gamma => {
+            let amplitude = values.get(0).cloned().unwrap_or(1.0);
+            let exponent = values.get(1).cloned().unwrap_or(1.0);
+            let offset = values.get(2).cloned().unwrap_or(0.0);
+            amplitude * c.powf(exponent) + offset
+        }
+    };
+
+    result.max(0.0).min(1.0)
+}
+
+// Write the lookup table for a component transfer filter, with
+// one block of mapped [r, g, b, a] values per input value.
+fn write_component_transfer_lut(filter_data: &FilterData, request: &mut GpuDataRequest) {
+    for i in 0 .. COMPONENT_TRANSFER_LUT_ENTRIES {
+        let c = i as f32 / (COMPONENT_TRANSFER_LUT_ENTRIES - 1) as f32;
+        request.push([
+            apply_transfer_func(filter_data.func_r_type, &filter_data.r_values, c),
+            apply_transfer_func(filter_data.func_g_type, &filter_data.g_values, c),
+            apply_transfer_func(filter_data.func_b_type, &filter_data.b_values, c),
+            apply_transfer_func(filter_data.func_a_type, &filter_data.a_values, c),
+        ]);
+    }
+}
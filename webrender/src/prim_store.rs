@@ -3,10 +3,11 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use api::{AlphaType, BorderRadius, BuiltDisplayList, ClipMode, ColorF, ComplexClipRegion};
-use api::{DeviceIntRect, DeviceIntSize, DevicePixelScale, Epoch, ExtendMode, FontRenderMode};
-use api::{GlyphInstance, GlyphKey, GradientStop, ImageKey, ImageRendering, ItemRange, ItemTag};
-use api::{LayerPoint, LayerRect, LayerSize, LayerToWorldTransform, LayerVector2D, LineOrientation};
-use api::{LineStyle, PremultipliedColorF, YuvColorSpace, YuvFormat};
+use api::{DeviceIntRect, DeviceIntSize, DevicePixelScale, Epoch, ExtendMode, FillRule};
+use api::{FontRenderMode, GlyphInstance, GlyphKey, GradientStop, ImageKey, ImageRendering};
+use api::{ItemRange, ItemTag, LayerPoint, LayerRect, LayerSize, LayerToWorldTransform};
+use api::{LayerVector2D, LineOrientation, LineStyle, PremultipliedColorF, PropertyBinding};
+use api::{TexelRect, YuvColorSpace, YuvFormat};
 use border::{BorderCornerInstance, BorderEdgeKind};
 use clip_scroll_tree::{ClipChainIndex, ClipScrollNodeIndex, CoordinateSystemId};
 use clip_scroll_node::ClipScrollNode;
@@ -17,6 +18,7 @@ use glyph_rasterizer::{FontInstance, FontTransform};
 use gpu_cache::{GpuBlockData, GpuCache, GpuCacheAddress, GpuCacheHandle, GpuDataRequest,
                 ToGpuBlocks};
 use gpu_types::{ClipChainRectIndex};
+use path::PathMask;
 use picture::{PictureKind, PicturePrimitive};
 use render_task::{BlitSource, RenderTask, RenderTaskCacheKey, RenderTaskCacheKeyKind};
 use render_task::RenderTaskId;
@@ -202,6 +204,9 @@ pub enum BrushKind {
     },
     Solid {
         color: ColorF,
+        // Where the color comes from. Bound colors are resolved against
+        // the scene properties each frame, updating `color` in place.
+        color_binding: PropertyBinding<ColorF>,
     },
     Clear,
     Line {
@@ -215,6 +220,8 @@ pub enum BrushKind {
         request: ImageRequest,
         current_epoch: Epoch,
         alpha_type: AlphaType,
+        // The part of the image (or tile) to draw, in texels.
+        sub_rect: Option<TexelRect>,
     },
     YuvImage {
         yuv_key: [ImageKey; 3],
@@ -232,6 +239,15 @@ pub enum BrushKind {
         end_radius: f32,
         ratio_xy: f32,
     },
+    ConicGradient {
+        gradient_index: CachedGradientIndex,
+        stops_range: ItemRange<GradientStop>,
+        extend_mode: ExtendMode,
+        center: LayerPoint,
+        angle: f32,
+        start_offset: f32,
+        end_offset: f32,
+    },
     LinearGradient {
         gradient_index: CachedGradientIndex,
         stops_range: ItemRange<GradientStop>,
@@ -240,10 +256,27 @@ pub enum BrushKind {
         reverse_stops: bool,
         start_point: LayerPoint,
         end_point: LayerPoint,
-    }
+    },
+    Path {
+        color: ColorF,
+        mask: PathMask,
+    },
 }
 
 impl BrushKind {
+    pub fn new_solid(color_binding: PropertyBinding<ColorF>) -> BrushKind {
+        // A bound color isn't known until the first frame is built.
+        let color = match color_binding {
+            PropertyBinding::Value(color) => color,
+            PropertyBinding::Binding(..) => ColorF::new(0.0, 0.0, 0.0, 0.0),
+        };
+
+        BrushKind::Solid {
+            color,
+            color_binding,
+        }
+    }
+
     fn supports_segments(&self) -> bool {
         match *self {
             BrushKind::Solid { .. } |
@@ -251,11 +284,13 @@ impl BrushKind {
             BrushKind::Image { .. } |
             BrushKind::YuvImage { .. } |
             BrushKind::RadialGradient { .. } |
+            BrushKind::ConicGradient { .. } |
             BrushKind::LinearGradient { .. } => true,
 
             BrushKind::Mask { .. } |
             BrushKind::Clear |
-            BrushKind::Line { .. } => false,
+            BrushKind::Line { .. } |
+            BrushKind::Path { .. } => false,
         }
     }
 }
@@ -333,10 +368,20 @@ impl BrushPrimitive {
         // has to match VECS_PER_SPECIFIC_BRUSH
         match self.kind {
             BrushKind::Picture |
-            BrushKind::Image { .. } |
             BrushKind::YuvImage { .. } => {
             }
-            BrushKind::Solid { color } => {
+            BrushKind::Image { sub_rect, .. } => {
+                // An invalid rect tells the shader to draw the whole image.
+                let sub_rect = sub_rect.unwrap_or(TexelRect::invalid());
+                request.push([
+                    sub_rect.uv0.x,
+                    sub_rect.uv0.y,
+                    sub_rect.uv1.x,
+                    sub_rect.uv1.y,
+                ]);
+            }
+            BrushKind::Solid { color, .. } |
+            BrushKind::Path { color, .. } => {
                 request.push(color.premultiplied());
             }
             BrushKind::Clear => {
@@ -409,6 +454,20 @@ impl BrushPrimitive {
                     pack_as_float(extend_mode as u32),
                 ]);
             }
+            BrushKind::ConicGradient { center, angle, start_offset, end_offset, extend_mode, .. } => {
+                request.push([
+                    center.x,
+                    center.y,
+                    start_offset,
+                    end_offset,
+                ]);
+                request.push([
+                    angle,
+                    pack_as_float(extend_mode as u32),
+                    0.0,
+                    0.0,
+                ]);
+            }
         }
     }
 }
@@ -452,6 +511,24 @@ impl ToGpuBlocks for ImagePrimitiveCpu {
             self.stretch_size.width, self.stretch_size.height,
             self.tile_spacing.width, self.tile_spacing.height,
         ]);
+
+        // Cached images have already been cropped to the texel rect,
+        // otherwise the shader selects it.
+        let sub_rect = match (&self.source, self.key.texel_rect) {
+            (&ImageSource::Default, Some(texel_rect)) => TexelRect::new(
+                texel_rect.origin.x as f32,
+                texel_rect.origin.y as f32,
+                texel_rect.max_x() as f32,
+                texel_rect.max_y() as f32,
+            ),
+            _ => TexelRect::invalid(),
+        };
+        request.push([
+            sub_rect.uv0.x,
+            sub_rect.uv0.y,
+            sub_rect.uv1.x,
+            sub_rect.uv1.y,
+        ]);
     }
 }
 
@@ -802,6 +879,35 @@ impl ToGpuBlocks for ImageMaskData {
     }
 }
 
+/// The GPU layout of a polygon clip: the bounding rect, a header
+/// with the point count, fill rule and clip mode, then the points,
+/// two per block.
+#[derive(Debug)]
+pub struct ClipPolygonData<'a> {
+    pub bounds: LayerRect,
+    pub points: &'a [LayerPoint],
+    pub fill_rule: FillRule,
+    pub mode: ClipMode,
+}
+
+impl<'a> ClipPolygonData<'a> {
+    pub fn write(&self, request: &mut GpuDataRequest) {
+        request.push(self.bounds);
+        request.push([
+            self.points.len() as f32,
+            self.fill_rule as u32 as f32,
+            self.mode as u32 as f32,
+            0.0,
+        ]);
+        for pair in self.points.chunks(2) {
+            let last = pair[pair.len() - 1];
+            request.push([pair[0].x, pair[0].y, last.x, last.y]);
+        }
+
+        assert!(request.current_used_block_num() <= MAX_VERTEX_TEXTURE_WIDTH);
+    }
+}
+
 #[derive(Debug)]
 pub struct ClipData {
     rect: ClipRect,
@@ -1000,13 +1106,15 @@ impl PrimitiveStore {
             PrimitiveContainer::Brush(brush) => {
                 let opacity = match brush.kind {
                     BrushKind::Clear => PrimitiveOpacity::translucent(),
-                    BrushKind::Solid { ref color } => PrimitiveOpacity::from_alpha(color.a),
+                    BrushKind::Solid { ref color, .. } => PrimitiveOpacity::from_alpha(color.a),
                     BrushKind::Mask { .. } => PrimitiveOpacity::translucent(),
                     BrushKind::Line { .. } => PrimitiveOpacity::translucent(),
                     BrushKind::Image { .. } => PrimitiveOpacity::translucent(),
                     BrushKind::YuvImage { .. } => PrimitiveOpacity::opaque(),
                     BrushKind::RadialGradient { .. } => PrimitiveOpacity::translucent(),
+                    BrushKind::ConicGradient { .. } => PrimitiveOpacity::translucent(),
                     BrushKind::LinearGradient { .. } => PrimitiveOpacity::translucent(),
+                    BrushKind::Path { .. } => PrimitiveOpacity::translucent(),
                     BrushKind::Picture => {
                         // TODO(gw): This is not currently used. In the future
                         //           we should detect opaque pictures.
@@ -1151,19 +1259,25 @@ impl PrimitiveStore {
 
                         // Work out whether this image is a normal / simple type, or if
                         // we need to pre-render it to the render task cache.
+                        // The texels of external images are only known at render time,
+                        // so they can't be copied into the cache; instead, the shader
+                        // selects the texel rect.
                         image_cpu.source = match image_cpu.key.texel_rect {
-                            Some(texel_rect) => {
+                            Some(texel_rect) if image_properties.external_image.is_none() => {
                                 ImageSource::Cache {
                                     // Size in device-pixels we need to allocate in render task cache.
                                     size: texel_rect.size,
                                     item: CacheItem::invalid(),
                                 }
                             }
-                            None => {
+                            _ => {
                                 // Simple image - just use a normal texture cache entry.
                                 ImageSource::Default
                             }
                         };
+
+                        // The GPU blocks depend on the image source.
+                        frame_state.gpu_cache.invalidate(&metadata.gpu_location);
                     }
 
                     // Set if we need to request the source image from the cache this frame.
@@ -1240,6 +1354,18 @@ impl PrimitiveStore {
                 let brush = &mut self.cpu_brushes[metadata.cpu_prim_index.0];
 
                 match brush.kind {
+                    BrushKind::Solid { ref mut color, ref color_binding } => {
+                        let current_color = frame_context
+                            .scene_properties
+                            .resolve_color(color_binding);
+                        if current_color != *color {
+                            // The color lives in the GPU cache, so a new value only
+                            // needs the cached blocks rebuilt, not a new scene.
+                            *color = current_color;
+                            metadata.opacity = PrimitiveOpacity::from_alpha(current_color.a);
+                            frame_state.gpu_cache.invalidate(&metadata.gpu_location);
+                        }
+                    }
                     BrushKind::Image { request, ref mut current_epoch, .. } => {
                         let image_properties = frame_state
                             .resource_cache
@@ -1273,7 +1399,8 @@ impl PrimitiveStore {
                             );
                         }
                     }
-                    BrushKind::RadialGradient { gradient_index, stops_range, .. } => {
+                    BrushKind::RadialGradient { gradient_index, stops_range, .. } |
+                    BrushKind::ConicGradient { gradient_index, stops_range, .. } => {
                         let stops_handle = &mut frame_state.cached_gradients[gradient_index.0].handle;
                         if let Some(mut request) = frame_state.gpu_cache.request(stops_handle) {
                             let gradient_builder = GradientGpuBlockBuilder::new(
@@ -1299,8 +1426,47 @@ impl PrimitiveStore {
                             );
                         }
                     }
+                    BrushKind::Path { ref mut mask, .. } => {
+                        // Rasterize the mask at the scale it is drawn at, so
+                        // it stays sharp when the path is scaled up.
+                        let transform = prim_run_context.scroll_node.world_content_transform.to_transform();
+                        let transform_scale = (transform.m11 * transform.m11 + transform.m12 * transform.m12)
+                            .max(transform.m21 * transform.m21 + transform.m22 * transform.m22)
+                            .sqrt();
+                        let (size, scale) =
+                            mask.mask_size(frame_context.device_pixel_scale.0 * transform_scale);
+
+                        // The key is cloned before the mask is borrowed again,
+                        // which only copies a reference to its ops.
+                        let key = mask.update(scale, frame_state.gpu_cache).cloned();
+                        if let Some(key) = key {
+                            mask.request_edges(frame_state.gpu_cache);
+
+                            // Request the cached mask. The closure is invoked when
+                            // the path or scale changed, or the mask was evicted.
+                            let edges = mask.edges_handle;
+                            let local_size = metadata.local_rect.size;
+                            mask.cache_item = frame_state.resource_cache.request_render_task(
+                                RenderTaskCacheKey {
+                                    size,
+                                    kind: RenderTaskCacheKeyKind::Path(key),
+                                },
+                                frame_state.gpu_cache,
+                                frame_state.render_tasks,
+                                |render_tasks| {
+                                    let task = RenderTask::new_path(size, edges, local_size);
+                                    let task_id = render_tasks.add(task);
+                                    pic_state.tasks.push(task_id);
+                                    (task_id, [0.0; 3], false)
+                                }
+                            );
+                        } else {
+                            // The path has too many edges to be drawn, and
+                            // the batching skips it without a mask.
+                            mask.cache_item = CacheItem::invalid();
+                        }
+                    }
                     BrushKind::Mask { .. } |
-                    BrushKind::Solid { .. } |
                     BrushKind::Clear |
                     BrushKind::Line { .. } |
                     BrushKind::Picture { .. } => {}
@@ -1422,7 +1588,8 @@ impl PrimitiveStore {
                         (rect, None, ClipMode::Clip)
                     }
                     ClipSource::BorderCorner(..) |
-                    ClipSource::Image(..) => {
+                    ClipSource::Image(..) |
+                    ClipSource::Polygon(..) => {
                         // TODO(gw): We can easily extend the segment builder
                         //           to support these clip sources in the
                         //           future, but they are rarely used.
@@ -1696,6 +1863,9 @@ impl PrimitiveStore {
     ) -> Option<LayerRect> {
         let mut may_need_clip_mask = true;
         let mut pic_state_for_children = PictureState::new();
+        // The input of backdrop filters is drawn below the backdrop
+        // picture's parent, by the pictures of the filters.
+        pic_state_for_children.backdrop = pic_state.backdrop;
 
         // Do some basic checks first, that can early out
         // without even knowing the local rect.
@@ -12,7 +12,7 @@ use std::io::Write;
 use std::mem;
 use std::path::PathBuf;
 
-pub static WEBRENDER_RECORDING_HEADER: u64 = 0xbeefbeefbeefbe01u64;
+pub static WEBRENDER_RECORDING_HEADER: u64 = 0xbeefbeefbeefbe03u64;
 
 pub trait ApiRecordingReceiver: Send + Debug {
     fn write_msg(&mut self, frame: u32, msg: &ApiMsg);
@@ -70,7 +70,8 @@ pub fn should_record_msg(msg: &ApiMsg) -> bool {
             for msg in &msgs.frame_ops {
                 match *msg {
                     FrameMsg::GetScrollNodeState(..) |
-                    FrameMsg::HitTest(..) => {}
+                    FrameMsg::HitTest(..) |
+                    FrameMsg::HitTestBatch(..) => {}
                     _ => { return true; }
                 }
             }
@@ -2,14 +2,18 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use animation::{AnimationSet, ScrollLinkSet};
 use api::{ApiMsg, BuiltDisplayList, ClearCache, DebugCommand};
 #[cfg(feature = "debugger")]
 use api::{BuiltDisplayListIter, SpecificDisplayItem};
 use api::{DeviceIntPoint, DevicePixelScale, DeviceUintPoint, DeviceUintRect, DeviceUintSize};
-use api::{DocumentId, DocumentLayer, Epoch, ExternalScrollId, FrameMsg, HitTestResult};
-use api::{IdNamespace, LayerPoint, PipelineId, RenderNotifier, SceneMsg, ScrollClamping};
-use api::{ScrollEventPhase, ScrollLocation, ScrollNodeState, TransactionMsg, WorldPoint};
-use api::channel::{MsgReceiver, PayloadReceiver, PayloadReceiverHelperMethods};
+use api::DisplayListError;
+use api::{DocumentId, DocumentLayer, Epoch, ExternalScrollId, FrameMsg, HitTestArea};
+use api::HitTestResult;
+use api::{IdNamespace, LayerPoint, PipelineId, PropertyBindingId, RenderNotifier, SceneMsg};
+use api::{ScrollClamping, ScrollEventPhase, ScrollLocation, ScrollNodeState, TransactionMsg};
+use api::WorldPoint;
+use api::channel::{MsgReceiver, Payload, PayloadReceiver, PayloadReceiverHelperMethods};
 use api::channel::{PayloadSender, PayloadSenderHelperMethods};
 #[cfg(feature = "capture")]
 use api::CaptureBits;
@@ -21,7 +25,7 @@ use debug_server;
 use frame::FlattenContext;
 use frame_builder::{FrameBuilder, FrameBuilderConfig};
 use gpu_cache::GpuCache;
-use hit_test::{HitTest, HitTester};
+use hit_test::{HitTest, HitTester, SharedHitTesters};
 use internal_types::{DebugOutput, FastHashMap, FastHashSet, RenderedDocument, ResultMsg};
 use profiler::{BackendProfileCounters, IpcProfileCounters, ResourceProfileCounters};
 use record::ApiRecordingReceiver;
@@ -39,6 +43,7 @@ use serde::{Serialize, Deserialize};
 use serde_json;
 #[cfg(any(feature = "capture", feature = "replay"))]
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::sync::atomic::{ATOMIC_USIZE_INIT, AtomicUsize, Ordering};
 use std::mem::replace;
 use std::sync::mpsc::{Sender, Receiver};
@@ -122,11 +127,18 @@ struct Document {
 
     /// A data structure to allow hit testing against rendered frames. This is updated
     /// every time we produce a fully rendered frame.
-    hit_tester: Option<HitTester>,
+    hit_tester: Option<Arc<HitTester>>,
 
     /// Properties that are resolved during frame building and can be changed at any time
     /// without requiring the scene to be re-built.
     dynamic_properties: SceneProperties,
+
+    /// Keyframe animations of the dynamic properties, sampled every time a frame
+    /// is generated.
+    animations: AnimationSet,
+
+    /// Dynamic properties driven by scroll frames, updated whenever they scroll.
+    scroll_links: ScrollLinkSet,
 }
 
 impl Document {
@@ -170,6 +182,8 @@ impl Document {
             render_on_hittest: false,
             hit_tester: None,
             dynamic_properties: SceneProperties::new(),
+            animations: AnimationSet::new(),
+            scroll_links: ScrollLinkSet::new(),
         }
     }
 
@@ -184,6 +198,8 @@ impl Document {
         self.current.removed_pipelines.extend(self.pending.removed_pipelines.drain(..));
         self.frame_builder = Some(frame_builder);
         self.current.scene = self.pending.scene.clone();
+        // The new scene may add scroll frames or restore their offsets.
+        self.update_scroll_links();
     }
 
     fn forward_transaction_to_scene_builder(
@@ -252,7 +268,9 @@ impl Document {
                 &mut resource_profile.gpu_cache,
                 &self.dynamic_properties,
             );
-            self.hit_tester = Some(frame_builder.create_hit_tester(&self.clip_scroll_tree));
+            self.hit_tester = Some(Arc::new(
+                frame_builder.create_hit_tester(&self.clip_scroll_tree)
+            ));
             frame
         };
 
@@ -288,7 +306,12 @@ impl Document {
         cursor: WorldPoint,
         phase: ScrollEventPhase,
     ) -> bool {
-        self.clip_scroll_tree.scroll(scroll_location, cursor, phase)
+        let scrolled =
+            self.clip_scroll_tree.scroll(scroll_location, cursor, phase, precise_time_ns());
+        if scrolled {
+            self.update_scroll_links();
+        }
+        scrolled
     }
 
     /// Returns true if the node actually changed position or false otherwise.
@@ -298,11 +321,62 @@ impl Document {
         id: ExternalScrollId,
         clamp: ScrollClamping
     ) -> bool {
-        self.clip_scroll_tree.scroll_node(origin, id, clamp)
+        let scrolled = self.clip_scroll_tree.scroll_node(origin, id, clamp);
+        if scrolled {
+            self.update_scroll_links();
+        }
+        scrolled
     }
 
     pub fn tick_scrolling_bounce_animations(&mut self) {
         self.clip_scroll_tree.tick_scrolling_bounce_animations();
+        self.update_scroll_links();
+    }
+
+    /// Write the values of the scroll-linked properties for the current
+    /// scroll offsets into the dynamic properties.
+    pub fn update_scroll_links(&mut self) {
+        if self.scroll_links.is_empty() {
+            return;
+        }
+
+        let properties = self.scroll_links.sample(&self.clip_scroll_tree);
+        self.dynamic_properties.add_properties(properties);
+    }
+
+    /// Advance the smooth scrolls to the current time. Returns the scroll frames whose
+    /// smooth scrolls have ended, with whether they were interrupted.
+    pub fn tick_smooth_scrolls(&mut self) -> Vec<(ExternalScrollId, bool)> {
+        if !self.clip_scroll_tree.has_smooth_scrolls() {
+            return Vec::new();
+        }
+
+        let ended = self.clip_scroll_tree.tick_smooth_scrolls(precise_time_ns());
+        self.update_scroll_links();
+        ended
+    }
+
+    /// Advance the flings of the scroll frames to the current time.
+    pub fn tick_flings(&mut self) {
+        if !self.clip_scroll_tree.has_flings() {
+            return;
+        }
+
+        if self.clip_scroll_tree.tick_flings(precise_time_ns()) {
+            self.update_scroll_links();
+        }
+    }
+
+    /// Write the current values of the running animations into the dynamic
+    /// properties. Returns the bindings whose animations have finished.
+    pub fn sample_animations(&mut self) -> Vec<PropertyBindingId> {
+        if self.animations.is_empty() {
+            return Vec::new();
+        }
+
+        let (properties, finished) = self.animations.sample(precise_time_ns());
+        self.dynamic_properties.add_properties(properties);
+        finished
     }
 
     pub fn get_scroll_node_state(&self) -> Vec<ScrollNodeState> {
@@ -318,6 +392,7 @@ impl Document {
         self.clip_scroll_tree = clip_scroll_tree;
         self.clip_scroll_tree.finalize_and_apply_pending_scroll_offsets(old_scrolling_states);
         self.pipeline_epoch_map = pipeline_epoch_map;
+        self.update_scroll_links();
     }
 
     pub fn reset(&mut self) -> ScrollStates {
@@ -431,10 +506,29 @@ pub struct RenderBackend {
 
     notifier: Box<RenderNotifier>,
     recorder: Option<Box<ApiRecordingReceiver>>,
+    shared_hit_testers: SharedHitTesters,
 
     enable_render_on_scroll: bool,
 }
 
+/// Waits for the payload of the given display list, sending payloads of other
+/// display lists back to the channel for whoever is waiting for them.
+fn recv_payload(
+    payload_rx: &PayloadReceiver,
+    payload_tx: &PayloadSender,
+    epoch: Epoch,
+    pipeline_id: PipelineId,
+) -> Payload {
+    let mut data;
+    while {
+        data = payload_rx.recv_payload().unwrap();
+        data.epoch != epoch || data.pipeline_id != pipeline_id
+    } {
+        payload_tx.send_payload(data).unwrap()
+    }
+    data
+}
+
 impl RenderBackend {
     pub fn new(
         api_rx: MsgReceiver<ApiMsg>,
@@ -448,6 +542,7 @@ impl RenderBackend {
         notifier: Box<RenderNotifier>,
         frame_config: FrameBuilderConfig,
         recorder: Option<Box<ApiRecordingReceiver>>,
+        shared_hit_testers: SharedHitTesters,
         enable_render_on_scroll: bool,
     ) -> RenderBackend {
         // The namespace_id should start from 1.
@@ -467,6 +562,7 @@ impl RenderBackend {
             documents: FastHashMap::default(),
             notifier,
             recorder,
+            shared_hit_testers,
             enable_render_on_scroll,
         }
     }
@@ -510,13 +606,7 @@ impl RenderBackend {
             } => {
                 profile_scope!("SetDisplayList");
 
-                let mut data;
-                while {
-                    data = self.payload_rx.recv_payload().unwrap();
-                    data.epoch != epoch || data.pipeline_id != pipeline_id
-                } {
-                    self.payload_tx.send_payload(data).unwrap()
-                }
+                let data = recv_payload(&self.payload_rx, &self.payload_tx, epoch, pipeline_id);
 
                 if let Some(ref mut r) = self.recorder {
                     r.write_payload(frame_counter, &data.to_data());
@@ -525,6 +615,14 @@ impl RenderBackend {
                 let built_display_list =
                     BuiltDisplayList::from_data(data.display_list_data, list_descriptor);
 
+                // Display lists may come from untrusted content processes, so a malformed one
+                // is rejected here rather than taking down the backend when the scene is built.
+                if let Err(error) = built_display_list.validate(pipeline_id) {
+                    warn!("Rejected display list for {:?} {:?}: {:?}", pipeline_id, epoch, error);
+                    self.notifier.display_list_rejected(document_id, pipeline_id, epoch, error);
+                    return DocumentOps::nop();
+                }
+
                 if !preserve_frame_state {
                     doc.discard_frame_state_for_pipeline(pipeline_id);
                 }
@@ -565,6 +663,61 @@ impl RenderBackend {
 
                 DocumentOps::build()
             }
+            SceneMsg::UpdateDisplayList {
+                epoch,
+                pipeline_id,
+                content_size,
+                list_descriptor,
+            } => {
+                profile_scope!("UpdateDisplayList");
+
+                let data = recv_payload(&self.payload_rx, &self.payload_tx, epoch, pipeline_id);
+
+                if let Some(ref mut r) = self.recorder {
+                    r.write_payload(frame_counter, &data.to_data());
+                }
+
+                let delta = BuiltDisplayList::from_data(data.display_list_data, list_descriptor);
+
+                // The delta is applied to the pending display list of the pipeline, so that it
+                // builds on earlier display lists and deltas of the same transaction. The whole
+                // updated display list is validated and flattened again, since primitives aren't
+                // kept per item group.
+                let result = match doc.pending.scene.pipelines.get(&pipeline_id) {
+                    Some(pipeline) => pipeline.display_list
+                        .apply_delta(&delta)
+                        .and_then(|display_list| {
+                            display_list.validate(pipeline_id)?;
+                            Ok((display_list, pipeline.background_color, pipeline.viewport_size))
+                        }),
+                    None => Err(DisplayListError::MissingDisplayList),
+                };
+
+                match result {
+                    Ok((display_list, background, viewport_size)) => {
+                        doc.pending.scene.set_display_list(
+                            pipeline_id,
+                            epoch,
+                            display_list,
+                            background,
+                            viewport_size,
+                            content_size,
+                        );
+                    }
+                    Err(error) => {
+                        warn!("Rejected display list delta for {:?} {:?}: {:?}",
+                            pipeline_id, epoch, error);
+                        self.notifier.display_list_rejected(document_id, pipeline_id, epoch, error);
+                        return DocumentOps::nop();
+                    }
+                }
+
+                if let Some(ref mut ros) = doc.render_on_scroll {
+                    *ros = false; //wait for `GenerateFrame`
+                }
+
+                DocumentOps::build()
+            }
             SceneMsg::SetRootPipeline(pipeline_id) => {
                 profile_scope!("SetRootPipeline");
 
@@ -619,11 +772,11 @@ impl RenderBackend {
                     ..DocumentOps::nop()
                 }
             }
-            FrameMsg::HitTest(pipeline_id, point, flags, tx) => {
+            FrameMsg::HitTest(pipeline_id, area, flags, tx) => {
 
                 let result = match doc.hit_tester {
                     Some(ref hit_tester) => {
-                        hit_tester.hit_test(HitTest::new(pipeline_id, point, flags))
+                        hit_tester.hit_test(HitTest::new(pipeline_id, area, flags))
                     }
                     None => HitTestResult { items: Vec::new() },
                 };
@@ -631,6 +784,22 @@ impl RenderBackend {
                 tx.send(result).unwrap();
                 DocumentOps::nop()
             }
+            FrameMsg::HitTestBatch(queries, tx) => {
+                let results = queries
+                    .into_iter()
+                    .map(|query| match doc.hit_tester {
+                        Some(ref hit_tester) => hit_tester.hit_test(HitTest::new(
+                            query.pipeline_id,
+                            HitTestArea::Point(query.point),
+                            query.flags,
+                        )),
+                        None => HitTestResult { items: Vec::new() },
+                    })
+                    .collect();
+
+                tx.send(results).unwrap();
+                DocumentOps::nop()
+            }
             FrameMsg::SetPan(pan) => {
                 doc.view.pan = pan;
                 DocumentOps::nop()
@@ -648,6 +817,20 @@ impl RenderBackend {
                     ..DocumentOps::nop()
                 }
             }
+            FrameMsg::SmoothScrollNodeWithId(origin, id, clamp, duration, easing) => {
+                profile_scope!("SmoothScrollNodeWithId");
+
+                let should_render = doc.clip_scroll_tree
+                    .smooth_scroll_node(origin, id, clamp, duration, easing, precise_time_ns())
+                    && doc.render_on_scroll == Some(true);
+
+                DocumentOps {
+                    scroll: true,
+                    render: should_render,
+                    composite: should_render,
+                    ..DocumentOps::nop()
+                }
+            }
             FrameMsg::TickScrollingBounce => {
                 profile_scope!("TickScrollingBounce");
 
@@ -662,6 +845,36 @@ impl RenderBackend {
                     ..DocumentOps::nop()
                 }
             }
+            FrameMsg::StartAnimation(animation) => {
+                doc.animations.start(animation, precise_time_ns());
+                DocumentOps::nop()
+            }
+            FrameMsg::StopAnimation(id) => {
+                doc.animations.stop(id);
+                DocumentOps::nop()
+            }
+            FrameMsg::TickAnimations => {
+                profile_scope!("TickAnimations");
+
+                let should_render = !doc.animations.is_empty() ||
+                    doc.clip_scroll_tree.has_smooth_scrolls() ||
+                    doc.clip_scroll_tree.has_flings();
+
+                DocumentOps {
+                    render: should_render,
+                    composite: should_render,
+                    ..DocumentOps::nop()
+                }
+            }
+            FrameMsg::AddScrollLink(link) => {
+                doc.scroll_links.add(link);
+                doc.update_scroll_links();
+                DocumentOps::nop()
+            }
+            FrameMsg::RemoveScrollLink(id) => {
+                doc.scroll_links.remove(id);
+                DocumentOps::nop()
+            }
             FrameMsg::GetScrollNodeState(tx) => {
                 profile_scope!("GetScrollNodeState");
                 tx.send(doc.get_scroll_node_state()).unwrap();
@@ -669,7 +882,10 @@ impl RenderBackend {
             }
             FrameMsg::UpdateDynamicProperties(property_bindings) => {
                 doc.dynamic_properties.set_properties(property_bindings);
-                DocumentOps::build()
+                doc.update_scroll_links();
+                // Bound properties are resolved while building the frame,
+                // so the scene doesn't need to be rebuilt for new values.
+                DocumentOps::nop()
             }
         }
     }
@@ -793,6 +1009,7 @@ impl RenderBackend {
             }
             ApiMsg::DeleteDocument(document_id) => {
                 self.documents.remove(&document_id);
+                self.shared_hit_testers.remove(document_id);
             }
             ApiMsg::ExternalEvent(evt) => {
                 self.notifier.external_event(evt);
@@ -806,6 +1023,7 @@ impl RenderBackend {
                     .collect::<Vec<_>>();
                 for document in document_ids {
                     self.documents.remove(&document);
+                    self.shared_hit_testers.remove(document);
                 }
             }
             ApiMsg::MemoryPressure => {
@@ -976,11 +1194,18 @@ impl RenderBackend {
 
         debug_assert!(op.render || !op.composite);
 
+        let mut ended_smooth_scrolls = Vec::new();
+        let mut finished_animations = Vec::new();
+
         if op.render {
             profile_scope!("generate frame");
 
             *frame_counter += 1;
 
+            ended_smooth_scrolls = doc.tick_smooth_scrolls();
+            doc.tick_flings();
+            finished_animations = doc.sample_animations();
+
             // borrow ck hack for profile_counters
             let (pending_update, rendered_document) = {
                 let _timer = profile_counters.total_time.timer();
@@ -994,6 +1219,10 @@ impl RenderBackend {
                 debug!("generated frame for document {:?} with {} passes",
                     document_id, rendered_document.frame.passes.len());
 
+                if let Some(ref hit_tester) = doc.hit_tester {
+                    self.shared_hit_testers.publish(document_id, Arc::clone(hit_tester));
+                }
+
                 let msg = ResultMsg::UpdateGpuCache(self.gpu_cache.extract_updates());
                 self.result_tx.send(msg).unwrap();
 
@@ -1016,6 +1245,14 @@ impl RenderBackend {
         if op.render || op.scroll {
             self.notifier.new_document_ready(document_id, op.scroll, op.composite);
         }
+
+        for (id, interrupted) in ended_smooth_scrolls {
+            self.notifier.smooth_scroll_ended(document_id, id, interrupted);
+        }
+
+        if !finished_animations.is_empty() {
+            self.notifier.animations_finished(document_id, finished_animations);
+        }
     }
 
     #[cfg(not(feature = "debugger"))]
@@ -1040,7 +1277,7 @@ impl RenderBackend {
                     display_item @ SpecificDisplayItem::PushStackingContext(..) => {
                         let mut subtraversal = item.sub_iter();
                         let mut child_node =
-                            debug_server::TreeNode::new(&display_item.debug_string());
+                            debug_server::TreeNode::new(display_item.debug_name());
                         self.traverse_items(&mut subtraversal, &mut child_node);
                         node.add_child(child_node);
                         Some(subtraversal)
@@ -1049,7 +1286,7 @@ impl RenderBackend {
                         return;
                     }
                     display_item => {
-                        node.add_item(&display_item.debug_string());
+                        node.add_item(display_item.debug_name());
                         None
                     }
                 }
@@ -1107,39 +1344,6 @@ impl RenderBackend {
     }
 }
 
-#[cfg(feature = "debugger")]
-trait ToDebugString {
-    fn debug_string(&self) -> String;
-}
-
-#[cfg(feature = "debugger")]
-impl ToDebugString for SpecificDisplayItem {
-    fn debug_string(&self) -> String {
-        match *self {
-            SpecificDisplayItem::Image(..) => String::from("image"),
-            SpecificDisplayItem::YuvImage(..) => String::from("yuv_image"),
-            SpecificDisplayItem::Text(..) => String::from("text"),
-            SpecificDisplayItem::Rectangle(..) => String::from("rectangle"),
-            SpecificDisplayItem::ClearRectangle => String::from("clear_rectangle"),
-            SpecificDisplayItem::Line(..) => String::from("line"),
-            SpecificDisplayItem::Gradient(..) => String::from("gradient"),
-            SpecificDisplayItem::RadialGradient(..) => String::from("radial_gradient"),
-            SpecificDisplayItem::BoxShadow(..) => String::from("box_shadow"),
-            SpecificDisplayItem::Border(..) => String::from("border"),
-            SpecificDisplayItem::PushStackingContext(..) => String::from("push_stacking_context"),
-            SpecificDisplayItem::Iframe(..) => String::from("iframe"),
-            SpecificDisplayItem::Clip(..) => String::from("clip"),
-            SpecificDisplayItem::ClipChain(..) => String::from("clip_chain"),
-            SpecificDisplayItem::ScrollFrame(..) => String::from("scroll_frame"),
-            SpecificDisplayItem::StickyFrame(..) => String::from("sticky_frame"),
-            SpecificDisplayItem::SetGradientStops => String::from("set_gradient_stops"),
-            SpecificDisplayItem::PopStackingContext => String::from("pop_stacking_context"),
-            SpecificDisplayItem::PushShadow(..) => String::from("push_shadow"),
-            SpecificDisplayItem::PopAllShadows => String::from("pop_all_shadows"),
-        }
-    }
-}
-
 impl RenderBackend {
     #[cfg(feature = "capture")]
     // Note: the mutable `self` is only needed here for resolving blob images
@@ -1269,6 +1473,8 @@ impl RenderBackend {
                 render_on_scroll: None,
                 render_on_hittest: false,
                 dynamic_properties: SceneProperties::new(),
+                animations: AnimationSet::new(),
+                scroll_links: ScrollLinkSet::new(),
                 hit_tester: None,
             };
 
@@ -2,15 +2,16 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{DeviceIntPoint, DeviceIntRect, DeviceIntSize, ImageDescriptor, ImageFormat};
-use api::PremultipliedColorF;
+use api::{CompositeOperator, DeviceIntPoint, DeviceIntRect, DeviceIntSize, DeviceVector2D};
+use api::{ImageDescriptor, ImageFormat, LayerSize, PremultipliedColorF};
 use box_shadow::BoxShadowCacheKey;
 use clip::ClipWorkItem;
 use clip_scroll_tree::CoordinateSystemId;
 use device::TextureFilter;
-use gpu_cache::GpuCache;
+use gpu_cache::{GpuCache, GpuCacheHandle};
 use gpu_types::PictureType;
 use internal_types::{FastHashMap, SavedTargetIndex, SourceTexture};
+use path::PathCacheKey;
 use picture::ContentOrigin;
 use prim_store::{PrimitiveIndex, ImageCacheKey};
 #[cfg(feature = "debugger")]
@@ -75,35 +76,66 @@ impl RenderTaskTree {
         pass_index: usize,
         passes: &mut Vec<RenderPass>,
     ) {
-        let task = &self.tasks[id.0 as usize];
+        // A task can be the child of more than one task (e.g. the
+        // saved backdrop of a backdrop filter), so find the pass of
+        // every task first, and then add each of them once.
+        let mut task_passes = vec![usize::MAX; self.tasks.len()];
+        self.find_pass(id, pass_index, &mut task_passes);
+
+        for (index, &pass_index) in task_passes.iter().enumerate() {
+            if pass_index == usize::MAX {
+                continue;
+            }
 
-        for child in &task.children {
-            self.assign_to_passes(*child, pass_index - 1, passes);
-        }
+            let task = &self.tasks[index];
 
-        // Sanity check - can be relaxed if needed
-        match task.location {
-            RenderTaskLocation::Fixed(..) => {
-                debug_assert!(pass_index == passes.len() - 1);
-            }
-            RenderTaskLocation::Dynamic(..) |
-            RenderTaskLocation::TextureCache(..) => {
-                debug_assert!(pass_index < passes.len() - 1);
+            // Sanity check - can be relaxed if needed
+            match task.location {
+                RenderTaskLocation::Fixed(..) => {
+                    debug_assert!(pass_index == passes.len() - 1);
+                }
+                RenderTaskLocation::Dynamic(..) |
+                RenderTaskLocation::TextureCache(..) => {
+                    debug_assert!(pass_index < passes.len() - 1);
+                }
             }
+
+            // If this task can be shared between multiple
+            // passes, render it in the first pass so that
+            // it is available to all subsequent passes.
+            let pass_index = if task.is_shared() {
+                debug_assert!(task.children.is_empty());
+                0
+            } else {
+                pass_index
+            };
+
+            let pass = &mut passes[pass_index];
+            pass.add_render_task(
+                RenderTaskId(index as u32),
+                task.get_dynamic_size(),
+                task.target_kind(),
+            );
         }
+    }
 
-        // If this task can be shared between multiple
-        // passes, render it in the first pass so that
-        // it is available to all subsequent passes.
-        let pass_index = if task.is_shared() {
-            debug_assert!(task.children.is_empty());
-            0
-        } else {
-            pass_index
-        };
+    // Find the latest pass that a task can be drawn in, which is
+    // before the pass of each task that reads from it.
+    fn find_pass(
+        &self,
+        id: RenderTaskId,
+        pass_index: usize,
+        task_passes: &mut Vec<usize>,
+    ) {
+        if task_passes[id.0 as usize] <= pass_index {
+            return;
+        }
+        task_passes[id.0 as usize] = pass_index;
 
-        let pass = &mut passes[pass_index];
-        pass.add_render_task(id, task.get_dynamic_size(), task.target_kind());
+        let task = &self.tasks[id.0 as usize];
+        for child in &task.children {
+            self.find_pass(*child, pass_index - 1, task_passes);
+        }
     }
 
     pub fn get_task_address(&self, id: RenderTaskId) -> RenderTaskAddress {
@@ -184,6 +216,50 @@ impl BlurTask {
     }
 }
 
+// The SVG filter primitive applied by an SVG filter task.
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum SvgFilterInfo {
+    ComponentTransfer,
+    Flood(PremultipliedColorF),
+    Offset(DeviceVector2D),
+    Composite(CompositeOperator),
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct SvgFilterTask {
+    pub info: SvgFilterInfo,
+    // The screen position of the output, used to line
+    // the output up with the input task(s).
+    pub screen_origin: DeviceIntPoint,
+    // The saved picture task holding the unfiltered
+    // stacking context, read by composite filters.
+    pub source_graphic: Option<RenderTaskId>,
+    // The lookup table of a component transfer filter.
+    pub extra_gpu_cache_handle: Option<GpuCacheHandle>,
+}
+
+impl SvgFilterTask {
+    #[cfg(feature = "debugger")]
+    fn print_with<T: PrintTreePrinter>(&self, pt: &mut T) {
+        pt.add_item(format!("filter: {:?}", self.info));
+        pt.add_item(format!("origin: {:?}", self.screen_origin));
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "capture", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct PathTask {
+    // The flattened edges of the path.
+    pub edges: GpuCacheHandle,
+    // The size of the local rect the mask covers.
+    pub local_size: LayerSize,
+}
+
 // Where the source data for a blit task can be found.
 #[derive(Debug)]
 #[cfg_attr(feature = "capture", derive(Serialize))]
@@ -222,6 +298,8 @@ pub enum RenderTaskKind {
     Readback(DeviceIntRect),
     Scaling(RenderTargetKind),
     Blit(BlitTask),
+    SvgFilter(SvgFilterTask),
+    Path(PathTask),
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -424,6 +502,50 @@ impl RenderTask {
         }
     }
 
+    // Construct a render task to apply an SVG filter primitive
+    // to the output of the input picture task. The output covers
+    // the screen rect given, which may differ from the input
+    // (e.g. for an offset filter).
+    pub fn new_svg_filter(
+        info: SvgFilterInfo,
+        input_task_id: RenderTaskId,
+        source_graphic: Option<RenderTaskId>,
+        screen_rect: DeviceIntRect,
+        extra_gpu_cache_handle: Option<GpuCacheHandle>,
+    ) -> Self {
+        RenderTask {
+            children: vec![input_task_id],
+            location: RenderTaskLocation::Dynamic(None, screen_rect.size),
+            kind: RenderTaskKind::SvgFilter(SvgFilterTask {
+                info,
+                screen_origin: screen_rect.origin,
+                source_graphic,
+                extra_gpu_cache_handle,
+            }),
+            clear_mode: ClearMode::Transparent,
+            saved_index: None,
+        }
+    }
+
+    // Construct a render task to rasterize the coverage of
+    // a path into an alpha mask.
+    pub fn new_path(
+        size: DeviceIntSize,
+        edges: GpuCacheHandle,
+        local_size: LayerSize,
+    ) -> Self {
+        RenderTask {
+            children: Vec::new(),
+            location: RenderTaskLocation::Dynamic(None, size),
+            kind: RenderTaskKind::Path(PathTask {
+                edges,
+                local_size,
+            }),
+            clear_mode: ClearMode::Zero,
+            saved_index: None,
+        }
+    }
+
     // Write (up to) 8 floats of data specific to the type
     // of render task that is provided to the GPU shaders
     // via a vertex texture.
@@ -477,6 +599,32 @@ impl RenderTask {
                     task.color.to_array()
                 )
             }
+            RenderTaskKind::SvgFilter(ref task) => {
+                (
+                    [
+                        task.screen_origin.x as f32,
+                        task.screen_origin.y as f32,
+                        0.0,
+                    ],
+                    match task.info {
+                        SvgFilterInfo::Flood(color) => color.to_array(),
+                        SvgFilterInfo::Offset(offset) => [offset.x, offset.y, 0.0, 0.0],
+                        SvgFilterInfo::Composite(CompositeOperator::Arithmetic(k)) => k,
+                        SvgFilterInfo::ComponentTransfer |
+                        SvgFilterInfo::Composite(..) => [0.0; 4],
+                    },
+                )
+            }
+            RenderTaskKind::Path(ref task) => {
+                (
+                    [
+                        task.local_size.width,
+                        task.local_size.height,
+                        0.0,
+                    ],
+                    [0.0; 4],
+                )
+            }
             RenderTaskKind::Readback(..) |
             RenderTaskKind::Scaling(..) |
             RenderTaskKind::Blit(..) => {
@@ -550,7 +698,8 @@ impl RenderTask {
         match self.kind {
             RenderTaskKind::Readback(..) => RenderTargetKind::Color,
 
-            RenderTaskKind::CacheMask(..) => {
+            RenderTaskKind::CacheMask(..) |
+            RenderTaskKind::Path(..) => {
                 RenderTargetKind::Alpha
             }
 
@@ -567,7 +716,8 @@ impl RenderTask {
                 task_info.target_kind
             }
 
-            RenderTaskKind::Blit(..) => {
+            RenderTaskKind::Blit(..) |
+            RenderTaskKind::SvgFilter(..) => {
                 RenderTargetKind::Color
             }
         }
@@ -586,7 +736,9 @@ impl RenderTask {
             RenderTaskKind::Readback(..) |
             RenderTaskKind::HorizontalBlur(..) |
             RenderTaskKind::Scaling(..) |
-            RenderTaskKind::Blit(..) => false,
+            RenderTaskKind::Blit(..) |
+            RenderTaskKind::SvgFilter(..) |
+            RenderTaskKind::Path(..) => false,
             RenderTaskKind::CacheMask(..) => true,
         }
     }
@@ -622,6 +774,14 @@ impl RenderTask {
                 pt.new_level("Blit".to_owned());
                 pt.add_item(format!("source: {:?}", task.source));
             }
+            RenderTaskKind::SvgFilter(ref task) => {
+                pt.new_level("SvgFilter".to_owned());
+                task.print_with(pt);
+            }
+            RenderTaskKind::Path(ref task) => {
+                pt.new_level("Path".to_owned());
+                pt.add_item(format!("local size: {:?}", task.local_size));
+            }
         }
 
         pt.add_item(format!("clear to: {:?}", self.clear_mode));
@@ -656,6 +816,7 @@ impl RenderTask {
 pub enum RenderTaskCacheKeyKind {
     BoxShadow(BoxShadowCacheKey),
     Image(ImageCacheKey),
+    Path(PathCacheKey),
 }
 
 #[derive(Debug, Hash, PartialEq, Eq)]
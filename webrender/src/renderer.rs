@@ -23,6 +23,7 @@ use batch::{BatchKey, BatchKind, BatchTextures, BrushBatchKind};
 use batch::{BrushImageSourceKind, TransformBatchKind};
 #[cfg(any(feature = "capture", feature = "replay"))]
 use capture::{CaptureConfig, ExternalCaptureImage, PlainExternalImage};
+use clip_scroll_node::DEFAULT_FLING_FRICTION;
 use debug_colors;
 use debug_render::DebugRenderer;
 #[cfg(feature = "debugger")]
@@ -39,6 +40,7 @@ use gleam::gl;
 use glyph_rasterizer::GlyphFormat;
 use gpu_cache::{GpuBlockData, GpuCacheUpdate, GpuCacheUpdateList};
 use gpu_types::PrimitiveInstance;
+use hit_test::SharedHitTesters;
 use internal_types::{SourceTexture, ORTHO_FAR_PLANE, ORTHO_NEAR_PLANE, ResourceCacheError};
 use internal_types::{CacheTextureId, DebugOutput, FastHashMap, RenderedDocument, ResultMsg};
 use internal_types::{TextureUpdateList, TextureUpdateOp, TextureUpdateSource};
@@ -92,6 +94,10 @@ const GPU_TAG_BRUSH_RADIAL_GRADIENT: GpuProfileTag = GpuProfileTag {
     label: "B_RadialGradient",
     color: debug_colors::LIGHTPINK,
 };
+const GPU_TAG_BRUSH_CONIC_GRADIENT: GpuProfileTag = GpuProfileTag {
+    label: "B_ConicGradient",
+    color: debug_colors::LIGHTSALMON,
+};
 const GPU_TAG_BRUSH_YUV_IMAGE: GpuProfileTag = GpuProfileTag {
     label: "B_YuvImage",
     color: debug_colors::DARKGREEN,
@@ -168,6 +174,14 @@ const GPU_TAG_BLUR: GpuProfileTag = GpuProfileTag {
     label: "Blur",
     color: debug_colors::VIOLET,
 };
+const GPU_TAG_SVG_FILTER: GpuProfileTag = GpuProfileTag {
+    label: "SvgFilter",
+    color: debug_colors::LEMONCHIFFON,
+};
+const GPU_TAG_PATH: GpuProfileTag = GpuProfileTag {
+    label: "Path",
+    color: debug_colors::SEAGREEN,
+};
 const GPU_TAG_BLIT: GpuProfileTag = GpuProfileTag {
     label: "Blit",
     color: debug_colors::LIME,
@@ -228,6 +242,7 @@ impl BatchKind {
                     BrushBatchKind::MixBlend { .. } => "Brush (Composite)",
                     BrushBatchKind::YuvImage(..) => "Brush (YuvImage)",
                     BrushBatchKind::RadialGradient => "Brush (RadialGradient)",
+                    BrushBatchKind::ConicGradient => "Brush (ConicGradient)",
                     BrushBatchKind::LinearGradient => "Brush (LinearGradient)",
                 }
             }
@@ -249,6 +264,7 @@ impl BatchKind {
                     BrushBatchKind::MixBlend { .. } => GPU_TAG_BRUSH_MIXBLEND,
                     BrushBatchKind::YuvImage(..) => GPU_TAG_BRUSH_YUV_IMAGE,
                     BrushBatchKind::RadialGradient => GPU_TAG_BRUSH_RADIAL_GRADIENT,
+                    BrushBatchKind::ConicGradient => GPU_TAG_BRUSH_CONIC_GRADIENT,
                     BrushBatchKind::LinearGradient => GPU_TAG_BRUSH_LINEAR_GRADIENT,
                 }
             }
@@ -420,6 +436,75 @@ const DESC_BLUR: VertexDescriptor = VertexDescriptor {
     ],
 };
 
+const DESC_SVG_FILTER: VertexDescriptor = VertexDescriptor {
+    vertex_attributes: &[
+        VertexAttribute {
+            name: "aPosition",
+            count: 2,
+            kind: VertexAttributeKind::F32,
+        },
+    ],
+    instance_attributes: &[
+        VertexAttribute {
+            name: "aFilterRenderTaskAddress",
+            count: 1,
+            kind: VertexAttributeKind::I32,
+        },
+        VertexAttribute {
+            name: "aFilterInput1TaskAddress",
+            count: 1,
+            kind: VertexAttributeKind::I32,
+        },
+        VertexAttribute {
+            name: "aFilterInput2TaskAddress",
+            count: 1,
+            kind: VertexAttributeKind::I32,
+        },
+        VertexAttribute {
+            name: "aFilterKind",
+            count: 1,
+            kind: VertexAttributeKind::I32,
+        },
+        VertexAttribute {
+            name: "aFilterInputCount",
+            count: 1,
+            kind: VertexAttributeKind::U16,
+        },
+        VertexAttribute {
+            name: "aFilterGenericInt",
+            count: 1,
+            kind: VertexAttributeKind::U16,
+        },
+        VertexAttribute {
+            name: "aFilterExtraDataAddress",
+            count: 2,
+            kind: VertexAttributeKind::U16,
+        },
+    ],
+};
+
+const DESC_PATH: VertexDescriptor = VertexDescriptor {
+    vertex_attributes: &[
+        VertexAttribute {
+            name: "aPosition",
+            count: 2,
+            kind: VertexAttributeKind::F32,
+        },
+    ],
+    instance_attributes: &[
+        VertexAttribute {
+            name: "aPathRenderTaskAddress",
+            count: 1,
+            kind: VertexAttributeKind::I32,
+        },
+        VertexAttribute {
+            name: "aPathEdgesAddress",
+            count: 2,
+            kind: VertexAttributeKind::U16,
+        },
+    ],
+};
+
 const DESC_CLIP: VertexDescriptor = VertexDescriptor {
     vertex_attributes: &[
         VertexAttribute {
@@ -473,6 +558,8 @@ enum VertexArrayKind {
     Primitive,
     Blur,
     Clip,
+    SvgFilter,
+    Path,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -1503,6 +1590,8 @@ fn create_prim_shader(
         VertexArrayKind::Primitive => DESC_PRIM_INSTANCES,
         VertexArrayKind::Blur => DESC_BLUR,
         VertexArrayKind::Clip => DESC_CLIP,
+        VertexArrayKind::SvgFilter => DESC_SVG_FILTER,
+        VertexArrayKind::Path => DESC_PATH,
     };
 
     let program = device.create_program(name, &prefix, &vertex_descriptor);
@@ -1591,6 +1680,7 @@ pub struct Renderer {
     pending_texture_updates: Vec<TextureUpdateList>,
     pending_gpu_cache_updates: Vec<GpuCacheUpdateList>,
     pending_shader_updates: Vec<PathBuf>,
+    shared_hit_testers: SharedHitTesters,
     active_documents: Vec<(DocumentId, RenderedDocument)>,
 
     // These are "cache shaders". These shaders are used to
@@ -1599,6 +1689,8 @@ pub struct Renderer {
     cs_text_run: LazilyCompiledShader,
     cs_blur_a8: LazilyCompiledShader,
     cs_blur_rgba8: LazilyCompiledShader,
+    cs_svg_filter: LazilyCompiledShader,
+    cs_path: LazilyCompiledShader,
 
     // Brush shaders
     brush_mask_corner: LazilyCompiledShader,
@@ -1613,6 +1705,7 @@ pub struct Renderer {
     brush_mix_blend: BrushShader,
     brush_yuv_image: Vec<Option<BrushShader>>,
     brush_radial_gradient: BrushShader,
+    brush_conic_gradient: BrushShader,
     brush_linear_gradient: BrushShader,
 
     /// These are "cache clip shaders". These shaders are used to
@@ -1621,6 +1714,7 @@ pub struct Renderer {
     cs_clip_rectangle: LazilyCompiledShader,
     cs_clip_image: LazilyCompiledShader,
     cs_clip_border: LazilyCompiledShader,
+    cs_clip_polygon: LazilyCompiledShader,
 
     // The are "primitive shaders". These shaders draw and blend
     // final results on screen. They are aware of tile boundaries.
@@ -1654,6 +1748,8 @@ pub struct Renderer {
     prim_vao: VAO,
     blur_vao: VAO,
     clip_vao: VAO,
+    svg_filter_vao: VAO,
+    path_vao: VAO,
 
     node_data_texture: VertexDataTexture,
     local_clip_rects_texture: VertexDataTexture,
@@ -1879,6 +1975,17 @@ impl Renderer {
                              options.precache_shaders)
         };
 
+        let brush_conic_gradient = try!{
+            BrushShader::new("brush_conic_gradient",
+                             &mut device,
+                             if options.enable_dithering {
+                                &dithering_feature
+                             } else {
+                                &[]
+                             },
+                             options.precache_shaders)
+        };
+
         let brush_linear_gradient = try!{
             BrushShader::new("brush_linear_gradient",
                              &mut device,
@@ -1906,6 +2013,22 @@ impl Renderer {
                                       options.precache_shaders)
         };
 
+        let cs_svg_filter = try!{
+            LazilyCompiledShader::new(ShaderKind::Cache(VertexArrayKind::SvgFilter),
+                                     "cs_svg_filter",
+                                      &[],
+                                      &mut device,
+                                      options.precache_shaders)
+        };
+
+        let cs_path = try!{
+            LazilyCompiledShader::new(ShaderKind::Cache(VertexArrayKind::Path),
+                                     "cs_path",
+                                      &[],
+                                      &mut device,
+                                      options.precache_shaders)
+        };
+
         let cs_clip_rectangle = try!{
             LazilyCompiledShader::new(ShaderKind::ClipCache,
                                       "cs_clip_rectangle",
@@ -1930,6 +2053,14 @@ impl Renderer {
                                       options.precache_shaders)
         };
 
+        let cs_clip_polygon = try!{
+            LazilyCompiledShader::new(ShaderKind::ClipCache,
+                                      "cs_clip_polygon",
+                                      &[],
+                                      &mut device,
+                                      options.precache_shaders)
+        };
+
         let ps_text_run = try!{
             TextShader::new("ps_text_run",
                             &mut device,
@@ -2164,6 +2295,8 @@ impl Renderer {
 
         let blur_vao = device.create_vao_with_new_instances(&DESC_BLUR, &prim_vao);
         let clip_vao = device.create_vao_with_new_instances(&DESC_CLIP, &prim_vao);
+        let svg_filter_vao = device.create_vao_with_new_instances(&DESC_SVG_FILTER, &prim_vao);
+        let path_vao = device.create_vao_with_new_instances(&DESC_PATH, &prim_vao);
 
         let texture_cache_upload_pbo = device.create_pbo();
 
@@ -2194,6 +2327,7 @@ impl Renderer {
             debug: options.debug,
             dual_source_blending_is_enabled: true,
             dual_source_blending_is_supported: ext_dual_source_blending,
+            fling_friction: options.fling_friction,
         };
 
         let device_pixel_ratio = options.device_pixel_ratio;
@@ -2228,6 +2362,8 @@ impl Renderer {
         let enable_render_on_scroll = options.enable_render_on_scroll;
 
         let blob_image_renderer = options.blob_image_renderer.take();
+        let shared_hit_testers = SharedHitTesters::new();
+        let shared_hit_testers_for_render_backend = shared_hit_testers.clone();
         let thread_listener_for_render_backend = thread_listener.clone();
         let thread_listener_for_scene_builder = thread_listener.clone();
         let rb_thread_name = format!("WRRenderBackend#{}", options.renderer_id.unwrap_or(0));
@@ -2273,6 +2409,7 @@ impl Renderer {
                     backend_notifier,
                     config,
                     recorder,
+                    shared_hit_testers_for_render_backend,
                     enable_render_on_scroll,
                 );
                 backend.run(backend_profile_counters);
@@ -2290,6 +2427,7 @@ impl Renderer {
             result_rx,
             debug_server,
             device,
+            shared_hit_testers,
             active_documents: Vec::new(),
             pending_texture_updates: Vec::new(),
             pending_gpu_cache_updates: Vec::new(),
@@ -2297,6 +2435,8 @@ impl Renderer {
             cs_text_run,
             cs_blur_a8,
             cs_blur_rgba8,
+            cs_svg_filter,
+            cs_path,
             brush_mask_corner,
             brush_mask_rounded_rect,
             brush_picture_rgba8,
@@ -2309,10 +2449,12 @@ impl Renderer {
             brush_mix_blend,
             brush_yuv_image,
             brush_radial_gradient,
+            brush_conic_gradient,
             brush_linear_gradient,
             cs_clip_rectangle,
             cs_clip_border,
             cs_clip_image,
+            cs_clip_polygon,
             ps_text_run,
             ps_text_run_dual_source,
             ps_image,
@@ -2334,6 +2476,8 @@ impl Renderer {
             prim_vao,
             blur_vao,
             clip_vao,
+            svg_filter_vao,
+            path_vao,
             node_data_texture,
             local_clip_rects_texture,
             render_task_texture,
@@ -2388,6 +2532,12 @@ impl Renderer {
         self.pipeline_info.epochs.get(&pipeline_id).cloned()
     }
 
+    /// Returns a handle for hit testing the last rendered frame of each document
+    /// from any thread, without a round trip to the render backend.
+    pub fn shared_hit_testers(&self) -> SharedHitTesters {
+        self.shared_hit_testers.clone()
+    }
+
     pub fn flush_pipeline_info(&mut self) -> PipelineInfo {
         mem::replace(&mut self.pipeline_info, PipelineInfo::default())
     }
@@ -2598,6 +2748,11 @@ impl Renderer {
             "Horizontal Blur",
             target.horizontal_blurs.len(),
         );
+        debug_target.add(
+            debug_server::BatchKind::Cache,
+            "SVG Filters",
+            target.svg_filters.iter().map(|&(_, ref instances)| instances.len()).sum(),
+        );
 
         for alpha_batch_container in &target.alpha_batch_containers {
             for (_, batch) in &alpha_batch_container.text_run_cache_prims {
@@ -3162,6 +3317,8 @@ impl Renderer {
             VertexArrayKind::Primitive => &self.prim_vao,
             VertexArrayKind::Clip => &self.clip_vao,
             VertexArrayKind::Blur => &self.blur_vao,
+            VertexArrayKind::SvgFilter => &self.svg_filter_vao,
+            VertexArrayKind::Path => &self.path_vao,
         };
 
         self.device.bind_vao(vao);
@@ -3285,6 +3442,15 @@ impl Renderer {
                             &mut self.renderer_errors,
                         );
                     }
+                    BrushBatchKind::ConicGradient => {
+                        self.brush_conic_gradient.bind(
+                            &mut self.device,
+                            key.blend_mode,
+                            projection,
+                            0,
+                            &mut self.renderer_errors,
+                        );
+                    }
                     BrushBatchKind::LinearGradient => {
                         self.brush_linear_gradient.bind(
                             &mut self.device,
@@ -3594,6 +3760,24 @@ impl Renderer {
             }
         }
 
+        // Draw any SVG filter primitives for this target.
+        if !target.svg_filters.is_empty() {
+            let _timer = self.gpu_profile.start_timer(GPU_TAG_SVG_FILTER);
+
+            self.device.set_blend(false);
+            self.cs_svg_filter
+                .bind(&mut self.device, projection, 0, &mut self.renderer_errors);
+
+            for &(ref textures, ref instances) in &target.svg_filters {
+                self.draw_instanced_batch(
+                    instances,
+                    VertexArrayKind::SvgFilter,
+                    textures,
+                    stats,
+                );
+            }
+        }
+
         self.handle_scaling(render_tasks, &target.scalings, SourceTexture::CacheRGBA8);
 
         // Draw any textrun caches for this target. For now, this
@@ -4121,6 +4305,22 @@ impl Renderer {
                     stats,
                 );
             }
+            // draw polygons
+            if !target.clip_batcher.polygons.is_empty() {
+                let _gm2 = self.gpu_profile.start_marker("clip polygons");
+                self.cs_clip_polygon.bind(
+                    &mut self.device,
+                    projection,
+                    0,
+                    &mut self.renderer_errors,
+                );
+                self.draw_instanced_batch(
+                    &target.clip_batcher.polygons,
+                    VertexArrayKind::Clip,
+                    &BatchTextures::no_texture(),
+                    stats,
+                );
+            }
         }
 
         self.gpu_profile.finish_sampler(alpha_sampler);
@@ -4173,6 +4373,23 @@ impl Renderer {
                 stats,
             );
         }
+
+        // Rasterize any path masks for this target. These
+        // cover every pixel of their task rect, so there's
+        // no need to clear them first.
+        if !target.paths.is_empty() {
+            let _timer = self.gpu_profile.start_timer(GPU_TAG_PATH);
+
+            self.cs_path
+                .bind(&mut self.device, &projection, 0, &mut self.renderer_errors);
+
+            self.draw_instanced_batch(
+                &target.paths,
+                VertexArrayKind::Path,
+                &BatchTextures::no_texture(),
+                stats,
+            );
+        }
     }
 
     fn update_deferred_resolves(&mut self, deferred_resolves: &[DeferredResolve]) -> Option<GpuCacheUpdateList> {
@@ -4731,10 +4948,14 @@ impl Renderer {
         self.device.delete_vao(self.prim_vao);
         self.device.delete_vao(self.clip_vao);
         self.device.delete_vao(self.blur_vao);
+        self.device.delete_vao(self.svg_filter_vao);
+        self.device.delete_vao(self.path_vao);
         self.debug.deinit(&mut self.device);
         self.cs_text_run.deinit(&mut self.device);
         self.cs_blur_a8.deinit(&mut self.device);
         self.cs_blur_rgba8.deinit(&mut self.device);
+        self.cs_svg_filter.deinit(&mut self.device);
+        self.cs_path.deinit(&mut self.device);
         self.brush_mask_rounded_rect.deinit(&mut self.device);
         self.brush_mask_corner.deinit(&mut self.device);
         self.brush_picture_rgba8.deinit(&mut self.device);
@@ -4745,9 +4966,11 @@ impl Renderer {
         self.brush_blend.deinit(&mut self.device);
         self.brush_mix_blend.deinit(&mut self.device);
         self.brush_radial_gradient.deinit(&mut self.device);
+        self.brush_conic_gradient.deinit(&mut self.device);
         self.brush_linear_gradient.deinit(&mut self.device);
         self.cs_clip_rectangle.deinit(&mut self.device);
         self.cs_clip_image.deinit(&mut self.device);
+        self.cs_clip_polygon.deinit(&mut self.device);
         self.cs_clip_border.deinit(&mut self.device);
         self.ps_text_run.deinit(&mut self.device);
         self.ps_text_run_dual_source.deinit(&mut self.device);
@@ -4859,6 +5082,9 @@ pub struct RendererOptions {
     pub debug_flags: DebugFlags,
     pub renderer_id: Option<u64>,
     pub disable_dual_source_blending: bool,
+    /// The fraction of its velocity that a fling of a scroll frame loses every
+    /// millisecond after the scroll gesture that started it ends.
+    pub fling_friction: f32,
 }
 
 impl Default for RendererOptions {
@@ -4891,6 +5117,7 @@ impl Default for RendererOptions {
             renderer_id: None,
             cached_programs: None,
             disable_dual_source_blending: false,
+            fling_friction: DEFAULT_FLING_FRICTION,
         }
     }
 }
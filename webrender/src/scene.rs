@@ -3,13 +3,13 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use api::{BuiltDisplayList, ColorF, DynamicProperties, Epoch, LayerSize, LayoutSize};
-use api::{FilterOp, LayoutTransform, PipelineId, PropertyBinding, PropertyBindingId};
+use api::{FilterData, FilterOp, LayoutTransform, PipelineId, PropertyBinding, PropertyBindingId};
 use api::{ItemRange, MixBlendMode, StackingContext};
 use internal_types::FastHashMap;
 use std::sync::Arc;
 
 /// Stores a map of the animated property bindings for the current display list. These
-/// can be used to animate the transform and/or opacity of a display list without
+/// can be used to animate the transform, opacity and/or colors of a display list without
 /// re-submitting the display list itself.
 #[cfg_attr(feature = "capture", derive(Serialize))]
 #[cfg_attr(feature = "replay", derive(Deserialize))]
@@ -17,6 +17,7 @@ use std::sync::Arc;
 pub struct SceneProperties {
     transform_properties: FastHashMap<PropertyBindingId, LayoutTransform>,
     float_properties: FastHashMap<PropertyBindingId, f32>,
+    color_properties: FastHashMap<PropertyBindingId, ColorF>,
 }
 
 impl SceneProperties {
@@ -24,6 +25,7 @@ impl SceneProperties {
         SceneProperties {
             transform_properties: FastHashMap::default(),
             float_properties: FastHashMap::default(),
+            color_properties: FastHashMap::default(),
         }
     }
 
@@ -31,7 +33,14 @@ impl SceneProperties {
     pub fn set_properties(&mut self, properties: DynamicProperties) {
         self.transform_properties.clear();
         self.float_properties.clear();
+        self.color_properties.clear();
 
+        self.add_properties(properties);
+    }
+
+    /// Add to the current property list, replacing the values of any
+    /// bindings that are already set.
+    pub fn add_properties(&mut self, properties: DynamicProperties) {
         for property in properties.transforms {
             self.transform_properties
                 .insert(property.key.id, property.value);
@@ -41,6 +50,11 @@ impl SceneProperties {
             self.float_properties
                 .insert(property.key.id, property.value);
         }
+
+        for property in properties.colors {
+            self.color_properties
+                .insert(property.key.id, property.value);
+        }
     }
 
     /// Get the current value for a transform property.
@@ -83,6 +97,27 @@ impl SceneProperties {
             }
         }
     }
+
+    /// Get the current value for a color property. A binding without
+    /// a value resolves to transparent, so nothing is drawn for it.
+    pub fn resolve_color(
+        &self,
+        property: &PropertyBinding<ColorF>,
+    ) -> ColorF {
+        match *property {
+            PropertyBinding::Value(value) => value,
+            PropertyBinding::Binding(ref key) => {
+                self.color_properties
+                    .get(&key.id)
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        warn!("Property binding has an invalid value.");
+                        debug!("key={:?}", key);
+                        ColorF::new(0.0, 0.0, 0.0, 0.0)
+                    })
+            }
+        }
+    }
 }
 
 /// A representation of the layout within the display port for a given document or iframe.
@@ -173,7 +208,11 @@ impl FilterOpHelpers for FilterOp {
             FilterOp::Saturate(..) |
             FilterOp::Sepia(..) |
             FilterOp::DropShadow(..) |
-            FilterOp::ColorMatrix(..) => true,
+            FilterOp::ColorMatrix(..) |
+            FilterOp::ComponentTransfer |
+            FilterOp::Flood(..) |
+            FilterOp::Offset(..) |
+            FilterOp::Composite(..) => true,
             FilterOp::Opacity(_, amount) => {
                 amount > OPACITY_EPSILON
             }
@@ -201,6 +240,10 @@ impl FilterOpHelpers for FilterOp {
                            0.0, 0.0, 0.0, 1.0,
                            0.0, 0.0, 0.0, 0.0]
             }
+            FilterOp::ComponentTransfer |
+            FilterOp::Flood(..) |
+            FilterOp::Composite(..) => false,
+            FilterOp::Offset(offset) => offset.x == 0.0 && offset.y == 0.0,
         }
     }
 }
@@ -212,6 +255,11 @@ pub trait StackingContextHelpers {
         display_list: &BuiltDisplayList,
         input_filters: ItemRange<FilterOp>,
     ) -> Vec<FilterOp>;
+    fn filter_data_for_compositing(
+        &self,
+        display_list: &BuiltDisplayList,
+        input_filter_data: ItemRange<FilterData>,
+    ) -> Vec<FilterData>;
 }
 
 impl StackingContextHelpers for StackingContext {
@@ -236,4 +284,12 @@ impl StackingContextHelpers for StackingContext {
         }
         filters
     }
+
+    fn filter_data_for_compositing(
+        &self,
+        display_list: &BuiltDisplayList,
+        input_filter_data: ItemRange<FilterData>,
+    ) -> Vec<FilterData> {
+        display_list.get(input_filter_data).collect()
+    }
 }
@@ -46,6 +46,12 @@ impl Spring {
         self.dest = dest
     }
 
+    /// Change how the spring behaves, keeping its current motion.
+    pub fn set_coefficients(&mut self, stiffness: f32, damping: f32) {
+        self.stiffness = stiffness;
+        self.damping = damping;
+    }
+
     pub fn current(&self) -> LayerPoint {
         self.cur
     }
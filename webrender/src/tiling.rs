@@ -2,24 +2,25 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use api::{ColorF, DeviceIntPoint, DeviceIntRect, DeviceIntSize, DevicePixelScale, DeviceUintPoint};
-use api::{DeviceUintRect, DeviceUintSize, DocumentLayer, FilterOp, ImageFormat, LayerRect};
-use api::{MixBlendMode, PipelineId};
-use batch::{AlphaBatchBuilder, AlphaBatchContainer, ClipBatcher, resolve_image};
+use api::{ColorF, CompositeOperator, DeviceIntPoint, DeviceIntRect, DeviceIntSize};
+use api::{DevicePixelScale, DeviceUintPoint, DeviceUintRect, DeviceUintSize, DocumentLayer};
+use api::{FilterData, FilterOp, ImageFormat, LayerRect, MixBlendMode, PipelineId};
+use batch::{AlphaBatchBuilder, AlphaBatchContainer, BatchTextures, ClipBatcher, resolve_image};
 use clip::{ClipStore};
 use clip_scroll_tree::{ClipScrollTree, ClipScrollNodeIndex};
 use device::{FrameId, Texture};
-use gpu_cache::{GpuCache};
+use gpu_cache::{GpuCache, GpuCacheAddress};
 use gpu_types::{BlurDirection, BlurInstance, BrushFlags, BrushInstance, ClipChainRectIndex};
 use gpu_types::{ClipScrollNodeData, ClipScrollNodeIndex as GPUClipScrollNodeIndex};
-use gpu_types::{PrimitiveInstance};
+use gpu_types::{PathInstance, PrimitiveInstance, SvgFilterInstance};
 use internal_types::{FastHashMap, SavedTargetIndex, SourceTexture};
 use picture::{PictureKind};
 use prim_store::{CachedGradient, PrimitiveIndex, PrimitiveKind, PrimitiveStore};
 use prim_store::{BrushMaskKind, BrushKind, DeferredResolve, EdgeAaSegmentMask};
 use profiler::FrameProfileCounters;
 use render_task::{BlitSource, RenderTaskAddress, RenderTaskId, RenderTaskKind};
-use render_task::{BlurTask, ClearMode, RenderTaskLocation, RenderTaskTree};
+use render_task::{BlurTask, ClearMode, RenderTaskLocation, RenderTaskTree, SvgFilterInfo};
+use render_task::SvgFilterTask;
 use resource_cache::ResourceCache;
 use std::{cmp, usize, f32, i32};
 use texture_allocator::GuillotineAllocator;
@@ -271,6 +272,8 @@ pub struct ColorRenderTarget {
     // List of blur operations to apply for this render target.
     pub vertical_blurs: Vec<BlurInstance>,
     pub horizontal_blurs: Vec<BlurInstance>,
+    // SVG filter primitives, batched by their secondary input.
+    pub svg_filters: Vec<(BatchTextures, Vec<SvgFilterInstance>)>,
     pub readbacks: Vec<DeviceIntRect>,
     pub scalings: Vec<ScalingInfo>,
     pub blits: Vec<BlitJob>,
@@ -297,6 +300,7 @@ impl RenderTarget for ColorRenderTarget {
             alpha_batch_containers: Vec::new(),
             vertical_blurs: Vec::new(),
             horizontal_blurs: Vec::new(),
+            svg_filters: Vec::new(),
             readbacks: Vec::new(),
             scalings: Vec::new(),
             blits: Vec::new(),
@@ -404,7 +408,18 @@ impl RenderTarget for ColorRenderTarget {
                     }
                 }
             }
-            RenderTaskKind::CacheMask(..) => {
+            RenderTaskKind::SvgFilter(ref task_info) => {
+                add_svg_filter_instances(
+                    &mut self.svg_filters,
+                    task_id,
+                    task_info,
+                    task.children[0],
+                    render_tasks,
+                    gpu_cache,
+                );
+            }
+            RenderTaskKind::CacheMask(..) |
+            RenderTaskKind::Path(..) => {
                 panic!("Should not be added to color target!");
             }
             RenderTaskKind::Readback(device_rect) => {
@@ -534,7 +549,9 @@ impl RenderTarget for AlphaRenderTarget {
 
         match task.kind {
             RenderTaskKind::Readback(..) |
-            RenderTaskKind::Blit(..) => {
+            RenderTaskKind::Blit(..) |
+            RenderTaskKind::SvgFilter(..) |
+            RenderTaskKind::Path(..) => {
                 panic!("BUG: should not be added to alpha target!");
             }
             RenderTaskKind::VerticalBlur(ref info) => {
@@ -599,7 +616,9 @@ impl RenderTarget for AlphaRenderTarget {
                                             BrushKind::Line { .. } |
                                             BrushKind::YuvImage { .. } |
                                             BrushKind::RadialGradient { .. } |
+                                            BrushKind::ConicGradient { .. } |
                                             BrushKind::LinearGradient { .. } |
+                                            BrushKind::Path { .. } |
                                             BrushKind::Image { .. } => {
                                                 unreachable!("bug: unexpected brush here");
                                             }
@@ -659,6 +678,7 @@ impl RenderTarget for AlphaRenderTarget {
 pub struct TextureCacheRenderTarget {
     pub horizontal_blurs: Vec<BlurInstance>,
     pub blits: Vec<BlitJob>,
+    pub paths: Vec<PathInstance>,
 }
 
 impl TextureCacheRenderTarget {
@@ -669,6 +689,7 @@ impl TextureCacheRenderTarget {
         TextureCacheRenderTarget {
             horizontal_blurs: Vec::new(),
             blits: Vec::new(),
+            paths: Vec::new(),
         }
     }
 
@@ -676,6 +697,7 @@ impl TextureCacheRenderTarget {
         &mut self,
         task_id: RenderTaskId,
         render_tasks: &RenderTaskTree,
+        gpu_cache: &GpuCache,
     ) {
         let task = &render_tasks[task_id];
 
@@ -707,11 +729,18 @@ impl TextureCacheRenderTarget {
                     }
                 }
             }
+            RenderTaskKind::Path(ref task_info) => {
+                self.paths.push(PathInstance {
+                    task_address: render_tasks.get_task_address(task_id),
+                    edges_address: gpu_cache.get_address(&task_info.edges),
+                });
+            }
             RenderTaskKind::VerticalBlur(..) |
             RenderTaskKind::Picture(..) |
             RenderTaskKind::CacheMask(..) |
             RenderTaskKind::Readback(..) |
-            RenderTaskKind::Scaling(..) => {
+            RenderTaskKind::Scaling(..) |
+            RenderTaskKind::SvgFilter(..) => {
                 panic!("BUG: unexpected task kind for texture cache target");
             }
         }
@@ -877,7 +906,7 @@ impl RenderPass {
                                 .or_insert(
                                     TextureCacheRenderTarget::new(None, DeviceIntSize::zero())
                                 );
-                            texture.add_task(task_id, render_tasks);
+                            texture.add_task(task_id, render_tasks, gpu_cache);
                         }
                         None => {
                             match target_kind {
@@ -915,18 +944,63 @@ pub struct CompositeOps {
     // Requires only a single texture as input (e.g. most filters)
     pub filters: Vec<FilterOp>,
 
+    // Transfer functions for each component transfer filter, in order.
+    pub filter_data: Vec<FilterData>,
+
     // Requires two source textures (e.g. mix-blend-mode)
     pub mix_blend_mode: Option<MixBlendMode>,
+
+    // Applied to what is drawn behind the stacking context
+    // (e.g. backdrop-filter)
+    pub backdrop_filters: Vec<FilterOp>,
 }
 
 impl CompositeOps {
-    pub fn new(filters: Vec<FilterOp>, mix_blend_mode: Option<MixBlendMode>) -> Self {
+    pub fn new(
+        filters: Vec<FilterOp>,
+        filter_data: Vec<FilterData>,
+        mix_blend_mode: Option<MixBlendMode>,
+        backdrop_filters: Vec<FilterOp>,
+    ) -> Self {
+        // Each component transfer filter uses the next set of filter data.
+        // Without any data left, the filter is the identity, so drop it.
+        let mut data_left = filter_data.len();
+        let filters = filters
+            .into_iter()
+            .filter(|filter| {
+                match *filter {
+                    FilterOp::ComponentTransfer if data_left == 0 => false,
+                    FilterOp::ComponentTransfer => {
+                        data_left -= 1;
+                        true
+                    }
+                    _ => true,
+                }
+            })
+            .collect();
+
+        // Backdrop filters have no filter data, so a component transfer
+        // is always the identity there.
+        let backdrop_filters = backdrop_filters
+            .into_iter()
+            .filter(|filter| {
+                match *filter {
+                    FilterOp::ComponentTransfer => false,
+                    _ => true,
+                }
+            })
+            .collect();
+
         CompositeOps {
             filters,
+            filter_data,
             mix_blend_mode,
+            backdrop_filters,
         }
     }
 
+    // The number of pictures pushed for the stacking context, on top
+    // of its own. Backdrop filters are drawn by pictures beside it.
     pub fn count(&self) -> usize {
         self.filters.len() + if self.mix_blend_mode.is_some() { 1 } else { 0 }
     }
@@ -995,3 +1069,78 @@ impl BlurTask {
         instances.push(instance);
     }
 }
+
+fn add_svg_filter_instances(
+    instances: &mut Vec<(BatchTextures, Vec<SvgFilterInstance>)>,
+    task_id: RenderTaskId,
+    task_info: &SvgFilterTask,
+    input_task_id: RenderTaskId,
+    render_tasks: &RenderTaskTree,
+    gpu_cache: &mut GpuCache,
+) {
+    // Note: has to match `FILTER_*` and `COMPOSITE_*` in cs_svg_filter.glsl
+    let (kind, input_count, generic_int) = match task_info.info {
+        SvgFilterInfo::ComponentTransfer => (0, 1, 0),
+        SvgFilterInfo::Flood(..) => (1, 0, 0),
+        SvgFilterInfo::Offset(..) => (2, 1, 0),
+        SvgFilterInfo::Composite(operator) => {
+            let operator = match operator {
+                CompositeOperator::Over => 0,
+                CompositeOperator::In => 1,
+                CompositeOperator::Out => 2,
+                CompositeOperator::Atop => 3,
+                CompositeOperator::Xor => 4,
+                CompositeOperator::Lighter => 5,
+                CompositeOperator::Arithmetic(..) => 6,
+            };
+            // Without a source graphic, the second input is transparent.
+            let input_count = if task_info.source_graphic.is_some() { 2 } else { 1 };
+            (3, input_count, operator)
+        }
+    };
+
+    // The unfiltered content of the stacking context is
+    // read from the target it was saved to.
+    let (source_texture, input_2_task_address) = match task_info.source_graphic {
+        Some(source_id) => {
+            let saved_index = render_tasks[source_id].saved_index.expect("no saved index!?");
+            debug_assert_ne!(saved_index, SavedTargetIndex::PENDING);
+            (
+                SourceTexture::RenderTaskCache(saved_index),
+                render_tasks.get_task_address(source_id),
+            )
+        }
+        None => {
+            (SourceTexture::Invalid, RenderTaskAddress(0))
+        }
+    };
+
+    let extra_data_address = match task_info.extra_gpu_cache_handle {
+        Some(ref handle) => gpu_cache.get_address(handle),
+        None => GpuCacheAddress::invalid(),
+    };
+
+    let instance = SvgFilterInstance {
+        task_address: render_tasks.get_task_address(task_id),
+        input_1_task_address: render_tasks.get_task_address(input_task_id),
+        input_2_task_address,
+        kind,
+        input_count,
+        generic_int,
+        extra_data_address,
+    };
+
+    let textures = BatchTextures {
+        colors: [source_texture, SourceTexture::Invalid, SourceTexture::Invalid],
+    };
+
+    match instances.last_mut() {
+        Some(&mut (ref last_textures, ref mut batch)) if last_textures.colors == textures.colors => {
+            batch.push(instance);
+            return;
+        }
+        _ => {}
+    }
+
+    instances.push((textures, vec![instance]));
+}
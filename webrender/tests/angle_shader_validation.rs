@@ -36,6 +36,10 @@ const SHADERS: &[Shader] = &[
         name: "cs_clip_border",
         features: CLIP_FEATURES,
     },
+    Shader {
+        name: "cs_clip_polygon",
+        features: CLIP_FEATURES,
+    },
     // Cache shaders
     Shader {
         name: "cs_blur",
@@ -45,6 +49,14 @@ const SHADERS: &[Shader] = &[
         name: "cs_text_run",
         features: CACHE_FEATURES,
     },
+    Shader {
+        name: "cs_svg_filter",
+        features: CACHE_FEATURES,
+    },
+    Shader {
+        name: "cs_path",
+        features: CACHE_FEATURES,
+    },
     // Prim shaders
     Shader {
         name: "ps_border_corner",
@@ -103,6 +115,10 @@ const SHADERS: &[Shader] = &[
         name: "brush_radial_gradient",
         features: &[ "DITHERING" ],
     },
+    Shader {
+        name: "brush_conic_gradient",
+        features: &[ "DITHERING" ],
+    },
     Shader {
         name: "brush_linear_gradient",
         features: &[],
@@ -0,0 +1,154 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use {ColorF, ExternalScrollId, LayoutTransform, PropertyBindingId, PropertyBindingKey};
+
+/// The timing function applied between two keyframes. These match the
+/// CSS timing functions of the same names.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum AnimationEasing {
+    Linear,
+    Ease,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// The x coordinates of both control points must be within [0, 1].
+    CubicBezier(f32, f32, f32, f32),
+    /// Jumps at the end of each of the given number of intervals.
+    Steps(u32),
+}
+
+/// The value of a property at a point in one iteration of an animation.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Keyframe<T> {
+    /// The position of this keyframe within an iteration, from 0 to 1.
+    pub offset: f32,
+    pub value: T,
+    /// The easing used on the way from this keyframe to the next one.
+    pub easing: AnimationEasing,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum AnimationIterations {
+    Count(u32),
+    Infinite,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum AnimationDirection {
+    Normal,
+    Reverse,
+    Alternate,
+    AlternateReverse,
+}
+
+/// A timeline of keyframes for a property binding, which is sampled by
+/// the render backend every time it generates a frame.
+///
+/// Transforms are interpolated component-wise, so keyframes of a rotation
+/// need to be close enough together for the result to look right.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Animation<T> {
+    pub key: PropertyBindingKey<T>,
+    /// Sorted by offset. The value before the first keyframe and after the
+    /// last one is the value of that keyframe.
+    pub keyframes: Vec<Keyframe<T>>,
+    /// The duration of a single iteration, in milliseconds.
+    pub duration: f32,
+    pub iterations: AnimationIterations,
+    pub direction: AnimationDirection,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum PropertyAnimation {
+    Transform(Animation<LayoutTransform>),
+    Float(Animation<f32>),
+    Color(Animation<ColorF>),
+}
+
+impl PropertyAnimation {
+    pub fn id(&self) -> PropertyBindingId {
+        match *self {
+            PropertyAnimation::Transform(ref animation) => animation.key.id,
+            PropertyAnimation::Float(ref animation) => animation.key.id,
+            PropertyAnimation::Color(ref animation) => animation.key.id,
+        }
+    }
+}
+
+impl From<Animation<LayoutTransform>> for PropertyAnimation {
+    fn from(animation: Animation<LayoutTransform>) -> Self {
+        PropertyAnimation::Transform(animation)
+    }
+}
+
+impl From<Animation<f32>> for PropertyAnimation {
+    fn from(animation: Animation<f32>) -> Self {
+        PropertyAnimation::Float(animation)
+    }
+}
+
+impl From<Animation<ColorF>> for PropertyAnimation {
+    fn from(animation: Animation<ColorF>) -> Self {
+        PropertyAnimation::Color(animation)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ScrollAxis {
+    Horizontal,
+    Vertical,
+}
+
+/// Drives a property binding from the scroll origin of a scroll frame rather
+/// than from time. The render backend updates the property whenever the scroll
+/// frame moves, without a round trip through the client.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ScrollLink<T> {
+    pub key: PropertyBindingKey<T>,
+    pub scroll_id: ExternalScrollId,
+    pub axis: ScrollAxis,
+    /// The scroll origins that map to the offsets 0 and 1 of the keyframes.
+    /// Scroll origins outside of this range are clamped to it.
+    pub start: f32,
+    pub end: f32,
+    /// Sorted by offset. Each pair of keyframes maps a part of the scroll
+    /// range to the values between them.
+    pub keyframes: Vec<Keyframe<T>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum PropertyScrollLink {
+    Transform(ScrollLink<LayoutTransform>),
+    Float(ScrollLink<f32>),
+    Color(ScrollLink<ColorF>),
+}
+
+impl PropertyScrollLink {
+    pub fn id(&self) -> PropertyBindingId {
+        match *self {
+            PropertyScrollLink::Transform(ref link) => link.key.id,
+            PropertyScrollLink::Float(ref link) => link.key.id,
+            PropertyScrollLink::Color(ref link) => link.key.id,
+        }
+    }
+}
+
+impl From<ScrollLink<LayoutTransform>> for PropertyScrollLink {
+    fn from(link: ScrollLink<LayoutTransform>) -> Self {
+        PropertyScrollLink::Transform(link)
+    }
+}
+
+impl From<ScrollLink<f32>> for PropertyScrollLink {
+    fn from(link: ScrollLink<f32>) -> Self {
+        PropertyScrollLink::Float(link)
+    }
+}
+
+impl From<ScrollLink<ColorF>> for PropertyScrollLink {
+    fn from(link: ScrollLink<ColorF>) -> Self {
+        PropertyScrollLink::Color(link)
+    }
+}
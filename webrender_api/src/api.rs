@@ -10,10 +10,12 @@ use std::marker::PhantomData;
 use std::path::PathBuf;
 use std::u32;
 use {BuiltDisplayList, BuiltDisplayListDescriptor, ColorF, DeviceIntPoint, DeviceUintRect};
-use {DeviceUintSize, ExternalScrollId, FontInstanceKey, FontInstanceOptions};
-use {FontInstancePlatformOptions, FontKey, FontVariation, GlyphDimensions, GlyphKey, ImageData};
-use {ImageDescriptor, ImageKey, ItemTag, LayoutPoint, LayoutSize, LayoutTransform, LayoutVector2D};
-use {NativeFontHandle, WorldPoint};
+use {AnimationEasing, DeviceUintSize, DisplayListError, ExternalScrollId, FontInstanceKey};
+use {FixedLayout, FontInstanceOptions, FontInstancePlatformOptions, FontKey, FontVariation};
+use GlyphDimensions;
+use {GlyphKey, ImageData, ImageDescriptor, ImageKey, ItemTag, LayoutPoint, LayoutRect, LayoutSize};
+use {LayoutTransform, LayoutVector2D, NativeFontHandle, PropertyAnimation, PropertyScrollLink};
+use {WorldPoint, WorldRect};
 
 pub type TileSize = u16;
 /// Documents are rendered in the ascending order of their associated layer values.
@@ -251,6 +253,35 @@ impl Transaction {
         self.payloads.push(Payload { epoch, pipeline_id, display_list_data });
     }
 
+    /// Replaces item groups in the display list of a pipeline with the item groups of
+    /// a delta, built by a builder from `DisplayListBuilder::new_delta`, so that only the
+    /// items that changed are sent. Items can be inserted by replacing an empty group.
+    /// Frame state is preserved, and the background and viewport size stay the same.
+    ///
+    /// A delta only saves transfer size: the unchanged items aren't built, serialized or
+    /// sent again. It doesn't make scene building any cheaper. The render backend splices
+    /// the delta into the previous display list, then validates the whole result and
+    /// rebuilds the scene from it, just like for `set_display_list`.
+    ///
+    /// If the delta doesn't apply, the pipeline keeps its previous display list, and
+    /// `RenderNotifier::display_list_rejected` is called.
+    pub fn update_display_list(
+        &mut self,
+        epoch: Epoch,
+        (pipeline_id, content_size, delta): (PipelineId, LayoutSize, BuiltDisplayList),
+    ) {
+        let (display_list_data, list_descriptor) = delta.into_data();
+        self.scene_ops.push(
+            SceneMsg::UpdateDisplayList {
+                epoch,
+                pipeline_id,
+                content_size,
+                list_descriptor,
+            }
+        );
+        self.payloads.push(Payload { epoch, pipeline_id, display_list_data });
+    }
+
     pub fn update_resources(&mut self, resources: ResourceUpdates) {
         self.resource_updates.merge(resources);
     }
@@ -292,6 +323,21 @@ impl Transaction {
         self.frame_ops.push(FrameMsg::ScrollNodeWithId(origin, id, clamp));
     }
 
+    /// Like `scroll_node_with_id`, but animates the scroll over `duration` milliseconds.
+    /// The render backend advances the scroll every time it generates a frame, until
+    /// `RenderNotifier::smooth_scroll_ended` is called. Scrolling the node in any other
+    /// way interrupts the smooth scroll, and starting another one replaces it.
+    pub fn smooth_scroll_node_with_id(
+        &mut self,
+        origin: LayoutPoint,
+        id: ExternalScrollId,
+        clamp: ScrollClamping,
+        duration: f32,
+        easing: AnimationEasing,
+    ) {
+        self.frame_ops.push(FrameMsg::SmoothScrollNodeWithId(origin, id, clamp, duration, easing));
+    }
+
     pub fn set_page_zoom(&mut self, page_zoom: ZoomFactor) {
         self.scene_ops.push(SceneMsg::SetPageZoom(page_zoom));
     }
@@ -308,6 +354,37 @@ impl Transaction {
         self.frame_ops.push(FrameMsg::TickScrollingBounce);
     }
 
+    /// Start animating a property binding on the render backend, replacing
+    /// any animation already running for the same binding. The animation
+    /// starts when the backend receives the transaction.
+    pub fn start_animation(&mut self, animation: PropertyAnimation) {
+        self.frame_ops.push(FrameMsg::StartAnimation(animation));
+    }
+
+    /// Stop animating a property binding. The binding keeps its last
+    /// sampled value until the next `update_dynamic_properties`.
+    pub fn stop_animation(&mut self, id: PropertyBindingId) {
+        self.frame_ops.push(FrameMsg::StopAnimation(id));
+    }
+
+    /// Generate a new frame with freshly sampled animations, smooth scrolls and
+    /// flings, if any are running.
+    pub fn tick_animations(&mut self) {
+        self.frame_ops.push(FrameMsg::TickAnimations);
+    }
+
+    /// Drive a property binding from the scroll origin of a scroll frame,
+    /// replacing any link already set for the same binding.
+    pub fn add_scroll_link(&mut self, link: PropertyScrollLink) {
+        self.frame_ops.push(FrameMsg::AddScrollLink(link));
+    }
+
+    /// Stop driving a property binding from a scroll frame. The binding keeps
+    /// its last value until the next `update_dynamic_properties`.
+    pub fn remove_scroll_link(&mut self, id: PropertyBindingId) {
+        self.frame_ops.push(FrameMsg::RemoveScrollLink(id));
+    }
+
     /// Generate a new frame.
     pub fn generate_frame(&mut self) {
         self.generate_frame = true;
@@ -415,8 +492,34 @@ pub struct HitTestItem {
     pub point_in_viewport: LayoutPoint,
 
     /// The coordinates of the original hit test point relative to the origin of this item.
-    /// This is useful for calculating things like text offsets in the client.
+    /// This is useful for calculating things like text offsets in the client. For hit tests
+    /// of an area, this is the center of the intersection with the item, or the point of the
+    /// item nearest to the hit test point.
     pub point_relative_to_item: LayoutPoint,
+
+    /// The part of the item inside the hit test area, relative to the origin of this item.
+    /// This is empty for hit tests of a point.
+    pub intersection: LayoutRect,
+}
+
+/// What a hit test looks for display items in.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum HitTestArea {
+    Point(WorldPoint),
+    /// Every item that intersects the rect, as with a drag selection.
+    Rect(WorldRect),
+    /// The single item nearest to the point, if it's no further away from it than the
+    /// given distance, as with a touch target. Items under the point are at a distance of
+    /// zero from it. `FIND_ALL` doesn't apply to this kind of hit test.
+    Nearest(WorldPoint, f32),
+}
+
+/// One of the hit tests of a batch, with the same meaning as the arguments of `hit_test`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct HitTestQuery {
+    pub pipeline_id: Option<PipelineId>,
+    pub point: WorldPoint,
+    pub flags: HitTestFlags,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -458,6 +561,12 @@ pub enum SceneMsg {
         content_size: LayoutSize,
         preserve_frame_state: bool,
     },
+    UpdateDisplayList {
+        list_descriptor: BuiltDisplayListDescriptor,
+        epoch: Epoch,
+        pipeline_id: PipelineId,
+        content_size: LayoutSize,
+    },
     SetWindowParameters {
         window_size: DeviceUintSize,
         inner_rect: DeviceUintRect,
@@ -468,13 +577,20 @@ pub enum SceneMsg {
 // Frame messages affect frame generation (applied after building the scene).
 #[derive(Clone, Deserialize, Serialize)]
 pub enum FrameMsg {
-    HitTest(Option<PipelineId>, WorldPoint, HitTestFlags, MsgSender<HitTestResult>),
+    HitTest(Option<PipelineId>, HitTestArea, HitTestFlags, MsgSender<HitTestResult>),
+    HitTestBatch(Vec<HitTestQuery>, MsgSender<Vec<HitTestResult>>),
     UpdateEpoch(PipelineId, Epoch),
     SetPan(DeviceIntPoint),
     EnableFrameOutput(PipelineId, bool),
     Scroll(ScrollLocation, WorldPoint, ScrollEventPhase),
     ScrollNodeWithId(LayoutPoint, ExternalScrollId, ScrollClamping),
+    SmoothScrollNodeWithId(LayoutPoint, ExternalScrollId, ScrollClamping, f32, AnimationEasing),
     TickScrollingBounce,
+    StartAnimation(PropertyAnimation),
+    StopAnimation(PropertyBindingId),
+    TickAnimations,
+    AddScrollLink(PropertyScrollLink),
+    RemoveScrollLink(PropertyBindingId),
     GetScrollNodeState(MsgSender<Vec<ScrollNodeState>>),
     UpdateDynamicProperties(DynamicProperties),
 }
@@ -483,6 +599,7 @@ impl fmt::Debug for SceneMsg {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str(match *self {
             SceneMsg::SetDisplayList { .. } => "SceneMsg::SetDisplayList",
+            SceneMsg::UpdateDisplayList { .. } => "SceneMsg::UpdateDisplayList",
             SceneMsg::SetPageZoom(..) => "SceneMsg::SetPageZoom",
             SceneMsg::SetPinchZoom(..) => "SceneMsg::SetPinchZoom",
             SceneMsg::RemovePipeline(..) => "SceneMsg::RemovePipeline",
@@ -496,10 +613,17 @@ impl fmt::Debug for FrameMsg {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str(match *self {
             FrameMsg::HitTest(..) => "FrameMsg::HitTest",
+            FrameMsg::HitTestBatch(..) => "FrameMsg::HitTestBatch",
             FrameMsg::SetPan(..) => "FrameMsg::SetPan",
             FrameMsg::Scroll(..) => "FrameMsg::Scroll",
             FrameMsg::ScrollNodeWithId(..) => "FrameMsg::ScrollNodeWithId",
+            FrameMsg::SmoothScrollNodeWithId(..) => "FrameMsg::SmoothScrollNodeWithId",
             FrameMsg::TickScrollingBounce => "FrameMsg::TickScrollingBounce",
+            FrameMsg::StartAnimation(..) => "FrameMsg::StartAnimation",
+            FrameMsg::StopAnimation(..) => "FrameMsg::StopAnimation",
+            FrameMsg::TickAnimations => "FrameMsg::TickAnimations",
+            FrameMsg::AddScrollLink(..) => "FrameMsg::AddScrollLink",
+            FrameMsg::RemoveScrollLink(..) => "FrameMsg::RemoveScrollLink",
             FrameMsg::GetScrollNodeState(..) => "FrameMsg::GetScrollNodeState",
             FrameMsg::EnableFrameOutput(..) => "FrameMsg::EnableFrameOutput",
             FrameMsg::UpdateDynamicProperties(..) => "FrameMsg::UpdateDynamicProperties",
@@ -896,11 +1020,44 @@ impl RenderApi {
 
         self.send_frame_msg(
             document_id,
-            FrameMsg::HitTest(pipeline_id, point, flags, tx)
+            FrameMsg::HitTest(pipeline_id, HitTestArea::Point(point), flags, tx)
+        );
+        rx.recv().unwrap()
+    }
+
+    /// Like `hit_test`, but for every display item in an area, or for the one display
+    /// item nearest to a point. With `POINT_RELATIVE_TO_PIPELINE_VIEWPORT`, the area is
+    /// relative to the viewport of the pipeline.
+    pub fn hit_test_area(
+        &self,
+        document_id: DocumentId,
+        pipeline_id: Option<PipelineId>,
+        area: HitTestArea,
+        flags: HitTestFlags,
+    ) -> HitTestResult {
+        let (tx, rx) = channel::msg_channel().unwrap();
+
+        self.send_frame_msg(
+            document_id,
+            FrameMsg::HitTest(pipeline_id, area, flags, tx)
         );
         rx.recv().unwrap()
     }
 
+    /// Does several hit tests on display items in the specified document in a
+    /// single round trip to the render backend, for example one for each pointer
+    /// of a multi-touch input. The results are in the same order as the queries.
+    pub fn hit_test_batch(
+        &self,
+        document_id: DocumentId,
+        queries: Vec<HitTestQuery>,
+    ) -> Vec<HitTestResult> {
+        let (tx, rx) = channel::msg_channel().unwrap();
+
+        self.send_frame_msg(document_id, FrameMsg::HitTestBatch(queries, tx));
+        rx.recv().unwrap()
+    }
+
     pub fn set_window_parameters(
         &self,
         document_id: DocumentId,
@@ -959,7 +1116,9 @@ pub enum ScrollEventPhase {
     /// The user performed a scroll. The Boolean flag indicates whether the user's fingers are
     /// down, if a touchpad is in use. (If false, the event is a touchpad fling.)
     Move(bool),
-    /// The user ended scrolling.
+    /// The user ended scrolling. If the fingers of the user were moving fast enough, the
+    /// scroll frame keeps decelerating on its own, as long as frames are generated with
+    /// `Transaction::tick_animations`.
     End,
 }
 
@@ -967,6 +1126,12 @@ pub enum ScrollEventPhase {
 pub struct ScrollNodeState {
     pub id: ExternalScrollId,
     pub scroll_offset: LayoutVector2D,
+    /// Whether a smooth scroll started through the API is still in progress.
+    pub is_smooth_scrolling: bool,
+    /// Whether the node is springing back after being overscrolled, or moving to
+    /// a snap destination. Clients keep sending `tick_scrolling_bounce_animations`
+    /// while this is true.
+    pub is_bouncing_back: bool,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
@@ -977,6 +1142,13 @@ pub enum ScrollLocation {
     Start,
     /// Scroll to very bottom of element.
     End,
+    /// Scroll by a number of lines along each axis, as with the arrow keys. Positive
+    /// amounts scroll towards the end of the content. The scroll is animated.
+    Lines(LayoutVector2D),
+    /// Scroll by a number of pages along each axis, as with the page up and page down
+    /// keys. Positive amounts scroll towards the end of the content. The scroll is
+    /// animated.
+    Pages(LayoutVector2D),
 }
 
 /// Represents a zoom factor.
@@ -1034,6 +1206,30 @@ impl<T> PropertyBindingKey<T> {
     }
 }
 
+impl FixedLayout for PropertyBindingId {
+    fn encode(&self, data: &mut Vec<u8>) {
+        self.namespace.encode(data);
+        self.uid.encode(data);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        let namespace = IdNamespace::decode(data)?;
+        let uid = u32::decode(data)?;
+        Some(PropertyBindingId { namespace, uid })
+    }
+}
+
+impl<T> FixedLayout for PropertyBindingKey<T> {
+    fn encode(&self, data: &mut Vec<u8>) {
+        self.id.encode(data);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        let id = PropertyBindingId::decode(data)?;
+        Some(PropertyBindingKey { id, _phantom: PhantomData })
+    }
+}
+
 /// A binding property can either be a specific value
 /// (the normal, non-animated case) or point to a binding location
 /// to fetch the current value from.
@@ -1070,12 +1266,31 @@ pub struct PropertyValue<T> {
 pub struct DynamicProperties {
     pub transforms: Vec<PropertyValue<LayoutTransform>>,
     pub floats: Vec<PropertyValue<f32>>,
+    pub colors: Vec<PropertyValue<ColorF>>,
 }
 
 pub trait RenderNotifier: Send {
     fn clone(&self) -> Box<RenderNotifier>;
     fn wake_up(&self);
     fn new_document_ready(&self, DocumentId, scrolled: bool, composite_needed: bool);
+    /// Called once the animations of these bindings have run all of their iterations.
+    fn animations_finished(&self, _document_id: DocumentId, _bindings: Vec<PropertyBindingId>) {}
+    /// Called once a smooth scroll has reached its destination, or has been interrupted.
+    fn smooth_scroll_ended(
+        &self,
+        _document_id: DocumentId,
+        _id: ExternalScrollId,
+        _interrupted: bool,
+    ) {}
+    /// Called when a display list fails validation. The display list is dropped, and the
+    /// pipeline keeps its previous display list.
+    fn display_list_rejected(
+        &self,
+        _document_id: DocumentId,
+        _pipeline_id: PipelineId,
+        _epoch: Epoch,
+        _error: DisplayListError,
+    ) {}
     fn external_event(&self, _evt: ExternalEvent) {
         unimplemented!()
     }
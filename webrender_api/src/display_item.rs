@@ -8,6 +8,7 @@ use euclid::{SideOffsets2D, TypedRect};
 use std::ops::Not;
 use {ColorF, FontInstanceKey, GlyphOptions, ImageKey, LayerPixel, LayoutPixel, LayoutPoint};
 use {LayoutRect, LayoutSize, LayoutTransform, LayoutVector2D, PipelineId, PropertyBinding};
+use TexelRect;
 
 
 // NOTE: some of these structs have an "IMPLICIT" comment.
@@ -102,7 +103,10 @@ pub enum SpecificDisplayItem {
     StickyFrame(StickyFrameDisplayItem),
     Rectangle(RectangleDisplayItem),
     ClearRectangle,
+    /// An area that is only hit tested, without being drawn.
+    HitTest,
     Line(LineDisplayItem),
+    Path(PathDisplayItem),
     Text(TextDisplayItem),
     Image(ImageDisplayItem),
     YuvImage(YuvImageDisplayItem),
@@ -110,6 +114,7 @@ pub enum SpecificDisplayItem {
     BoxShadow(BoxShadowDisplayItem),
     Gradient(GradientDisplayItem),
     RadialGradient(RadialGradientDisplayItem),
+    ConicGradient(ConicGradientDisplayItem),
     ClipChain(ClipChainItem),
     Iframe(IframeDisplayItem),
     PushStackingContext(PushStackingContextDisplayItem),
@@ -117,6 +122,155 @@ pub enum SpecificDisplayItem {
     SetGradientStops,
     PushShadow(Shadow),
     PopAllShadows,
+    PushItemGroup(ItemGroupId),
+    PopItemGroup,
+    PushFragmentDefinition(FragmentId),
+    PopFragmentDefinition,
+    Fragment(FragmentDisplayItem),
+}
+
+impl SpecificDisplayItem {
+    /// A name for the kind of the item, for debugging output and statistics.
+    pub fn debug_name(&self) -> &'static str {
+        match *self {
+            SpecificDisplayItem::Clip(..) => "clip",
+            SpecificDisplayItem::ScrollFrame(..) => "scroll_frame",
+            SpecificDisplayItem::StickyFrame(..) => "sticky_frame",
+            SpecificDisplayItem::Rectangle(..) => "rectangle",
+            SpecificDisplayItem::ClearRectangle => "clear_rectangle",
+            SpecificDisplayItem::HitTest => "hit_test",
+            SpecificDisplayItem::Line(..) => "line",
+            SpecificDisplayItem::Path(..) => "path",
+            SpecificDisplayItem::Text(..) => "text",
+            SpecificDisplayItem::Image(..) => "image",
+            SpecificDisplayItem::YuvImage(..) => "yuv_image",
+            SpecificDisplayItem::Border(..) => "border",
+            SpecificDisplayItem::BoxShadow(..) => "box_shadow",
+            SpecificDisplayItem::Gradient(..) => "gradient",
+            SpecificDisplayItem::RadialGradient(..) => "radial_gradient",
+            SpecificDisplayItem::ConicGradient(..) => "conic_gradient",
+            SpecificDisplayItem::ClipChain(..) => "clip_chain",
+            SpecificDisplayItem::Iframe(..) => "iframe",
+            SpecificDisplayItem::PushStackingContext(..) => "push_stacking_context",
+            SpecificDisplayItem::PopStackingContext => "pop_stacking_context",
+            SpecificDisplayItem::SetGradientStops => "set_gradient_stops",
+            SpecificDisplayItem::PushShadow(..) => "push_shadow",
+            SpecificDisplayItem::PopAllShadows => "pop_all_shadows",
+            SpecificDisplayItem::PushItemGroup(..) => "push_item_group",
+            SpecificDisplayItem::PopItemGroup => "pop_item_group",
+            SpecificDisplayItem::PushFragmentDefinition(..) => "push_fragment_definition",
+            SpecificDisplayItem::PopFragmentDefinition => "pop_fragment_definition",
+            SpecificDisplayItem::Fragment(..) => "fragment",
+        }
+    }
+
+    pub fn kind(&self) -> DisplayItemKind {
+        match *self {
+            SpecificDisplayItem::Clip(..) => DisplayItemKind::Clip,
+            SpecificDisplayItem::ScrollFrame(..) => DisplayItemKind::ScrollFrame,
+            SpecificDisplayItem::StickyFrame(..) => DisplayItemKind::StickyFrame,
+            SpecificDisplayItem::Rectangle(..) => DisplayItemKind::Rectangle,
+            SpecificDisplayItem::ClearRectangle => DisplayItemKind::ClearRectangle,
+            SpecificDisplayItem::HitTest => DisplayItemKind::HitTest,
+            SpecificDisplayItem::Line(..) => DisplayItemKind::Line,
+            SpecificDisplayItem::Path(..) => DisplayItemKind::Path,
+            SpecificDisplayItem::Text(..) => DisplayItemKind::Text,
+            SpecificDisplayItem::Image(..) => DisplayItemKind::Image,
+            SpecificDisplayItem::YuvImage(..) => DisplayItemKind::YuvImage,
+            SpecificDisplayItem::Border(..) => DisplayItemKind::Border,
+            SpecificDisplayItem::BoxShadow(..) => DisplayItemKind::BoxShadow,
+            SpecificDisplayItem::Gradient(..) => DisplayItemKind::Gradient,
+            SpecificDisplayItem::RadialGradient(..) => DisplayItemKind::RadialGradient,
+            SpecificDisplayItem::ConicGradient(..) => DisplayItemKind::ConicGradient,
+            SpecificDisplayItem::ClipChain(..) => DisplayItemKind::ClipChain,
+            SpecificDisplayItem::Iframe(..) => DisplayItemKind::Iframe,
+            SpecificDisplayItem::PushStackingContext(..) => DisplayItemKind::PushStackingContext,
+            SpecificDisplayItem::PopStackingContext => DisplayItemKind::PopStackingContext,
+            SpecificDisplayItem::SetGradientStops => DisplayItemKind::SetGradientStops,
+            SpecificDisplayItem::PushShadow(..) => DisplayItemKind::PushShadow,
+            SpecificDisplayItem::PopAllShadows => DisplayItemKind::PopAllShadows,
+            SpecificDisplayItem::PushItemGroup(..) => DisplayItemKind::PushItemGroup,
+            SpecificDisplayItem::PopItemGroup => DisplayItemKind::PopItemGroup,
+            SpecificDisplayItem::PushFragmentDefinition(..) => {
+                DisplayItemKind::PushFragmentDefinition
+            }
+            SpecificDisplayItem::PopFragmentDefinition => DisplayItemKind::PopFragmentDefinition,
+            SpecificDisplayItem::Fragment(..) => DisplayItemKind::Fragment,
+        }
+    }
+}
+
+/// The kind of a display item. It's stored in front of every item of a display list,
+/// so that items can be told apart and skipped without decoding them.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DisplayItemKind {
+    Clip,
+    ScrollFrame,
+    StickyFrame,
+    Rectangle,
+    ClearRectangle,
+    HitTest,
+    Line,
+    Path,
+    Text,
+    Image,
+    YuvImage,
+    Border,
+    BoxShadow,
+    Gradient,
+    RadialGradient,
+    ConicGradient,
+    ClipChain,
+    Iframe,
+    PushStackingContext,
+    PopStackingContext,
+    SetGradientStops,
+    PushShadow,
+    PopAllShadows,
+    PushItemGroup,
+    PopItemGroup,
+    PushFragmentDefinition,
+    PopFragmentDefinition,
+    Fragment,
+}
+
+/// Every kind of display item, indexed by its value.
+const DISPLAY_ITEM_KINDS: [DisplayItemKind; 28] = [
+    DisplayItemKind::Clip,
+    DisplayItemKind::ScrollFrame,
+    DisplayItemKind::StickyFrame,
+    DisplayItemKind::Rectangle,
+    DisplayItemKind::ClearRectangle,
+    DisplayItemKind::HitTest,
+    DisplayItemKind::Line,
+    DisplayItemKind::Path,
+    DisplayItemKind::Text,
+    DisplayItemKind::Image,
+    DisplayItemKind::YuvImage,
+    DisplayItemKind::Border,
+    DisplayItemKind::BoxShadow,
+    DisplayItemKind::Gradient,
+    DisplayItemKind::RadialGradient,
+    DisplayItemKind::ConicGradient,
+    DisplayItemKind::ClipChain,
+    DisplayItemKind::Iframe,
+    DisplayItemKind::PushStackingContext,
+    DisplayItemKind::PopStackingContext,
+    DisplayItemKind::SetGradientStops,
+    DisplayItemKind::PushShadow,
+    DisplayItemKind::PopAllShadows,
+    DisplayItemKind::PushItemGroup,
+    DisplayItemKind::PopItemGroup,
+    DisplayItemKind::PushFragmentDefinition,
+    DisplayItemKind::PopFragmentDefinition,
+    DisplayItemKind::Fragment,
+];
+
+impl DisplayItemKind {
+    pub fn from_u8(value: u8) -> Option<DisplayItemKind> {
+        DISPLAY_ITEM_KINDS.get(value as usize).cloned()
+    }
 }
 
 /// This is a "complete" version of the DI specifics,
@@ -126,13 +280,15 @@ pub enum SpecificDisplayItem {
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub enum CompletelySpecificDisplayItem {
-    Clip(ClipDisplayItem, Vec<ComplexClipRegion>),
+    Clip(ClipDisplayItem, Vec<ComplexClipRegion>, Vec<LayoutPoint>),
     ClipChain(ClipChainItem, Vec<ClipId>),
-    ScrollFrame(ScrollFrameDisplayItem, Vec<ComplexClipRegion>),
+    ScrollFrame(ScrollFrameDisplayItem, Vec<ComplexClipRegion>, Vec<ScrollSnapArea>),
     StickyFrame(StickyFrameDisplayItem),
     Rectangle(RectangleDisplayItem),
     ClearRectangle,
+    HitTest,
     Line(LineDisplayItem),
+    Path(PathDisplayItem, Vec<PathOp>),
     Text(TextDisplayItem, Vec<GlyphInstance>),
     Image(ImageDisplayItem),
     YuvImage(YuvImageDisplayItem),
@@ -140,19 +296,44 @@ pub enum CompletelySpecificDisplayItem {
     BoxShadow(BoxShadowDisplayItem),
     Gradient(GradientDisplayItem),
     RadialGradient(RadialGradientDisplayItem),
+    ConicGradient(ConicGradientDisplayItem),
     Iframe(IframeDisplayItem),
-    PushStackingContext(PushStackingContextDisplayItem, Vec<FilterOp>),
+    PushStackingContext(PushStackingContextDisplayItem, Vec<FilterOp>, Vec<FilterData>, Vec<FilterOp>),
     PopStackingContext,
     SetGradientStops(Vec<GradientStop>),
     PushShadow(Shadow),
     PopAllShadows,
+    PushItemGroup(ItemGroupId),
+    PopItemGroup,
+    PushFragmentDefinition(FragmentId),
+    PopFragmentDefinition,
+    Fragment(FragmentDisplayItem),
+}
+
+/// Identifies a group of items in the display list of a pipeline, so that the items can
+/// be replaced later with `Transaction::update_display_list`, without sending the rest of
+/// the display list again. Item groups have no effect on rendering.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct ItemGroupId(pub u64);
+
+/// Identifies a fragment: a list of items that is defined once in the display list of a
+/// pipeline, and drawn wherever a `FragmentDisplayItem` refers to it.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct FragmentId(pub u64);
+
+/// Draws the items of a fragment, offset by the origin of the bounds of this item, and
+/// with the clip and scroll node of this item instead of their own.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FragmentDisplayItem {
+    pub id: FragmentId,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub struct ClipDisplayItem {
     pub id: ClipId,
     pub image_mask: Option<ImageMask>,
-}
+    pub polygon: Option<ClipPolygon>,
+} // IMPLICIT: polygon_points: Vec<LayoutPoint>
 
 /// The minimum and maximum allowable offset for a sticky frame in a single dimension.
 #[repr(C)]
@@ -217,11 +398,81 @@ pub struct ScrollFrameDisplayItem {
     pub external_id: Option<ExternalScrollId>,
     pub image_mask: Option<ImageMask>,
     pub scroll_sensitivity: ScrollSensitivity,
+    pub overscroll_behavior: OverscrollBehavior,
+    pub snap_type: ScrollSnapType,
+}
+
+/// What happens when a scroll gesture goes past the bounds of a scroll frame.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum OverscrollBehavior {
+    /// The default of the platform, which is elastic on macOS and clamped elsewhere.
+    Auto,
+    /// Clamped, and the rest of the gesture isn't handed off to the root scroll frame.
+    None,
+    Clamp,
+    Elastic(OverscrollElasticity),
+}
+
+/// The spring that a scroll frame bounces back from overscrolling with.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct OverscrollElasticity {
+    /// How hard the spring pulls the scroll frame back to its bounds.
+    pub stiffness: f32,
+    /// How quickly the bounce settles. 1.0 means it doesn't oscillate.
+    pub damping: f32,
+    /// How far past its bounds the scroll frame can be stretched, in pixels.
+    pub max_stretch: f32,
+}
+
+/// How a scroll frame snaps to its snap areas along an axis, as with the CSS
+/// `scroll-snap-type` property.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ScrollSnapStrictness {
+    None,
+    /// Always snap to the nearest snap area once scrolling ends.
+    Mandatory,
+    /// Only snap if a snap area is close to where scrolling ends.
+    Proximity,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScrollSnapType {
+    pub x: ScrollSnapStrictness,
+    pub y: ScrollSnapStrictness,
+}
+
+impl ScrollSnapType {
+    pub fn none() -> Self {
+        ScrollSnapType {
+            x: ScrollSnapStrictness::None,
+            y: ScrollSnapStrictness::None,
+        }
+    }
+}
+
+/// Which edge of a snap area gets aligned with the same edge of the scroll frame,
+/// as with the CSS `scroll-snap-align` property.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ScrollSnapAlignment {
+    None,
+    Start,
+    Center,
+    End,
+}
+
+/// A part of the content of a scroll frame that the scroll frame can snap to. The
+/// rect is in the same space as the content rect of the scroll frame. A snap point
+/// is an empty snap area aligned to its start.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScrollSnapArea {
+    pub rect: LayoutRect,
+    pub align_x: ScrollSnapAlignment,
+    pub align_y: ScrollSnapAlignment,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub struct RectangleDisplayItem {
-    pub color: ColorF,
+    pub color: PropertyBinding<ColorF>,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
@@ -248,6 +499,54 @@ pub enum LineStyle {
     Wavy,
 }
 
+/// A vector path, filled and optionally stroked. The path is made of
+/// the `PathOp`s stored after the item, in the same coordinate space
+/// as the item bounds. Anything outside the bounds is not drawn.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PathDisplayItem {
+    pub fill_rule: FillRule,
+    pub fill_color: ColorF,
+    pub stroke: Option<PathStroke>,
+} // IMPLICIT: ops: Vec<PathOp>
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum PathOp {
+    MoveTo(LayoutPoint),
+    LineTo(LayoutPoint),
+    /// A quadratic bezier curve with one control point.
+    QuadTo(LayoutPoint, LayoutPoint),
+    /// A cubic bezier curve with two control points.
+    CubicTo(LayoutPoint, LayoutPoint, LayoutPoint),
+    Close,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PathStroke {
+    pub width: f32,
+    pub color: ColorF,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// The largest ratio of the miter length to the stroke width
+    /// before a miter join is drawn as a bevel instead.
+    pub miter_limit: f32,
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub struct TextDisplayItem {
     pub font_key: FontInstanceKey,
@@ -297,12 +596,22 @@ pub struct ImageBorder {
 pub struct GradientBorder {
     pub gradient: Gradient,
     pub outset: SideOffsets2D<f32>,
+    /// Where to slice the gradient, which is sized to the border image
+    /// area, into a 9 patch.
+    pub slice: SideOffsets2D<f32>,
+    pub repeat_horizontal: RepeatMode,
+    pub repeat_vertical: RepeatMode,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub struct RadialGradientBorder {
     pub gradient: RadialGradient,
     pub outset: SideOffsets2D<f32>,
+    /// Where to slice the gradient, which is sized to the border image
+    /// area, into a 9 patch.
+    pub slice: SideOffsets2D<f32>,
+    pub repeat_horizontal: RepeatMode,
+    pub repeat_vertical: RepeatMode,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
@@ -443,6 +752,25 @@ pub struct RadialGradientDisplayItem {
     pub tile_spacing: LayoutSize,
 }
 
+/// A conic (angular) gradient, sweeping the stops clockwise around
+/// `center`, starting at `angle` radians from the positive y axis
+/// (i.e. "12 o'clock"), as in CSS `conic-gradient()`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ConicGradient {
+    pub center: LayoutPoint,
+    pub angle: f32,
+    pub start_offset: f32,
+    pub end_offset: f32,
+    pub extend_mode: ExtendMode,
+} // IMPLICIT stops: Vec<GradientStop>
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ConicGradientDisplayItem {
+    pub gradient: ConicGradient,
+    pub tile_size: LayoutSize,
+    pub tile_spacing: LayoutSize,
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub struct PushStackingContextDisplayItem {
     pub stacking_context: StackingContext,
@@ -456,7 +784,7 @@ pub struct StackingContext {
     pub perspective: Option<LayoutTransform>,
     pub mix_blend_mode: MixBlendMode,
     pub reference_frame_id: Option<ClipId>,
-} // IMPLICIT: filters: Vec<FilterOp>
+} // IMPLICIT: filters: Vec<FilterOp>, filter_data: Vec<FilterData>, backdrop_filters: Vec<FilterOp>
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -506,6 +834,57 @@ pub enum FilterOp {
     Sepia(f32),
     DropShadow(LayoutVector2D, f32, ColorF),
     ColorMatrix([f32; 20]),
+    /// Applies a transfer function to each color channel, like SVG
+    /// `feComponentTransfer`. The functions are given by the stacking
+    /// context's filter data: the n-th `ComponentTransfer` filter uses
+    /// the n-th `FilterData`.
+    ComponentTransfer,
+    /// Replaces the content with a solid color, like SVG `feFlood`.
+    Flood(ColorF),
+    /// Moves the content by an offset, like SVG `feOffset`.
+    Offset(LayoutVector2D),
+    /// Composites the result of the preceding filters with the unfiltered
+    /// content of the stacking context, like SVG `feComposite` with
+    /// `in2="SourceGraphic"`.
+    Composite(CompositeOperator),
+}
+
+/// The Porter-Duff operators, and the arithmetic one with its `k1` to `k4`
+/// coefficients, of SVG `feComposite`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum CompositeOperator {
+    Over,
+    In,
+    Out,
+    Atop,
+    Xor,
+    Lighter,
+    Arithmetic([f32; 4]),
+}
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ComponentTransferFuncType {
+    Identity = 0,
+    Table = 1,
+    Discrete = 2,
+    Linear = 3,
+    Gamma = 4,
+}
+
+/// The transfer function of each channel of a `ComponentTransfer` filter.
+/// The values are the lookup table for `Table` and `Discrete`, the slope and
+/// intercept for `Linear`, and the amplitude, exponent and offset for `Gamma`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FilterData {
+    pub func_r_type: ComponentTransferFuncType,
+    pub r_values: Vec<f32>,
+    pub func_g_type: ComponentTransferFuncType,
+    pub g_values: Vec<f32>,
+    pub func_b_type: ComponentTransferFuncType,
+    pub b_values: Vec<f32>,
+    pub func_a_type: ComponentTransferFuncType,
+    pub a_values: Vec<f32>,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
@@ -521,6 +900,14 @@ pub struct ImageDisplayItem {
     pub tile_spacing: LayoutSize,
     pub image_rendering: ImageRendering,
     pub alpha_type: AlphaType,
+    /// How the image is repeated across the item's rect along each axis.
+    /// `Round` and `Space` adjust the stretch size and tile spacing so that
+    /// a whole number of tiles fit, as for CSS `background-repeat`.
+    pub repeat_horizontal: RepeatMode,
+    pub repeat_vertical: RepeatMode,
+    /// The part of the image to draw, in texels. The whole image is
+    /// drawn if this is None.
+    pub source_rect: Option<TexelRect>,
 }
 
 #[repr(u32)]
@@ -752,6 +1139,31 @@ impl BorderRadius {
     }
 }
 
+/// How to decide which points are inside a self-intersecting polygon.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub enum FillRule {
+    Nonzero,
+    Evenodd,
+}
+
+/// A polygon clip. The points of the polygon are stored
+/// after the clip item that uses it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ClipPolygon {
+    pub fill_rule: FillRule,
+    /// Whether we are clipping inside or outside
+    /// the polygon.
+    pub mode: ClipMode,
+}
+
+impl ClipPolygon {
+    pub fn new(fill_rule: FillRule, mode: ClipMode) -> Self {
+        ClipPolygon { fill_rule, mode }
+    }
+}
+
 impl ComplexClipRegion {
     /// Create a new complex clip region.
     pub fn new(
@@ -2,28 +2,33 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use bincode;
+use byteorder::{ByteOrder, LittleEndian};
 use euclid::SideOffsets2D;
 #[cfg(feature = "deserialize")]
-use serde::de::Deserializer;
+use serde::de::{Deserialize, Deserializer};
 #[cfg(feature = "serialize")]
-use serde::ser::{Serializer, SerializeSeq};
-use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
+use serde::ser::{Serialize, Serializer, SerializeSeq};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::marker::PhantomData;
-use std::{io, mem, ptr, slice};
+use std::{iter, mem};
 use time::precise_time_ns;
 use {AlphaType, BorderDetails, BorderDisplayItem, BorderRadius, BorderWidths, BoxShadowClipMode};
 use {BoxShadowDisplayItem, ClipAndScrollInfo, ClipChainId, ClipChainItem, ClipDisplayItem, ClipId};
-use {ColorF, ComplexClipRegion, DisplayItem, ExtendMode, ExternalScrollId, FilterOp};
-use {FontInstanceKey, GlyphInstance, GlyphOptions, Gradient, GradientDisplayItem, GradientStop};
-use {IframeDisplayItem, ImageDisplayItem, ImageKey, ImageMask, ImageRendering, LayerPrimitiveInfo};
+use {ClipPolygon, ColorF, ComplexClipRegion, ConicGradient, ConicGradientDisplayItem, DisplayItem};
+use DisplayItemKind;
+use {ExtendMode, ExternalScrollId, FillRule, FilterData, FilterOp, FixedLayout, FontInstanceKey};
+use {FragmentDisplayItem, FragmentId, GlyphInstance, GlyphOptions, Gradient, GradientDisplayItem};
+use {GradientStop, IframeDisplayItem};
+use {ImageDisplayItem, ImageKey, ImageMask, ImageRendering, ItemGroupId, LayerPrimitiveInfo};
 use {LayoutPoint, LayoutPrimitiveInfo, LayoutRect, LayoutSize, LayoutTransform, LayoutVector2D};
-use {LineDisplayItem, LineOrientation, LineStyle, LocalClip, MixBlendMode, PipelineId};
-use {PropertyBinding, PushStackingContextDisplayItem, RadialGradient, RadialGradientDisplayItem};
-use {RectangleDisplayItem, ScrollFrameDisplayItem, ScrollPolicy, ScrollSensitivity, Shadow};
-use {SpecificDisplayItem, StackingContext, StickyFrameDisplayItem, StickyOffsetBounds};
-use {TextDisplayItem, TransformStyle, YuvColorSpace, YuvData, YuvImageDisplayItem};
+use {LineDisplayItem, LineOrientation, LineStyle, LocalClip, MixBlendMode, OverscrollBehavior};
+use {PathDisplayItem, PathOp, PathStroke, PipelineId, PropertyBinding};
+use PushStackingContextDisplayItem;
+use {RadialGradient, RadialGradientDisplayItem, RectangleDisplayItem, RepeatMode};
+use {ScrollFrameDisplayItem, ScrollPolicy, ScrollSensitivity, ScrollSnapArea, ScrollSnapType};
+use {Shadow, SpecificDisplayItem, StackingContext};
+use {StickyFrameDisplayItem, StickyOffsetBounds, TexelRect, TextDisplayItem, TransformStyle};
+use {YuvColorSpace, YuvData, YuvImageDisplayItem};
 
 // We don't want to push a long text-run. If a text-run is too long, split it into several parts.
 // This needs to be set to (renderer::MAX_VERTEX_TEXTURE_WIDTH - VECS_PER_PRIM_HEADER - VECS_PER_TEXT_RUN) * 2
@@ -32,14 +37,39 @@ pub const MAX_TEXT_RUN_LENGTH: usize = 2038;
 // We start at 2, because the root reference is always 0 and the root scroll node is always 1.
 const FIRST_CLIP_ID: usize = 2;
 
+/// The number of clip ids that a display list can be built with. Scene building reserves
+/// room for a node for every clip id, whether the display list defines it or not.
+pub const MAX_CLIP_IDS: usize = 1 << 20;
+
+/// The version of the format in which display list items are serialized. It needs to be
+/// bumped whenever the format changes, so that recordings made with another version are
+/// detected instead of misread.
+pub const DISPLAY_LIST_FORMAT_VERSION: u32 = 2;
+
+/// Each item of a display list is stored as a record, which starts with a fixed-layout
+/// header: the size of the rest of the record as a little-endian `u32`, followed by the
+/// `DisplayItemKind` of the item as a byte. The rest of the record is the item in the
+/// layout of `FixedLayout`, followed by its auxiliary lists.
+const ITEM_HEADER_SIZE: usize = 5;
+
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[derive(Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct ItemRange<T> {
     start: usize,
     length: usize,
     _boo: PhantomData<T>,
 }
 
+// Implemented by hand, since deriving these would require `T` to be `Copy`,
+// which isn't the case for every kind of auxiliary data.
+impl<T> Copy for ItemRange<T> {}
+
+impl<T> Clone for ItemRange<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
 impl<T> Default for ItemRange<T> {
     fn default() -> Self {
         ItemRange {
@@ -60,7 +90,7 @@ impl<T> ItemRange<T> {
 /// A display list.
 #[derive(Clone, Default)]
 pub struct BuiltDisplayList {
-    /// Serde encoded bytes. Mostly DisplayItems, but some mixed in slices.
+    /// The records of the items, each holding a DisplayItem and its auxiliary lists.
     data: Vec<u8>,
     descriptor: BuiltDisplayListDescriptor,
 }
@@ -70,7 +100,7 @@ pub struct BuiltDisplayList {
 /// A display list consists of some number of display list items, followed by a number of display
 /// items.
 #[repr(C)]
-#[derive(Copy, Clone, Default, Deserialize, Serialize)]
+#[derive(Copy, Clone, Deserialize, Serialize)]
 pub struct BuiltDisplayListDescriptor {
     /// The first IPC time stamp: before any work has been done
     builder_start_time: u64,
@@ -80,17 +110,36 @@ pub struct BuiltDisplayListDescriptor {
     send_start_time: u64,
     /// The amount of clips ids assigned while building this display list.
     total_clip_ids: usize,
+    /// The amount of clip chain ids assigned while building this display list.
+    total_clip_chain_ids: u64,
+    /// The `DISPLAY_LIST_FORMAT_VERSION` of the builder of this display list.
+    format_version: u32,
 }
 
+/// Walks over the items of a display list. Each call to `next` decodes the item into
+/// the iterator, and notes where its auxiliary lists are, while `peek_kind` and
+/// `skip_item` only read the header of the record in place.
+///
+/// A list that isn't validated may be malformed, or may have been built with another
+/// `DISPLAY_LIST_FORMAT_VERSION`. The iteration then ends early, and `error` tells why.
 pub struct BuiltDisplayListIter<'a> {
     list: &'a BuiltDisplayList,
     data: &'a [u8],
+    /// The index of the next record, counting every serialized item, as in the errors
+    /// of `BuiltDisplayList::validate`.
+    index: usize,
+    error: Option<DisplayListError>,
     cur_item: DisplayItem,
     cur_stops: ItemRange<GradientStop>,
     cur_glyphs: ItemRange<GlyphInstance>,
     cur_filters: ItemRange<FilterOp>,
+    cur_filter_data: ItemRange<FilterData>,
+    cur_backdrop_filters: ItemRange<FilterOp>,
     cur_clip_chain_items: ItemRange<ClipId>,
     cur_complex_clip: (ItemRange<ComplexClipRegion>, usize),
+    cur_polygon_points: ItemRange<LayoutPoint>,
+    cur_path_ops: ItemRange<PathOp>,
+    cur_snap_areas: ItemRange<ScrollSnapArea>,
     peeking: Peek,
 }
 
@@ -112,7 +161,33 @@ pub struct AuxIter<'a, T> {
     _boo: PhantomData<T>,
 }
 
-impl BuiltDisplayListDescriptor {}
+/// Where an item group is in the data of a display list.
+struct ItemGroupRange {
+    id: ItemGroupId,
+    start: usize,
+    end: usize,
+    /// The number of groups that this one is nested in.
+    depth: usize,
+}
+
+impl Default for BuiltDisplayListDescriptor {
+    fn default() -> Self {
+        BuiltDisplayListDescriptor {
+            builder_start_time: 0,
+            builder_finish_time: 0,
+            send_start_time: 0,
+            total_clip_ids: 0,
+            total_clip_chain_ids: 0,
+            format_version: DISPLAY_LIST_FORMAT_VERSION,
+        }
+    }
+}
+
+impl BuiltDisplayListDescriptor {
+    pub fn format_version(&self) -> u32 {
+        self.format_version
+    }
+}
 
 impl BuiltDisplayList {
     pub fn from_data(data: Vec<u8>, descriptor: BuiltDisplayListDescriptor) -> BuiltDisplayList {
@@ -149,41 +224,255 @@ impl BuiltDisplayList {
         self.descriptor.total_clip_ids
     }
 
+    /// Breaks the display list down into the items and auxiliary data of each kind, to
+    /// find out what takes up space in it. The display list doesn't need to be validated:
+    /// counting stops at the first item that can't be read.
+    pub fn stats(&self) -> DisplayListStats {
+        use SpecificDisplayItem::*;
+
+        let (builder_start_time, builder_finish_time, send_start_time) = self.times();
+        let mut stats = DisplayListStats {
+            total_bytes: self.data.len(),
+            build_time: builder_finish_time.saturating_sub(builder_start_time),
+            send_delay: send_start_time.saturating_sub(builder_finish_time),
+            ..DisplayListStats::default()
+        };
+
+        let mut stacking_context_depth = 0;
+        let mut start = 0;
+        let mut iter = self.iter();
+        while let Some(item) = iter.next() {
+            let end = self.data.len() - item.iter.data.len();
+            stats.items
+                .entry(item.item().debug_name())
+                .or_insert_with(SizeStats::default)
+                .add(1, end - start);
+            start = end;
+
+            stats.gradient_stops.add_aux(self, item.gradient_stops());
+
+            // Not every auxiliary list is reset between items, so only the lists that
+            // belong to the kind of the item are counted.
+            match *item.item() {
+                Text(_) => stats.glyphs.add_aux(self, item.glyphs()),
+                Path(_) => stats.other_aux.add_aux(self, item.path_ops()),
+                Clip(_) => {
+                    stats.complex_clips.add_aux(self, item.complex_clip().0);
+                    stats.other_aux.add_aux(self, item.polygon_points());
+                    stats.clip_nodes += 1;
+                }
+                ScrollFrame(_) => {
+                    stats.complex_clips.add_aux(self, item.complex_clip().0);
+                    stats.other_aux.add_aux(self, item.snap_areas());
+                    stats.clip_nodes += 1;
+                    stats.scroll_nodes += 1;
+                }
+                StickyFrame(_) => stats.sticky_nodes += 1,
+                ClipChain(_) => {
+                    stats.other_aux.add_aux(self, item.clip_chain_items());
+                    stats.clip_chains += 1;
+                }
+                Iframe(_) => {
+                    stats.clip_nodes += 1;
+                    stats.reference_frames += 1;
+                    stats.scroll_nodes += 1;
+                }
+                PushStackingContext(ref item_info) => {
+                    stats.filters.add_aux(self, item.filters());
+                    stats.filters.add_aux(self, item.filter_data());
+                    stats.filters.add_aux(self, item.backdrop_filters());
+                    if item_info.stacking_context.reference_frame_id.is_some() {
+                        stats.reference_frames += 1;
+                    }
+                    stacking_context_depth += 1;
+                    stats.max_stacking_context_depth =
+                        stats.max_stacking_context_depth.max(stacking_context_depth);
+                }
+                // The display list may not be validated, so it may pop more stacking
+                // contexts than it pushes.
+                PopStackingContext => {
+                    stacking_context_depth = stacking_context_depth.saturating_sub(1);
+                }
+                _ => {}
+            }
+        }
+
+        stats.error = iter.error().cloned();
+        stats
+    }
+
+    /// Checks that the display list is well formed, so that iterating over it and building
+    /// a scene from it can't panic. Display lists from untrusted producers, such as those
+    /// received from content processes, should be validated before they are used.
+    pub fn validate(&self, pipeline_id: PipelineId) -> Result<(), DisplayListError> {
+        if self.descriptor.format_version != DISPLAY_LIST_FORMAT_VERSION {
+            return Err(DisplayListError::UnsupportedFormat(self.descriptor.format_version));
+        }
+        if self.total_clip_ids() > MAX_CLIP_IDS {
+            return Err(DisplayListError::TooManyClipIds(self.total_clip_ids()));
+        }
+        DisplayListValidator::new(pipeline_id, self.total_clip_ids()).validate(self.item_slice())
+    }
+
+    /// Returns a copy of this display list where the item groups in the delta, which
+    /// is built by a builder from `DisplayListBuilder::new_delta`, replace the item
+    /// groups with the same ids. Groups nested in a replaced group are replaced along
+    /// with it. The result should be validated before it's used.
+    pub fn apply_delta(&self, delta: &BuiltDisplayList) -> Result<Self, DisplayListError> {
+        if delta.descriptor.format_version != DISPLAY_LIST_FORMAT_VERSION {
+            return Err(DisplayListError::UnsupportedFormat(delta.descriptor.format_version));
+        }
+        // Only the structure of the delta can be checked on its own, since its items
+        // refer to clips that are defined by the display list it applies to.
+        DisplayListValidator::structure_only().validate(delta.item_slice())?;
+
+        let mut replacements = HashMap::new();
+        let mut replaced_len = 0;
+        for group in delta.item_groups().iter().filter(|group| group.depth == 0) {
+            if replacements.insert(group.id, &delta.data[group.start .. group.end]).is_some() {
+                return Err(DisplayListError::DuplicateItemGroup(group.id));
+            }
+            replaced_len += group.end - group.start;
+        }
+        if replaced_len != delta.data.len() {
+            return Err(DisplayListError::ItemOutsideItemGroup);
+        }
+
+        let mut groups = self.item_groups();
+        let mut ids = HashSet::new();
+        if let Some(group) = groups.iter().find(|group| !ids.insert(group.id)) {
+            return Err(DisplayListError::DuplicateItemGroup(group.id));
+        }
+        groups.retain(|group| replacements.contains_key(&group.id));
+        for id in replacements.keys() {
+            if !groups.iter().any(|group| group.id == *id) {
+                return Err(DisplayListError::UnknownItemGroup(*id));
+            }
+        }
+
+        let mut data = Vec::with_capacity(self.data.len() + delta.data.len());
+        let mut copied_until = 0;
+        for group in &groups {
+            if group.start < copied_until {
+                continue;
+            }
+            data.extend_from_slice(&self.data[copied_until .. group.start]);
+            data.extend_from_slice(replacements[&group.id]);
+            copied_until = group.end;
+        }
+        data.extend_from_slice(&self.data[copied_until ..]);
+
+        let descriptor = &self.descriptor;
+        Ok(BuiltDisplayList {
+            data,
+            descriptor: BuiltDisplayListDescriptor {
+                total_clip_ids: descriptor.total_clip_ids.max(delta.descriptor.total_clip_ids),
+                total_clip_chain_ids: descriptor.total_clip_chain_ids
+                    .max(delta.descriptor.total_clip_chain_ids),
+                ..delta.descriptor
+            },
+        })
+    }
+
+    /// The byte ranges of the item groups of a validated display list, including their
+    /// markers, in the order in which they start.
+    fn item_groups(&self) -> Vec<ItemGroupRange> {
+        let mut groups = Vec::new();
+        let mut open_groups = Vec::new();
+        let mut start = 0;
+        let mut iter = self.iter();
+        loop {
+            let item = match iter.next() {
+                Some(item) => *item.item(),
+                None => break,
+            };
+            let end = self.data.len() - iter.data.len();
+
+            match item {
+                SpecificDisplayItem::PushItemGroup(id) => {
+                    let depth = open_groups.len();
+                    open_groups.push(groups.len());
+                    groups.push(ItemGroupRange { id, start, end, depth });
+                }
+                SpecificDisplayItem::PopItemGroup => {
+                    let group = open_groups.pop().expect("Unbalanced item groups");
+                    groups[group].end = end;
+                }
+                _ => {}
+            }
+
+            start = end;
+        }
+        groups
+    }
+
     pub fn iter(&self) -> BuiltDisplayListIter {
         BuiltDisplayListIter::new(self)
     }
 
-    pub fn get<'de, T: Deserialize<'de>>(&self, range: ItemRange<T>) -> AuxIter<T> {
+    pub fn get<T: FixedLayout>(&self, range: ItemRange<T>) -> AuxIter<T> {
         AuxIter::new(&self.data[range.start .. range.start + range.length])
     }
 }
 
+/// Splits the record of the next item off the data, returning the kind byte of the
+/// item and the rest of the record, or `None` if the record doesn't fit in the data.
+fn split_item_record<'a>(data: &mut &'a [u8]) -> Option<(u8, &'a [u8])> {
+    if data.len() < ITEM_HEADER_SIZE {
+        return None;
+    }
+    let size = LittleEndian::read_u32(&data[.. 4]) as usize;
+    let kind = data[4];
+    let rest = &data[ITEM_HEADER_SIZE ..];
+    if size > rest.len() {
+        return None;
+    }
+
+    let (record, rest) = rest.split_at(size);
+    *data = rest;
+    Some((kind, record))
+}
+
+/// Writes the header of an item record, and returns the offset of the record, for
+/// `end_item_record` to fill in its size once the item and its auxiliary lists are written.
+fn begin_item_record(data: &mut Vec<u8>, kind: DisplayItemKind) -> usize {
+    let offset = data.len();
+    data.extend_from_slice(&[0, 0, 0, 0, kind as u8]);
+    offset
+}
+
+fn end_item_record(data: &mut Vec<u8>, offset: usize) {
+    let size = data.len() - offset - ITEM_HEADER_SIZE;
+    debug_assert!(size <= u32::max_value() as usize);
+    LittleEndian::write_u32(&mut data[offset .. offset + 4], size as u32);
+}
+
 /// Returns the byte-range the slice occupied, and the number of elements
 /// in the slice.
-fn skip_slice<T: for<'de> Deserialize<'de>>(
+fn skip_slice<T: FixedLayout>(
     list: &BuiltDisplayList,
     data: &mut &[u8],
-) -> (ItemRange<T>, usize) {
+) -> Option<(ItemRange<T>, usize)> {
     let base = list.data.as_ptr() as usize;
 
-    let byte_size: usize = bincode::deserialize_from(data, bincode::Infinite)
-                                    .expect("MEH: malicious input?");
+    let byte_size = usize::decode(data)?;
     let start = data.as_ptr() as usize;
-    let item_count: usize = bincode::deserialize_from(data, bincode::Infinite)
-                                    .expect("MEH: malicious input?");
-
-    // Remember how many bytes item_count occupied
-    let item_count_size = data.as_ptr() as usize - start;
+    let item_count = usize::decode(data)?;
+    // Every value takes up at least a byte, which keeps a bogus count from being used
+    // as the size hint of an `AuxIter`.
+    if byte_size > data.len() || item_count > byte_size {
+        return None;
+    }
 
     let range = ItemRange {
-        start: start - base,                      // byte offset to item_count
-        length: byte_size + item_count_size,      // number of bytes for item_count + payload
+        start: start - base,                          // byte offset to item_count
+        length: byte_size + mem::size_of::<u64>(),    // number of bytes for item_count + payload
         _boo: PhantomData,
     };
 
     // Adjust data pointer to skip read values
     *data = &data[byte_size ..];
-    (range, item_count)
+    Some((range, item_count))
 }
 
 
@@ -193,9 +482,18 @@ impl<'a> BuiltDisplayListIter<'a> {
     }
 
     pub fn new_with_list_and_data(list: &'a BuiltDisplayList, data: &'a [u8]) -> Self {
+        // Items of another version can't be decoded, so none of them are read.
+        let format_version = list.descriptor.format_version;
+        let (data, error) = if format_version == DISPLAY_LIST_FORMAT_VERSION {
+            (data, None)
+        } else {
+            (&[][..], Some(DisplayListError::UnsupportedFormat(format_version)))
+        };
         BuiltDisplayListIter {
             list,
-            data: &data,
+            data,
+            index: 0,
+            error,
             cur_item: DisplayItem {
                 // Dummy data, will be overwritten by `next`
                 item: SpecificDisplayItem::PopStackingContext,
@@ -206,8 +504,13 @@ impl<'a> BuiltDisplayListIter<'a> {
             cur_stops: ItemRange::default(),
             cur_glyphs: ItemRange::default(),
             cur_filters: ItemRange::default(),
+            cur_filter_data: ItemRange::default(),
+            cur_backdrop_filters: ItemRange::default(),
             cur_clip_chain_items: ItemRange::default(),
             cur_complex_clip: (ItemRange::default(), 0),
+            cur_polygon_points: ItemRange::default(),
+            cur_path_ops: ItemRange::default(),
+            cur_snap_areas: ItemRange::default(),
             peeking: Peek::NotPeeking,
         }
     }
@@ -216,6 +519,19 @@ impl<'a> BuiltDisplayListIter<'a> {
         self.list
     }
 
+    /// Why the iteration ended before the end of the display list, if it did.
+    pub fn error(&self) -> Option<&DisplayListError> {
+        self.error.as_ref()
+    }
+
+    /// Ends the iteration because of a malformed record.
+    fn fail<'b>(&'b mut self, error: DisplayListError) -> Option<DisplayItemRef<'a, 'b>> {
+        self.data = &[];
+        self.error = Some(error);
+        self.peeking = Peek::NotPeeking;
+        None
+    }
+
     pub fn next<'b>(&'b mut self) -> Option<DisplayItemRef<'a, 'b>> {
         use SpecificDisplayItem::*;
 
@@ -233,36 +549,32 @@ impl<'a> BuiltDisplayListIter<'a> {
         // Don't let these bleed into another item
         self.cur_stops = ItemRange::default();
         self.cur_complex_clip = (ItemRange::default(), 0);
+        self.cur_polygon_points = ItemRange::default();
         self.cur_clip_chain_items = ItemRange::default();
+        self.cur_snap_areas = ItemRange::default();
 
         loop {
             if self.data.len() == 0 {
                 return None;
             }
 
-            {
-                let reader = bincode::read_types::IoReader::new(UnsafeReader::new(&mut self.data));
-                let mut deserializer = bincode::Deserializer::new(reader, bincode::Infinite);
-                Deserialize::deserialize_in_place(&mut deserializer, &mut self.cur_item)
-                    .expect("MEH: malicious process?");
-            }
-
-            match self.cur_item.item {
-                SetGradientStops => {
-                    self.cur_stops = skip_slice::<GradientStop>(self.list, &mut self.data).0;
+            let index = self.index;
+            self.index += 1;
+            let (kind, mut record) = match split_item_record(&mut self.data) {
+                Some(split) => split,
+                None => return self.fail(DisplayListError::InvalidItem(index)),
+            };
+            self.cur_item = match DisplayItem::decode(&mut record) {
+                Some(item) if DisplayItemKind::from_u8(kind) == Some(item.item.kind()) => item,
+                _ => return self.fail(DisplayListError::InvalidItem(index)),
+            };
 
-                    // This is a dummy item, skip over it
-                    continue;
-                }
-                ClipChain(_) => {
-                    self.cur_clip_chain_items = skip_slice::<ClipId>(self.list, &mut self.data).0;
-                }
-                Clip(_) | ScrollFrame(_) => {
-                    self.cur_complex_clip = self.skip_slice::<ComplexClipRegion>()
-                }
-                Text(_) => self.cur_glyphs = self.skip_slice::<GlyphInstance>().0,
-                PushStackingContext(_) => self.cur_filters = self.skip_slice::<FilterOp>().0,
-                _ => { /* do nothing */ }
+            if self.skip_aux(&mut record).is_none() {
+                return self.fail(DisplayListError::InvalidAuxData(index));
+            }
+            if self.cur_item.item == SetGradientStops {
+                // This is a dummy item, skip over it
+                continue;
             }
 
             break;
@@ -271,8 +583,61 @@ impl<'a> BuiltDisplayListIter<'a> {
         Some(self.as_ref())
     }
 
-    fn skip_slice<T: for<'de> Deserialize<'de>>(&mut self) -> (ItemRange<T>, usize) {
-        skip_slice::<T>(self.list, &mut self.data)
+    /// Notes where the auxiliary lists of the current item are in its record.
+    fn skip_aux(&mut self, record: &mut &[u8]) -> Option<()> {
+        use SpecificDisplayItem::*;
+
+        let list = self.list;
+        match self.cur_item.item {
+            SetGradientStops => {
+                self.cur_stops = skip_slice::<GradientStop>(list, record)?.0;
+            }
+            ClipChain(_) => {
+                self.cur_clip_chain_items = skip_slice::<ClipId>(list, record)?.0;
+            }
+            Clip(_) => {
+                self.cur_complex_clip = skip_slice::<ComplexClipRegion>(list, record)?;
+                self.cur_polygon_points = skip_slice::<LayoutPoint>(list, record)?.0;
+            }
+            ScrollFrame(_) => {
+                self.cur_complex_clip = skip_slice::<ComplexClipRegion>(list, record)?;
+                self.cur_snap_areas = skip_slice::<ScrollSnapArea>(list, record)?.0;
+            }
+            Text(_) => self.cur_glyphs = skip_slice::<GlyphInstance>(list, record)?.0,
+            Path(_) => self.cur_path_ops = skip_slice::<PathOp>(list, record)?.0,
+            PushStackingContext(_) => {
+                self.cur_filters = skip_slice::<FilterOp>(list, record)?.0;
+                self.cur_filter_data = skip_slice::<FilterData>(list, record)?.0;
+                self.cur_backdrop_filters = skip_slice::<FilterOp>(list, record)?.0;
+            }
+            _ => { /* do nothing */ }
+        }
+        Some(())
+    }
+
+    /// Returns the kind of the next item from its record header, without decoding the
+    /// item. Unlike `peek`, gradient stops are reported as an item of their own.
+    pub fn peek_kind(&self) -> Option<DisplayItemKind> {
+        if self.peeking == Peek::IsPeeking {
+            return Some(self.cur_item.item.kind());
+        }
+        if self.data.len() < ITEM_HEADER_SIZE {
+            return None;
+        }
+        DisplayItemKind::from_u8(self.data[4])
+    }
+
+    /// Skips over the next item without decoding it.
+    pub fn skip_item(&mut self) {
+        if self.peeking == Peek::IsPeeking {
+            self.peeking = Peek::NotPeeking;
+            return;
+        }
+        let index = self.index;
+        self.index += 1;
+        if split_item_record(&mut self.data).is_none() {
+            self.fail(DisplayListError::InvalidItem(index));
+        }
     }
 
     pub fn as_ref<'b>(&'b self) -> DisplayItemRef<'a, 'b> {
@@ -294,20 +659,29 @@ impl<'a> BuiltDisplayListIter<'a> {
 
     pub fn skip_current_stacking_context(&mut self) {
         let mut depth = 0;
-        while let Some(item) = self.next() {
-            match *item.item() {
-                SpecificDisplayItem::PushStackingContext(..) => depth += 1,
-                SpecificDisplayItem::PopStackingContext if depth == 0 => return,
-                SpecificDisplayItem::PopStackingContext => depth -= 1,
+        while let Some(kind) = self.peek_kind() {
+            self.skip_item();
+            match kind {
+                DisplayItemKind::PushStackingContext => depth += 1,
+                DisplayItemKind::PopStackingContext if depth == 0 => return,
+                DisplayItemKind::PopStackingContext => depth -= 1,
                 _ => {}
             }
-            debug_assert!(depth >= 0);
+        }
+    }
+
+    pub fn skip_current_fragment_definition(&mut self) {
+        while let Some(kind) = self.peek_kind() {
+            self.skip_item();
+            if kind == DisplayItemKind::PopFragmentDefinition {
+                return;
+            }
         }
     }
 
     pub fn current_stacking_context_empty(&mut self) -> bool {
-        match self.peek() {
-            Some(item) => *item.item() == SpecificDisplayItem::PopStackingContext,
+        match self.peek_kind() {
+            Some(kind) => kind == DisplayItemKind::PopStackingContext,
             None => true,
         }
     }
@@ -358,6 +732,10 @@ impl<'a, 'b> DisplayItemRef<'a, 'b> {
         self.iter.cur_complex_clip
     }
 
+    pub fn polygon_points(&self) -> ItemRange<LayoutPoint> {
+        self.iter.cur_polygon_points
+    }
+
     pub fn gradient_stops(&self) -> ItemRange<GradientStop> {
         self.iter.cur_stops
     }
@@ -366,10 +744,26 @@ impl<'a, 'b> DisplayItemRef<'a, 'b> {
         self.iter.cur_glyphs
     }
 
+    pub fn path_ops(&self) -> ItemRange<PathOp> {
+        self.iter.cur_path_ops
+    }
+
+    pub fn snap_areas(&self) -> ItemRange<ScrollSnapArea> {
+        self.iter.cur_snap_areas
+    }
+
     pub fn filters(&self) -> ItemRange<FilterOp> {
         self.iter.cur_filters
     }
 
+    pub fn filter_data(&self) -> ItemRange<FilterData> {
+        self.iter.cur_filter_data
+    }
+
+    pub fn backdrop_filters(&self) -> ItemRange<FilterOp> {
+        self.iter.cur_backdrop_filters
+    }
+
     pub fn clip_chain_items(&self) -> ItemRange<ClipId> {
         self.iter.cur_clip_chain_items
     }
@@ -384,17 +778,20 @@ impl<'a, 'b> DisplayItemRef<'a, 'b> {
 
     // Creates a new iterator where this element's iterator is, to hack around borrowck.
     pub fn sub_iter(&self) -> BuiltDisplayListIter<'a> {
-        BuiltDisplayListIter::new_with_list_and_data(self.iter.list, self.iter.data)
+        let mut iter = BuiltDisplayListIter::new_with_list_and_data(self.iter.list, self.iter.data);
+        iter.index = self.iter.index;
+        iter
     }
 }
 
-impl<'de, 'a, T: Deserialize<'de>> AuxIter<'a, T> {
+impl<'a, T: FixedLayout> AuxIter<'a, T> {
+    /// Iterates over the values of an auxiliary list. A value that can't be decoded
+    /// ends the iteration, which only happens with display lists that aren't validated.
     pub fn new(mut data: &'a [u8]) -> Self {
-        let size: usize = if data.len() == 0 {
+        let size = if data.len() == 0 {
             0 // Accept empty ItemRanges pointing anywhere
         } else {
-            bincode::deserialize_from(&mut UnsafeReader::new(&mut data), bincode::Infinite)
-                .expect("MEH: malicious input?")
+            usize::decode(&mut data).unwrap_or(0)
         };
 
         AuxIter {
@@ -405,18 +802,22 @@ impl<'de, 'a, T: Deserialize<'de>> AuxIter<'a, T> {
     }
 }
 
-impl<'a, T: for<'de> Deserialize<'de>> Iterator for AuxIter<'a, T> {
+impl<'a, T: FixedLayout> Iterator for AuxIter<'a, T> {
     type Item = T;
 
     fn next(&mut self) -> Option<T> {
         if self.size == 0 {
-            None
-        } else {
-            self.size -= 1;
-            Some(
-                bincode::deserialize_from(&mut UnsafeReader::new(&mut self.data), bincode::Infinite)
-                    .expect("MEH: malicious input?"),
-            )
+            return None;
+        }
+        match T::decode(&mut self.data) {
+            Some(value) => {
+                self.size -= 1;
+                Some(value)
+            }
+            None => {
+                self.size = 0;
+                None
+            }
         }
     }
 
@@ -425,7 +826,390 @@ impl<'a, T: for<'de> Deserialize<'de>> Iterator for AuxIter<'a, T> {
     }
 }
 
-impl<'a, T: for<'de> Deserialize<'de>> ::std::iter::ExactSizeIterator for AuxIter<'a, T> {}
+impl<'a, T: FixedLayout> ::std::iter::ExactSizeIterator for AuxIter<'a, T> {}
+
+/// The number of values of some kind in a display list, and the bytes they take up.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SizeStats {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+impl SizeStats {
+    fn add(&mut self, count: usize, bytes: usize) {
+        self.count += count;
+        self.bytes += bytes;
+    }
+
+    fn add_aux<T: FixedLayout>(
+        &mut self,
+        list: &BuiltDisplayList,
+        range: ItemRange<T>,
+    ) {
+        self.add(list.get(range).len(), range.length);
+    }
+}
+
+/// What a display list is made of, as reported by `BuiltDisplayList::stats`.
+#[derive(Clone, Debug, Default)]
+pub struct DisplayListStats {
+    /// The items of each kind, keyed by `SpecificDisplayItem::debug_name`. The bytes
+    /// of an item include its auxiliary lists and the gradient stops set for it.
+    pub items: BTreeMap<&'static str, SizeStats>,
+    pub glyphs: SizeStats,
+    pub gradient_stops: SizeStats,
+    /// The filters, filter data and backdrop filters of stacking contexts.
+    pub filters: SizeStats,
+    pub complex_clips: SizeStats,
+    /// The polygon points, path operations, clip chain items and scroll snap areas.
+    pub other_aux: SizeStats,
+    pub total_bytes: usize,
+    pub max_stacking_context_depth: usize,
+    /// The nodes that the display list adds to the clip scroll tree. Scroll frames add
+    /// a clip node and a scroll node, and iframes add a reference frame as well.
+    pub clip_nodes: usize,
+    pub scroll_nodes: usize,
+    pub sticky_nodes: usize,
+    pub reference_frames: usize,
+    pub clip_chains: usize,
+    /// The time between the creation of the builder and the end of `finalize`, in
+    /// nanoseconds.
+    pub build_time: u64,
+    /// The time between the end of `finalize` and the start of sending the display
+    /// list, in nanoseconds, or zero if it hasn't been sent.
+    pub send_delay: u64,
+    /// Why the items after the ones counted here couldn't be read, for a display list
+    /// that isn't validated.
+    pub error: Option<DisplayListError>,
+}
+
+/// The ways in which a display list can be malformed. Items are identified by
+/// their index in the display list, counting every serialized item.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum DisplayListError {
+    /// The display list was built with another `DISPLAY_LIST_FORMAT_VERSION`.
+    UnsupportedFormat(u32),
+    /// The item couldn't be decoded, or its record extends past the end of the list.
+    InvalidItem(usize),
+    /// An auxiliary list of the item, such as its glyphs or gradient stops, extends
+    /// past the end of the display list, or couldn't be decoded.
+    InvalidAuxData(usize),
+    /// The item pops a stacking context that wasn't pushed. An index of the number
+    /// of items means that a stacking context wasn't popped by the end of the list.
+    UnbalancedStackingContext(usize),
+    /// The item refers to a clip that isn't defined earlier in the display list, or
+    /// that belongs to another pipeline, or defines a clip outside of the range of
+    /// clip ids that the display list was built with. This is also the error for a
+    /// clip chain that is used as the scroll node of an item or as a clip of a clip chain.
+    UnknownClipId(usize, ClipId),
+    /// The item refers to a clip chain that isn't defined earlier in the display list,
+    /// or that belongs to another pipeline.
+    UnknownClipChainId(usize, ClipChainId),
+    /// The item defines a clip that is already defined.
+    DuplicateClipId(usize, ClipId),
+    /// The item defines a clip chain that is already defined.
+    DuplicateClipChainId(usize, ClipChainId),
+    /// The display list was built with more than `MAX_CLIP_IDS` clip ids.
+    TooManyClipIds(usize),
+    /// The text item has more glyphs than `MAX_TEXT_RUN_LENGTH`.
+    TooManyGlyphs(usize, usize),
+    /// The gradient item has fewer than the two stops it needs.
+    MissingGradientStops(usize, usize),
+    /// The item pops an item group that wasn't pushed. An index of the number of
+    /// items means that an item group wasn't popped by the end of the list.
+    UnbalancedItemGroup(usize),
+    /// A display list delta replaces an item group that isn't in the display list.
+    UnknownItemGroup(ItemGroupId),
+    /// A display list delta has two item groups with the same id, or replaces an item
+    /// group whose id is used by more than one group of the display list.
+    DuplicateItemGroup(ItemGroupId),
+    /// A display list delta has items outside of the item groups that it replaces.
+    ItemOutsideItemGroup,
+    /// A display list delta was sent for a pipeline without a display list.
+    MissingDisplayList,
+    /// The item ends a fragment definition that wasn't started. An index of the number
+    /// of items means that a fragment definition wasn't ended by the end of the list.
+    UnbalancedFragmentDefinition(usize),
+    /// The item is inside a fragment definition, but defines clips, reference frames,
+    /// iframes, item groups or fragments, or draws a fragment, which would happen
+    /// again for every instance of the fragment.
+    InvalidItemInFragment(usize),
+    /// The item draws a fragment that isn't defined earlier in the display list.
+    UnknownFragment(usize, FragmentId),
+    /// The item defines a fragment that is already defined.
+    DuplicateFragment(usize, FragmentId),
+}
+
+/// Walks over the serialized items of a display list without trusting any of them,
+/// keeping track of the clips, clip chains, stacking contexts, item groups and fragments
+/// they define.
+struct DisplayListValidator {
+    /// The pipeline and the number of clip ids of the display list, when the clips
+    /// and fragments that items refer to are checked.
+    references: Option<(PipelineId, usize)>,
+    clip_ids: HashSet<ClipId>,
+    clip_chain_ids: HashSet<ClipChainId>,
+    fragment_ids: HashSet<FragmentId>,
+    stacking_context_depth: usize,
+    item_group_depth: usize,
+    /// The fragment being defined, and the stacking context depth at its start.
+    fragment_definition: Option<(FragmentId, usize)>,
+}
+
+impl DisplayListValidator {
+    fn new(pipeline_id: PipelineId, total_clip_ids: usize) -> Self {
+        let mut validator = DisplayListValidator::structure_only();
+        validator.references = Some((pipeline_id, total_clip_ids));
+        validator.clip_ids.insert(ClipId::root_reference_frame(pipeline_id));
+        validator.clip_ids.insert(ClipId::root_scroll_node(pipeline_id));
+        validator
+    }
+
+    fn structure_only() -> Self {
+        DisplayListValidator {
+            references: None,
+            clip_ids: HashSet::new(),
+            clip_chain_ids: HashSet::new(),
+            fragment_ids: HashSet::new(),
+            stacking_context_depth: 0,
+            item_group_depth: 0,
+            fragment_definition: None,
+        }
+    }
+
+    fn validate(mut self, mut data: &[u8]) -> Result<(), DisplayListError> {
+        use SpecificDisplayItem::*;
+
+        let mut index = 0;
+        let mut stop_count = 0;
+        while !data.is_empty() {
+            let (kind, mut record) = split_item_record(&mut data)
+                .ok_or(DisplayListError::InvalidItem(index))?;
+            let item = DisplayItem::decode(&mut record)
+                .ok_or(DisplayListError::InvalidItem(index))?;
+            if DisplayItemKind::from_u8(kind) != Some(item.item.kind()) {
+                return Err(DisplayListError::InvalidItem(index));
+            }
+
+            // As with the iterator, gradient stops only apply to the item after them.
+            let stops = mem::replace(&mut stop_count, 0);
+            if item.item != SetGradientStops {
+                self.check_node_id(index, item.clip_and_scroll.scroll_node_id)?;
+                if let Some(clip_node_id) = item.clip_and_scroll.clip_node_id {
+                    self.check_clip_id(index, clip_node_id)?;
+                }
+            }
+
+            if self.fragment_definition.is_some() {
+                let allowed = match item.item {
+                    Clip(_) | ScrollFrame(_) | StickyFrame(_) | ClipChain(_) | Iframe(_) |
+                    PushItemGroup(_) | PopItemGroup | PushFragmentDefinition(_) |
+                    Fragment(_) => false,
+                    PushStackingContext(ref item) => {
+                        item.stacking_context.reference_frame_id.is_none()
+                    }
+                    _ => true,
+                };
+                if !allowed {
+                    return Err(DisplayListError::InvalidItemInFragment(index));
+                }
+            }
+
+            match item.item {
+                SetGradientStops => {
+                    stop_count = validate_aux::<GradientStop, _>(&mut record, index, |_| Ok(()))?;
+                }
+                ClipChain(ref chain) => {
+                    if let Some(parent) = chain.parent {
+                        self.check_clip_chain_id(index, parent)?;
+                    }
+                    validate_aux::<ClipId, _>(&mut record, index, |id| {
+                        self.check_node_id(index, id)
+                    })?;
+                    self.define_clip_chain_id(index, chain.id)?;
+                }
+                Clip(ref clip) => {
+                    validate_aux::<ComplexClipRegion, _>(&mut record, index, |_| Ok(()))?;
+                    validate_aux::<LayoutPoint, _>(&mut record, index, |_| Ok(()))?;
+                    self.define_clip_id(index, clip.id)?;
+                }
+                ScrollFrame(ref frame) => {
+                    validate_aux::<ComplexClipRegion, _>(&mut record, index, |_| Ok(()))?;
+                    validate_aux::<ScrollSnapArea, _>(&mut record, index, |_| Ok(()))?;
+                    self.define_clip_id(index, frame.clip_id)?;
+                    self.define_clip_id(index, frame.scroll_frame_id)?;
+                }
+                StickyFrame(ref frame) => self.define_clip_id(index, frame.id)?,
+                Iframe(ref iframe) => self.define_clip_id(index, iframe.clip_id)?,
+                Text(_) => {
+                    let count = validate_aux::<GlyphInstance, _>(&mut record, index, |_| Ok(()))?;
+                    if count > MAX_TEXT_RUN_LENGTH {
+                        return Err(DisplayListError::TooManyGlyphs(index, count));
+                    }
+                }
+                Path(_) => {
+                    validate_aux::<PathOp, _>(&mut record, index, |_| Ok(()))?;
+                }
+                Gradient(_) |
+                RadialGradient(_) |
+                ConicGradient(_) |
+                Border(BorderDisplayItem { details: BorderDetails::Gradient(_), .. }) |
+                Border(BorderDisplayItem { details: BorderDetails::RadialGradient(_), .. }) => {
+                    if stops < 2 {
+                        return Err(DisplayListError::MissingGradientStops(index, stops));
+                    }
+                }
+                PushStackingContext(ref item) => {
+                    validate_aux::<FilterOp, _>(&mut record, index, |_| Ok(()))?;
+                    validate_aux::<FilterData, _>(&mut record, index, |_| Ok(()))?;
+                    validate_aux::<FilterOp, _>(&mut record, index, |_| Ok(()))?;
+                    if let Some(reference_frame_id) = item.stacking_context.reference_frame_id {
+                        self.define_clip_id(index, reference_frame_id)?;
+                    }
+                    self.stacking_context_depth += 1;
+                }
+                PopStackingContext => {
+                    let min_depth = self.fragment_definition.map_or(0, |(_, depth)| depth);
+                    if self.stacking_context_depth == min_depth {
+                        return Err(DisplayListError::UnbalancedStackingContext(index));
+                    }
+                    self.stacking_context_depth -= 1;
+                }
+                PushItemGroup(_) => self.item_group_depth += 1,
+                PopItemGroup => {
+                    if self.item_group_depth == 0 {
+                        return Err(DisplayListError::UnbalancedItemGroup(index));
+                    }
+                    self.item_group_depth -= 1;
+                }
+                PushFragmentDefinition(id) => {
+                    if self.fragment_ids.contains(&id) {
+                        return Err(DisplayListError::DuplicateFragment(index, id));
+                    }
+                    self.fragment_definition = Some((id, self.stacking_context_depth));
+                }
+                PopFragmentDefinition => match self.fragment_definition.take() {
+                    Some((id, depth)) if depth == self.stacking_context_depth => {
+                        self.fragment_ids.insert(id);
+                    }
+                    Some(..) => return Err(DisplayListError::UnbalancedStackingContext(index)),
+                    None => return Err(DisplayListError::UnbalancedFragmentDefinition(index)),
+                },
+                Fragment(ref fragment) => {
+                    if self.references.is_some() && !self.fragment_ids.contains(&fragment.id) {
+                        return Err(DisplayListError::UnknownFragment(index, fragment.id));
+                    }
+                }
+                _ => {}
+            }
+
+            // Anything left in the record isn't read by the iterator.
+            if !record.is_empty() {
+                return Err(DisplayListError::InvalidAuxData(index));
+            }
+
+            index += 1;
+        }
+
+        if self.stacking_context_depth != 0 {
+            return Err(DisplayListError::UnbalancedStackingContext(index));
+        }
+        if self.item_group_depth != 0 {
+            return Err(DisplayListError::UnbalancedItemGroup(index));
+        }
+        if self.fragment_definition.is_some() {
+            return Err(DisplayListError::UnbalancedFragmentDefinition(index));
+        }
+        Ok(())
+    }
+
+    fn check_clip_id(&self, index: usize, id: ClipId) -> Result<(), DisplayListError> {
+        match id {
+            ClipId::ClipChain(chain_id) => self.check_clip_chain_id(index, chain_id),
+            ClipId::Clip(..) if self.references.is_none() || self.clip_ids.contains(&id) => Ok(()),
+            ClipId::Clip(..) => Err(DisplayListError::UnknownClipId(index, id)),
+        }
+    }
+
+    /// Checks an id that scene building maps to a node of the clip scroll tree, which
+    /// a clip chain doesn't have.
+    fn check_node_id(&self, index: usize, id: ClipId) -> Result<(), DisplayListError> {
+        match id {
+            ClipId::Clip(..) => self.check_clip_id(index, id),
+            ClipId::ClipChain(..) => Err(DisplayListError::UnknownClipId(index, id)),
+        }
+    }
+
+    fn check_clip_chain_id(&self, index: usize, id: ClipChainId) -> Result<(), DisplayListError> {
+        if self.references.is_none() || self.clip_chain_ids.contains(&id) {
+            Ok(())
+        } else {
+            Err(DisplayListError::UnknownClipChainId(index, id))
+        }
+    }
+
+    fn define_clip_id(&mut self, index: usize, id: ClipId) -> Result<(), DisplayListError> {
+        let (pipeline_id, total_clip_ids) = match self.references {
+            Some(references) => references,
+            None => return Ok(()),
+        };
+        match id {
+            ClipId::Clip(clip_index, clip_pipeline_id)
+                if clip_pipeline_id == pipeline_id && clip_index < total_clip_ids => {
+                if self.clip_ids.insert(id) {
+                    Ok(())
+                } else {
+                    Err(DisplayListError::DuplicateClipId(index, id))
+                }
+            }
+            _ => Err(DisplayListError::UnknownClipId(index, id)),
+        }
+    }
+
+    fn define_clip_chain_id(
+        &mut self,
+        index: usize,
+        id: ClipChainId,
+    ) -> Result<(), DisplayListError> {
+        match self.references {
+            Some((pipeline_id, _)) if id.1 != pipeline_id =>
+                Err(DisplayListError::UnknownClipChainId(index, id)),
+            None => Ok(()),
+            Some(..) if self.clip_chain_ids.insert(id) => Ok(()),
+            Some(..) => Err(DisplayListError::DuplicateClipChainId(index, id)),
+        }
+    }
+}
+
+/// Decodes every value of an auxiliary list, in the same format that `skip_slice`
+/// reads, and returns the number of values in it.
+fn validate_aux<T, F>(
+    data: &mut &[u8],
+    index: usize,
+    mut check: F,
+) -> Result<usize, DisplayListError>
+where
+    T: FixedLayout,
+    F: FnMut(T) -> Result<(), DisplayListError>,
+{
+    let byte_size = usize::decode(data).ok_or(DisplayListError::InvalidAuxData(index))?;
+    let count = usize::decode(data).ok_or(DisplayListError::InvalidAuxData(index))?;
+    if byte_size > data.len() {
+        return Err(DisplayListError::InvalidAuxData(index));
+    }
+
+    let (mut payload, rest) = data.split_at(byte_size);
+    for _ in 0 .. count {
+        let value = T::decode(&mut payload).ok_or(DisplayListError::InvalidAuxData(index))?;
+        check(value)?;
+    }
+    if !payload.is_empty() {
+        return Err(DisplayListError::InvalidAuxData(index));
+    }
+
+    *data = rest;
+    Ok(count)
+}
 
 
 #[cfg(feature = "serialize")]
@@ -442,7 +1226,8 @@ impl Serialize for BuiltDisplayList {
                 item: match display_item.item {
                     SpecificDisplayItem::Clip(v) => Clip(
                         v,
-                        item.iter.list.get(item.iter.cur_complex_clip.0).collect()
+                        item.iter.list.get(item.iter.cur_complex_clip.0).collect(),
+                        item.iter.list.get(item.iter.cur_polygon_points).collect(),
                     ),
                     SpecificDisplayItem::ClipChain(v) => ClipChain(
                         v,
@@ -450,12 +1235,18 @@ impl Serialize for BuiltDisplayList {
                     ),
                     SpecificDisplayItem::ScrollFrame(v) => ScrollFrame(
                         v,
-                        item.iter.list.get(item.iter.cur_complex_clip.0).collect()
+                        item.iter.list.get(item.iter.cur_complex_clip.0).collect(),
+                        item.iter.list.get(item.iter.cur_snap_areas).collect(),
                     ),
                     SpecificDisplayItem::StickyFrame(v) => StickyFrame(v),
                     SpecificDisplayItem::Rectangle(v) => Rectangle(v),
                     SpecificDisplayItem::ClearRectangle => ClearRectangle,
+                    SpecificDisplayItem::HitTest => HitTest,
                     SpecificDisplayItem::Line(v) => Line(v),
+                    SpecificDisplayItem::Path(v) => Path(
+                        v,
+                        item.iter.list.get(item.iter.cur_path_ops).collect()
+                    ),
                     SpecificDisplayItem::Text(v) => Text(
                         v,
                         item.iter.list.get(item.iter.cur_glyphs).collect()
@@ -466,10 +1257,13 @@ impl Serialize for BuiltDisplayList {
                     SpecificDisplayItem::BoxShadow(v) => BoxShadow(v),
                     SpecificDisplayItem::Gradient(v) => Gradient(v),
                     SpecificDisplayItem::RadialGradient(v) => RadialGradient(v),
+                    SpecificDisplayItem::ConicGradient(v) => ConicGradient(v),
                     SpecificDisplayItem::Iframe(v) => Iframe(v),
                     SpecificDisplayItem::PushStackingContext(v) => PushStackingContext(
                         v,
-                        item.iter.list.get(item.iter.cur_filters).collect()
+                        item.iter.list.get(item.iter.cur_filters).collect(),
+                        item.iter.list.get(item.iter.cur_filter_data).collect(),
+                        item.iter.list.get(item.iter.cur_backdrop_filters).collect(),
                     ),
                     SpecificDisplayItem::PopStackingContext => PopStackingContext,
                     SpecificDisplayItem::SetGradientStops => SetGradientStops(
@@ -477,6 +1271,11 @@ impl Serialize for BuiltDisplayList {
                     ),
                     SpecificDisplayItem::PushShadow(v) => PushShadow(v),
                     SpecificDisplayItem::PopAllShadows => PopAllShadows,
+                    SpecificDisplayItem::PushItemGroup(v) => PushItemGroup(v),
+                    SpecificDisplayItem::PopItemGroup => PopItemGroup,
+                    SpecificDisplayItem::PushFragmentDefinition(v) => PushFragmentDefinition(v),
+                    SpecificDisplayItem::PopFragmentDefinition => PopFragmentDefinition,
+                    SpecificDisplayItem::Fragment(v) => Fragment(v),
                 },
                 clip_and_scroll: display_item.clip_and_scroll,
                 info: display_item.info,
@@ -506,21 +1305,25 @@ impl<'de> Deserialize<'de> for BuiltDisplayList {
         let mut data = Vec::new();
         let mut temp = Vec::new();
         let mut total_clip_ids = FIRST_CLIP_ID;
+        let mut total_clip_chain_ids = 0;
         for complete in list {
             let item = DisplayItem {
                 item: match complete.item {
-                    Clip(specific_item, complex_clips) => {
+                    Clip(specific_item, complex_clips, polygon_points) => {
                         total_clip_ids += 1;
                         DisplayListBuilder::push_iter_impl(&mut temp, complex_clips);
+                        DisplayListBuilder::push_iter_impl(&mut temp, polygon_points);
                         SpecificDisplayItem::Clip(specific_item)
                     },
                     ClipChain(specific_item, clip_chain_ids) => {
+                        total_clip_chain_ids = total_clip_chain_ids.max(specific_item.id.0 + 1);
                         DisplayListBuilder::push_iter_impl(&mut temp, clip_chain_ids);
                         SpecificDisplayItem::ClipChain(specific_item)
                     }
-                    ScrollFrame(specific_item, complex_clips) => {
+                    ScrollFrame(specific_item, complex_clips, snap_areas) => {
                         total_clip_ids += 2;
                         DisplayListBuilder::push_iter_impl(&mut temp, complex_clips);
+                        DisplayListBuilder::push_iter_impl(&mut temp, snap_areas);
                         SpecificDisplayItem::ScrollFrame(specific_item)
                     },
                     StickyFrame(specific_item) => {
@@ -529,7 +1332,12 @@ impl<'de> Deserialize<'de> for BuiltDisplayList {
                     }
                     Rectangle(specific_item) => SpecificDisplayItem::Rectangle(specific_item),
                     ClearRectangle => SpecificDisplayItem::ClearRectangle,
+                    HitTest => SpecificDisplayItem::HitTest,
                     Line(specific_item) => SpecificDisplayItem::Line(specific_item),
+                    Path(specific_item, ops) => {
+                        DisplayListBuilder::push_iter_impl(&mut temp, ops);
+                        SpecificDisplayItem::Path(specific_item)
+                    },
                     Text(specific_item, glyphs) => {
                         DisplayListBuilder::push_iter_impl(&mut temp, glyphs);
                         SpecificDisplayItem::Text(specific_item)
@@ -541,15 +1349,19 @@ impl<'de> Deserialize<'de> for BuiltDisplayList {
                     Gradient(specific_item) => SpecificDisplayItem::Gradient(specific_item),
                     RadialGradient(specific_item) =>
                         SpecificDisplayItem::RadialGradient(specific_item),
+                    ConicGradient(specific_item) =>
+                        SpecificDisplayItem::ConicGradient(specific_item),
                     Iframe(specific_item) => {
                         total_clip_ids += 1;
                         SpecificDisplayItem::Iframe(specific_item)
                     }
-                    PushStackingContext(specific_item, filters) => {
+                    PushStackingContext(specific_item, filters, filter_data, backdrop_filters) => {
                         if specific_item.stacking_context.reference_frame_id.is_some() {
                             total_clip_ids += 1;
                         }
                         DisplayListBuilder::push_iter_impl(&mut temp, filters);
+                        DisplayListBuilder::push_iter_impl(&mut temp, filter_data);
+                        DisplayListBuilder::push_iter_impl(&mut temp, backdrop_filters);
                         SpecificDisplayItem::PushStackingContext(specific_item)
                     },
                     PopStackingContext => SpecificDisplayItem::PopStackingContext,
@@ -559,13 +1371,20 @@ impl<'de> Deserialize<'de> for BuiltDisplayList {
                     },
                     PushShadow(specific_item) => SpecificDisplayItem::PushShadow(specific_item),
                     PopAllShadows => SpecificDisplayItem::PopAllShadows,
+                    PushItemGroup(id) => SpecificDisplayItem::PushItemGroup(id),
+                    PopItemGroup => SpecificDisplayItem::PopItemGroup,
+                    PushFragmentDefinition(id) => SpecificDisplayItem::PushFragmentDefinition(id),
+                    PopFragmentDefinition => SpecificDisplayItem::PopFragmentDefinition,
+                    Fragment(specific_item) => SpecificDisplayItem::Fragment(specific_item),
                 },
                 clip_and_scroll: complete.clip_and_scroll,
                 info: complete.info,
             };
-            serialize_fast(&mut data, &item);
+            let offset = begin_item_record(&mut data, item.item.kind());
+            item.encode(&mut data);
             // the aux data is serialized after the item, hence the temporary
             data.extend(temp.drain(..));
+            end_item_record(&mut data, offset);
         }
 
         Ok(BuiltDisplayList {
@@ -575,229 +1394,17 @@ impl<'de> Deserialize<'de> for BuiltDisplayList {
                 builder_finish_time: 1,
                 send_start_time: 0,
                 total_clip_ids,
+                total_clip_chain_ids,
+                format_version: DISPLAY_LIST_FORMAT_VERSION,
             },
         })
     }
 }
 
-// This is a replacement for bincode::serialize_into(&vec)
-// The default implementation Write for Vec will basically
-// call extend_from_slice(). Serde ends up calling that for every
-// field of a struct that we're serializing. extend_from_slice()
-// does not get inlined and thus we end up calling a generic memcpy()
-// implementation. If we instead reserve enough room for the serialized
-// struct in the Vec ahead of time we can rely on that and use
-// the following UnsafeVecWriter to write into the vec without
-// any checks. This writer assumes that size returned by the
-// serialize function will not change between calls to serialize_into:
-//
-// For example, the following struct will cause memory unsafety when
-// used with UnsafeVecWriter.
-//
-// struct S {
-//    first: Cell<bool>,
-// }
-//
-// impl Serialize for S {
-//    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-//        where S: Serializer
-//    {
-//        if self.first.get() {
-//            self.first.set(false);
-//            ().serialize(serializer)
-//        } else {
-//            0.serialize(serializer)
-//        }
-//    }
-// }
-//
-
-struct UnsafeVecWriter(*mut u8);
-
-impl Write for UnsafeVecWriter {
-    #[inline(always)]
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        unsafe {
-            ptr::copy_nonoverlapping(buf.as_ptr(), self.0, buf.len());
-            self.0 = self.0.offset(buf.len() as isize);
-        }
-        Ok(buf.len())
-    }
-
-    #[inline(always)]
-    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        unsafe {
-            ptr::copy_nonoverlapping(buf.as_ptr(), self.0, buf.len());
-            self.0 = self.0.offset(buf.len() as isize);
-        }
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn flush(&mut self) -> io::Result<()> { Ok(()) }
-}
-
-struct SizeCounter(usize);
-
-impl<'a> Write for SizeCounter {
-    #[inline(always)]
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.0 += buf.len();
-        Ok(buf.len())
-    }
-
-    #[inline(always)]
-    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        self.0 += buf.len();
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn flush(&mut self) -> io::Result<()> { Ok(()) }
-}
-
-/// Serializes a value assuming the Serialize impl has a stable size across two
-/// invocations.
-///
-/// If this assumption is incorrect, the result will be Undefined Behaviour. This
-/// assumption should hold for all derived Serialize impls, which is all we currently
-/// use.
-fn serialize_fast<T: Serialize>(vec: &mut Vec<u8>, e: &T) {
-    // manually counting the size is faster than vec.reserve(bincode::serialized_size(&e) as usize) for some reason
-    let mut size = SizeCounter(0);
-    bincode::serialize_into(&mut size, e, bincode::Infinite).unwrap();
-    vec.reserve(size.0);
-
-    let old_len = vec.len();
-    let ptr = unsafe { vec.as_mut_ptr().offset(old_len as isize) };
-    let mut w = UnsafeVecWriter(ptr);
-    bincode::serialize_into(&mut w, e, bincode::Infinite).unwrap();
-
-    // fix up the length
-    unsafe { vec.set_len(old_len + size.0); }
-
-    // make sure we wrote the right amount
-    debug_assert_eq!(((w.0 as usize) - (vec.as_ptr() as usize)), vec.len());
-}
-
-/// Serializes an iterator, assuming:
-///
-/// * The Clone impl is trivial (e.g. we're just memcopying a slice iterator)
-/// * The ExactSizeIterator impl is stable and correct across a Clone
-/// * The Serialize impl has a stable size across two invocations
-///
-/// If the first is incorrect, webrender will be very slow. If the other two are
-/// incorrect, the result will be Undefined Behaviour! The ExactSizeIterator
-/// bound would ideally be replaced with a TrustedLen bound to protect us a bit
-/// better, but that trait isn't stable (and won't be for a good while, if ever).
-///
-/// Debug asserts are included that should catch all Undefined Behaviour, but
-/// we can't afford to include these in release builds.
-fn serialize_iter_fast<I>(vec: &mut Vec<u8>, iter: I) -> usize
-where I: ExactSizeIterator + Clone,
-      I::Item: Serialize,
-{
-    // manually counting the size is faster than vec.reserve(bincode::serialized_size(&e) as usize) for some reason
-    let mut size = SizeCounter(0);
-    let mut count1 = 0;
-
-    for e in iter.clone() {
-        bincode::serialize_into(&mut size, &e, bincode::Infinite).unwrap();
-        count1 += 1;
-    }
-
-    vec.reserve(size.0);
-
-    let old_len = vec.len();
-    let ptr = unsafe { vec.as_mut_ptr().offset(old_len as isize) };
-    let mut w = UnsafeVecWriter(ptr);
-    let mut count2 = 0;
-
-    for e in iter {
-        bincode::serialize_into(&mut w, &e, bincode::Infinite).unwrap();
-        count2 += 1;
-    }
-
-    // fix up the length
-    unsafe { vec.set_len(old_len + size.0); }
-
-    // make sure we wrote the right amount
-    debug_assert_eq!(((w.0 as usize) - (vec.as_ptr() as usize)), vec.len());
-    debug_assert_eq!(count1, count2);
-
-    count1
-}
-
-// This uses a (start, end) representation instead of (start, len) so that
-// only need to update a single field as we read through it. This
-// makes it easier for llvm to understand what's going on. (https://github.com/rust-lang/rust/issues/45068)
-// We update the slice only once we're done reading
-struct UnsafeReader<'a: 'b, 'b> {
-    start: *const u8,
-    end: *const u8,
-    slice: &'b mut &'a [u8],
-}
-
-impl<'a, 'b> UnsafeReader<'a, 'b> {
-    #[inline(always)]
-    fn new(buf: &'b mut &'a [u8]) -> UnsafeReader<'a, 'b> {
-        unsafe {
-            let end = buf.as_ptr().offset(buf.len() as isize);
-            let start = buf.as_ptr();
-            UnsafeReader { start: start, end, slice: buf }
-        }
-    }
-
-    // This read implementation is significantly faster than the standard &[u8] one.
-    //
-    // First, it only supports reading exactly buf.len() bytes. This ensures that
-    // the argument to memcpy is always buf.len() and will allow a constant buf.len()
-    // to be propagated through to memcpy which LLVM will turn into explicit loads and
-    // stores. The standard implementation does a len = min(slice.len(), buf.len())
-    //
-    // Second, we only need to adjust 'start' after reading and it's only adjusted by a
-    // constant. This allows LLVM to avoid adjusting the length field after ever read
-    // and lets it be aggregated into a single adjustment.
-    #[inline(always)]
-    fn read_internal(&mut self, buf: &mut [u8]) {
-        // this is safe because we panic if start + buf.len() > end
-        unsafe {
-            assert!(self.start.offset(buf.len() as isize) <= self.end, "UnsafeReader: read past end of target");
-            ptr::copy_nonoverlapping(self.start, buf.as_mut_ptr(), buf.len());
-            self.start = self.start.offset(buf.len() as isize);
-        }
-    }
-}
-
-impl<'a, 'b> Drop for UnsafeReader<'a, 'b> {
-    // this adjusts input slice so that it properly represents the amount that's left.
-    #[inline(always)]
-    fn drop(&mut self) {
-        // this is safe because we know that start and end are contained inside the original slice
-        unsafe {
-            *self.slice = slice::from_raw_parts(self.start, (self.end as usize) - (self.start as usize));
-        }
-    }
-}
-
-impl<'a, 'b> Read for UnsafeReader<'a, 'b> {
-    // These methods were not being inlined and we need them to be so that the memcpy
-    // is for a constant size
-    #[inline(always)]
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.read_internal(buf);
-        Ok(buf.len())
-    }
-    #[inline(always)]
-    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
-        self.read_internal(buf);
-        Ok(())
-    }
-}
-
 #[derive(Clone, Debug)]
 pub struct SaveState {
     dl_len: usize,
+    open_record: Option<usize>,
     clip_stack_len: usize,
     next_clip_id: usize,
     next_clip_chain_id: u64,
@@ -816,6 +1423,9 @@ pub struct DisplayListBuilder {
     /// outside the bounds of the display list items themselves.
     content_size: LayoutSize,
     save_state: Option<SaveState>,
+    /// The offset of the record of the last item, whose size is filled in when the next
+    /// item is pushed, since its auxiliary lists are pushed separately after it.
+    open_record: Option<usize>,
 }
 
 impl DisplayListBuilder {
@@ -841,9 +1451,28 @@ impl DisplayListBuilder {
             builder_start_time: start_time,
             content_size,
             save_state: None,
+            open_record: None,
         }
     }
 
+    /// Creates a builder for a delta to the display list of a pipeline, which is sent
+    /// with `Transaction::update_display_list`. The delta should only contain item
+    /// groups, each of which replaces the group with the same id. Clip ids continue
+    /// from those of the display list or delta that was sent last, which `previous`
+    /// describes, so that new clips don't collide with the ones that are kept. As they
+    /// never go back down, a full display list needs to be sent before they reach
+    /// `MAX_CLIP_IDS`.
+    pub fn new_delta(
+        pipeline_id: PipelineId,
+        content_size: LayoutSize,
+        previous: &BuiltDisplayListDescriptor,
+    ) -> Self {
+        let mut builder = Self::new(pipeline_id, content_size);
+        builder.next_clip_id = previous.total_clip_ids;
+        builder.next_clip_chain_id = previous.total_clip_chain_ids;
+        builder
+    }
+
     /// Return the content size for this display list
     pub fn content_size(&self) -> LayoutSize {
         self.content_size
@@ -862,6 +1491,7 @@ impl DisplayListBuilder {
         self.save_state = Some(SaveState {
             clip_stack_len: self.clip_stack.len(),
             dl_len: self.data.len(),
+            open_record: self.open_record,
             next_clip_id: self.next_clip_id,
             next_clip_chain_id: self.next_clip_chain_id,
         });
@@ -873,6 +1503,7 @@ impl DisplayListBuilder {
 
         self.clip_stack.truncate(state.clip_stack_len);
         self.data.truncate(state.dl_len);
+        self.open_record = state.open_record;
         self.next_clip_id = state.next_clip_id;
         self.next_clip_chain_id = state.next_clip_chain_id;
     }
@@ -883,6 +1514,7 @@ impl DisplayListBuilder {
     }
 
     pub fn print_display_list(&mut self) {
+        self.end_open_record();
         let mut temp = BuiltDisplayList::default();
         mem::swap(&mut temp.data, &mut self.data);
 
@@ -896,15 +1528,28 @@ impl DisplayListBuilder {
         self.data = temp.data;
     }
 
+    fn end_open_record(&mut self) {
+        if let Some(offset) = self.open_record.take() {
+            end_item_record(&mut self.data, offset);
+        }
+    }
+
+    /// Starts the record of an item, which holds the item and the auxiliary lists that
+    /// are pushed after it.
+    fn push_display_item(&mut self, item: &DisplayItem) {
+        self.end_open_record();
+        let offset = begin_item_record(&mut self.data, item.item.kind());
+        item.encode(&mut self.data);
+        self.open_record = Some(offset);
+    }
+
     fn push_item(&mut self, item: SpecificDisplayItem, info: &LayoutPrimitiveInfo) {
-        serialize_fast(
-            &mut self.data,
-            &DisplayItem {
-                item,
-                clip_and_scroll: *self.clip_stack.last().unwrap(),
-                info: *info,
-            },
-        )
+        let clip_and_scroll = *self.clip_stack.last().unwrap();
+        self.push_display_item(&DisplayItem {
+            item,
+            clip_and_scroll,
+            info: *info,
+        })
     }
 
     fn push_item_with_clip_scroll_info(
@@ -913,57 +1558,50 @@ impl DisplayListBuilder {
         info: &LayoutPrimitiveInfo,
         scrollinfo: ClipAndScrollInfo
     ) {
-        serialize_fast(
-            &mut self.data,
-            &DisplayItem {
-                item,
-                clip_and_scroll: scrollinfo,
-                info: *info,
-            },
-        )
+        self.push_display_item(&DisplayItem {
+            item,
+            clip_and_scroll: scrollinfo,
+            info: *info,
+        })
     }
 
     fn push_new_empty_item(&mut self, item: SpecificDisplayItem) {
         let info = LayoutPrimitiveInfo::new(LayoutRect::zero());
-        serialize_fast(
-            &mut self.data,
-            &DisplayItem {
-                item,
-                clip_and_scroll: *self.clip_stack.last().unwrap(),
-                info,
-            }
-        )
+        let clip_and_scroll = *self.clip_stack.last().unwrap();
+        self.push_display_item(&DisplayItem {
+            item,
+            clip_and_scroll,
+            info,
+        })
     }
 
     fn push_iter_impl<I>(data: &mut Vec<u8>, iter_source: I)
     where
         I: IntoIterator,
-        I::IntoIter: ExactSizeIterator + Clone,
-        I::Item: Serialize,
+        I::IntoIter: ExactSizeIterator,
+        I::Item: FixedLayout,
     {
         let iter = iter_source.into_iter();
         let len = iter.len();
         // Format:
-        // payload_byte_size: usize, item_count: usize, [I; item_count]
+        // payload_byte_size: u64, item_count: u64, [I; item_count]
 
         // We write a dummy value so there's room for later
         let byte_size_offset = data.len();
-        serialize_fast(data, &0usize);
-        serialize_fast(data, &len);
+        0usize.encode(data);
+        len.encode(data);
         let payload_offset = data.len();
 
-        let count = serialize_iter_fast(data, iter);
+        let mut count = 0;
+        for value in iter {
+            value.encode(data);
+            count += 1;
+        }
 
         // Now write the actual byte_size
-        let final_offset = data.len();
-        let byte_size = final_offset - payload_offset;
-
-        // Note we don't use serialize_fast because we don't want to change the Vec's len
-        bincode::serialize_into(
-            &mut &mut data[byte_size_offset..],
-            &byte_size,
-            bincode::Infinite,
-        ).unwrap();
+        let byte_size = data.len() - payload_offset;
+        let byte_size_end = byte_size_offset + mem::size_of::<u64>();
+        LittleEndian::write_u64(&mut data[byte_size_offset .. byte_size_end], byte_size as u64);
 
         debug_assert_eq!(len, count);
     }
@@ -971,13 +1609,23 @@ impl DisplayListBuilder {
     fn push_iter<I>(&mut self, iter: I)
     where
         I: IntoIterator,
-        I::IntoIter: ExactSizeIterator + Clone,
-        I::Item: Serialize,
+        I::IntoIter: ExactSizeIterator,
+        I::Item: FixedLayout,
     {
         Self::push_iter_impl(&mut self.data, iter);
     }
 
     pub fn push_rect(&mut self, info: &LayoutPrimitiveInfo, color: ColorF) {
+        self.push_rect_with_animation(info, PropertyBinding::Value(color));
+    }
+
+    /// Push a rectangle whose color can be updated through `DynamicProperties`
+    /// without rebuilding the display list.
+    pub fn push_rect_with_animation(
+        &mut self,
+        info: &LayoutPrimitiveInfo,
+        color: PropertyBinding<ColorF>,
+    ) {
         let item = SpecificDisplayItem::Rectangle(RectangleDisplayItem { color });
         self.push_item(item, info);
     }
@@ -986,6 +1634,12 @@ impl DisplayListBuilder {
         self.push_item(SpecificDisplayItem::ClearRectangle, info);
     }
 
+    /// Pushes an area that hit tests like a rect with the same tag, clip and
+    /// transform, but isn't drawn. It is skipped unless `info.tag` is set.
+    pub fn push_hit_test(&mut self, info: &LayoutPrimitiveInfo) {
+        self.push_item(SpecificDisplayItem::HitTest, info);
+    }
+
     pub fn push_line(
         &mut self,
         info: &LayoutPrimitiveInfo,
@@ -1004,11 +1658,89 @@ impl DisplayListBuilder {
         self.push_item(item, info);
     }
 
-    pub fn push_image(
+    /// Pushes a vector path, filled with `fill_color` using `fill_rule`,
+    /// and stroked on top if a `stroke` is given.
+    pub fn push_path<I>(
+        &mut self,
+        info: &LayoutPrimitiveInfo,
+        ops: I,
+        fill_rule: FillRule,
+        fill_color: ColorF,
+        stroke: Option<PathStroke>,
+    )
+    where
+        I: IntoIterator<Item = PathOp>,
+        I::IntoIter: ExactSizeIterator + Clone,
+    {
+        let item = SpecificDisplayItem::Path(PathDisplayItem {
+            fill_rule,
+            fill_color,
+            stroke,
+        });
+
+        self.push_item(item, info);
+        self.push_iter(ops);
+    }
+
+    pub fn push_image(
+        &mut self,
+        info: &LayoutPrimitiveInfo,
+        stretch_size: LayoutSize,
+        tile_spacing: LayoutSize,
+        image_rendering: ImageRendering,
+        alpha_type: AlphaType,
+        key: ImageKey,
+    ) {
+        self.push_repeating_image(
+            info,
+            stretch_size,
+            tile_spacing,
+            RepeatMode::Repeat,
+            RepeatMode::Repeat,
+            image_rendering,
+            alpha_type,
+            key,
+        );
+    }
+
+    /// Push an image that is repeated across `info.rect` according to the
+    /// given repeat mode along each axis. `push_image` is equivalent to
+    /// using `RepeatMode::Repeat` for both.
+    pub fn push_repeating_image(
+        &mut self,
+        info: &LayoutPrimitiveInfo,
+        stretch_size: LayoutSize,
+        tile_spacing: LayoutSize,
+        repeat_horizontal: RepeatMode,
+        repeat_vertical: RepeatMode,
+        image_rendering: ImageRendering,
+        alpha_type: AlphaType,
+        key: ImageKey,
+    ) {
+        self.push_sub_image(
+            info,
+            None,
+            stretch_size,
+            tile_spacing,
+            repeat_horizontal,
+            repeat_vertical,
+            image_rendering,
+            alpha_type,
+            key,
+        );
+    }
+
+    /// Push the part of an image given by `source_rect`, in texels, as if
+    /// it were the whole image. This is useful for sprite sheets, and to
+    /// avoid an extra clip when only part of an image is visible.
+    pub fn push_sub_image(
         &mut self,
         info: &LayoutPrimitiveInfo,
+        source_rect: Option<TexelRect>,
         stretch_size: LayoutSize,
         tile_spacing: LayoutSize,
+        repeat_horizontal: RepeatMode,
+        repeat_vertical: RepeatMode,
         image_rendering: ImageRendering,
         alpha_type: AlphaType,
         key: ImageKey,
@@ -1019,6 +1751,9 @@ impl DisplayListBuilder {
             tile_spacing,
             image_rendering,
             alpha_type,
+            repeat_horizontal,
+            repeat_vertical,
+            source_rect,
         });
 
         self.push_item(item, info);
@@ -1056,7 +1791,7 @@ impl DisplayListBuilder {
 
         for split_glyphs in glyphs.chunks(MAX_TEXT_RUN_LENGTH) {
             self.push_item(item, info);
-            self.push_iter(split_glyphs);
+            self.push_iter(split_glyphs.iter().cloned());
         }
     }
 
@@ -1236,6 +1971,29 @@ impl DisplayListBuilder {
         }
     }
 
+    // NOTE: gradients must be pushed in the order they're created
+    // because create_gradient stores the stops in anticipation
+    pub fn create_conic_gradient(
+        &mut self,
+        center: LayoutPoint,
+        angle: f32,
+        mut stops: Vec<GradientStop>,
+        extend_mode: ExtendMode,
+    ) -> ConicGradient {
+        let (start_offset, end_offset) =
+            DisplayListBuilder::normalize_stops(&mut stops, extend_mode);
+
+        self.push_stops(&stops);
+
+        ConicGradient {
+            center,
+            angle,
+            start_offset,
+            end_offset,
+            extend_mode,
+        }
+    }
+
     pub fn push_border(
         &mut self,
         info: &LayoutPrimitiveInfo,
@@ -1320,6 +2078,25 @@ impl DisplayListBuilder {
         self.push_item(item, info);
     }
 
+    /// Pushes a conic gradient to be displayed.
+    ///
+    /// See [`push_gradient`](#method.push_gradient) for explanation.
+    pub fn push_conic_gradient(
+        &mut self,
+        info: &LayoutPrimitiveInfo,
+        gradient: ConicGradient,
+        tile_size: LayoutSize,
+        tile_spacing: LayoutSize,
+    ) {
+        let item = SpecificDisplayItem::ConicGradient(ConicGradientDisplayItem {
+            gradient,
+            tile_size,
+            tile_spacing,
+        });
+
+        self.push_item(item, info);
+    }
+
     pub fn push_stacking_context(
         &mut self,
         info: &LayoutPrimitiveInfo,
@@ -1329,6 +2106,64 @@ impl DisplayListBuilder {
         perspective: Option<LayoutTransform>,
         mix_blend_mode: MixBlendMode,
         filters: Vec<FilterOp>,
+    ) {
+        self.push_stacking_context_with_filter_data(
+            info,
+            scroll_policy,
+            transform,
+            transform_style,
+            perspective,
+            mix_blend_mode,
+            filters,
+            Vec::new(),
+        );
+    }
+
+    /// Push a stacking context with `ComponentTransfer` filters. The n-th
+    /// `ComponentTransfer` filter uses the n-th entry of `filter_data`.
+    pub fn push_stacking_context_with_filter_data(
+        &mut self,
+        info: &LayoutPrimitiveInfo,
+        scroll_policy: ScrollPolicy,
+        transform: Option<PropertyBinding<LayoutTransform>>,
+        transform_style: TransformStyle,
+        perspective: Option<LayoutTransform>,
+        mix_blend_mode: MixBlendMode,
+        filters: Vec<FilterOp>,
+        filter_data: Vec<FilterData>,
+    ) {
+        self.push_stacking_context_with_backdrop_filters(
+            info,
+            scroll_policy,
+            transform,
+            transform_style,
+            perspective,
+            mix_blend_mode,
+            filters,
+            filter_data,
+            Vec::new(),
+        );
+    }
+
+    /// Push a stacking context that also filters whatever has already
+    /// been drawn behind it, as with CSS `backdrop-filter`. The
+    /// `backdrop_filters` are applied in order, before the contents
+    /// of the stacking context are drawn on top.
+    ///
+    /// The filtered backdrop is clipped to the bounds of the stacking
+    /// context. Backdrop filters take no filter data, so a component
+    /// transfer is the identity and is ignored.
+    pub fn push_stacking_context_with_backdrop_filters(
+        &mut self,
+        info: &LayoutPrimitiveInfo,
+        scroll_policy: ScrollPolicy,
+        transform: Option<PropertyBinding<LayoutTransform>>,
+        transform_style: TransformStyle,
+        perspective: Option<LayoutTransform>,
+        mix_blend_mode: MixBlendMode,
+        filters: Vec<FilterOp>,
+        filter_data: Vec<FilterData>,
+        backdrop_filters: Vec<FilterOp>,
     ) {
         let reference_frame_id = if transform.is_some() || perspective.is_some() {
             Some(self.generate_clip_id())
@@ -1348,7 +2183,9 @@ impl DisplayListBuilder {
         });
 
         self.push_item(item, info);
-        self.push_iter(&filters);
+        self.push_iter(filters);
+        self.push_iter(filter_data);
+        self.push_iter(backdrop_filters);
     }
 
     pub fn pop_stacking_context(&mut self) {
@@ -1360,7 +2197,7 @@ impl DisplayListBuilder {
             return;
         }
         self.push_new_empty_item(SpecificDisplayItem::SetGradientStops);
-        self.push_iter(stops);
+        self.push_iter(stops.iter().cloned());
     }
 
     fn generate_clip_id(&mut self) -> ClipId {
@@ -1381,6 +2218,7 @@ impl DisplayListBuilder {
         complex_clips: I,
         image_mask: Option<ImageMask>,
         scroll_sensitivity: ScrollSensitivity,
+        overscroll_behavior: OverscrollBehavior,
     ) -> ClipId
     where
         I: IntoIterator<Item = ComplexClipRegion>,
@@ -1394,7 +2232,43 @@ impl DisplayListBuilder {
             clip_rect,
             complex_clips,
             image_mask,
-            scroll_sensitivity)
+            scroll_sensitivity,
+            overscroll_behavior)
+    }
+
+    /// Define a scroll frame that snaps to the given areas of its content once
+    /// a scroll gesture or a fling ends.
+    pub fn define_scroll_frame_with_snapping<I, S>(
+        &mut self,
+        external_id: Option<ExternalScrollId>,
+        content_rect: LayoutRect,
+        clip_rect: LayoutRect,
+        complex_clips: I,
+        image_mask: Option<ImageMask>,
+        scroll_sensitivity: ScrollSensitivity,
+        overscroll_behavior: OverscrollBehavior,
+        snap_type: ScrollSnapType,
+        snap_areas: S,
+    ) -> ClipId
+    where
+        I: IntoIterator<Item = ComplexClipRegion>,
+        I::IntoIter: ExactSizeIterator + Clone,
+        S: IntoIterator<Item = ScrollSnapArea>,
+        S::IntoIter: ExactSizeIterator + Clone,
+    {
+        let parent = self.clip_stack.last().unwrap().scroll_node_id;
+        self.push_scroll_frame(
+            parent,
+            external_id,
+            content_rect,
+            clip_rect,
+            complex_clips,
+            image_mask,
+            scroll_sensitivity,
+            overscroll_behavior,
+            snap_type,
+            snap_areas,
+        )
     }
 
     pub fn define_scroll_frame_with_parent<I>(
@@ -1406,10 +2280,44 @@ impl DisplayListBuilder {
         complex_clips: I,
         image_mask: Option<ImageMask>,
         scroll_sensitivity: ScrollSensitivity,
+        overscroll_behavior: OverscrollBehavior,
+    ) -> ClipId
+    where
+        I: IntoIterator<Item = ComplexClipRegion>,
+        I::IntoIter: ExactSizeIterator + Clone,
+    {
+        self.push_scroll_frame(
+            parent,
+            external_id,
+            content_rect,
+            clip_rect,
+            complex_clips,
+            image_mask,
+            scroll_sensitivity,
+            overscroll_behavior,
+            ScrollSnapType::none(),
+            iter::empty(),
+        )
+    }
+
+    fn push_scroll_frame<I, S>(
+        &mut self,
+        parent: ClipId,
+        external_id: Option<ExternalScrollId>,
+        content_rect: LayoutRect,
+        clip_rect: LayoutRect,
+        complex_clips: I,
+        image_mask: Option<ImageMask>,
+        scroll_sensitivity: ScrollSensitivity,
+        overscroll_behavior: OverscrollBehavior,
+        snap_type: ScrollSnapType,
+        snap_areas: S,
     ) -> ClipId
     where
         I: IntoIterator<Item = ComplexClipRegion>,
         I::IntoIter: ExactSizeIterator + Clone,
+        S: IntoIterator<Item = ScrollSnapArea>,
+        S::IntoIter: ExactSizeIterator + Clone,
     {
         let clip_id = self.generate_clip_id();
         let scroll_frame_id = self.generate_clip_id();
@@ -1419,6 +2327,8 @@ impl DisplayListBuilder {
             external_id,
             image_mask,
             scroll_sensitivity,
+            overscroll_behavior,
+            snap_type,
         });
 
         self.push_item_with_clip_scroll_info(
@@ -1427,6 +2337,7 @@ impl DisplayListBuilder {
             ClipAndScrollInfo::simple(parent),
         );
         self.push_iter(complex_clips);
+        self.push_iter(snap_areas);
 
         scroll_frame_id
     }
@@ -1475,11 +2386,65 @@ impl DisplayListBuilder {
     where
         I: IntoIterator<Item = ComplexClipRegion>,
         I::IntoIter: ExactSizeIterator + Clone,
+    {
+        self.define_clip_with_parent_and_polygon(
+            parent,
+            clip_rect,
+            complex_clips,
+            image_mask,
+            None,
+            iter::empty(),
+        )
+    }
+
+    /// Defines a clip that, in addition to the regular clip rect, complex
+    /// clips and image mask, only shows the area inside (or, with
+    /// `ClipMode::ClipOut`, outside) the polygon described by `points`.
+    pub fn define_clip_with_polygon<I, P>(
+        &mut self,
+        clip_rect: LayoutRect,
+        complex_clips: I,
+        image_mask: Option<ImageMask>,
+        polygon: ClipPolygon,
+        points: P,
+    ) -> ClipId
+    where
+        I: IntoIterator<Item = ComplexClipRegion>,
+        I::IntoIter: ExactSizeIterator + Clone,
+        P: IntoIterator<Item = LayoutPoint>,
+        P::IntoIter: ExactSizeIterator + Clone,
+    {
+        let parent = self.clip_stack.last().unwrap().scroll_node_id;
+        self.define_clip_with_parent_and_polygon(
+            parent,
+            clip_rect,
+            complex_clips,
+            image_mask,
+            Some(polygon),
+            points,
+        )
+    }
+
+    pub fn define_clip_with_parent_and_polygon<I, P>(
+        &mut self,
+        parent: ClipId,
+        clip_rect: LayoutRect,
+        complex_clips: I,
+        image_mask: Option<ImageMask>,
+        polygon: Option<ClipPolygon>,
+        points: P,
+    ) -> ClipId
+    where
+        I: IntoIterator<Item = ComplexClipRegion>,
+        I::IntoIter: ExactSizeIterator + Clone,
+        P: IntoIterator<Item = LayoutPoint>,
+        P::IntoIter: ExactSizeIterator + Clone,
     {
         let id = self.generate_clip_id();
         let item = SpecificDisplayItem::Clip(ClipDisplayItem {
             id,
             image_mask: image_mask,
+            polygon,
         });
 
         let info = LayoutPrimitiveInfo::new(clip_rect);
@@ -1487,6 +2452,7 @@ impl DisplayListBuilder {
         let scrollinfo = ClipAndScrollInfo::simple(parent);
         self.push_item_with_clip_scroll_info(item, &info, scrollinfo);
         self.push_iter(complex_clips);
+        self.push_iter(points);
         id
     }
 
@@ -1546,9 +2512,41 @@ impl DisplayListBuilder {
         self.push_new_empty_item(SpecificDisplayItem::PopAllShadows);
     }
 
-    pub fn finalize(self) -> (PipelineId, LayoutSize, BuiltDisplayList) {
+    /// Starts a group of items that can be replaced by a later display list delta.
+    /// Replacing a group saves sending the rest of the display list again, but the
+    /// scene is still built from the whole of it.
+    pub fn push_item_group(&mut self, id: ItemGroupId) {
+        self.push_new_empty_item(SpecificDisplayItem::PushItemGroup(id));
+    }
+
+    pub fn pop_item_group(&mut self) {
+        self.push_new_empty_item(SpecificDisplayItem::PopItemGroup);
+    }
+
+    /// Starts the definition of a fragment. The items up to `pop_fragment_definition`
+    /// aren't drawn here, but wherever `push_fragment` draws the fragment later on in
+    /// the display list, so that they are only sent once. A fragment can't define
+    /// clips, reference frames or iframes, and can't draw other fragments; draw it
+    /// inside a transformed stacking context or reference frame instead.
+    pub fn push_fragment_definition(&mut self, id: FragmentId) {
+        self.push_new_empty_item(SpecificDisplayItem::PushFragmentDefinition(id));
+    }
+
+    pub fn pop_fragment_definition(&mut self) {
+        self.push_new_empty_item(SpecificDisplayItem::PopFragmentDefinition);
+    }
+
+    /// Draws a fragment defined earlier, offset by the origin of `info.rect`. The items
+    /// of the fragment use the current clip and scroll node instead of their own.
+    pub fn push_fragment(&mut self, info: &LayoutPrimitiveInfo, id: FragmentId) {
+        let item = SpecificDisplayItem::Fragment(FragmentDisplayItem { id });
+        self.push_item(item, info);
+    }
+
+    pub fn finalize(mut self) -> (PipelineId, LayoutSize, BuiltDisplayList) {
         assert!(self.save_state.is_none(), "Finalized DisplayListBuilder with a pending save");
 
+        self.end_open_record();
         let end_time = precise_time_ns();
 
 
@@ -1561,9 +2559,554 @@ impl DisplayListBuilder {
                     builder_finish_time: end_time,
                     send_start_time: 0,
                     total_clip_ids: self.next_clip_id,
+                    total_clip_chain_ids: self.next_clip_chain_id,
+                    format_version: DISPLAY_LIST_FORMAT_VERSION,
                 },
                 data: self.data,
             },
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{BuiltDisplayList, DisplayListBuilder, DisplayListError, FIRST_CLIP_ID};
+    use super::{MAX_CLIP_IDS, MAX_TEXT_RUN_LENGTH, SizeStats};
+    use {ClipAndScrollInfo, ClipChainId, ClipId, ColorF, ComponentTransferFuncType, DisplayItem};
+    use {DisplayItemKind, ExtendMode, FilterData, FilterOp, FixedLayout, FontInstanceKey};
+    use {FragmentId, GlyphInstance, GradientStop, IdNamespace, ItemGroupId, LayoutPoint};
+    use {LayoutPrimitiveInfo, LayoutRect, LayoutSize, LayoutVector2D, MixBlendMode, PipelineId};
+    use {ScrollPolicy, SpecificDisplayItem, TextDisplayItem, TransformStyle};
+
+    fn rect_info() -> LayoutPrimitiveInfo {
+        LayoutPrimitiveInfo::new(LayoutRect::new(LayoutPoint::zero(), LayoutSize::new(10., 10.)))
+    }
+
+    fn build<F: FnOnce(&mut DisplayListBuilder)>(f: F) -> BuiltDisplayList {
+        let mut builder = DisplayListBuilder::new(PipelineId::dummy(), LayoutSize::new(10., 10.));
+        f(&mut builder);
+        builder.finalize().2
+    }
+
+    #[test]
+    fn test_validate() {
+        let pipeline_id = PipelineId::dummy();
+
+        let list = build(|builder| {
+            let clip_id = builder.define_clip(LayoutRect::zero(), vec![], None);
+            builder.push_clip_id(clip_id);
+            builder.push_rect(&rect_info(), ColorF::new(1.0, 1.0, 1.0, 1.0));
+            builder.pop_clip_id();
+        });
+        assert_eq!(list.validate(pipeline_id), Ok(()));
+
+        // Cutting the list short leaves a partial item.
+        let (mut data, descriptor) = list.into_data();
+        let len = data.len();
+        data.truncate(len - 1);
+        let list = BuiltDisplayList::from_data(data, descriptor);
+        assert_eq!(list.validate(pipeline_id), Err(DisplayListError::InvalidItem(1)));
+
+        let unknown_clip_id = ClipId::Clip(57, pipeline_id);
+        let list = build(|builder| {
+            builder.push_clip_id(unknown_clip_id);
+            builder.push_rect(&rect_info(), ColorF::new(1.0, 1.0, 1.0, 1.0));
+            builder.pop_clip_id();
+        });
+        assert_eq!(
+            list.validate(pipeline_id),
+            Err(DisplayListError::UnknownClipId(0, unknown_clip_id))
+        );
+
+        // Items can be clipped by the clip that an iframe defines, which is the
+        // first clip id that the builder generates.
+        let list = build(|builder| {
+            builder.push_iframe(&rect_info(), PipelineId(1, 0));
+            builder.push_clip_id(ClipId::Clip(FIRST_CLIP_ID, pipeline_id));
+            builder.push_rect(&rect_info(), ColorF::new(1.0, 1.0, 1.0, 1.0));
+            builder.pop_clip_id();
+        });
+        assert_eq!(list.validate(pipeline_id), Ok(()));
+
+        let list = build(|builder| {
+            builder.push_stacking_context(
+                &rect_info(),
+                ScrollPolicy::Scrollable,
+                None,
+                TransformStyle::Flat,
+                None,
+                MixBlendMode::Normal,
+                Vec::new(),
+            );
+        });
+        assert_eq!(
+            list.validate(pipeline_id),
+            Err(DisplayListError::UnbalancedStackingContext(1))
+        );
+
+        let list = build(|builder| builder.pop_stacking_context());
+        assert_eq!(
+            list.validate(pipeline_id),
+            Err(DisplayListError::UnbalancedStackingContext(0))
+        );
+    }
+
+    fn push_text(builder: &mut DisplayListBuilder) {
+        let item = SpecificDisplayItem::Text(TextDisplayItem {
+            font_key: FontInstanceKey::new(IdNamespace(0), 0),
+            color: ColorF::new(0.0, 0.0, 0.0, 1.0),
+            glyph_options: None,
+        });
+        builder.push_item(item, &rect_info());
+    }
+
+    fn push_gradient(builder: &mut DisplayListBuilder) {
+        let gradient = builder.create_gradient(
+            LayoutPoint::zero(),
+            LayoutPoint::new(10., 0.),
+            vec![
+                GradientStop { offset: 0.0, color: ColorF::new(0.0, 0.0, 0.0, 1.0) },
+                GradientStop { offset: 1.0, color: ColorF::new(1.0, 1.0, 1.0, 1.0) },
+            ],
+            ExtendMode::Clamp,
+        );
+        let size = LayoutSize::new(10., 10.);
+        builder.push_gradient(&rect_info(), gradient, size, LayoutSize::zero());
+    }
+
+    #[test]
+    fn test_validate_references() {
+        let pipeline_id = PipelineId::dummy();
+
+        let list = build(|builder| {
+            let clip_id = builder.define_clip(LayoutRect::zero(), vec![], None);
+            let chain_id = builder.define_clip_chain(None, vec![clip_id]);
+            builder.push_clip_and_scroll_info(ClipAndScrollInfo::new(
+                ClipId::root_scroll_node(pipeline_id),
+                ClipId::ClipChain(chain_id),
+            ));
+            push_gradient(builder);
+            builder.pop_clip_id();
+        });
+        assert_eq!(list.validate(pipeline_id), Ok(()));
+
+        let unknown_chain_id = ClipChainId(5, pipeline_id);
+        let list = build(|builder| {
+            builder.define_clip_chain(Some(unknown_chain_id), vec![]);
+        });
+        assert_eq!(
+            list.validate(pipeline_id),
+            Err(DisplayListError::UnknownClipChainId(0, unknown_chain_id))
+        );
+
+        // Only the first gradient gets the stops that were created for it.
+        let list = build(|builder| {
+            let gradient = builder.create_gradient(
+                LayoutPoint::zero(),
+                LayoutPoint::new(10., 0.),
+                vec![
+                    GradientStop { offset: 0.0, color: ColorF::new(0.0, 0.0, 0.0, 1.0) },
+                    GradientStop { offset: 1.0, color: ColorF::new(1.0, 1.0, 1.0, 1.0) },
+                ],
+                ExtendMode::Clamp,
+            );
+            let size = LayoutSize::new(10., 10.);
+            builder.push_gradient(&rect_info(), gradient, size, LayoutSize::zero());
+            builder.push_gradient(&rect_info(), gradient, size, LayoutSize::zero());
+        });
+        assert_eq!(
+            list.validate(pipeline_id),
+            Err(DisplayListError::MissingGradientStops(2, 0))
+        );
+    }
+
+    #[test]
+    fn test_validate_clip_chain_as_node() {
+        let pipeline_id = PipelineId::dummy();
+        let chain_id = ClipId::ClipChain(ClipChainId(0, pipeline_id));
+
+        let list = build(|builder| {
+            builder.define_clip_chain(None, vec![]);
+            builder.push_clip_id(chain_id);
+            builder.push_rect(&rect_info(), ColorF::new(1.0, 1.0, 1.0, 1.0));
+            builder.pop_clip_id();
+        });
+        assert_eq!(list.validate(pipeline_id), Err(DisplayListError::UnknownClipId(1, chain_id)));
+
+        let list = build(|builder| {
+            builder.define_clip_chain(None, vec![]);
+            builder.define_clip_chain(None, vec![chain_id]);
+        });
+        assert_eq!(list.validate(pipeline_id), Err(DisplayListError::UnknownClipId(1, chain_id)));
+    }
+
+    #[test]
+    fn test_validate_clip_definitions() {
+        let pipeline_id = PipelineId::dummy();
+
+        let list = build(|builder| {
+            builder.define_clip(LayoutRect::zero(), vec![], None);
+            builder.next_clip_id = FIRST_CLIP_ID;
+            builder.define_clip(LayoutRect::zero(), vec![], None);
+        });
+        assert_eq!(
+            list.validate(pipeline_id),
+            Err(DisplayListError::DuplicateClipId(1, ClipId::Clip(FIRST_CLIP_ID, pipeline_id)))
+        );
+
+        let list = build(|builder| {
+            builder.define_clip_chain(None, vec![]);
+            builder.next_clip_chain_id = 0;
+            builder.define_clip_chain(None, vec![]);
+        });
+        assert_eq!(
+            list.validate(pipeline_id),
+            Err(DisplayListError::DuplicateClipChainId(1, ClipChainId(0, pipeline_id)))
+        );
+
+        let list = build(|builder| builder.next_clip_id = MAX_CLIP_IDS + 1);
+        assert_eq!(
+            list.validate(pipeline_id),
+            Err(DisplayListError::TooManyClipIds(MAX_CLIP_IDS + 1))
+        );
+    }
+
+    #[test]
+    fn test_validate_aux_data() {
+        let pipeline_id = PipelineId::dummy();
+
+        let list = build(|builder| {
+            push_text(builder);
+            let glyph = GlyphInstance { index: 0, point: LayoutPoint::zero() };
+            builder.push_iter(vec![glyph; MAX_TEXT_RUN_LENGTH + 1]);
+        });
+        assert_eq!(
+            list.validate(pipeline_id),
+            Err(DisplayListError::TooManyGlyphs(0, MAX_TEXT_RUN_LENGTH + 1))
+        );
+
+        // Three bytes can't hold a glyph.
+        let list = build(|builder| {
+            push_text(builder);
+            builder.push_iter(vec![0u8; 3]);
+        });
+        assert_eq!(list.validate(pipeline_id), Err(DisplayListError::InvalidAuxData(0)));
+    }
+
+    fn rect_origins(list: &BuiltDisplayList) -> Vec<LayoutPoint> {
+        let mut origins = Vec::new();
+        let mut iter = list.iter();
+        while let Some(item) = iter.next() {
+            if let SpecificDisplayItem::Rectangle(..) = *item.item() {
+                origins.push(item.rect().origin);
+            }
+        }
+        origins
+    }
+
+    fn push_rect_at(builder: &mut DisplayListBuilder, x: f32) {
+        let info = LayoutPrimitiveInfo::new(LayoutRect::new(
+            LayoutPoint::new(x, 0.),
+            LayoutSize::new(10., 10.),
+        ));
+        builder.push_rect(&info, ColorF::new(1.0, 1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_apply_delta() {
+        let list = build(|builder| {
+            push_rect_at(builder, 0.);
+            builder.push_item_group(ItemGroupId(1));
+            push_rect_at(builder, 10.);
+            builder.pop_item_group();
+            builder.push_item_group(ItemGroupId(2));
+            builder.pop_item_group();
+        });
+
+        // Replacing one group with two items and filling the empty one.
+        let delta = build(|builder| {
+            builder.push_item_group(ItemGroupId(2));
+            push_rect_at(builder, 40.);
+            builder.pop_item_group();
+            builder.push_item_group(ItemGroupId(1));
+            push_rect_at(builder, 20.);
+            push_rect_at(builder, 30.);
+            builder.pop_item_group();
+        });
+        let updated = list.apply_delta(&delta).unwrap();
+        assert_eq!(updated.validate(PipelineId::dummy()), Ok(()));
+        assert_eq!(
+            rect_origins(&updated),
+            vec![0., 20., 30., 40.].into_iter().map(|x| LayoutPoint::new(x, 0.)).collect::<Vec<_>>()
+        );
+
+        let delta = build(|builder| {
+            builder.push_item_group(ItemGroupId(3));
+            builder.pop_item_group();
+        });
+        assert_eq!(
+            list.apply_delta(&delta).err(),
+            Some(DisplayListError::UnknownItemGroup(ItemGroupId(3)))
+        );
+
+        let delta = build(|builder| push_rect_at(builder, 0.));
+        assert_eq!(list.apply_delta(&delta).err(), Some(DisplayListError::ItemOutsideItemGroup));
+
+        let delta = build(|builder| {
+            builder.push_item_group(ItemGroupId(1));
+            builder.pop_item_group();
+            builder.push_item_group(ItemGroupId(1));
+            push_rect_at(builder, 20.);
+            builder.pop_item_group();
+        });
+        assert_eq!(
+            list.apply_delta(&delta).err(),
+            Some(DisplayListError::DuplicateItemGroup(ItemGroupId(1)))
+        );
+
+        let list = build(|builder| {
+            builder.push_item_group(ItemGroupId(1));
+            builder.pop_item_group();
+            builder.push_item_group(ItemGroupId(1));
+            builder.pop_item_group();
+        });
+        let delta = build(|builder| {
+            builder.push_item_group(ItemGroupId(1));
+            builder.pop_item_group();
+        });
+        assert_eq!(
+            list.apply_delta(&delta).err(),
+            Some(DisplayListError::DuplicateItemGroup(ItemGroupId(1)))
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let list = build(|builder| {
+            builder.push_stacking_context(
+                &rect_info(),
+                ScrollPolicy::Scrollable,
+                None,
+                TransformStyle::Flat,
+                None,
+                MixBlendMode::Normal,
+                Vec::new(),
+            );
+            let clip_id = builder.define_clip(LayoutRect::zero(), vec![], None);
+            builder.push_clip_id(clip_id);
+            push_rect_at(builder, 0.);
+            push_rect_at(builder, 10.);
+            builder.pop_clip_id();
+            builder.pop_stacking_context();
+        });
+
+        let stats = list.stats();
+        assert_eq!(stats.items["rectangle"].count, 2);
+        assert_eq!(stats.items["clip"].count, 1);
+        assert_eq!(stats.items["push_stacking_context"].count, 1);
+        assert_eq!(stats.items.values().map(|item| item.bytes).sum::<usize>(), stats.total_bytes);
+        assert_eq!(stats.glyphs, SizeStats::default());
+        assert_eq!(stats.max_stacking_context_depth, 1);
+        assert_eq!(stats.clip_nodes, 1);
+        assert_eq!(stats.scroll_nodes, 0);
+
+        let list = build(|builder| builder.pop_stacking_context());
+        assert_eq!(list.stats().max_stacking_context_depth, 0);
+
+        // Only the items before a corrupt one are counted.
+        let (mut data, descriptor) = build(|builder| {
+            push_rect_at(builder, 0.);
+            push_rect_at(builder, 10.);
+        }).into_data();
+        let len = data.len();
+        data.truncate(len - 1);
+        let stats = BuiltDisplayList::from_data(data, descriptor).stats();
+        assert_eq!(stats.items["rectangle"].count, 1);
+        assert_eq!(stats.error, Some(DisplayListError::InvalidItem(1)));
+    }
+
+    #[test]
+    fn test_validate_fragments() {
+        let pipeline_id = PipelineId::dummy();
+
+        let list = build(|builder| {
+            builder.push_fragment_definition(FragmentId(1));
+            push_rect_at(builder, 0.);
+            builder.pop_fragment_definition();
+            builder.push_fragment(&rect_info(), FragmentId(1));
+            builder.push_fragment(&rect_info(), FragmentId(1));
+        });
+        assert_eq!(list.validate(pipeline_id), Ok(()));
+
+        let list = build(|builder| builder.push_fragment(&rect_info(), FragmentId(1)));
+        assert_eq!(
+            list.validate(pipeline_id),
+            Err(DisplayListError::UnknownFragment(0, FragmentId(1)))
+        );
+
+        let list = build(|builder| {
+            builder.push_fragment_definition(FragmentId(1));
+            builder.define_clip(LayoutRect::zero(), vec![], None);
+            builder.pop_fragment_definition();
+        });
+        assert_eq!(list.validate(pipeline_id), Err(DisplayListError::InvalidItemInFragment(1)));
+
+        let list = build(|builder| builder.push_fragment_definition(FragmentId(1)));
+        assert_eq!(
+            list.validate(pipeline_id),
+            Err(DisplayListError::UnbalancedFragmentDefinition(1))
+        );
+    }
+
+    #[test]
+    fn test_item_records() {
+        for value in 0 .. 256 {
+            if let Some(kind) = DisplayItemKind::from_u8(value as u8) {
+                assert_eq!(kind as usize, value);
+            }
+        }
+
+        let list = build(|builder| {
+            builder.push_rect(&rect_info(), ColorF::new(1.0, 1.0, 1.0, 1.0));
+            builder.push_stacking_context(
+                &rect_info(),
+                ScrollPolicy::Scrollable,
+                None,
+                TransformStyle::Flat,
+                None,
+                MixBlendMode::Normal,
+                Vec::new(),
+            );
+            builder.push_rect(&rect_info(), ColorF::new(1.0, 1.0, 1.0, 1.0));
+            builder.pop_stacking_context();
+            builder.push_clear_rect(&rect_info());
+        });
+        assert_eq!(list.validate(PipelineId::dummy()), Ok(()));
+
+        let mut iter = list.iter();
+        assert_eq!(iter.peek_kind(), Some(DisplayItemKind::Rectangle));
+        iter.skip_item();
+        assert_eq!(iter.peek_kind(), Some(DisplayItemKind::PushStackingContext));
+        iter.next();
+        iter.skip_current_stacking_context();
+        assert_eq!(iter.peek_kind(), Some(DisplayItemKind::ClearRectangle));
+        iter.skip_item();
+        assert_eq!(iter.peek_kind(), None);
+
+        let (data, mut descriptor) = list.into_data();
+        descriptor.format_version = 0;
+        let list = BuiltDisplayList::from_data(data, descriptor);
+        assert_eq!(
+            list.validate(PipelineId::dummy()),
+            Err(DisplayListError::UnsupportedFormat(0))
+        );
+    }
+
+    #[test]
+    fn test_iter_errors() {
+        let (data, mut descriptor) = build(|builder| builder.push_clear_rect(&rect_info()))
+            .into_data();
+        descriptor.format_version = 0;
+        let list = BuiltDisplayList::from_data(data, descriptor);
+        let mut iter = list.iter();
+        assert!(iter.next().is_none());
+        assert_eq!(iter.error(), Some(&DisplayListError::UnsupportedFormat(0)));
+
+        // Cutting the list short leaves a partial item.
+        let (mut data, descriptor) = build(|builder| {
+            builder.push_clear_rect(&rect_info());
+            builder.push_clear_rect(&rect_info());
+        }).into_data();
+        let len = data.len();
+        data.truncate(len - 1);
+        let list = BuiltDisplayList::from_data(data, descriptor);
+        let mut iter = list.iter();
+        assert!(iter.next().is_some());
+        assert!(iter.next().is_none());
+        assert_eq!(iter.error(), Some(&DisplayListError::InvalidItem(1)));
+        assert!(iter.next().is_none());
+
+        // The header of the record doesn't match the item in it.
+        let (mut data, descriptor) = build(|builder| builder.push_clear_rect(&rect_info()))
+            .into_data();
+        data[4] = DisplayItemKind::PopStackingContext as u8;
+        let list = BuiltDisplayList::from_data(data, descriptor);
+        let mut iter = list.iter();
+        assert!(iter.next().is_none());
+        assert_eq!(iter.error(), Some(&DisplayListError::InvalidItem(0)));
+
+        // A text item without its glyphs.
+        let list = build(push_text);
+        let mut iter = list.iter();
+        assert!(iter.next().is_none());
+        assert_eq!(iter.error(), Some(&DisplayListError::InvalidAuxData(0)));
+
+        // Glyphs that can't be decoded end the iteration over them.
+        let list = build(|builder| {
+            push_text(builder);
+            builder.push_iter(vec![0u8; 3]);
+        });
+        let mut iter = list.iter();
+        let item = iter.next().unwrap();
+        assert_eq!(list.get(item.glyphs()).count(), 0);
+    }
+
+    #[test]
+    fn test_fixed_layout() {
+        let glyphs = [
+            GlyphInstance { index: 1, point: LayoutPoint::new(1.0, 2.0) },
+            GlyphInstance { index: 2, point: LayoutPoint::new(3.0, 4.0) },
+        ];
+        let black = ColorF::new(0.0, 0.0, 0.0, 1.0);
+        let filters = vec![
+            FilterOp::DropShadow(LayoutVector2D::new(1.0, 2.0), 3.0, black),
+            FilterOp::ComponentTransfer,
+        ];
+        let filter_data = vec![FilterData {
+            func_r_type: ComponentTransferFuncType::Table,
+            r_values: vec![0.0, 0.5, 1.0],
+            func_g_type: ComponentTransferFuncType::Identity,
+            g_values: vec![],
+            func_b_type: ComponentTransferFuncType::Identity,
+            b_values: vec![],
+            func_a_type: ComponentTransferFuncType::Linear,
+            a_values: vec![1.0, 0.0],
+        }];
+        let font_key = FontInstanceKey::new(IdNamespace(1), 2);
+        let list = build(|builder| {
+            builder.push_stacking_context_with_filter_data(
+                &rect_info(),
+                ScrollPolicy::Scrollable,
+                None,
+                TransformStyle::Flat,
+                None,
+                MixBlendMode::Multiply,
+                filters.clone(),
+                filter_data.clone(),
+            );
+            builder.push_text(&rect_info(), &glyphs, font_key, black, None);
+            builder.pop_stacking_context();
+        });
+        assert_eq!(list.validate(PipelineId::dummy()), Ok(()));
+
+        let mut iter = list.iter();
+        {
+            let item = iter.next().unwrap();
+            assert_eq!(list.get(item.filters()).collect::<Vec<_>>(), filters);
+            assert_eq!(list.get(item.filter_data()).collect::<Vec<_>>(), filter_data);
+        }
+        let item = iter.next().unwrap();
+        match *item.item() {
+            SpecificDisplayItem::Text(ref text) => assert_eq!(text.font_key, font_key),
+            _ => panic!("Expected a text item"),
+        }
+        assert_eq!(list.get(item.glyphs()).collect::<Vec<_>>(), glyphs);
+
+        let mut data = Vec::new();
+        item.display_item().encode(&mut data);
+        assert_eq!(DisplayItem::decode(&mut &data[..]), Some(*item.display_item()));
+        assert_eq!(DisplayItem::decode(&mut &data[.. data.len() - 1]), None);
+        data[0] = 255;
+        assert_eq!(DisplayItem::decode(&mut &data[..]), None);
+    }
+}
@@ -0,0 +1,976 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The binary layout of display items and of the values in their auxiliary lists.
+//!
+//! Every value is written as its fields in declaration order, without padding. Numbers
+//! are little-endian, `usize` is widened to a `u64`, and `bool` is a byte that is 0 or 1.
+//! An enum is a byte with the discriminant of its variant, followed by the fields of the
+//! variant, and an `Option` is an enum whose `Some` variant is 1. A `Vec` is its length
+//! as a `u64`, followed by its elements. Decoding checks every read against the end of
+//! the data and every discriminant against the variants of its enum.
+
+use byteorder::{ByteOrder, LittleEndian};
+use euclid::{SideOffsets2D, TypedPoint2D, TypedRect, TypedSize2D, TypedTransform3D};
+use euclid::TypedVector2D;
+use {AlphaType, BorderDetails, BorderDisplayItem, BorderRadius, BorderSide, BorderStyle};
+use {BorderWidths, BoxShadowClipMode, BoxShadowDisplayItem, ClipAndScrollInfo, ClipChainId};
+use {ClipChainItem, ClipDisplayItem, ClipId, ClipMode, ClipPolygon, ColorF, ComplexClipRegion};
+use {ComponentTransferFuncType, CompositeOperator, ConicGradient, ConicGradientDisplayItem};
+use {DisplayItemKind, ExtendMode, ExternalScrollId, FillRule, FilterData, FilterOp};
+use {FontInstanceFlags, FontInstanceKey, FontRenderMode, FragmentDisplayItem, FragmentId};
+use {GenericDisplayItem, GlyphInstance, GlyphOptions, Gradient, GradientBorder};
+use {GradientDisplayItem, GradientStop, IdNamespace, IframeDisplayItem, ImageBorder};
+use {ImageDisplayItem, ImageKey, ImageMask, ImageRendering, ItemGroupId, LineCap, LineDisplayItem};
+use {LineJoin, LineOrientation, LineStyle, LocalClip, MixBlendMode, NinePatchDescriptor};
+use {NormalBorder, OverscrollBehavior, OverscrollElasticity, PathDisplayItem, PathOp, PathStroke};
+use {PipelineId, PrimitiveInfo, PropertyBinding, PushStackingContextDisplayItem, RadialGradient};
+use {RadialGradientBorder, RadialGradientDisplayItem, RectangleDisplayItem, RepeatMode};
+use {ScrollFrameDisplayItem, ScrollPolicy, ScrollSensitivity, ScrollSnapAlignment, ScrollSnapArea};
+use {ScrollSnapStrictness, ScrollSnapType, Shadow, SpecificDisplayItem, StackingContext};
+use {StickyFrameDisplayItem, StickyOffsetBounds, TexelRect, TextDisplayItem, TransformStyle};
+use {YuvColorSpace, YuvData, YuvImageDisplayItem};
+
+/// A value that can be written to and read from a display list, in the layout described
+/// in the documentation of this module.
+pub trait FixedLayout: Sized {
+    /// Appends the value to the data.
+    fn encode(&self, data: &mut Vec<u8>);
+
+    /// Reads a value from the start of the data and advances the data past it, or
+    /// returns `None` if the data is too short or doesn't hold a valid value.
+    fn decode(data: &mut &[u8]) -> Option<Self>;
+}
+
+/// Splits the given number of bytes off the start of the data.
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if data.len() < len {
+        return None;
+    }
+    let (bytes, rest) = data.split_at(len);
+    *data = rest;
+    Some(bytes)
+}
+
+macro_rules! impl_fixed_layout_for_number {
+    ($ty:ty, $size:expr, $read:ident, $write:ident) => {
+        impl FixedLayout for $ty {
+            fn encode(&self, data: &mut Vec<u8>) {
+                let mut bytes = [0; $size];
+                LittleEndian::$write(&mut bytes, *self);
+                data.extend_from_slice(&bytes);
+            }
+
+            fn decode(data: &mut &[u8]) -> Option<Self> {
+                take(data, $size).map(LittleEndian::$read)
+            }
+        }
+    };
+}
+
+impl_fixed_layout_for_number!(u16, 2, read_u16, write_u16);
+impl_fixed_layout_for_number!(u32, 4, read_u32, write_u32);
+impl_fixed_layout_for_number!(u64, 8, read_u64, write_u64);
+impl_fixed_layout_for_number!(f32, 4, read_f32, write_f32);
+
+/// Implements `FixedLayout` for a struct by encoding its fields in the given order,
+/// which must be the order in which they are declared.
+macro_rules! impl_fixed_layout_for_struct {
+    ($name:ident { $($field:ident),* }) => {
+        impl FixedLayout for $name {
+            fn encode(&self, data: &mut Vec<u8>) {
+                $(self.$field.encode(data);)*
+            }
+
+            fn decode(data: &mut &[u8]) -> Option<Self> {
+                Some($name { $($field: FixedLayout::decode(data)?),* })
+            }
+        }
+    };
+    ($name:ident ( $($field:ident),* )) => {
+        impl FixedLayout for $name {
+            fn encode(&self, data: &mut Vec<u8>) {
+                let $name($(ref $field),*) = *self;
+                $($field.encode(data);)*
+            }
+
+            fn decode(data: &mut &[u8]) -> Option<Self> {
+                $(let $field = FixedLayout::decode(data)?;)*
+                Some($name($($field),*))
+            }
+        }
+    };
+}
+
+/// Implements `FixedLayout` for an enum whose variants have no fields.
+macro_rules! impl_fixed_layout_for_unit_enum {
+    ($name:ident { $($variant:ident),* }) => {
+        impl FixedLayout for $name {
+            fn encode(&self, data: &mut Vec<u8>) {
+                data.push(*self as u8);
+            }
+
+            fn decode(data: &mut &[u8]) -> Option<Self> {
+                let discriminant = u8::decode(data)?;
+                $(
+                    if discriminant == $name::$variant as u8 {
+                        return Some($name::$variant);
+                    }
+                )*
+                None
+            }
+        }
+    };
+}
+
+impl FixedLayout for u8 {
+    fn encode(&self, data: &mut Vec<u8>) {
+        data.push(*self);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        take(data, 1).map(|bytes| bytes[0])
+    }
+}
+
+impl FixedLayout for usize {
+    fn encode(&self, data: &mut Vec<u8>) {
+        (*self as u64).encode(data);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        let value = u64::decode(data)?;
+        // Values that don't fit in a `usize` on this platform are rejected.
+        if value as usize as u64 != value {
+            return None;
+        }
+        Some(value as usize)
+    }
+}
+
+impl FixedLayout for bool {
+    fn encode(&self, data: &mut Vec<u8>) {
+        data.push(*self as u8);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        match u8::decode(data)? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FixedLayout> FixedLayout for Option<T> {
+    fn encode(&self, data: &mut Vec<u8>) {
+        match *self {
+            None => data.push(0),
+            Some(ref value) => {
+                data.push(1);
+                value.encode(data);
+            }
+        }
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        match u8::decode(data)? {
+            0 => Some(None),
+            1 => T::decode(data).map(Some),
+            _ => None,
+        }
+    }
+}
+
+impl<A: FixedLayout, B: FixedLayout> FixedLayout for (A, B) {
+    fn encode(&self, data: &mut Vec<u8>) {
+        self.0.encode(data);
+        self.1.encode(data);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        let a = A::decode(data)?;
+        let b = B::decode(data)?;
+        Some((a, b))
+    }
+}
+
+impl<T: FixedLayout> FixedLayout for Vec<T> {
+    fn encode(&self, data: &mut Vec<u8>) {
+        self.len().encode(data);
+        for value in self {
+            value.encode(data);
+        }
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        let len = usize::decode(data)?;
+        // Every value takes up at least a byte, so this keeps a bogus length from
+        // allocating more than the data could hold.
+        if len > data.len() {
+            return None;
+        }
+        let mut values = Vec::with_capacity(len);
+        for _ in 0 .. len {
+            values.push(T::decode(data)?);
+        }
+        Some(values)
+    }
+}
+
+macro_rules! impl_fixed_layout_for_array {
+    ($($len:expr),*) => {
+        $(
+            impl<T: FixedLayout + Copy + Default> FixedLayout for [T; $len] {
+                fn encode(&self, data: &mut Vec<u8>) {
+                    for value in self {
+                        value.encode(data);
+                    }
+                }
+
+                fn decode(data: &mut &[u8]) -> Option<Self> {
+                    let mut values = [T::default(); $len];
+                    for value in values.iter_mut() {
+                        *value = T::decode(data)?;
+                    }
+                    Some(values)
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_layout_for_array!(4, 16, 20);
+
+impl<U> FixedLayout for TypedPoint2D<f32, U> {
+    fn encode(&self, data: &mut Vec<u8>) {
+        self.x.encode(data);
+        self.y.encode(data);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        let x = f32::decode(data)?;
+        let y = f32::decode(data)?;
+        Some(TypedPoint2D::new(x, y))
+    }
+}
+
+impl<U> FixedLayout for TypedVector2D<f32, U> {
+    fn encode(&self, data: &mut Vec<u8>) {
+        self.x.encode(data);
+        self.y.encode(data);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        let x = f32::decode(data)?;
+        let y = f32::decode(data)?;
+        Some(TypedVector2D::new(x, y))
+    }
+}
+
+impl<U> FixedLayout for TypedSize2D<f32, U> {
+    fn encode(&self, data: &mut Vec<u8>) {
+        self.width.encode(data);
+        self.height.encode(data);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        let width = f32::decode(data)?;
+        let height = f32::decode(data)?;
+        Some(TypedSize2D::new(width, height))
+    }
+}
+
+impl<U> FixedLayout for TypedRect<f32, U> {
+    fn encode(&self, data: &mut Vec<u8>) {
+        self.origin.encode(data);
+        self.size.encode(data);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        let origin = TypedPoint2D::decode(data)?;
+        let size = TypedSize2D::decode(data)?;
+        Some(TypedRect::new(origin, size))
+    }
+}
+
+impl<T: FixedLayout + Copy> FixedLayout for SideOffsets2D<T> {
+    fn encode(&self, data: &mut Vec<u8>) {
+        self.top.encode(data);
+        self.right.encode(data);
+        self.bottom.encode(data);
+        self.left.encode(data);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        let top = T::decode(data)?;
+        let right = T::decode(data)?;
+        let bottom = T::decode(data)?;
+        let left = T::decode(data)?;
+        Some(SideOffsets2D::new(top, right, bottom, left))
+    }
+}
+
+impl<Src, Dst> FixedLayout for TypedTransform3D<f32, Src, Dst> {
+    fn encode(&self, data: &mut Vec<u8>) {
+        self.to_row_major_array().encode(data);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        let m = <[f32; 16]>::decode(data)?;
+        Some(TypedTransform3D::row_major(
+            m[0], m[1], m[2], m[3],
+            m[4], m[5], m[6], m[7],
+            m[8], m[9], m[10], m[11],
+            m[12], m[13], m[14], m[15],
+        ))
+    }
+}
+
+impl_fixed_layout_for_struct!(IdNamespace(namespace));
+impl_fixed_layout_for_struct!(PipelineId(source_id, id));
+impl_fixed_layout_for_struct!(ImageKey(namespace, key));
+impl_fixed_layout_for_struct!(FontInstanceKey(namespace, key));
+impl_fixed_layout_for_struct!(ClipChainId(id, pipeline_id));
+impl_fixed_layout_for_struct!(ExternalScrollId(id, pipeline_id));
+impl_fixed_layout_for_struct!(ItemGroupId(id));
+impl_fixed_layout_for_struct!(FragmentId(id));
+
+impl<T: FixedLayout> FixedLayout for PropertyBinding<T> {
+    fn encode(&self, data: &mut Vec<u8>) {
+        match *self {
+            PropertyBinding::Value(ref value) => {
+                data.push(0);
+                value.encode(data);
+            }
+            PropertyBinding::Binding(ref key) => {
+                data.push(1);
+                key.encode(data);
+            }
+        }
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        match u8::decode(data)? {
+            0 => T::decode(data).map(PropertyBinding::Value),
+            1 => FixedLayout::decode(data).map(PropertyBinding::Binding),
+            _ => None,
+        }
+    }
+}
+
+impl_fixed_layout_for_struct!(ColorF { r, g, b, a });
+impl_fixed_layout_for_struct!(TexelRect { uv0, uv1 });
+impl_fixed_layout_for_struct!(GlyphInstance { index, point });
+impl_fixed_layout_for_struct!(GlyphOptions { render_mode, flags });
+impl_fixed_layout_for_unit_enum!(FontRenderMode { Mono, Alpha, Subpixel });
+
+impl FixedLayout for FontInstanceFlags {
+    fn encode(&self, data: &mut Vec<u8>) {
+        self.bits().encode(data);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        FontInstanceFlags::from_bits(u32::decode(data)?)
+    }
+}
+
+impl<T: FixedLayout> FixedLayout for GenericDisplayItem<T> {
+    fn encode(&self, data: &mut Vec<u8>) {
+        self.item.encode(data);
+        self.clip_and_scroll.encode(data);
+        self.info.encode(data);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        Some(GenericDisplayItem {
+            item: T::decode(data)?,
+            clip_and_scroll: FixedLayout::decode(data)?,
+            info: FixedLayout::decode(data)?,
+        })
+    }
+}
+
+impl<T> FixedLayout for PrimitiveInfo<T> {
+    fn encode(&self, data: &mut Vec<u8>) {
+        self.rect.encode(data);
+        self.local_clip.encode(data);
+        self.is_backface_visible.encode(data);
+        self.tag.encode(data);
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        Some(PrimitiveInfo {
+            rect: FixedLayout::decode(data)?,
+            local_clip: FixedLayout::decode(data)?,
+            is_backface_visible: FixedLayout::decode(data)?,
+            tag: FixedLayout::decode(data)?,
+        })
+    }
+}
+
+impl FixedLayout for SpecificDisplayItem {
+    fn encode(&self, data: &mut Vec<u8>) {
+        use SpecificDisplayItem::*;
+
+        data.push(self.kind() as u8);
+        match *self {
+            Clip(ref item) => item.encode(data),
+            ScrollFrame(ref item) => item.encode(data),
+            StickyFrame(ref item) => item.encode(data),
+            Rectangle(ref item) => item.encode(data),
+            Line(ref item) => item.encode(data),
+            Path(ref item) => item.encode(data),
+            Text(ref item) => item.encode(data),
+            Image(ref item) => item.encode(data),
+            YuvImage(ref item) => item.encode(data),
+            Border(ref item) => item.encode(data),
+            BoxShadow(ref item) => item.encode(data),
+            Gradient(ref item) => item.encode(data),
+            RadialGradient(ref item) => item.encode(data),
+            ConicGradient(ref item) => item.encode(data),
+            ClipChain(ref item) => item.encode(data),
+            Iframe(ref item) => item.encode(data),
+            PushStackingContext(ref item) => item.encode(data),
+            PushShadow(ref shadow) => shadow.encode(data),
+            PushItemGroup(ref id) => id.encode(data),
+            PushFragmentDefinition(ref id) => id.encode(data),
+            Fragment(ref item) => item.encode(data),
+            ClearRectangle |
+            HitTest |
+            PopStackingContext |
+            SetGradientStops |
+            PopAllShadows |
+            PopItemGroup |
+            PopFragmentDefinition => {}
+        }
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        use DisplayItemKind as Kind;
+        use SpecificDisplayItem::*;
+
+        let kind = DisplayItemKind::from_u8(u8::decode(data)?)?;
+        Some(match kind {
+            Kind::Clip => Clip(FixedLayout::decode(data)?),
+            Kind::ScrollFrame => ScrollFrame(FixedLayout::decode(data)?),
+            Kind::StickyFrame => StickyFrame(FixedLayout::decode(data)?),
+            Kind::Rectangle => Rectangle(FixedLayout::decode(data)?),
+            Kind::ClearRectangle => ClearRectangle,
+            Kind::HitTest => HitTest,
+            Kind::Line => Line(FixedLayout::decode(data)?),
+            Kind::Path => Path(FixedLayout::decode(data)?),
+            Kind::Text => Text(FixedLayout::decode(data)?),
+            Kind::Image => Image(FixedLayout::decode(data)?),
+            Kind::YuvImage => YuvImage(FixedLayout::decode(data)?),
+            Kind::Border => Border(FixedLayout::decode(data)?),
+            Kind::BoxShadow => BoxShadow(FixedLayout::decode(data)?),
+            Kind::Gradient => Gradient(FixedLayout::decode(data)?),
+            Kind::RadialGradient => RadialGradient(FixedLayout::decode(data)?),
+            Kind::ConicGradient => ConicGradient(FixedLayout::decode(data)?),
+            Kind::ClipChain => ClipChain(FixedLayout::decode(data)?),
+            Kind::Iframe => Iframe(FixedLayout::decode(data)?),
+            Kind::PushStackingContext => PushStackingContext(FixedLayout::decode(data)?),
+            Kind::PopStackingContext => PopStackingContext,
+            Kind::SetGradientStops => SetGradientStops,
+            Kind::PushShadow => PushShadow(FixedLayout::decode(data)?),
+            Kind::PopAllShadows => PopAllShadows,
+            Kind::PushItemGroup => PushItemGroup(FixedLayout::decode(data)?),
+            Kind::PopItemGroup => PopItemGroup,
+            Kind::PushFragmentDefinition => PushFragmentDefinition(FixedLayout::decode(data)?),
+            Kind::PopFragmentDefinition => PopFragmentDefinition,
+            Kind::Fragment => Fragment(FixedLayout::decode(data)?),
+        })
+    }
+}
+
+impl_fixed_layout_for_struct!(ClipAndScrollInfo { scroll_node_id, clip_node_id });
+
+impl FixedLayout for ClipId {
+    fn encode(&self, data: &mut Vec<u8>) {
+        match *self {
+            ClipId::Clip(index, pipeline_id) => {
+                data.push(0);
+                index.encode(data);
+                pipeline_id.encode(data);
+            }
+            ClipId::ClipChain(id) => {
+                data.push(1);
+                id.encode(data);
+            }
+        }
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        match u8::decode(data)? {
+            0 => {
+                let index = usize::decode(data)?;
+                let pipeline_id = PipelineId::decode(data)?;
+                Some(ClipId::Clip(index, pipeline_id))
+            }
+            1 => ClipChainId::decode(data).map(ClipId::ClipChain),
+            _ => None,
+        }
+    }
+}
+
+impl FixedLayout for LocalClip {
+    fn encode(&self, data: &mut Vec<u8>) {
+        match *self {
+            LocalClip::Rect(ref rect) => {
+                data.push(0);
+                rect.encode(data);
+            }
+            LocalClip::RoundedRect(ref rect, ref region) => {
+                data.push(1);
+                rect.encode(data);
+                region.encode(data);
+            }
+        }
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        match u8::decode(data)? {
+            0 => FixedLayout::decode(data).map(LocalClip::Rect),
+            1 => {
+                let rect = FixedLayout::decode(data)?;
+                let region = FixedLayout::decode(data)?;
+                Some(LocalClip::RoundedRect(rect, region))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl_fixed_layout_for_struct!(ComplexClipRegion { rect, radii, mode });
+impl_fixed_layout_for_unit_enum!(ClipMode { Clip, ClipOut });
+impl_fixed_layout_for_struct!(BorderRadius { top_left, top_right, bottom_left, bottom_right });
+impl_fixed_layout_for_struct!(ImageMask { image, rect, repeat });
+impl_fixed_layout_for_struct!(ClipPolygon { fill_rule, mode });
+impl_fixed_layout_for_unit_enum!(FillRule { Nonzero, Evenodd });
+impl_fixed_layout_for_struct!(ClipDisplayItem { id, image_mask, polygon });
+impl_fixed_layout_for_struct!(ClipChainItem { id, parent });
+
+impl_fixed_layout_for_struct!(StickyFrameDisplayItem {
+    id,
+    margins,
+    vertical_offset_bounds,
+    horizontal_offset_bounds,
+    previously_applied_offset
+});
+impl_fixed_layout_for_struct!(StickyOffsetBounds { min, max });
+
+impl_fixed_layout_for_struct!(ScrollFrameDisplayItem {
+    clip_id,
+    scroll_frame_id,
+    external_id,
+    image_mask,
+    scroll_sensitivity,
+    overscroll_behavior,
+    snap_type
+});
+impl_fixed_layout_for_unit_enum!(ScrollSensitivity { ScriptAndInputEvents, Script });
+impl_fixed_layout_for_struct!(OverscrollElasticity { stiffness, damping, max_stretch });
+impl_fixed_layout_for_struct!(ScrollSnapType { x, y });
+impl_fixed_layout_for_unit_enum!(ScrollSnapStrictness { None, Mandatory, Proximity });
+impl_fixed_layout_for_struct!(ScrollSnapArea { rect, align_x, align_y });
+impl_fixed_layout_for_unit_enum!(ScrollSnapAlignment { None, Start, Center, End });
+
+impl FixedLayout for OverscrollBehavior {
+    fn encode(&self, data: &mut Vec<u8>) {
+        match *self {
+            OverscrollBehavior::Auto => data.push(0),
+            OverscrollBehavior::None => data.push(1),
+            OverscrollBehavior::Clamp => data.push(2),
+            OverscrollBehavior::Elastic(ref elasticity) => {
+                data.push(3);
+                elasticity.encode(data);
+            }
+        }
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        match u8::decode(data)? {
+            0 => Some(OverscrollBehavior::Auto),
+            1 => Some(OverscrollBehavior::None),
+            2 => Some(OverscrollBehavior::Clamp),
+            3 => FixedLayout::decode(data).map(OverscrollBehavior::Elastic),
+            _ => None,
+        }
+    }
+}
+
+impl_fixed_layout_for_struct!(RectangleDisplayItem { color });
+impl_fixed_layout_for_struct!(LineDisplayItem { orientation, wavy_line_thickness, color, style });
+impl_fixed_layout_for_unit_enum!(LineOrientation { Vertical, Horizontal });
+impl_fixed_layout_for_unit_enum!(LineStyle { Solid, Dotted, Dashed, Wavy });
+
+impl_fixed_layout_for_struct!(PathDisplayItem { fill_rule, fill_color, stroke });
+impl_fixed_layout_for_struct!(PathStroke { width, color, join, cap, miter_limit });
+impl_fixed_layout_for_unit_enum!(LineJoin { Miter, Round, Bevel });
+impl_fixed_layout_for_unit_enum!(LineCap { Butt, Round, Square });
+
+impl FixedLayout for PathOp {
+    fn encode(&self, data: &mut Vec<u8>) {
+        match *self {
+            PathOp::MoveTo(ref to) => {
+                data.push(0);
+                to.encode(data);
+            }
+            PathOp::LineTo(ref to) => {
+                data.push(1);
+                to.encode(data);
+            }
+            PathOp::QuadTo(ref ctrl, ref to) => {
+                data.push(2);
+                ctrl.encode(data);
+                to.encode(data);
+            }
+            PathOp::CubicTo(ref ctrl1, ref ctrl2, ref to) => {
+                data.push(3);
+                ctrl1.encode(data);
+                ctrl2.encode(data);
+                to.encode(data);
+            }
+            PathOp::Close => data.push(4),
+        }
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        match u8::decode(data)? {
+            0 => FixedLayout::decode(data).map(PathOp::MoveTo),
+            1 => FixedLayout::decode(data).map(PathOp::LineTo),
+            2 => {
+                let ctrl = FixedLayout::decode(data)?;
+                let to = FixedLayout::decode(data)?;
+                Some(PathOp::QuadTo(ctrl, to))
+            }
+            3 => {
+                let ctrl1 = FixedLayout::decode(data)?;
+                let ctrl2 = FixedLayout::decode(data)?;
+                let to = FixedLayout::decode(data)?;
+                Some(PathOp::CubicTo(ctrl1, ctrl2, to))
+            }
+            4 => Some(PathOp::Close),
+            _ => None,
+        }
+    }
+}
+
+impl_fixed_layout_for_struct!(TextDisplayItem { font_key, color, glyph_options });
+
+impl_fixed_layout_for_struct!(BorderDisplayItem { widths, details });
+impl_fixed_layout_for_struct!(BorderWidths { left, top, right, bottom });
+impl_fixed_layout_for_struct!(NormalBorder { left, right, top, bottom, radius });
+impl_fixed_layout_for_struct!(BorderSide { color, style });
+impl_fixed_layout_for_unit_enum!(BorderStyle {
+    None, Solid, Double, Dotted, Dashed, Hidden, Groove, Ridge, Inset, Outset
+});
+impl_fixed_layout_for_struct!(ImageBorder {
+    image_key,
+    patch,
+    fill,
+    outset,
+    repeat_horizontal,
+    repeat_vertical
+});
+impl_fixed_layout_for_struct!(NinePatchDescriptor { width, height, slice });
+impl_fixed_layout_for_unit_enum!(RepeatMode { Stretch, Repeat, Round, Space });
+impl_fixed_layout_for_struct!(GradientBorder {
+    gradient,
+    outset,
+    slice,
+    repeat_horizontal,
+    repeat_vertical
+});
+impl_fixed_layout_for_struct!(RadialGradientBorder {
+    gradient,
+    outset,
+    slice,
+    repeat_horizontal,
+    repeat_vertical
+});
+
+impl FixedLayout for BorderDetails {
+    fn encode(&self, data: &mut Vec<u8>) {
+        match *self {
+            BorderDetails::Normal(ref border) => {
+                data.push(0);
+                border.encode(data);
+            }
+            BorderDetails::Image(ref border) => {
+                data.push(1);
+                border.encode(data);
+            }
+            BorderDetails::Gradient(ref border) => {
+                data.push(2);
+                border.encode(data);
+            }
+            BorderDetails::RadialGradient(ref border) => {
+                data.push(3);
+                border.encode(data);
+            }
+        }
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        match u8::decode(data)? {
+            0 => FixedLayout::decode(data).map(BorderDetails::Normal),
+            1 => FixedLayout::decode(data).map(BorderDetails::Image),
+            2 => FixedLayout::decode(data).map(BorderDetails::Gradient),
+            3 => FixedLayout::decode(data).map(BorderDetails::RadialGradient),
+            _ => None,
+        }
+    }
+}
+
+impl_fixed_layout_for_struct!(BoxShadowDisplayItem {
+    box_bounds,
+    offset,
+    color,
+    blur_radius,
+    spread_radius,
+    border_radius,
+    clip_mode
+});
+impl_fixed_layout_for_unit_enum!(BoxShadowClipMode { Outset, Inset });
+impl_fixed_layout_for_struct!(Shadow { offset, color, blur_radius });
+
+impl_fixed_layout_for_unit_enum!(ExtendMode { Clamp, Repeat });
+impl_fixed_layout_for_struct!(GradientStop { offset, color });
+impl_fixed_layout_for_struct!(Gradient { start_point, end_point, extend_mode });
+impl_fixed_layout_for_struct!(GradientDisplayItem { gradient, tile_size, tile_spacing });
+impl_fixed_layout_for_struct!(RadialGradient {
+    start_center,
+    start_radius,
+    end_center,
+    end_radius,
+    ratio_xy,
+    extend_mode
+});
+impl_fixed_layout_for_struct!(RadialGradientDisplayItem { gradient, tile_size, tile_spacing });
+impl_fixed_layout_for_struct!(ConicGradient {
+    center,
+    angle,
+    start_offset,
+    end_offset,
+    extend_mode
+});
+impl_fixed_layout_for_struct!(ConicGradientDisplayItem { gradient, tile_size, tile_spacing });
+
+impl_fixed_layout_for_struct!(IframeDisplayItem { clip_id, pipeline_id });
+impl_fixed_layout_for_struct!(FragmentDisplayItem { id });
+
+impl_fixed_layout_for_struct!(PushStackingContextDisplayItem { stacking_context });
+impl_fixed_layout_for_struct!(StackingContext {
+    scroll_policy,
+    transform,
+    transform_style,
+    perspective,
+    mix_blend_mode,
+    reference_frame_id
+});
+impl_fixed_layout_for_unit_enum!(ScrollPolicy { Scrollable, Fixed });
+impl_fixed_layout_for_unit_enum!(TransformStyle { Flat, Preserve3D });
+impl_fixed_layout_for_unit_enum!(MixBlendMode {
+    Normal, Multiply, Screen, Overlay, Darken, Lighten, ColorDodge, ColorBurn, HardLight,
+    SoftLight, Difference, Exclusion, Hue, Saturation, Color, Luminosity
+});
+
+impl FixedLayout for FilterOp {
+    fn encode(&self, data: &mut Vec<u8>) {
+        match *self {
+            FilterOp::Blur(radius) => {
+                data.push(0);
+                radius.encode(data);
+            }
+            FilterOp::Brightness(amount) => {
+                data.push(1);
+                amount.encode(data);
+            }
+            FilterOp::Contrast(amount) => {
+                data.push(2);
+                amount.encode(data);
+            }
+            FilterOp::Grayscale(amount) => {
+                data.push(3);
+                amount.encode(data);
+            }
+            FilterOp::HueRotate(angle) => {
+                data.push(4);
+                angle.encode(data);
+            }
+            FilterOp::Invert(amount) => {
+                data.push(5);
+                amount.encode(data);
+            }
+            FilterOp::Opacity(ref binding, amount) => {
+                data.push(6);
+                binding.encode(data);
+                amount.encode(data);
+            }
+            FilterOp::Saturate(amount) => {
+                data.push(7);
+                amount.encode(data);
+            }
+            FilterOp::Sepia(amount) => {
+                data.push(8);
+                amount.encode(data);
+            }
+            FilterOp::DropShadow(ref offset, blur_radius, ref color) => {
+                data.push(9);
+                offset.encode(data);
+                blur_radius.encode(data);
+                color.encode(data);
+            }
+            FilterOp::ColorMatrix(ref matrix) => {
+                data.push(10);
+                matrix.encode(data);
+            }
+            FilterOp::ComponentTransfer => data.push(11),
+            FilterOp::Flood(ref color) => {
+                data.push(12);
+                color.encode(data);
+            }
+            FilterOp::Offset(ref offset) => {
+                data.push(13);
+                offset.encode(data);
+            }
+            FilterOp::Composite(ref operator) => {
+                data.push(14);
+                operator.encode(data);
+            }
+        }
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        match u8::decode(data)? {
+            0 => f32::decode(data).map(FilterOp::Blur),
+            1 => f32::decode(data).map(FilterOp::Brightness),
+            2 => f32::decode(data).map(FilterOp::Contrast),
+            3 => f32::decode(data).map(FilterOp::Grayscale),
+            4 => f32::decode(data).map(FilterOp::HueRotate),
+            5 => f32::decode(data).map(FilterOp::Invert),
+            6 => {
+                let binding = FixedLayout::decode(data)?;
+                let amount = f32::decode(data)?;
+                Some(FilterOp::Opacity(binding, amount))
+            }
+            7 => f32::decode(data).map(FilterOp::Saturate),
+            8 => f32::decode(data).map(FilterOp::Sepia),
+            9 => {
+                let offset = FixedLayout::decode(data)?;
+                let blur_radius = f32::decode(data)?;
+                let color = FixedLayout::decode(data)?;
+                Some(FilterOp::DropShadow(offset, blur_radius, color))
+            }
+            10 => FixedLayout::decode(data).map(FilterOp::ColorMatrix),
+            11 => Some(FilterOp::ComponentTransfer),
+            12 => FixedLayout::decode(data).map(FilterOp::Flood),
+            13 => FixedLayout::decode(data).map(FilterOp::Offset),
+            14 => FixedLayout::decode(data).map(FilterOp::Composite),
+            _ => None,
+        }
+    }
+}
+
+impl FixedLayout for CompositeOperator {
+    fn encode(&self, data: &mut Vec<u8>) {
+        match *self {
+            CompositeOperator::Over => data.push(0),
+            CompositeOperator::In => data.push(1),
+            CompositeOperator::Out => data.push(2),
+            CompositeOperator::Atop => data.push(3),
+            CompositeOperator::Xor => data.push(4),
+            CompositeOperator::Lighter => data.push(5),
+            CompositeOperator::Arithmetic(ref k) => {
+                data.push(6);
+                k.encode(data);
+            }
+        }
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        match u8::decode(data)? {
+            0 => Some(CompositeOperator::Over),
+            1 => Some(CompositeOperator::In),
+            2 => Some(CompositeOperator::Out),
+            3 => Some(CompositeOperator::Atop),
+            4 => Some(CompositeOperator::Xor),
+            5 => Some(CompositeOperator::Lighter),
+            6 => FixedLayout::decode(data).map(CompositeOperator::Arithmetic),
+            _ => None,
+        }
+    }
+}
+
+impl_fixed_layout_for_struct!(FilterData {
+    func_r_type,
+    r_values,
+    func_g_type,
+    g_values,
+    func_b_type,
+    b_values,
+    func_a_type,
+    a_values
+});
+impl_fixed_layout_for_unit_enum!(ComponentTransferFuncType {
+    Identity, Table, Discrete, Linear, Gamma
+});
+
+impl_fixed_layout_for_struct!(ImageDisplayItem {
+    image_key,
+    stretch_size,
+    tile_spacing,
+    image_rendering,
+    alpha_type,
+    repeat_horizontal,
+    repeat_vertical,
+    source_rect
+});
+impl_fixed_layout_for_unit_enum!(ImageRendering { Auto, CrispEdges, Pixelated });
+impl_fixed_layout_for_unit_enum!(AlphaType { Alpha, PremultipliedAlpha });
+
+impl_fixed_layout_for_struct!(YuvImageDisplayItem { yuv_data, color_space, image_rendering });
+impl_fixed_layout_for_unit_enum!(YuvColorSpace { Rec601, Rec709 });
+
+impl FixedLayout for YuvData {
+    fn encode(&self, data: &mut Vec<u8>) {
+        match *self {
+            YuvData::NV12(y, cb_cr) => {
+                data.push(0);
+                y.encode(data);
+                cb_cr.encode(data);
+            }
+            YuvData::PlanarYCbCr(y, cb, cr) => {
+                data.push(1);
+                y.encode(data);
+                cb.encode(data);
+                cr.encode(data);
+            }
+            YuvData::InterleavedYCbCr(y_cb_cr) => {
+                data.push(2);
+                y_cb_cr.encode(data);
+            }
+        }
+    }
+
+    fn decode(data: &mut &[u8]) -> Option<Self> {
+        match u8::decode(data)? {
+            0 => {
+                let y = ImageKey::decode(data)?;
+                let cb_cr = ImageKey::decode(data)?;
+                Some(YuvData::NV12(y, cb_cr))
+            }
+            1 => {
+                let y = ImageKey::decode(data)?;
+                let cb = ImageKey::decode(data)?;
+                let cr = ImageKey::decode(data)?;
+                Some(YuvData::PlanarYCbCr(y, cb, cr))
+            }
+            2 => ImageKey::decode(data).map(YuvData::InterleavedYCbCr),
+            _ => None,
+        }
+    }
+}
@@ -6,7 +6,6 @@
 #![cfg_attr(feature = "cargo-clippy", allow(too_many_arguments, float_cmp))]
 
 extern crate app_units;
-extern crate bincode;
 #[macro_use]
 extern crate bitflags;
 extern crate byteorder;
@@ -27,19 +26,23 @@ extern crate serde_derive;
 extern crate time;
 
 
+mod animation;
 mod api;
 pub mod channel;
 mod color;
 mod display_item;
 mod display_list;
+mod fixed_layout;
 mod font;
 mod image;
 mod units;
 
+pub use animation::*;
 pub use api::*;
 pub use color::*;
 pub use display_item::*;
 pub use display_list::*;
+pub use fixed_layout::*;
 pub use font::*;
 pub use image::*;
 pub use units::*;
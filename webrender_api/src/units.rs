@@ -120,7 +120,7 @@ pub fn as_scroll_parent_vector(vector: &LayerVector2D) -> ScrollLayerVector2D {
 /// may grow. Storing them as texel coords and normalizing
 /// the UVs in the vertex shader means nothing needs to be
 /// updated on the CPU when the texture size changes.
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TexelRect {
     pub uv0: DevicePoint,
     pub uv1: DevicePoint,
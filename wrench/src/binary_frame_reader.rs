@@ -146,7 +146,8 @@ impl WrenchThing for BinaryFrameReader {
                             }
                             for doc_msg in &txn.scene_ops {
                                 match *doc_msg {
-                                    SceneMsg::SetDisplayList { .. } => {
+                                    SceneMsg::SetDisplayList { .. } |
+                                    SceneMsg::UpdateDisplayList { .. } => {
                                         found_frame_marker = false;
                                         found_display_list = true;
                                     }
@@ -5,9 +5,13 @@
 use WindowWrapper;
 use blob;
 use euclid::{TypedRect, TypedSize2D, TypedPoint2D};
+use std::f32;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicIsize, Ordering};
 use std::sync::mpsc::Receiver;
+use std::thread;
+use std::time::{Duration, Instant};
+use webrender::RendererOptions;
 use webrender::api::*;
 use wrench::Wrench;
 
@@ -29,6 +33,10 @@ fn rect<T: Copy, U>(x: T, y: T, width: T, height: T) -> TypedRect<T, U> {
     TypedRect::new(point(x, y), size(width, height))
 }
 
+fn duration_ms(duration: Duration) -> f32 {
+    duration.as_secs() as f32 * 1000.0 + duration.subsec_nanos() as f32 / 1_000_000.0
+}
+
 impl<'a> RawtestHarness<'a> {
     pub fn new(wrench: &'a mut Wrench, window: &'a mut WindowWrapper, rx: Receiver<()>) -> Self {
         RawtestHarness {
@@ -40,10 +48,21 @@ impl<'a> RawtestHarness<'a> {
 
     pub fn run(mut self) {
         self.test_hit_testing();
+        self.test_hit_testing_area();
+        self.test_display_list_delta();
         self.test_retained_blob_images_test();
         self.test_blob_update_test();
         self.test_blob_update_epoch_test();
         self.test_tile_decomposition();
+        self.test_blob_repeat_modes();
+        self.test_rect_color_binding();
+        self.test_color_animation();
+        self.test_scroll_link();
+        self.test_smooth_scroll();
+        self.test_fling();
+        self.test_scroll_snapping();
+        self.test_overscroll_elasticity();
+        self.test_keyboard_scroll();
         self.test_save_restore();
         self.test_capture();
     }
@@ -121,6 +140,715 @@ impl<'a> RawtestHarness<'a> {
         self.wrench.api.update_resources(resources);
     }
 
+    fn test_blob_repeat_modes(&mut self) {
+        println!("\tblob repeat modes...");
+        let window_size = self.window.get_inner_size();
+
+        let test_size = DeviceUintSize::new(400, 400);
+
+        let window_rect = DeviceUintRect::new(
+            point(0, window_size.height - test_size.height),
+            test_size,
+        );
+        let layout_size = LayoutSize::new(400., 400.);
+        let mut resources = ResourceUpdates::new();
+
+        let blob_img = self.wrench.api.generate_image_key();
+        resources.add_image(
+            blob_img,
+            ImageDescriptor::new(100, 100, ImageFormat::BGRA8, true),
+            ImageData::new_blob_image(blob::serialize_blob(ColorU::new(50, 50, 150, 255))),
+            Some(64),
+        );
+
+        let info = LayoutPrimitiveInfo::new(rect(0.0, 60.0, 360.0, 230.0));
+
+        // Three tiles fit horizontally with 30px between them, and the
+        // tiles are scaled to 115px high to fit twice vertically.
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id, layout_size);
+        builder.push_repeating_image(
+            &info,
+            size(100.0, 100.0),
+            size(0.0, 0.0),
+            RepeatMode::Space,
+            RepeatMode::Round,
+            ImageRendering::Auto,
+            AlphaType::PremultipliedAlpha,
+            blob_img,
+        );
+
+        let mut epoch = Epoch(0);
+
+        self.submit_dl(&mut epoch, layout_size, builder, Some(resources));
+        let pixels_repeat_modes = self.render_and_get_pixels(window_rect);
+
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id, layout_size);
+        builder.push_image(
+            &info,
+            size(100.0, 115.0),
+            size(30.0, 0.0),
+            ImageRendering::Auto,
+            AlphaType::PremultipliedAlpha,
+            blob_img,
+        );
+
+        self.submit_dl(&mut epoch, layout_size, builder, None);
+        let pixels_explicit = self.render_and_get_pixels(window_rect);
+
+        assert!(pixels_repeat_modes == pixels_explicit);
+
+        // Leaving a tiled blob image in the resource cache
+        // confuses the `test_capture`.
+        resources = ResourceUpdates::new();
+        resources.delete_image(blob_img);
+        self.wrench.api.update_resources(resources);
+    }
+
+    fn test_rect_color_binding(&mut self) {
+        println!("\trect color binding...");
+        let window_size = self.window.get_inner_size();
+
+        let test_size = DeviceUintSize::new(100, 100);
+
+        let window_rect = DeviceUintRect::new(
+            point(0, window_size.height - test_size.height),
+            test_size,
+        );
+        let layout_size = LayoutSize::new(100., 100.);
+
+        let color_key = self.wrench.api.generate_property_binding_key();
+        let red = ColorF::new(1.0, 0.0, 0.0, 1.0);
+        let green = ColorF::new(0.0, 1.0, 0.0, 1.0);
+
+        let info = LayoutPrimitiveInfo::new(rect(0.0, 0.0, 100.0, 100.0));
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id, layout_size);
+        builder.push_rect_with_animation(&info, PropertyBinding::Binding(color_key));
+
+        let mut txn = Transaction::new();
+        txn.set_display_list(
+            Epoch(0),
+            Some(ColorF::new(1.0, 1.0, 1.0, 1.0)),
+            layout_size,
+            builder.finalize(),
+            false,
+        );
+        txn.update_dynamic_properties(DynamicProperties {
+            transforms: vec![],
+            floats: vec![],
+            colors: vec![color_key.with(red)],
+        });
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+
+        let pixels = self.render_and_get_pixels(window_rect);
+        assert_eq!(&pixels[0 .. 4], &[255, 0, 0, 255]);
+
+        // A new color only needs a new frame, not a new display list.
+        let mut txn = Transaction::new();
+        txn.update_dynamic_properties(DynamicProperties {
+            transforms: vec![],
+            floats: vec![],
+            colors: vec![color_key.with(green)],
+        });
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+
+        let pixels = self.render_and_get_pixels(window_rect);
+        assert_eq!(&pixels[0 .. 4], &[0, 255, 0, 255]);
+    }
+
+    fn test_color_animation(&mut self) {
+        println!("\tcolor animation...");
+        let window_size = self.window.get_inner_size();
+
+        let test_size = DeviceUintSize::new(100, 100);
+
+        let window_rect = DeviceUintRect::new(
+            point(0, window_size.height - test_size.height),
+            test_size,
+        );
+        let layout_size = LayoutSize::new(100., 100.);
+
+        let color_key = self.wrench.api.generate_property_binding_key();
+        let animation = |duration| Animation {
+            key: color_key,
+            keyframes: vec![
+                Keyframe {
+                    offset: 0.0,
+                    value: ColorF::new(1.0, 0.0, 0.0, 1.0),
+                    easing: AnimationEasing::Steps(1),
+                },
+                Keyframe {
+                    offset: 1.0,
+                    value: ColorF::new(0.0, 1.0, 0.0, 1.0),
+                    easing: AnimationEasing::Linear,
+                },
+            ],
+            duration,
+            iterations: AnimationIterations::Count(1),
+            direction: AnimationDirection::Normal,
+        };
+
+        let info = LayoutPrimitiveInfo::new(rect(0.0, 0.0, 100.0, 100.0));
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id, layout_size);
+        builder.push_rect_with_animation(&info, PropertyBinding::Binding(color_key));
+
+        // The step only happens at the end, which this animation won't reach.
+        let mut txn = Transaction::new();
+        txn.set_display_list(
+            Epoch(0),
+            Some(ColorF::new(1.0, 1.0, 1.0, 1.0)),
+            layout_size,
+            builder.finalize(),
+            false,
+        );
+        txn.start_animation(animation(1.0e9).into());
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+
+        let pixels = self.render_and_get_pixels(window_rect);
+        assert_eq!(&pixels[0 .. 4], &[255, 0, 0, 255]);
+
+        // An animation without a duration finishes as soon as it's sampled,
+        // and replaces the one running for the same binding.
+        let mut txn = Transaction::new();
+        txn.start_animation(animation(0.0).into());
+        txn.tick_animations();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+
+        let pixels = self.render_and_get_pixels(window_rect);
+        assert_eq!(&pixels[0 .. 4], &[0, 255, 0, 255]);
+    }
+
+    fn test_scroll_link(&mut self) {
+        println!("\tscroll link...");
+        let window_size = self.window.get_inner_size();
+
+        let test_size = DeviceUintSize::new(100, 100);
+
+        let window_rect = DeviceUintRect::new(
+            point(0, window_size.height - test_size.height),
+            test_size,
+        );
+        let layout_size = LayoutSize::new(200., 100.);
+
+        let color_key = self.wrench.api.generate_property_binding_key();
+        let scroll_id = ExternalScrollId(1, self.wrench.root_pipeline_id);
+
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id, layout_size);
+
+        let info = LayoutPrimitiveInfo::new(rect(0.0, 0.0, 100.0, 100.0));
+        builder.push_rect_with_animation(&info, PropertyBinding::Binding(color_key));
+
+        let clip_id = builder.define_scroll_frame(
+            Some(scroll_id),
+            rect(100.0, 0.0, 100.0, 1000.0),
+            rect(100.0, 0.0, 100.0, 100.0),
+            vec![],
+            None,
+            ScrollSensitivity::ScriptAndInputEvents,
+            OverscrollBehavior::Auto,
+        );
+        builder.push_clip_id(clip_id);
+        let info = LayoutPrimitiveInfo::new(rect(100.0, 0.0, 100.0, 1000.0));
+        builder.push_rect(&info, ColorF::new(0.0, 0.0, 1.0, 1.0));
+        builder.pop_clip_id();
+
+        // Scrolling the frame down by 100 pixels fades the rect from red to green.
+        let link = ScrollLink {
+            key: color_key,
+            scroll_id,
+            axis: ScrollAxis::Vertical,
+            start: 0.0,
+            end: 100.0,
+            keyframes: vec![
+                Keyframe {
+                    offset: 0.0,
+                    value: ColorF::new(1.0, 0.0, 0.0, 1.0),
+                    easing: AnimationEasing::Linear,
+                },
+                Keyframe {
+                    offset: 1.0,
+                    value: ColorF::new(0.0, 1.0, 0.0, 1.0),
+                    easing: AnimationEasing::Linear,
+                },
+            ],
+        };
+
+        let mut txn = Transaction::new();
+        txn.set_display_list(
+            Epoch(0),
+            Some(ColorF::new(1.0, 1.0, 1.0, 1.0)),
+            layout_size,
+            builder.finalize(),
+            false,
+        );
+        txn.add_scroll_link(link.into());
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+
+        let pixels = self.render_and_get_pixels(window_rect);
+        assert_eq!(&pixels[0 .. 4], &[255, 0, 0, 255]);
+
+        // Scrolling past the end of the range clamps to the last keyframe.
+        let mut txn = Transaction::new();
+        txn.scroll_node_with_id(
+            LayoutPoint::new(0.0, 300.0),
+            scroll_id,
+            ScrollClamping::ToContentBounds,
+        );
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+
+        let pixels = self.render_and_get_pixels(window_rect);
+        assert_eq!(&pixels[0 .. 4], &[0, 255, 0, 255]);
+
+        let mut txn = Transaction::new();
+        txn.remove_scroll_link(color_key.id);
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+    }
+
+    fn test_smooth_scroll(&mut self) {
+        println!("\tsmooth scroll...");
+        let layout_size = LayoutSize::new(100., 100.);
+        // Not shared with other tests, which leave their scroll frames scrolled.
+        let scroll_id = ExternalScrollId(2, self.wrench.root_pipeline_id);
+
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id, layout_size);
+        let clip_id = builder.define_scroll_frame(
+            Some(scroll_id),
+            rect(0.0, 0.0, 100.0, 1000.0),
+            rect(0.0, 0.0, 100.0, 100.0),
+            vec![],
+            None,
+            ScrollSensitivity::ScriptAndInputEvents,
+            OverscrollBehavior::Auto,
+        );
+        builder.push_clip_id(clip_id);
+        let info = LayoutPrimitiveInfo::new(rect(0.0, 0.0, 100.0, 1000.0));
+        builder.push_rect(&info, ColorF::new(0.0, 0.0, 1.0, 1.0));
+        builder.pop_clip_id();
+
+        let mut epoch = Epoch(0);
+        self.submit_dl(&mut epoch, layout_size, builder, None);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+
+        let scroll_state = |wrench: &Wrench| {
+            let states = wrench.api.get_scroll_node_state(wrench.document_id);
+            let state = states.iter().find(|state| state.id == scroll_id).unwrap();
+            (state.scroll_offset.y, state.is_smooth_scrolling)
+        };
+
+        // The step only happens at the end, which this scroll won't reach.
+        let mut txn = Transaction::new();
+        txn.smooth_scroll_node_with_id(
+            LayoutPoint::new(0.0, 100.0),
+            scroll_id,
+            ScrollClamping::ToContentBounds,
+            1.0e9,
+            AnimationEasing::Steps(1),
+        );
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+        assert_eq!(scroll_state(self.wrench), (0.0, true));
+
+        // Jumping to another offset interrupts the smooth scroll.
+        let mut txn = Transaction::new();
+        txn.scroll_node_with_id(
+            LayoutPoint::new(0.0, 50.0),
+            scroll_id,
+            ScrollClamping::ToContentBounds,
+        );
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+        assert_eq!(scroll_state(self.wrench), (-50.0, false));
+
+        // A smooth scroll without a duration ends on the first frame.
+        let mut txn = Transaction::new();
+        txn.smooth_scroll_node_with_id(
+            LayoutPoint::new(0.0, 100.0),
+            scroll_id,
+            ScrollClamping::ToContentBounds,
+            0.0,
+            AnimationEasing::Linear,
+        );
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+        assert_eq!(scroll_state(self.wrench), (-100.0, false));
+    }
+
+    fn test_fling(&mut self) {
+        println!("\tfling...");
+        let layout_size = LayoutSize::new(100., 100.);
+        // Not shared with other tests, which leave their scroll frames scrolled.
+        let scroll_id = ExternalScrollId(6, self.wrench.root_pipeline_id);
+        let friction = RendererOptions::default().fling_friction;
+
+        // Tall enough that the first fling never reaches the bottom.
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id, layout_size);
+        let clip_id = builder.define_scroll_frame(
+            Some(scroll_id),
+            rect(0.0, 0.0, 100.0, 10000.0),
+            rect(0.0, 0.0, 100.0, 100.0),
+            vec![],
+            None,
+            ScrollSensitivity::ScriptAndInputEvents,
+            OverscrollBehavior::Elastic(OverscrollElasticity {
+                stiffness: 0.2,
+                damping: 1.0,
+                max_stretch: 20.0,
+            }),
+        );
+        builder.push_clip_id(clip_id);
+        let info = LayoutPrimitiveInfo::new(rect(0.0, 0.0, 100.0, 10000.0));
+        builder.push_rect(&info, ColorF::new(0.0, 0.0, 1.0, 1.0));
+        builder.pop_clip_id();
+
+        let mut epoch = Epoch(0);
+        self.submit_dl(&mut epoch, layout_size, builder, None);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+
+        let scroll_state = |wrench: &Wrench| {
+            let states = wrench.api.get_scroll_node_state(wrench.document_id);
+            let state = states.iter().find(|state| state.id == scroll_id).unwrap();
+            (state.scroll_offset.y, state.is_bouncing_back)
+        };
+
+        // A fling covers `velocity / -ln(1 - friction)` before it comes to rest.
+        let fling_distance_per_velocity = -1.0 / (1.0 - friction).ln();
+
+        // Swipe down, one event every few milliseconds, and let go.
+        let step = 20.0;
+        self.send_scroll(0.0, ScrollEventPhase::Start);
+        let mut last_event = Instant::now();
+        let mut swipe_speeds = Vec::new();
+        for _ in 0 .. 5 {
+            thread::sleep(Duration::from_millis(10));
+            let now = Instant::now();
+            swipe_speeds.push(step / duration_ms(now - last_event));
+            last_event = now;
+            self.send_scroll(-step, ScrollEventPhase::Move(true));
+        }
+        assert_eq!(scroll_state(self.wrench), (-5.0 * step, false));
+        self.send_scroll(0.0, ScrollEventPhase::End);
+
+        // The fling carries on in the direction of the swipe.
+        let first_tick = Instant::now();
+        self.tick_fling();
+        let first_offset = scroll_state(self.wrench).0;
+        assert!(first_offset < -5.0 * step);
+
+        thread::sleep(Duration::from_millis(200));
+        let second_tick = Instant::now();
+        self.tick_fling();
+        let second_offset = scroll_state(self.wrench).0;
+        assert!(second_offset < first_offset);
+
+        let mut offset = second_offset;
+        let mut settled = false;
+        for _ in 0 .. 200 {
+            thread::sleep(Duration::from_millis(50));
+            self.tick_fling();
+            let (new_offset, bouncing_back) = scroll_state(self.wrench);
+            assert!(!bouncing_back);
+            assert!(new_offset <= offset);
+            settled = new_offset == offset;
+            offset = new_offset;
+            if settled {
+                break;
+            }
+        }
+        assert!(settled);
+
+        // The fling was launched with the velocity of the swipe.
+        let velocity = (-5.0 * step - offset) / fling_distance_per_velocity;
+        let slowest = swipe_speeds.iter().fold(f32::INFINITY, |a, &b| a.min(b));
+        let fastest = swipe_speeds.iter().fold(0.0f32, |a, &b| a.max(b));
+        assert!(velocity > slowest * 0.5 && velocity < fastest * 1.5);
+
+        // It slowed down at the configured rate: the distance left to go shrinks by
+        // `1 - friction` every millisecond.
+        let remaining_ratio = (second_offset - offset) / (first_offset - offset);
+        let decay_ms = remaining_ratio.ln() / (1.0 - friction).ln();
+        let elapsed_ms = duration_ms(second_tick - first_tick);
+        assert!((decay_ms - elapsed_ms).abs() < elapsed_ms * 0.3);
+
+        // Flinging back up past the top hands off to the overscroll spring.
+        let mut txn = Transaction::new();
+        txn.scroll_node_with_id(
+            LayoutPoint::new(0.0, 10.0 * step),
+            scroll_id,
+            ScrollClamping::ToContentBounds,
+        );
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+
+        self.send_scroll(0.0, ScrollEventPhase::Start);
+        for _ in 0 .. 5 {
+            thread::sleep(Duration::from_millis(10));
+            self.send_scroll(step, ScrollEventPhase::Move(true));
+        }
+        assert_eq!(scroll_state(self.wrench), (-5.0 * step, false));
+        self.send_scroll(0.0, ScrollEventPhase::End);
+
+        let mut offset = -5.0 * step;
+        let mut bouncing_back = false;
+        for _ in 0 .. 200 {
+            thread::sleep(Duration::from_millis(16));
+            self.tick_fling();
+            let state = scroll_state(self.wrench);
+            assert!(state.0 > offset);
+            offset = state.0;
+            bouncing_back = state.1;
+            if bouncing_back {
+                break;
+            }
+        }
+        assert!(bouncing_back);
+        assert!(offset > 0.0);
+
+        // The spring brings the frame back to the top, without overshooting.
+        for _ in 0 .. 100 {
+            let mut txn = Transaction::new();
+            txn.tick_scrolling_bounce_animations();
+            txn.generate_frame();
+            self.wrench.api.send_transaction(self.wrench.document_id, txn);
+            self.rx.recv().unwrap();
+            self.wrench.render();
+
+            let state = scroll_state(self.wrench);
+            assert!(state.0 <= offset && state.0 >= 0.0);
+            offset = state.0;
+            bouncing_back = state.1;
+            if !bouncing_back {
+                break;
+            }
+        }
+        assert_eq!((offset, bouncing_back), (0.0, false));
+    }
+
+    fn send_scroll(&mut self, delta_y: f32, phase: ScrollEventPhase) {
+        let mut txn = Transaction::new();
+        txn.scroll(
+            ScrollLocation::Delta(LayoutVector2D::new(0.0, delta_y)),
+            WorldPoint::new(50.0, 50.0),
+            phase,
+        );
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+    }
+
+    fn tick_fling(&mut self) {
+        let mut txn = Transaction::new();
+        txn.tick_animations();
+        // Still get a frame to wait for once the fling is over.
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+    }
+
+    fn test_scroll_snapping(&mut self) {
+        println!("\tscroll snapping...");
+        let layout_size = LayoutSize::new(100., 100.);
+        // Not shared with other tests, which leave their scroll frames scrolled.
+        let scroll_id = ExternalScrollId(3, self.wrench.root_pipeline_id);
+
+        let snap_areas = (0 .. 10).map(|i| ScrollSnapArea {
+            rect: rect(0.0, i as f32 * 100.0, 100.0, 100.0),
+            align_x: ScrollSnapAlignment::None,
+            align_y: ScrollSnapAlignment::Start,
+        }).collect::<Vec<_>>();
+
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id, layout_size);
+        let clip_id = builder.define_scroll_frame_with_snapping(
+            Some(scroll_id),
+            rect(0.0, 0.0, 100.0, 1000.0),
+            rect(0.0, 0.0, 100.0, 100.0),
+            vec![],
+            None,
+            ScrollSensitivity::ScriptAndInputEvents,
+            OverscrollBehavior::Auto,
+            ScrollSnapType {
+                x: ScrollSnapStrictness::None,
+                y: ScrollSnapStrictness::Mandatory,
+            },
+            snap_areas,
+        );
+        builder.push_clip_id(clip_id);
+        let info = LayoutPrimitiveInfo::new(rect(0.0, 0.0, 100.0, 1000.0));
+        builder.push_rect(&info, ColorF::new(0.0, 0.0, 1.0, 1.0));
+        builder.pop_clip_id();
+
+        let mut epoch = Epoch(0);
+        self.submit_dl(&mut epoch, layout_size, builder, None);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+
+        // A scroll gesture without any movement of the fingers doesn't fling, so
+        // the frame snaps back to the nearest snap area right away.
+        let cursor = WorldPoint::new(50.0, 50.0);
+        let mut txn = Transaction::new();
+        txn.scroll(
+            ScrollLocation::Delta(LayoutVector2D::new(0.0, -130.0)),
+            cursor,
+            ScrollEventPhase::Start,
+        );
+        txn.scroll(
+            ScrollLocation::Delta(LayoutVector2D::zero()),
+            cursor,
+            ScrollEventPhase::End,
+        );
+        for _ in 0 .. 100 {
+            txn.tick_scrolling_bounce_animations();
+        }
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+
+        let states = self.wrench.api.get_scroll_node_state(self.wrench.document_id);
+        let state = states.iter().find(|state| state.id == scroll_id).unwrap();
+        assert_eq!(state.scroll_offset, LayoutVector2D::new(0.0, -100.0));
+    }
+
+    fn test_overscroll_elasticity(&mut self) {
+        println!("\toverscroll elasticity...");
+        let layout_size = LayoutSize::new(100., 100.);
+        // Not shared with other tests, which leave their scroll frames scrolled.
+        let scroll_id = ExternalScrollId(4, self.wrench.root_pipeline_id);
+
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id, layout_size);
+        let clip_id = builder.define_scroll_frame(
+            Some(scroll_id),
+            rect(0.0, 0.0, 100.0, 1000.0),
+            rect(0.0, 0.0, 100.0, 100.0),
+            vec![],
+            None,
+            ScrollSensitivity::ScriptAndInputEvents,
+            OverscrollBehavior::Elastic(OverscrollElasticity {
+                stiffness: 0.2,
+                damping: 1.0,
+                max_stretch: 20.0,
+            }),
+        );
+        builder.push_clip_id(clip_id);
+        let info = LayoutPrimitiveInfo::new(rect(0.0, 0.0, 100.0, 1000.0));
+        builder.push_rect(&info, ColorF::new(0.0, 0.0, 1.0, 1.0));
+        builder.pop_clip_id();
+
+        let mut epoch = Epoch(0);
+        self.submit_dl(&mut epoch, layout_size, builder, None);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+
+        let scroll_state = |wrench: &Wrench| {
+            let states = wrench.api.get_scroll_node_state(wrench.document_id);
+            let state = states.iter().find(|state| state.id == scroll_id).unwrap();
+            (state.scroll_offset.y, state.is_bouncing_back)
+        };
+
+        // Scrolling past the top stretches the frame up to its maximum, and it
+        // starts bouncing back once the gesture ends.
+        let cursor = WorldPoint::new(50.0, 50.0);
+        let mut txn = Transaction::new();
+        txn.scroll(
+            ScrollLocation::Delta(LayoutVector2D::new(0.0, 100.0)),
+            cursor,
+            ScrollEventPhase::Start,
+        );
+        txn.scroll(
+            ScrollLocation::Delta(LayoutVector2D::zero()),
+            cursor,
+            ScrollEventPhase::End,
+        );
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+        assert_eq!(scroll_state(self.wrench), (20.0, true));
+
+        let mut txn = Transaction::new();
+        for _ in 0 .. 100 {
+            txn.tick_scrolling_bounce_animations();
+        }
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+        assert_eq!(scroll_state(self.wrench), (0.0, false));
+    }
+
+    fn test_keyboard_scroll(&mut self) {
+        println!("\tkeyboard scroll...");
+        let layout_size = LayoutSize::new(100., 100.);
+        // Not shared with other tests, which leave their scroll frames scrolled.
+        let scroll_id = ExternalScrollId(5, self.wrench.root_pipeline_id);
+
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id, layout_size);
+        let clip_id = builder.define_scroll_frame(
+            Some(scroll_id),
+            rect(0.0, 0.0, 100.0, 1000.0),
+            rect(0.0, 0.0, 100.0, 100.0),
+            vec![],
+            None,
+            ScrollSensitivity::ScriptAndInputEvents,
+            OverscrollBehavior::Auto,
+        );
+        builder.push_clip_id(clip_id);
+        let info = LayoutPrimitiveInfo::new(rect(0.0, 0.0, 100.0, 1000.0));
+        builder.push_rect(&info, ColorF::new(0.0, 0.0, 1.0, 1.0));
+        builder.pop_clip_id();
+
+        let mut epoch = Epoch(0);
+        self.submit_dl(&mut epoch, layout_size, builder, None);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+
+        let scroll_state = |wrench: &Wrench| {
+            let states = wrench.api.get_scroll_node_state(wrench.document_id);
+            let state = states.iter().find(|state| state.id == scroll_id).unwrap();
+            (state.scroll_offset.y, state.is_smooth_scrolling)
+        };
+
+        // Two line scrolls in a row add up, and are animated.
+        let cursor = WorldPoint::new(50.0, 50.0);
+        let mut txn = Transaction::new();
+        for _ in 0 .. 2 {
+            txn.scroll(
+                ScrollLocation::Lines(LayoutVector2D::new(0.0, 1.0)),
+                cursor,
+                ScrollEventPhase::Start,
+            );
+        }
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+        assert!(scroll_state(self.wrench).1);
+
+        // Wait for the smooth scroll to be over.
+        thread::sleep(Duration::from_millis(200));
+        let mut txn = Transaction::new();
+        txn.tick_animations();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+        self.rx.recv().unwrap();
+        self.wrench.render();
+        assert_eq!(scroll_state(self.wrench), (-80.0, false));
+    }
+
     fn test_retained_blob_images_test(&mut self) {
         println!("\tretained blob images test...");
         let blob_img;
@@ -634,6 +1362,34 @@ impl<'a> RawtestHarness<'a> {
         builder.pop_clip_id();
 
 
+        // Add a rectangle that is clipped by a triangular polygon clip.
+        let rect = LayoutRect::new(LayoutPoint::new(0., 200.), LayoutSize::new(100., 100.));
+        let clip_id = builder.define_clip_with_polygon(
+            rect,
+            vec![],
+            None,
+            ClipPolygon::new(FillRule::Nonzero, ClipMode::Clip),
+            vec![
+                LayoutPoint::new(0., 200.),
+                LayoutPoint::new(100., 200.),
+                LayoutPoint::new(0., 300.),
+            ],
+        );
+        builder.push_clip_id(clip_id);
+        let mut info = LayoutPrimitiveInfo::new(rect);
+        info.tag = Some((0, 6));
+        builder.push_rect(&info, ColorF::new(1.0, 1.0, 1.0, 1.0));
+        builder.pop_clip_id();
+
+
+        // Add an invisible hit test area that is clipped by a rounded rect, at 300,200.
+        let rect = LayoutRect::new(LayoutPoint::new(300., 200.), LayoutSize::new(100., 100.));
+        let mut info = LayoutPrimitiveInfo::with_clip(
+            rect, LocalClip::RoundedRect(rect, make_rounded_complex_clip(&rect, 20.)));
+        info.tag = Some((0, 7));
+        builder.push_hit_test(&info);
+
+
         let mut epoch = Epoch(0);
         self.submit_dl(&mut epoch, layout_size, builder, None);
 
@@ -690,6 +1446,169 @@ impl<'a> RawtestHarness<'a> {
         test_rounded_rectangle(WorldPoint::new(200., 0.), WorldSize::new(100., 100.), (0, 3));
         test_rounded_rectangle(WorldPoint::new(100., 100.), WorldSize::new(100., 100.), (0, 4));
         test_rounded_rectangle(WorldPoint::new(200., 100.), WorldSize::new(100., 100.), (0, 5));
+        test_rounded_rectangle(WorldPoint::new(300., 200.), WorldSize::new(100., 100.), (0, 7));
+
+        // Only the half of the rectangle inside the triangle should be hit.
+        assert_hit_test(WorldPoint::new(25., 225.), vec![(0, 6), (0, 1)]);
+        assert_hit_test(WorldPoint::new(75., 275.), vec![(0, 1)]);
+
+        // A batch of hit tests gives the same results as doing them one at a time.
+        let points = [
+            WorldPoint::new(-10., -10.),
+            WorldPoint::new(150., 50.),
+            WorldPoint::new(25., 225.),
+        ];
+        let queries = points.iter().map(|&point| HitTestQuery {
+            pipeline_id: None,
+            point,
+            flags: HitTestFlags::FIND_ALL,
+        }).collect();
+        let results = self.wrench.api.hit_test_batch(self.wrench.document_id, queries);
+        assert_eq!(results.len(), points.len());
+        for (result, point) in results.iter().zip(points.iter()) {
+            assert_eq!(result.items, hit_test(*point).items);
+        }
+
+        // Hit testing from this thread against the last rendered frame gives the same results
+        // as going through the render backend.
+        let shared_hit_testers = self.wrench.renderer.shared_hit_testers();
+        for point in &points {
+            let result = shared_hit_testers.hit_test(
+                self.wrench.document_id,
+                None,
+                HitTestArea::Point(*point),
+                HitTestFlags::FIND_ALL,
+            );
+            assert_eq!(result.items, hit_test(*point).items);
+        }
+    }
+
+    fn test_hit_testing_area(&mut self) {
+        println!("\thit testing area test...");
+
+        let layout_size = LayoutSize::new(400., 400.);
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id, layout_size);
+
+        // Add two 100x100 rectangles, at 100,100 and at 300,100.
+        let mut info = LayoutPrimitiveInfo::new(rect(100., 100., 100., 100.));
+        info.tag = Some((0, 1));
+        builder.push_rect(&info, ColorF::new(1.0, 1.0, 1.0, 1.0));
+
+        let mut info = LayoutPrimitiveInfo::new(rect(300., 100., 100., 100.));
+        info.tag = Some((0, 2));
+        builder.push_rect(&info, ColorF::new(1.0, 1.0, 1.0, 1.0));
+
+        let mut epoch = Epoch(0);
+        self.submit_dl(&mut epoch, layout_size, builder, None);
+
+        // We render to ensure that the hit tester is up to date with the current scene.
+        self.rx.recv().unwrap();
+        self.wrench.render();
+
+        let hit_test = | area: HitTestArea | -> HitTestResult {
+            self.wrench.api.hit_test_area(
+                self.wrench.document_id,
+                None,
+                area,
+                HitTestFlags::FIND_ALL,
+            )
+        };
+
+        // A rect across both rectangles hits the topmost one first, and reports the part of
+        // each rectangle inside the rect.
+        let result = hit_test(HitTestArea::Rect(rect(150., 150., 200., 10.)));
+        assert_eq!(result.items.len(), 2);
+        assert_eq!(result.items[0].tag, (0, 2));
+        assert_eq!(result.items[0].intersection, rect(0., 50., 50., 10.));
+        assert_eq!(result.items[1].tag, (0, 1));
+        assert_eq!(result.items[1].intersection, rect(50., 50., 50., 10.));
+
+        // A rect between the rectangles doesn't hit anything.
+        let result = hit_test(HitTestArea::Rect(rect(210., 100., 80., 100.)));
+        assert!(result.items.is_empty());
+
+        // The nearest rectangle is found along with the point on it nearest to the query.
+        let result = hit_test(HitTestArea::Nearest(WorldPoint::new(80., 150.), 30.));
+        assert_eq!(result.items.len(), 1);
+        assert_eq!(result.items[0].tag, (0, 1));
+        assert_eq!(result.items[0].point_relative_to_item, LayoutPoint::new(0., 50.));
+
+        let result = hit_test(HitTestArea::Nearest(WorldPoint::new(270., 150.), 40.));
+        assert_eq!(result.items.len(), 1);
+        assert_eq!(result.items[0].tag, (0, 2));
+
+        // Nothing is within the maximum distance of the middle of the gap.
+        let result = hit_test(HitTestArea::Nearest(WorldPoint::new(250., 150.), 30.));
+        assert!(result.items.is_empty());
+    }
+
+    fn test_display_list_delta(&mut self) {
+        println!("\tdisplay list delta test...");
+
+        let layout_size = LayoutSize::new(400., 400.);
+        let mut builder = DisplayListBuilder::new(self.wrench.root_pipeline_id, layout_size);
+
+        // A rectangle that stays the same, and one in a group that the delta replaces.
+        let mut info = LayoutPrimitiveInfo::new(rect(0., 0., 100., 100.));
+        info.tag = Some((0, 1));
+        builder.push_rect(&info, ColorF::new(1.0, 0.0, 0.0, 1.0));
+
+        builder.push_item_group(ItemGroupId(1));
+        let mut info = LayoutPrimitiveInfo::new(rect(100., 0., 100., 100.));
+        info.tag = Some((0, 2));
+        builder.push_rect(&info, ColorF::new(0.0, 1.0, 0.0, 1.0));
+        builder.pop_item_group();
+
+        let (pipeline_id, content_size, display_list) = builder.finalize();
+        let descriptor = *display_list.descriptor();
+
+        let mut epoch = Epoch(0);
+        let mut txn = Transaction::new();
+        txn.set_display_list(
+            epoch,
+            Some(ColorF::new(1.0, 1.0, 1.0, 1.0)),
+            layout_size,
+            (pipeline_id, content_size, display_list),
+            false,
+        );
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+
+        self.rx.recv().unwrap();
+        self.wrench.render();
+
+        let hit_test = |wrench: &Wrench, x: f32, y: f32| -> Vec<ItemTag> {
+            wrench.api.hit_test(
+                wrench.document_id,
+                None,
+                WorldPoint::new(x, y),
+                HitTestFlags::empty(),
+            ).items.iter().map(|item| item.tag).collect()
+        };
+
+        assert_eq!(hit_test(self.wrench, 50., 50.), vec![(0, 1)]);
+        assert_eq!(hit_test(self.wrench, 150., 50.), vec![(0, 2)]);
+
+        // Move the grouped rectangle down and give it a new tag.
+        let mut builder = DisplayListBuilder::new_delta(pipeline_id, layout_size, &descriptor);
+        builder.push_item_group(ItemGroupId(1));
+        let mut info = LayoutPrimitiveInfo::new(rect(100., 100., 100., 100.));
+        info.tag = Some((0, 3));
+        builder.push_rect(&info, ColorF::new(0.0, 0.0, 1.0, 1.0));
+        builder.pop_item_group();
+
+        epoch.0 += 1;
+        let mut txn = Transaction::new();
+        txn.update_display_list(epoch, builder.finalize());
+        txn.generate_frame();
+        self.wrench.api.send_transaction(self.wrench.document_id, txn);
+
+        self.rx.recv().unwrap();
+        self.wrench.render();
+
+        assert_eq!(hit_test(self.wrench, 50., 50.), vec![(0, 1)]);
+        assert!(hit_test(self.wrench, 150., 50.).is_empty());
+        assert_eq!(hit_test(self.wrench, 150., 150.), vec![(0, 3)]);
     }
 
 }
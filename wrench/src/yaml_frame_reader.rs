@@ -190,6 +190,7 @@ pub struct YamlFrameReader {
 
     watch_source: bool,
     list_resources: bool,
+    print_stats: bool,
 
     /// A HashMap of offsets which specify what scroll offsets particular
     /// scroll layers should be initialized with.
@@ -206,11 +207,50 @@ pub struct YamlFrameReader {
     clip_id_map: HashMap<u64, ClipId>,
 }
 
+fn print_display_list_stats(pipeline_id: PipelineId, stats: &DisplayListStats) {
+    println!(
+        "Display list of {:?}: {} bytes, built in {:.3}ms",
+        pipeline_id,
+        stats.total_bytes,
+        stats.build_time as f64 / 1_000_000.0,
+    );
+    if stats.send_delay != 0 {
+        println!("  sent {:.3}ms after being built", stats.send_delay as f64 / 1_000_000.0);
+    }
+    for (name, item) in &stats.items {
+        println!("  {:<26} {:>8} items {:>10} bytes", name, item.count, item.bytes);
+    }
+    let aux = [
+        ("glyphs", stats.glyphs),
+        ("gradient stops", stats.gradient_stops),
+        ("filters", stats.filters),
+        ("complex clips", stats.complex_clips),
+        ("other auxiliary data", stats.other_aux),
+    ];
+    for &(name, values) in &aux {
+        println!("  {:<26} {:>8} values {:>9} bytes", name, values.count, values.bytes);
+    }
+    println!("  stacking context depth: {}", stats.max_stacking_context_depth);
+    println!(
+        "  clip nodes: {}, scroll nodes: {}, sticky nodes: {}, reference frames: {}, \
+         clip chains: {}",
+        stats.clip_nodes,
+        stats.scroll_nodes,
+        stats.sticky_nodes,
+        stats.reference_frames,
+        stats.clip_chains,
+    );
+    if let Some(ref error) = stats.error {
+        println!("  the rest of the display list is malformed: {:?}", error);
+    }
+}
+
 impl YamlFrameReader {
     pub fn new(yaml_path: &Path) -> YamlFrameReader {
         YamlFrameReader {
             watch_source: false,
             list_resources: false,
+            print_stats: false,
             frame_built: false,
             yaml_path: yaml_path.to_owned(),
             aux_dir: yaml_path.parent().unwrap().to_owned(),
@@ -250,6 +290,7 @@ impl YamlFrameReader {
 
         let mut y = YamlFrameReader::new(&yaml_file);
         y.list_resources = args.is_present("list-resources");
+        y.print_stats = args.is_present("stats");
         y.watch_source = args.is_present("watch");
         y.queue_depth = args.value_of("queue")
             .map(|s| s.parse::<u32>().unwrap())
@@ -300,7 +341,11 @@ impl YamlFrameReader {
         let mut builder = DisplayListBuilder::new(pipeline_id, content_size);
         let mut info = LayoutPrimitiveInfo::new(LayoutRect::zero());
         self.add_stacking_context_from_yaml(&mut builder, wrench, yaml, true, &mut info);
-        self.display_lists.push(builder.finalize());
+        let (pipeline_id, content_size, display_list) = builder.finalize();
+        if self.print_stats {
+            print_display_list_stats(pipeline_id, &display_list.stats());
+        }
+        self.display_lists.push((pipeline_id, content_size, display_list));
     }
 
     fn to_complex_clip_region(&mut self, item: &Yaml) -> ComplexClipRegion {
@@ -330,6 +375,27 @@ impl YamlFrameReader {
         }
     }
 
+    fn to_clip_polygon(&mut self, item: &Yaml) -> Option<(ClipPolygon, Vec<LayoutPoint>)> {
+        if item.is_badvalue() {
+            return None;
+        }
+
+        let points = match item["points"] {
+            Yaml::Array(ref array) => array
+                .iter()
+                .map(|point| point.as_point().expect("polygon points must be [x, y]"))
+                .collect(),
+            _ => panic!("polygon clip must have points"),
+        };
+        let fill_rule = item["fill-rule"]
+            .as_fill_rule()
+            .unwrap_or(FillRule::Nonzero);
+        let mode = item["clip-mode"]
+            .as_clip_mode()
+            .unwrap_or(ClipMode::Clip);
+        Some((ClipPolygon::new(fill_rule, mode), points))
+    }
+
     fn to_sticky_offset_bounds(&mut self, item: &Yaml) -> StickyOffsetBounds {
         match *item {
             Yaml::Array(ref array) => StickyOffsetBounds::new(
@@ -675,6 +741,56 @@ impl YamlFrameReader {
         }
     }
 
+    fn to_conic_gradient(&mut self, dl: &mut DisplayListBuilder, item: &Yaml) -> ConicGradient {
+        let center = item["center"]
+            .as_point()
+            .expect("conic gradient must have center");
+        // The angle is specified in degrees, clockwise from the top.
+        let angle = item["angle"].as_force_f32().unwrap_or(0.0).to_radians();
+        let stops = item["stops"]
+            .as_vec()
+            .expect("conic gradient must have stops")
+            .chunks(2)
+            .map(|chunk| {
+                GradientStop {
+                    offset: chunk[0]
+                        .as_force_f32()
+                        .expect("gradient stop offset is not f32"),
+                    color: chunk[1]
+                        .as_colorf()
+                        .expect("gradient stop color is not color"),
+                }
+            })
+            .collect::<Vec<_>>();
+        let extend_mode = if item["repeat"].as_bool().unwrap_or(false) {
+            ExtendMode::Repeat
+        } else {
+            ExtendMode::Clamp
+        };
+
+        if item["start-offset"].is_badvalue() {
+            dl.create_conic_gradient(center, angle, stops, extend_mode)
+        } else {
+            // Already normalized (e.g. written out by wrench itself), so
+            // push the stops as they are.
+            let start_offset = item["start-offset"]
+                .as_force_f32()
+                .expect("conic gradient start offset is not f32");
+            let end_offset = item["end-offset"]
+                .as_force_f32()
+                .expect("conic gradient must have end offset");
+            dl.push_stops(&stops);
+
+            ConicGradient {
+                center,
+                angle,
+                start_offset,
+                end_offset,
+                extend_mode,
+            }
+        }
+    }
+
     fn handle_rect(
         &mut self,
         dl: &mut DisplayListBuilder,
@@ -705,6 +821,18 @@ impl YamlFrameReader {
         dl.push_clear_rect(&info);
     }
 
+    fn handle_hit_test(
+        &mut self,
+        dl: &mut DisplayListBuilder,
+        item: &Yaml,
+        info: &mut LayoutPrimitiveInfo,
+    ) {
+        info.rect = item["bounds"]
+            .as_rect()
+            .expect("hit-test type must have bounds");
+        dl.push_hit_test(&info);
+    }
+
     fn handle_line(
         &mut self,
         dl: &mut DisplayListBuilder,
@@ -765,6 +893,51 @@ impl YamlFrameReader {
         );
     }
 
+    fn handle_path(
+        &mut self,
+        dl: &mut DisplayListBuilder,
+        item: &Yaml,
+        info: &mut LayoutPrimitiveInfo,
+    ) {
+        info.rect = item["bounds"]
+            .as_rect()
+            .expect("path type must have bounds");
+        let ops = item["path"]
+            .as_path_ops()
+            .expect("path type must have a path");
+        let fill_rule = item["fill-rule"]
+            .as_fill_rule()
+            .unwrap_or(FillRule::Nonzero);
+
+        let stroke = if item["stroke"].is_badvalue() {
+            None
+        } else {
+            let stroke = &item["stroke"];
+            Some(PathStroke {
+                width: stroke["width"].as_f32().unwrap_or(1.0),
+                color: stroke["color"].as_colorf().unwrap_or(*BLACK_COLOR),
+                join: stroke["join"]
+                    .as_str()
+                    .and_then(LineJoin::from_str)
+                    .unwrap_or(LineJoin::Miter),
+                cap: stroke["cap"]
+                    .as_str()
+                    .and_then(LineCap::from_str)
+                    .unwrap_or(LineCap::Butt),
+                miter_limit: stroke["miter-limit"].as_f32().unwrap_or(4.0),
+            })
+        };
+
+        // Stroked paths are only filled when given a color.
+        let default_fill_color = match stroke {
+            Some(..) => ColorF::new(0.0, 0.0, 0.0, 0.0),
+            None => *BLACK_COLOR,
+        };
+        let fill_color = item["color"].as_colorf().unwrap_or(default_fill_color);
+
+        dl.push_path(&info, ops, fill_rule, fill_color, stroke);
+    }
+
     fn handle_gradient(
         &mut self,
         dl: &mut DisplayListBuilder,
@@ -809,6 +982,28 @@ impl YamlFrameReader {
         dl.push_radial_gradient(&info, gradient, tile_size, tile_spacing);
     }
 
+    fn handle_conic_gradient(
+        &mut self,
+        dl: &mut DisplayListBuilder,
+        item: &Yaml,
+        info: &mut LayoutPrimitiveInfo,
+    ) {
+        let bounds_key = if item["type"].is_badvalue() {
+            "conic-gradient"
+        } else {
+            "bounds"
+        };
+        let bounds = item[bounds_key]
+            .as_rect()
+            .expect("conic gradient must have bounds");
+        info.rect = bounds;
+        let gradient = self.to_conic_gradient(dl, item);
+        let tile_size = item["tile-size"].as_size().unwrap_or(bounds.size);
+        let tile_spacing = item["tile-spacing"].as_size().unwrap_or(LayoutSize::zero());
+
+        dl.push_conic_gradient(&info, gradient, tile_size, tile_spacing);
+    }
+
     fn handle_border(
         &mut self,
         dl: &mut DisplayListBuilder,
@@ -909,26 +1104,12 @@ impl YamlFrameReader {
                         .as_vec_f32()
                         .expect("border must have outset");
                     let outset = broadcast(&outset, 4);
-                    let repeat_horizontal = match item["repeat-horizontal"]
-                        .as_str()
-                        .expect("border must have repeat-horizontal")
-                    {
-                        "stretch" => RepeatMode::Stretch,
-                        "repeat" => RepeatMode::Repeat,
-                        "round" => RepeatMode::Round,
-                        "space" => RepeatMode::Space,
-                        s => panic!("Unknown box border image repeat mode {}", s),
-                    };
-                    let repeat_vertical = match item["repeat-vertical"]
-                        .as_str()
-                        .expect("border must have repeat-vertical")
-                    {
-                        "stretch" => RepeatMode::Stretch,
-                        "repeat" => RepeatMode::Repeat,
-                        "round" => RepeatMode::Round,
-                        "space" => RepeatMode::Space,
-                        s => panic!("Unknown box border image repeat mode {}", s),
-                    };
+                    let repeat_horizontal = item["repeat-horizontal"]
+                        .as_repeat_mode()
+                        .expect("border must have repeat-horizontal");
+                    let repeat_vertical = item["repeat-vertical"]
+                        .as_repeat_mode()
+                        .expect("border must have repeat-vertical");
                     Some(BorderDetails::Image(ImageBorder {
                         image_key,
                         patch: NinePatchDescriptor {
@@ -948,9 +1129,29 @@ impl YamlFrameReader {
                         .as_vec_f32()
                         .expect("borders must have outset");
                     let outset = broadcast(&outset, 4);
+                    // The slices default to the border widths.
+                    let slice = match item["slice"].as_vec_f32() {
+                        Some(slice) => {
+                            let slice = broadcast(&slice, 4);
+                            SideOffsets2D::new(slice[0], slice[1], slice[2], slice[3])
+                        }
+                        None => SideOffsets2D::new(
+                            widths.top,
+                            widths.right,
+                            widths.bottom,
+                            widths.left,
+                        ),
+                    };
                     Some(BorderDetails::Gradient(GradientBorder {
                         gradient,
                         outset: SideOffsets2D::new(outset[0], outset[1], outset[2], outset[3]),
+                        slice,
+                        repeat_horizontal: item["repeat-horizontal"]
+                            .as_repeat_mode()
+                            .unwrap_or(RepeatMode::Stretch),
+                        repeat_vertical: item["repeat-vertical"]
+                            .as_repeat_mode()
+                            .unwrap_or(RepeatMode::Stretch),
                     }))
                 }
                 "radial-gradient" => {
@@ -959,9 +1160,29 @@ impl YamlFrameReader {
                         .as_vec_f32()
                         .expect("borders must have outset");
                     let outset = broadcast(&outset, 4);
+                    // The slices default to the border widths.
+                    let slice = match item["slice"].as_vec_f32() {
+                        Some(slice) => {
+                            let slice = broadcast(&slice, 4);
+                            SideOffsets2D::new(slice[0], slice[1], slice[2], slice[3])
+                        }
+                        None => SideOffsets2D::new(
+                            widths.top,
+                            widths.right,
+                            widths.bottom,
+                            widths.left,
+                        ),
+                    };
                     Some(BorderDetails::RadialGradient(RadialGradientBorder {
                         gradient,
                         outset: SideOffsets2D::new(outset[0], outset[1], outset[2], outset[3]),
+                        slice,
+                        repeat_horizontal: item["repeat-horizontal"]
+                            .as_repeat_mode()
+                            .unwrap_or(RepeatMode::Stretch),
+                        repeat_vertical: item["repeat-vertical"]
+                            .as_repeat_mode()
+                            .unwrap_or(RepeatMode::Stretch),
                     }))
                 }
                 _ => {
@@ -1135,7 +1356,27 @@ impl YamlFrameReader {
                 item
             ),
         };
-        dl.push_image(&info, stretch_size, tile_spacing, rendering, alpha_type, image_key);
+        let repeat_horizontal = item["repeat-horizontal"]
+            .as_repeat_mode()
+            .unwrap_or(RepeatMode::Repeat);
+        let repeat_vertical = item["repeat-vertical"]
+            .as_repeat_mode()
+            .unwrap_or(RepeatMode::Repeat);
+        // The source rect is given in texels, as x, y, width, height.
+        let source_rect = item["source-rect"].as_rect().map(|r| {
+            TexelRect::new(r.origin.x, r.origin.y, r.max_x(), r.max_y())
+        });
+        dl.push_sub_image(
+            &info,
+            source_rect,
+            stretch_size,
+            tile_spacing,
+            repeat_horizontal,
+            repeat_vertical,
+            rendering,
+            alpha_type,
+            image_key,
+        );
     }
 
     fn handle_text(
@@ -1257,6 +1498,31 @@ impl YamlFrameReader {
         dl.push_iframe(&info, pipeline_id);
     }
 
+    fn handle_fragment_definition(
+        &mut self,
+        dl: &mut DisplayListBuilder,
+        wrench: &mut Wrench,
+        item: &Yaml,
+    ) {
+        let id = item["id"].as_i64().expect("fragment definition must have an id");
+        dl.push_fragment_definition(FragmentId(id as u64));
+        if !item["items"].is_badvalue() {
+            self.add_display_list_items_from_yaml(dl, wrench, &item["items"]);
+        }
+        dl.pop_fragment_definition();
+    }
+
+    fn handle_fragment(
+        &mut self,
+        dl: &mut DisplayListBuilder,
+        item: &Yaml,
+        info: &mut LayoutPrimitiveInfo,
+    ) {
+        info.rect = item["bounds"].as_rect().expect("fragment must have bounds");
+        let id = item["id"].as_i64().expect("fragment must have an id");
+        dl.push_fragment(&info, FragmentId(id as u64));
+    }
+
     pub fn get_local_clip_for_item(&mut self, yaml: &Yaml, full_clip: LayoutRect) -> LocalClip {
         let rect = yaml["clip-rect"].as_rect().unwrap_or(full_clip);
         let complex_clip = &yaml["complex-clip"];
@@ -1299,6 +1565,8 @@ impl YamlFrameReader {
                 "gradient"
             } else if !item["radial-gradient"].is_badvalue() {
                 "radial-gradient"
+            } else if !item["conic-gradient"].is_badvalue() {
+                "conic-gradient"
             } else {
                 item["type"].as_str().unwrap_or("unknown")
             };
@@ -1323,7 +1591,9 @@ impl YamlFrameReader {
             match item_type {
                 "rect" => self.handle_rect(dl, item, &mut info),
                 "clear-rect" => self.handle_clear_rect(dl, item, &mut info),
+                "hit-test" => self.handle_hit_test(dl, item, &mut info),
                 "line" => self.handle_line(dl, item, &mut info),
+                "path" => self.handle_path(dl, item, &mut info),
                 "image" => self.handle_image(dl, wrench, item, &mut info),
                 "yuv-image" => self.handle_yuv_image(dl, wrench, item, &mut info),
                 "text" | "glyphs" => self.handle_text(dl, wrench, item, &mut info),
@@ -1334,8 +1604,11 @@ impl YamlFrameReader {
                 "border" => self.handle_border(dl, wrench, item, &mut info),
                 "gradient" => self.handle_gradient(dl, item, &mut info),
                 "radial-gradient" => self.handle_radial_gradient(dl, item, &mut info),
+                "conic-gradient" => self.handle_conic_gradient(dl, item, &mut info),
                 "box-shadow" => self.handle_box_shadow(dl, item, &mut info),
                 "iframe" => self.handle_iframe(dl, item, &mut info),
+                "fragment-definition" => self.handle_fragment_definition(dl, wrench, item),
+                "fragment" => self.handle_fragment(dl, item, &mut info),
                 "stacking-context" => {
                     self.add_stacking_context_from_yaml(dl, wrench, item, false, &mut info)
                 }
@@ -1380,6 +1653,7 @@ impl YamlFrameReader {
             complex_clips,
             image_mask,
             ScrollSensitivity::Script,
+            OverscrollBehavior::Auto,
         );
         if let Some(numeric_id) = numeric_id {
             self.clip_id_map.insert(numeric_id, real_id);
@@ -1481,7 +1755,16 @@ impl YamlFrameReader {
         let complex_clips = self.to_complex_clip_regions(&yaml["complex"]);
         let image_mask = self.to_image_mask(&yaml["image-mask"], wrench);
 
-        let real_id = dl.define_clip(clip_rect, complex_clips, image_mask);
+        let real_id = match self.to_clip_polygon(&yaml["polygon"]) {
+            Some((polygon, points)) => dl.define_clip_with_polygon(
+                clip_rect,
+                complex_clips,
+                image_mask,
+                polygon,
+                points,
+            ),
+            None => dl.define_clip(clip_rect, complex_clips, image_mask),
+        };
         if let Some(numeric_id) = numeric_id {
             self.clip_id_map.insert(numeric_id as u64, real_id);
         }
@@ -1554,10 +1837,12 @@ impl YamlFrameReader {
         }
 
         let filters = yaml["filters"].as_vec_filter_op().unwrap_or(vec![]);
+        let filter_data = yaml["filter-datas"].as_vec_filter_data().unwrap_or(vec![]);
+        let backdrop_filters = yaml["backdrop-filters"].as_vec_filter_op().unwrap_or(vec![]);
         info.rect = bounds;
         info.local_clip = LocalClip::from(bounds);
 
-        dl.push_stacking_context(
+        dl.push_stacking_context_with_backdrop_filters(
             &info,
             scroll_policy,
             transform.into(),
@@ -1565,6 +1850,8 @@ impl YamlFrameReader {
             perspective,
             mix_blend_mode,
             filters,
+            filter_data,
+            backdrop_filters,
         );
 
         if !yaml["items"].is_badvalue() {
@@ -178,22 +178,35 @@ fn maybe_radius_yaml(radius: &BorderRadius) -> Option<Yaml> {
     }
 }
 
-fn write_sc(parent: &mut Table, sc: &StackingContext, properties: &SceneProperties, filter_iter: AuxIter<FilterOp>) {
-    enum_node(parent, "scroll-policy", sc.scroll_policy);
-
-    matrix4d_node(parent, "transform", &properties.resolve_layout_transform(&sc.transform));
-
-    enum_node(parent, "transform-style", sc.transform_style);
+fn transfer_func_to_string(func_type: ComponentTransferFuncType, values: &[f32]) -> String {
+    let name = match func_type {
+        ComponentTransferFuncType::Identity => return "identity".to_owned(),
+        ComponentTransferFuncType::Table => "table",
+        ComponentTransferFuncType::Discrete => "discrete",
+        ComponentTransferFuncType::Linear => "linear",
+        ComponentTransferFuncType::Gamma => "gamma",
+    };
+    let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    format!("{}({})", name, values.join(", "))
+}
 
-    if let Some(perspective) = sc.perspective {
-        matrix4d_node(parent, "perspective", &perspective);
+fn path_ops_to_string(ops: &[PathOp]) -> String {
+    let mut commands = Vec::new();
+    for op in ops {
+        commands.push(match *op {
+            PathOp::MoveTo(p) => format!("M {} {}", p.x, p.y),
+            PathOp::LineTo(p) => format!("L {} {}", p.x, p.y),
+            PathOp::QuadTo(c, p) => format!("Q {} {} {} {}", c.x, c.y, p.x, p.y),
+            PathOp::CubicTo(c1, c2, p) => {
+                format!("C {} {} {} {} {} {}", c1.x, c1.y, c2.x, c2.y, p.x, p.y)
+            }
+            PathOp::Close => "Z".to_owned(),
+        });
     }
+    commands.join(" ")
+}
 
-    // mix_blend_mode
-    if sc.mix_blend_mode != MixBlendMode::Normal {
-        enum_node(parent, "mix-blend-mode", sc.mix_blend_mode)
-    }
-    // filters
+fn filters_to_yaml(filter_iter: AuxIter<FilterOp>, properties: &SceneProperties) -> Vec<Yaml> {
     let mut filters = vec![];
     for filter in filter_iter {
         match filter {
@@ -218,10 +231,83 @@ fn write_sc(parent: &mut Table, sc: &StackingContext, properties: &SceneProperti
             FilterOp::ColorMatrix(matrix) => {
                 filters.push(Yaml::String(format!("color-matrix({:?})", matrix)))
             }
+            FilterOp::ComponentTransfer => {
+                filters.push(Yaml::String("component-transfer".to_owned()))
+            }
+            FilterOp::Flood(color) => {
+                filters.push(Yaml::String(format!("flood([{}, {}, {}, {}])",
+                                                  color.r * 255.0,
+                                                  color.g * 255.0,
+                                                  color.b * 255.0,
+                                                  color.a)))
+            }
+            FilterOp::Offset(offset) => {
+                filters.push(Yaml::String(format!("offset({}, {})", offset.x, offset.y)))
+            }
+            FilterOp::Composite(operator) => {
+                let operator = match operator {
+                    CompositeOperator::Over => "over".to_owned(),
+                    CompositeOperator::In => "in".to_owned(),
+                    CompositeOperator::Out => "out".to_owned(),
+                    CompositeOperator::Atop => "atop".to_owned(),
+                    CompositeOperator::Xor => "xor".to_owned(),
+                    CompositeOperator::Lighter => "lighter".to_owned(),
+                    CompositeOperator::Arithmetic(k) => {
+                        format!("arithmetic, {}, {}, {}, {}", k[0], k[1], k[2], k[3])
+                    }
+                };
+                filters.push(Yaml::String(format!("composite({})", operator)))
+            }
         }
     }
 
+    filters
+}
+
+fn write_sc(
+    parent: &mut Table,
+    sc: &StackingContext,
+    properties: &SceneProperties,
+    filter_iter: AuxIter<FilterOp>,
+    filter_data_iter: AuxIter<FilterData>,
+    backdrop_filter_iter: AuxIter<FilterOp>,
+) {
+    enum_node(parent, "scroll-policy", sc.scroll_policy);
+
+    matrix4d_node(parent, "transform", &properties.resolve_layout_transform(&sc.transform));
+
+    enum_node(parent, "transform-style", sc.transform_style);
+
+    if let Some(perspective) = sc.perspective {
+        matrix4d_node(parent, "perspective", &perspective);
+    }
+
+    // mix_blend_mode
+    if sc.mix_blend_mode != MixBlendMode::Normal {
+        enum_node(parent, "mix-blend-mode", sc.mix_blend_mode)
+    }
+    // filters
+    let filters = filters_to_yaml(filter_iter, properties);
     yaml_node(parent, "filters", Yaml::Array(filters));
+
+    let mut filter_datas = vec![];
+    for data in filter_data_iter {
+        let mut v = new_table();
+        str_node(&mut v, "r", &transfer_func_to_string(data.func_r_type, &data.r_values));
+        str_node(&mut v, "g", &transfer_func_to_string(data.func_g_type, &data.g_values));
+        str_node(&mut v, "b", &transfer_func_to_string(data.func_b_type, &data.b_values));
+        str_node(&mut v, "a", &transfer_func_to_string(data.func_a_type, &data.a_values));
+        filter_datas.push(Yaml::Hash(v));
+    }
+
+    if !filter_datas.is_empty() {
+        yaml_node(parent, "filter-datas", Yaml::Array(filter_datas));
+    }
+
+    let backdrop_filters = filters_to_yaml(backdrop_filter_iter, properties);
+    if !backdrop_filters.is_empty() {
+        yaml_node(parent, "backdrop-filters", Yaml::Array(backdrop_filters));
+    }
 }
 
 #[cfg(target_os = "windows")]
@@ -628,6 +714,27 @@ impl YamlFrameWriter {
         Some(Yaml::Array(complex_items))
     }
 
+    fn make_clip_polygon_node(
+        &mut self,
+        polygon: &Option<ClipPolygon>,
+        points: ItemRange<LayoutPoint>,
+        list: &BuiltDisplayList,
+    ) -> Option<Yaml> {
+        let polygon = match *polygon {
+            Some(ref polygon) => polygon,
+            None => return None,
+        };
+
+        let mut t = new_table();
+        let points = list.get(points)
+            .map(|point| f32_vec_yaml(&[point.x, point.y], false))
+            .collect();
+        yaml_node(&mut t, "points", Yaml::Array(points));
+        enum_node(&mut t, "fill-rule", polygon.fill_rule);
+        enum_node(&mut t, "clip-mode", polygon.mode);
+        Some(Yaml::Hash(t))
+    }
+
     fn make_clip_mask_image_node(&mut self, image_mask: &Option<ImageMask>) -> Option<Yaml> {
         let mask = match image_mask {
             &Some(ref mask) => mask,
@@ -682,11 +789,14 @@ impl YamlFrameWriter {
             match *base.item() {
                 Rectangle(item) => {
                     str_node(&mut v, "type", "rect");
-                    color_node(&mut v, "color", item.color);
+                    color_node(&mut v, "color", scene.properties.resolve_color(&item.color));
                 }
                 ClearRectangle => {
                     str_node(&mut v, "type", "clear-rect");;
                 }
+                HitTest => {
+                    str_node(&mut v, "type", "hit-test");
+                }
                 Line(item) => {
                     str_node(&mut v, "type", "line");
                     if let LineStyle::Wavy = item.style {
@@ -696,6 +806,23 @@ impl YamlFrameWriter {
                     color_node(&mut v, "color", item.color);
                     str_node(&mut v, "style", item.style.as_str());
                 }
+                // Note: `Path` alone is std::path::Path here.
+                SpecificDisplayItem::Path(item) => {
+                    str_node(&mut v, "type", "path");
+                    let ops: Vec<PathOp> = display_list.get(base.path_ops()).collect();
+                    str_node(&mut v, "path", &path_ops_to_string(&ops));
+                    enum_node(&mut v, "fill-rule", item.fill_rule);
+                    color_node(&mut v, "color", item.fill_color);
+                    if let Some(stroke) = item.stroke {
+                        let mut stroke_table = new_table();
+                        f32_node(&mut stroke_table, "width", stroke.width);
+                        color_node(&mut stroke_table, "color", stroke.color);
+                        enum_node(&mut stroke_table, "join", stroke.join);
+                        enum_node(&mut stroke_table, "cap", stroke.cap);
+                        f32_node(&mut stroke_table, "miter-limit", stroke.miter_limit);
+                        table_node(&mut v, "stroke", stroke_table);
+                    }
+                }
                 Text(item) => {
                     let gi = display_list.get(base.glyphs());
                     let mut indices: Vec<u32> = vec![];
@@ -763,6 +890,24 @@ impl YamlFrameWriter {
                     }
                     size_node(&mut v, "stretch-size", &item.stretch_size);
                     size_node(&mut v, "tile-spacing", &item.tile_spacing);
+                    if let Some(ref source_rect) = item.source_rect {
+                        f32_vec_node(
+                            &mut v,
+                            "source-rect",
+                            &[
+                                source_rect.uv0.x,
+                                source_rect.uv0.y,
+                                source_rect.uv1.x - source_rect.uv0.x,
+                                source_rect.uv1.y - source_rect.uv0.y,
+                            ],
+                        );
+                    }
+                    if item.repeat_horizontal != RepeatMode::Repeat {
+                        enum_node(&mut v, "repeat-horizontal", item.repeat_horizontal);
+                    }
+                    if item.repeat_vertical != RepeatMode::Repeat {
+                        enum_node(&mut v, "repeat-vertical", item.repeat_vertical);
+                    }
                     match item.image_rendering {
                         ImageRendering::Auto => (),
                         ImageRendering::CrispEdges => str_node(&mut v, "rendering", "crisp-edges"),
@@ -892,6 +1037,15 @@ impl YamlFrameWriter {
                                 details.gradient.extend_mode == ExtendMode::Repeat,
                             );
                             yaml_node(&mut v, "outset", f32_vec_yaml(&outset, true));
+                            let slice: Vec<f32> = vec![
+                                details.slice.top,
+                                details.slice.right,
+                                details.slice.bottom,
+                                details.slice.left,
+                            ];
+                            yaml_node(&mut v, "slice", f32_vec_yaml(&slice, true));
+                            enum_node(&mut v, "repeat-horizontal", details.repeat_horizontal);
+                            enum_node(&mut v, "repeat-vertical", details.repeat_vertical);
                         }
                         BorderDetails::RadialGradient(ref details) => {
                             let widths: Vec<f32> = vec![
@@ -925,6 +1079,15 @@ impl YamlFrameWriter {
                                 details.gradient.extend_mode == ExtendMode::Repeat,
                             );
                             yaml_node(&mut v, "outset", f32_vec_yaml(&outset, true));
+                            let slice: Vec<f32> = vec![
+                                details.slice.top,
+                                details.slice.right,
+                                details.slice.bottom,
+                                details.slice.left,
+                            ];
+                            yaml_node(&mut v, "slice", f32_vec_yaml(&slice, true));
+                            enum_node(&mut v, "repeat-horizontal", details.repeat_horizontal);
+                            enum_node(&mut v, "repeat-vertical", details.repeat_vertical);
                         }
                     }
                 }
@@ -983,6 +1146,26 @@ impl YamlFrameWriter {
                         item.gradient.extend_mode == ExtendMode::Repeat,
                     );
                 }
+                ConicGradient(item) => {
+                    str_node(&mut v, "type", "conic-gradient");
+                    point_node(&mut v, "center", &item.gradient.center);
+                    f32_node(&mut v, "angle", item.gradient.angle.to_degrees());
+                    f32_node(&mut v, "start-offset", item.gradient.start_offset);
+                    f32_node(&mut v, "end-offset", item.gradient.end_offset);
+                    size_node(&mut v, "tile-size", &item.tile_size);
+                    size_node(&mut v, "tile-spacing", &item.tile_spacing);
+                    let mut stops = vec![];
+                    for stop in display_list.get(base.gradient_stops()) {
+                        stops.push(Yaml::Real(stop.offset.to_string()));
+                        stops.push(Yaml::String(color_to_string(stop.color)));
+                    }
+                    yaml_node(&mut v, "stops", Yaml::Array(stops));
+                    bool_node(
+                        &mut v,
+                        "repeat",
+                        item.gradient.extend_mode == ExtendMode::Repeat,
+                    );
+                }
                 Iframe(item) => {
                     str_node(&mut v, "type", "iframe");
                     u32_vec_node(&mut v, "id", &[item.pipeline_id.0, item.pipeline_id.1]);
@@ -990,7 +1173,16 @@ impl YamlFrameWriter {
                 PushStackingContext(item) => {
                     str_node(&mut v, "type", "stacking-context");
                     let filters = display_list.get(base.filters());
-                    write_sc(&mut v, &item.stacking_context, &scene.properties, filters);
+                    let filter_data = display_list.get(base.filter_data());
+                    let backdrop_filters = display_list.get(base.backdrop_filters());
+                    write_sc(
+                        &mut v,
+                        &item.stacking_context,
+                        &scene.properties,
+                        filters,
+                        filter_data,
+                        backdrop_filters,
+                    );
 
                     let mut sub_iter = base.sub_iter();
                     self.write_display_list(&mut v, display_list, scene, &mut sub_iter, clip_id_mapper);
@@ -1013,6 +1205,14 @@ impl YamlFrameWriter {
                     if let Some(mask_yaml) = self.make_clip_mask_image_node(&item.image_mask) {
                         yaml_node(&mut v, "image-mask", mask_yaml);
                     }
+
+                    if let Some(polygon) = self.make_clip_polygon_node(
+                        &item.polygon,
+                        base.polygon_points(),
+                        display_list,
+                    ) {
+                        yaml_node(&mut v, "polygon", polygon);
+                    }
                 }
                 ClipChain(item) => {
                     str_node(&mut v, "type", "clip-chain");
@@ -1086,7 +1286,7 @@ impl YamlFrameWriter {
                     yaml_node(&mut v, "previously-applied-offset", Yaml::Array(applied));
                 }
 
-                PopStackingContext => return,
+                PopStackingContext | PopFragmentDefinition => return,
                 SetGradientStops => panic!("dummy item yielded?"),
                 PushShadow(shadow) => {
                     str_node(&mut v, "type", "shadow");
@@ -1097,6 +1297,18 @@ impl YamlFrameWriter {
                 PopAllShadows => {
                     str_node(&mut v, "type", "pop-all-shadows");
                 }
+                PushItemGroup(..) | PopItemGroup => continue,
+                PushFragmentDefinition(id) => {
+                    str_node(&mut v, "type", "fragment-definition");
+                    usize_node(&mut v, "id", id.0 as usize);
+                    let mut sub_iter = base.sub_iter();
+                    self.write_display_list(&mut v, display_list, scene, &mut sub_iter, clip_id_mapper);
+                    continue_traversal = Some(sub_iter);
+                }
+                Fragment(item) => {
+                    str_node(&mut v, "type", "fragment");
+                    usize_node(&mut v, "id", item.id.0 as usize);
+                }
             }
             if !v.is_empty() {
                 list.push(Yaml::Hash(v));
@@ -1114,6 +1326,9 @@ impl YamlFrameWriter {
     ) {
         let mut list = vec![];
         self.write_display_list_items(&mut list, display_list, scene, list_iterator, clip_id_mapper);
+        if let Some(error) = list_iterator.error() {
+            println!("Warning: display list ends with a malformed item: {:?}", error);
+        }
         parent.insert(Yaml::String("items".to_owned()), Yaml::Array(list));
     }
 }
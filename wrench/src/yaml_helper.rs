@@ -32,10 +32,15 @@ pub trait YamlHelper {
     fn as_border_radius(&self) -> Option<BorderRadius>;
     fn as_transform_style(&self) -> Option<TransformStyle>;
     fn as_clip_mode(&self) -> Option<ClipMode>;
+    fn as_fill_rule(&self) -> Option<FillRule>;
+    fn as_path_ops(&self) -> Option<Vec<PathOp>>;
+    fn as_repeat_mode(&self) -> Option<RepeatMode>;
     fn as_mix_blend_mode(&self) -> Option<MixBlendMode>;
     fn as_scroll_policy(&self) -> Option<ScrollPolicy>;
     fn as_filter_op(&self) -> Option<FilterOp>;
     fn as_vec_filter_op(&self) -> Option<Vec<FilterOp>>;
+    fn as_filter_data(&self) -> Option<FilterData>;
+    fn as_vec_filter_data(&self) -> Option<Vec<FilterData>>;
 }
 
 fn string_to_color(color: &str) -> Option<ColorF> {
@@ -71,6 +76,32 @@ fn string_to_color(color: &str) -> Option<ColorF> {
     }
 }
 
+// Parse a component transfer function like "table(0, 0.5, 1)".
+// A missing function is the identity.
+fn string_to_transfer_func(func: Option<&str>) -> Option<(ComponentTransferFuncType, Vec<f32>)> {
+    let func = match func {
+        Some(func) => func,
+        None => return Some((ComponentTransferFuncType::Identity, vec![])),
+    };
+    let (name, args, _) = parse_function(func);
+    let values = args.iter()
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| arg.parse().unwrap())
+        .collect();
+    let func_type = match name {
+        "identity" => ComponentTransferFuncType::Identity,
+        "table" => ComponentTransferFuncType::Table,
+        "discrete" => ComponentTransferFuncType::Discrete,
+        "linear" => ComponentTransferFuncType::Linear,
+        "gamma" => ComponentTransferFuncType::Gamma,
+        _ => {
+            println!("Unrecognized transfer function '{}'", func);
+            return None;
+        }
+    };
+    Some((func_type, values))
+}
+
 pub trait StringEnum: Sized {
     fn from_str(&str) -> Option<Self>;
     fn as_str(&self) -> &'static str;
@@ -140,6 +171,28 @@ define_string_enum!(
 
 define_string_enum!(ClipMode, [Clip = "clip", ClipOut = "clip-out"]);
 
+define_string_enum!(FillRule, [Nonzero = "nonzero", Evenodd = "evenodd"]);
+
+define_string_enum!(LineJoin, [Miter = "miter", Round = "round", Bevel = "bevel"]);
+
+define_string_enum!(LineCap, [Butt = "butt", Round = "round", Square = "square"]);
+
+define_string_enum!(
+    RepeatMode,
+    [
+        Stretch = "stretch",
+        Repeat = "repeat",
+        Round = "round",
+        Space = "space"
+    ]
+);
+
+fn next_point<'a, I: Iterator<Item = &'a str>>(tokens: &mut I) -> Option<LayoutPoint> {
+    let x = tokens.next().and_then(|x| f32::from_str(x).ok());
+    let y = tokens.next().and_then(|y| f32::from_str(y).ok());
+    x.and_then(|x| y.map(|y| LayoutPoint::new(x, y)))
+}
+
 // Rotate around `axis` by `degrees` angle
 fn make_rotation(
     origin: &LayoutPoint,
@@ -531,6 +584,55 @@ impl YamlHelper for Yaml {
         self.as_str().and_then(|x| StringEnum::from_str(x))
     }
 
+    fn as_fill_rule(&self) -> Option<FillRule> {
+        self.as_str().and_then(|x| StringEnum::from_str(x))
+    }
+
+    // Parses the absolute commands of the SVG path syntax,
+    // e.g. "M 0 0 L 10 0 Q 20 0 20 10 C 20 20 10 20 0 20 Z".
+    fn as_path_ops(&self) -> Option<Vec<PathOp>> {
+        let mut tokens = match self.as_str() {
+            Some(s) => s.split(|c: char| c.is_whitespace() || c == ',').filter(|t| !t.is_empty()),
+            None => return None,
+        };
+
+        let mut ops = Vec::new();
+        while let Some(command) = tokens.next() {
+            let op = match command {
+                "M" => next_point(&mut tokens).map(PathOp::MoveTo),
+                "L" => next_point(&mut tokens).map(PathOp::LineTo),
+                "Q" => {
+                    let ctrl = next_point(&mut tokens);
+                    let to = next_point(&mut tokens);
+                    ctrl.and_then(|ctrl| to.map(|to| PathOp::QuadTo(ctrl, to)))
+                }
+                "C" => {
+                    let ctrl1 = next_point(&mut tokens);
+                    let ctrl2 = next_point(&mut tokens);
+                    let to = next_point(&mut tokens);
+                    ctrl1.and_then(|ctrl1| {
+                        ctrl2.and_then(|ctrl2| to.map(|to| PathOp::CubicTo(ctrl1, ctrl2, to)))
+                    })
+                }
+                "Z" => Some(PathOp::Close),
+                _ => {
+                    println!("Unrecognized path command '{}'", command);
+                    None
+                }
+            };
+            match op {
+                Some(op) => ops.push(op),
+                None => return None,
+            }
+        }
+
+        Some(ops)
+    }
+
+    fn as_repeat_mode(&self) -> Option<RepeatMode> {
+        self.as_str().and_then(|x| StringEnum::from_str(x))
+    }
+
     fn as_filter_op(&self) -> Option<FilterOp> {
         if let Some(s) = self.as_str() {
             match parse_function(s) {
@@ -576,6 +678,37 @@ impl YamlHelper for Yaml {
                     matrix.clone_from_slice(&m);
                     Some(FilterOp::ColorMatrix(matrix))
                 }
+                ("component-transfer", _, _) => {
+                    Some(FilterOp::ComponentTransfer)
+                }
+                ("flood", ref args, _) if args.len() == 1 => {
+                    let str = format!("---\ncolor: {}\n", args[0]);
+                    let mut yaml_doc = YamlLoader::load_from_str(&str).expect("Failed to parse flood");
+                    let yaml = yaml_doc.pop().unwrap();
+                    Some(FilterOp::Flood(yaml["color"].as_colorf().unwrap()))
+                }
+                ("offset", ref args, _) if args.len() == 2 => {
+                    Some(FilterOp::Offset(LayoutVector2D::new(
+                        args[0].parse().unwrap(),
+                        args[1].parse().unwrap(),
+                    )))
+                }
+                ("composite", ref args, _) if args.len() == 1 => {
+                    let operator = match args[0] {
+                        "over" => CompositeOperator::Over,
+                        "in" => CompositeOperator::In,
+                        "out" => CompositeOperator::Out,
+                        "atop" => CompositeOperator::Atop,
+                        "xor" => CompositeOperator::Xor,
+                        "lighter" => CompositeOperator::Lighter,
+                        _ => return None,
+                    };
+                    Some(FilterOp::Composite(operator))
+                }
+                ("composite", ref args, _) if args.len() == 5 && args[0] == "arithmetic" => {
+                    let k: Vec<f32> = args[1 ..].iter().map(|f| f.parse().unwrap()).collect();
+                    Some(FilterOp::Composite(CompositeOperator::Arithmetic([k[0], k[1], k[2], k[3]])))
+                }
                 (_, _, _) => None,
             }
         } else {
@@ -590,4 +723,32 @@ impl YamlHelper for Yaml {
             self.as_filter_op().map(|op| vec![op])
         }
     }
+
+    fn as_filter_data(&self) -> Option<FilterData> {
+        if self.as_hash().is_none() {
+            return None;
+        }
+        let (func_r_type, r_values) = string_to_transfer_func(self["r"].as_str())?;
+        let (func_g_type, g_values) = string_to_transfer_func(self["g"].as_str())?;
+        let (func_b_type, b_values) = string_to_transfer_func(self["b"].as_str())?;
+        let (func_a_type, a_values) = string_to_transfer_func(self["a"].as_str())?;
+        Some(FilterData {
+            func_r_type,
+            r_values,
+            func_g_type,
+            g_values,
+            func_b_type,
+            b_values,
+            func_a_type,
+            a_values,
+        })
+    }
+
+    fn as_vec_filter_data(&self) -> Option<Vec<FilterData>> {
+        if let Some(v) = self.as_vec() {
+            Some(v.iter().map(|x| x.as_filter_data().unwrap()).collect())
+        } else {
+            self.as_filter_data().map(|data| vec![data])
+        }
+    }
 }